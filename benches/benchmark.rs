@@ -1,7 +1,8 @@
+#![allow(clippy::approx_constant)]
+
 use criterion::{criterion_group, criterion_main, Criterion};
 use serde::Serialize;
 use serde_ton::ser::ReverseSerializer;
-use serde_cbor;
 
 #[derive(Serialize)]
 struct SimpleStruct {
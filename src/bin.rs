@@ -0,0 +1,708 @@
+//! `ton` コマンド
+//!
+//! TON ファイルの中身を確認したり、JSON / CBOR / TON Text と変換したりする
+//! Rust を書かずにサーバー上のデータを調べるためのもの
+
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error as StdError;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Serialize, Serializer};
+
+use serde_ton::de::{from_slice, read_head, type_name};
+use serde_ton::ser::{to_vec, value_to_vec};
+use serde_ton::value::num::{Float, Int, UInt};
+use serde_ton::value::prefix::{compression, prefix, self_describe, size_prefix};
+use serde_ton::value::value::{KeyValue, Value};
+use serde_ton::{RawTonRef, RecoveryReport, TonRef};
+
+const USAGE: &str = "\
+usage: ton <command> [options] [file]
+
+file を省略するか - を指定すると標準入力から読む
+
+commands:
+    dump [file]             値ごとに注釈を付けた hex を表示する
+    validate [file]         構造と中身が正しいか検査する
+    recover [file]          壊れたデータから読める値を探す (-o で回収した値を Array で書き出す)
+    stats [file]            型ごとの個数やサイズを表示する
+    get <path> [file]       パスの値を表示する (例: .users[3].address.zip)
+    to-json [file]          TON を JSON に変換する
+    from-json [file]        JSON を TON に変換する
+    to-cbor [file]          TON を CBOR に変換する
+    from-cbor [file]        CBOR を TON に変換する
+    to-text [file]          TON を TON Text に変換する
+    from-text [file]        TON Text を TON に変換する
+
+options:
+    -o, --output <file>     標準出力の代わりにファイルに書き込む
+    --pretty                to-json: 整形して出力する
+    --compact               to-text, get: 1 行で出力する
+    --json                  get: JSON で出力する
+    -h, --help              このヘルプを表示する";
+
+/// dump で 1 行に表示する最大バイト数
+const DUMP_BYTES: usize = 12;
+
+/// dump で表示する値の最大文字数
+const DUMP_VALUE_CHARS: usize = 60;
+
+/// 走査するネストの上限
+const MAX_DEPTH: usize = 1024;
+
+type CliResult<T> = Result<T, Box<dyn StdError>>;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(args) {
+        Ok(code) => process::exit(code),
+        Err(err) => {
+            eprintln!("ton: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+/// コマンドライン引数
+#[derive(Default)]
+struct Args {
+    command: String,
+    positional: Vec<String>,
+    output: Option<String>,
+    pretty: bool,
+    compact: bool,
+    json: bool,
+    help: bool,
+}
+
+impl Args {
+    fn parse(args: Vec<String>) -> CliResult<Self> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-o" | "--output" => match args.next() {
+                    Some(path) => parsed.output = Some(path),
+                    None => return Err(format!("{} requires a file name", arg).into()),
+                },
+                "--pretty" => parsed.pretty = true,
+                "--compact" => parsed.compact = true,
+                "--json" => parsed.json = true,
+                "-h" | "--help" => parsed.help = true,
+                "-" => parsed.positional.push(arg),
+                _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg).into()),
+                _ if parsed.command.is_empty() => parsed.command = arg,
+                _ => parsed.positional.push(arg),
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// 位置引数を取り出す
+    ///
+    /// required の数だけ必須で、残りは最大 optional 個まで
+    fn positional(&self, required: usize, optional: usize) -> CliResult<Vec<Option<&str>>> {
+        let len = self.positional.len();
+        if len < required || len > required + optional {
+            return Err(format!("wrong number of arguments for '{}'\n\n{}", self.command, USAGE).into());
+        }
+        Ok((0..required + optional)
+            .map(|i| self.positional.get(i).map(String::as_str))
+            .collect())
+    }
+
+    /// 入力ファイルを 1 つだけ取る
+    fn input(&self) -> CliResult<Vec<u8>> {
+        let args = self.positional(0, 1)?;
+        read_input(args[0])
+    }
+}
+
+fn run(args: Vec<String>) -> CliResult<i32> {
+    let args = Args::parse(args)?;
+    if args.help || args.command.is_empty() || args.command == "help" {
+        println!("{}", USAGE);
+        return Ok(0);
+    }
+
+    match args.command.as_str() {
+        "dump" => {
+            let input = args.input()?;
+            write_output(&args, dump(&input).as_bytes())?;
+        }
+        "validate" => {
+            let input = args.input()?;
+            return match serde_ton::validate(&input) {
+                Ok(report) => {
+                    println!(
+                        "ok: {} bytes, {} values, {} containers, max depth {}, {} checksums",
+                        report.size, report.values, report.containers, report.max_depth, report.checksums
+                    );
+                    Ok(0)
+                }
+                Err(err) => {
+                    println!("invalid: {}", err);
+                    Ok(1)
+                }
+            };
+        }
+        "recover" => {
+            let input = args.positional(0, 1)?;
+            let input = read_input(input[0])?;
+            let report = serde_ton::recover(&input);
+            print!("{}", recover_report(&report));
+            if let Some(path) = &args.output {
+                let values: Vec<&RawTonRef> = report.values.iter().map(|v| v.value.as_raw_ton()).collect();
+                fs::write(path, to_vec(&values)?).map_err(|err| format!("{}: {}", path, err))?;
+            }
+        }
+        "stats" => {
+            let input = args.input()?;
+            write_output(&args, stats(&input)?.as_bytes())?;
+        }
+        "get" => {
+            let positional = args.positional(1, 1)?;
+            let path = positional[0].unwrap_or_default();
+            let input = read_input(positional[1])?;
+            let value: Value = from_slice(&input)?;
+            let found = lookup(&value, path)?;
+            let text = if args.json {
+                to_json(found, !args.compact)?
+            } else {
+                to_text(found, args.compact)
+            };
+            write_output(&args, text.as_bytes())?;
+        }
+        "to-json" => {
+            let value: Value = from_slice(&args.input()?)?;
+            write_output(&args, to_json(&value, args.pretty)?.as_bytes())?;
+        }
+        "from-json" => {
+            let value: Value = serde_json::from_slice(&args.input()?)?;
+            write_output(&args, &value_to_vec(&value)?)?;
+        }
+        "to-cbor" => {
+            let value: Value = from_slice(&args.input()?)?;
+            let cbor = serde_cbor::to_vec(&Plain::new(&value, false))?;
+            write_output(&args, &cbor)?;
+        }
+        "from-cbor" => {
+            let value: Value = serde_cbor::from_slice(&args.input()?)?;
+            write_output(&args, &value_to_vec(&value)?)?;
+        }
+        "to-text" => {
+            let value: Value = from_slice(&args.input()?)?;
+            write_output(&args, to_text(&value, args.compact).as_bytes())?;
+        }
+        "from-text" => {
+            let input = String::from_utf8(args.input()?)?;
+            let value: Value = serde_ton::text::from_str(&input)?;
+            write_output(&args, &value_to_vec(&value)?)?;
+        }
+        command => return Err(format!("unknown command: {}\n\n{}", command, USAGE).into()),
+    }
+    Ok(0)
+}
+
+fn read_input(path: Option<&str>) -> CliResult<Vec<u8>> {
+    match path {
+        None | Some("-") => {
+            let mut buf = Vec::new();
+            io::stdin().lock().read_to_end(&mut buf)?;
+            Ok(buf)
+        }
+        Some(path) => fs::read(path).map_err(|err| format!("{}: {}", path, err).into()),
+    }
+}
+
+fn write_output(args: &Args, bytes: &[u8]) -> CliResult<()> {
+    match &args.output {
+        Some(path) => fs::write(path, bytes).map_err(|err| format!("{}: {}", path, err).into()),
+        None => {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(bytes)?;
+            stdout.flush()?;
+            Ok(())
+        }
+    }
+}
+
+fn to_text(value: &Value, compact: bool) -> String {
+    if compact {
+        format!("{}\n", value)
+    } else {
+        format!("{:#}\n", value)
+    }
+}
+
+fn to_json(value: &Value, pretty: bool) -> CliResult<String> {
+    let plain = Plain::new(value, true);
+    let mut json = if pretty {
+        serde_json::to_string_pretty(&plain)?
+    } else {
+        serde_json::to_string(&plain)?
+    };
+    json.push('\n');
+    Ok(json)
+}
+
+/// バイナリを走査した 1 つの値
+struct Node {
+    head: u8,
+    /// body の先頭
+    start: usize,
+    /// データ長を含めた head の先頭
+    head_start: usize,
+    /// 値の終端 (head の次)
+    end: usize,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn is_container(head: u8) -> bool {
+        matches!(head & !size_prefix::MASK, prefix::ARRAY | prefix::OBJECT | prefix::TABLE | prefix::META)
+    }
+}
+
+/// バイナリ全体の構造
+struct Document {
+    root: Node,
+    /// ルートの値より前にあるバイト数
+    leading: usize,
+    /// 末尾に self-describe trailer があるか
+    trailer: bool,
+}
+
+fn parse_document(buf: &[u8]) -> Result<Document, String> {
+    let trailer = buf.ends_with(&self_describe::TON_V1_REV_TAG);
+    let end = if trailer { buf.len() - self_describe::TON_V1_REV_TAG.len() } else { buf.len() };
+    let root = parse_node(buf, end, 0)?;
+    Ok(Document {
+        leading: root.start,
+        root,
+        trailer,
+    })
+}
+
+/// end の直前にある値を読む
+fn parse_node(buf: &[u8], end: usize, depth: usize) -> Result<Node, String> {
+    if depth > MAX_DEPTH {
+        return Err(format!("nesting is deeper than {} at offset {:#x}", MAX_DEPTH, end));
+    }
+    let head = read_head(buf, end).map_err(|err| err.to_string())?;
+
+    let mut children = Vec::new();
+    if Node::is_container(head.head) {
+        let mut cursor = head.head_start;
+        while cursor > head.start {
+            let child = parse_node(buf, cursor, depth + 1)?;
+            if child.start < head.start {
+                return Err(format!(
+                    "{} at offset {:#x} overflows its parent {} at offset {:#x}",
+                    type_name(child.head), child.start, type_name(head.head), head.start
+                ));
+            }
+            cursor = child.start;
+            children.push(child);
+        }
+        children.reverse();
+    }
+
+    Ok(Node {
+        head: head.head,
+        start: head.start,
+        head_start: head.head_start,
+        end: head.end,
+        children,
+    })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ")
+}
+
+fn dump(buf: &[u8]) -> String {
+    let mut out = String::new();
+    match parse_document(buf) {
+        Ok(doc) => {
+            if doc.leading > 0 {
+                out.push_str(&format!(
+                    "{:08x}  {:<width$}  ({} bytes before the root value)\n",
+                    0, "..", doc.leading, width = DUMP_BYTES * 3
+                ));
+            }
+            dump_node(buf, &doc.root, 0, "", &mut out);
+            if doc.trailer {
+                let start = buf.len() - self_describe::TON_V1_REV_TAG.len();
+                out.push_str(&format!(
+                    "{:08x}  {:<width$}  self-describe trailer\n",
+                    start, hex(&buf[start..]), width = DUMP_BYTES * 3
+                ));
+            }
+        }
+        Err(err) => out.push_str(&format!("error: {}\n", err)),
+    }
+    out
+}
+
+/// ファイル上の並び順 (body -> head) で 1 行ずつ出力する
+fn dump_node(buf: &[u8], node: &Node, depth: usize, label: &str, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    let kind = node.head & !size_prefix::MASK;
+    for (i, child) in node.children.iter().enumerate() {
+        // OBJECT は value -> key, TABLE は column -> key の順で並び、TABLE の最後は行数
+        let label = match (kind, i % 2) {
+            (prefix::TABLE, _) if i + 1 == node.children.len() => "rows ",
+            (prefix::OBJECT, 0) => "value ",
+            (prefix::TABLE, 0) => "column ",
+            (prefix::OBJECT | prefix::TABLE, _) => "key ",
+            _ => "",
+        };
+        dump_node(buf, child, depth + 1, label, out);
+    }
+
+    let (offset, bytes, summary) = if Node::is_container(node.head) {
+        let summary = match node.head & !size_prefix::MASK {
+            prefix::OBJECT => format!("{} entries, {} bytes", node.children.len() / 2, node.head_start - node.start),
+            prefix::ARRAY => format!("{} items, {} bytes", node.children.len(), node.head_start - node.start),
+            prefix::TABLE => format!("{} columns, {} bytes", node.children.len().saturating_sub(1) / 2, node.head_start - node.start),
+            _ => format!("{} bytes", node.head_start - node.start),
+        };
+        (node.head_start, &buf[node.head_start..node.end], summary)
+    } else if node.head & !size_prefix::MASK == prefix::PADDING {
+        (node.start, &buf[node.start..node.end], format!("{} bytes", node.head_start - node.start))
+    } else if node.head & !size_prefix::MASK == prefix::REF {
+        // 参照先は head の位置で示す
+        let summary = match TonRef::at(buf, node.end - 1).and_then(TonRef::resolve) {
+            Ok(target) => format!("-> {:08x} {}", target.offset(), target.type_name()),
+            Err(err) => format!("<invalid: {}>", err),
+        };
+        (node.start, &buf[node.start..node.end], summary)
+    } else if node.head & !size_prefix::MASK == prefix::KEYREF {
+        // key の辞書はルートの後ろにあるので、文書全体から引く
+        let summary = match TonRef::at(buf, node.end - 1).ok().and_then(|key| key.as_str()) {
+            Some(key) => format!("{:?}", key),
+            None => "<external key>".to_string(),
+        };
+        (node.start, &buf[node.start..node.end], summary)
+    } else if node.head & !size_prefix::MASK == prefix::COMPRESSED {
+        // 圧縮したサイズと展開したサイズ、展開した値を並べる
+        let body = &buf[node.start..node.head_start];
+        let trailer = body.len().checked_sub(compression::TRAILER_LEN).map(|split| &body[split..]);
+        let summary = match (trailer, from_slice::<Value>(&buf[node.start..node.end])) {
+            (Some(trailer), Ok(value)) => format!(
+                "codec {}, {} -> {} bytes, {}",
+                trailer[8],
+                body.len(),
+                u64::from_le_bytes(trailer[..8].try_into().unwrap()),
+                truncate(&value.to_string(), DUMP_VALUE_CHARS)
+            ),
+            (_, Err(err)) => format!("<invalid: {}>", err),
+            (None, _) => "<invalid>".to_string(),
+        };
+        (node.start, &buf[node.start..node.end], summary)
+    } else {
+        let summary = match from_slice::<Value>(&buf[node.start..node.end]) {
+            Ok(value) => truncate(&value.to_string(), DUMP_VALUE_CHARS),
+            Err(err) => format!("<invalid: {}>", err),
+        };
+        (node.start, &buf[node.start..node.end], summary)
+    };
+
+    let shown = if bytes.len() > DUMP_BYTES {
+        format!("{} ..", hex(&bytes[..DUMP_BYTES - 1]))
+    } else {
+        hex(bytes)
+    };
+    out.push_str(&format!(
+        "{:08x}  {:<width$}  {}{}{} {}\n",
+        offset, shown, indent, label, type_name(node.head), summary, width = DUMP_BYTES * 3
+    ));
+}
+
+/// recover の結果を前から順に 1 行ずつ表示する
+fn recover_report(report: &RecoveryReport) -> String {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for recovered in &report.values {
+        let value = &recovered.value;
+        let preview = match value.to_value() {
+            Ok(value) => truncate(&value.to_string(), DUMP_VALUE_CHARS),
+            Err(err) => format!("<{}>", err),
+        };
+        let tag = if recovered.self_describe { " (self-describe)" } else { "" };
+        lines.push((
+            recovered.range.start,
+            format!(
+                "value    {:>8}..{:<8} {} {} bytes{}  {}",
+                recovered.range.start,
+                recovered.range.end,
+                value.type_name(),
+                recovered.range.len(),
+                tag,
+                preview
+            ),
+        ));
+    }
+    for range in &report.damaged {
+        lines.push((range.start, format!("damaged  {:>8}..{:<8} {} bytes", range.start, range.end, range.len())));
+    }
+    lines.sort_by_key(|(start, _)| *start);
+
+    let mut out = String::new();
+    for (_, line) in lines {
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out.push_str(&format!(
+        "recovered {} values, {} damaged bytes in {} regions\n",
+        report.values.len(),
+        report.damaged_bytes(),
+        report.damaged.len()
+    ));
+    out
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    match s.char_indices().nth(max) {
+        Some((index, _)) => format!("{}...", &s[..index]),
+        None => s.to_string(),
+    }
+}
+
+/// 型ごとの集計
+#[derive(Default)]
+struct TypeStats {
+    count: usize,
+    bytes: usize,
+    head_bytes: usize,
+}
+
+#[derive(Default)]
+struct Stats {
+    types: BTreeMap<u8, TypeStats>,
+    values: usize,
+    max_depth: usize,
+    max_items: usize,
+}
+
+impl Stats {
+    fn collect(&mut self, node: &Node, depth: usize) {
+        let entry = self.types.entry(node.head & !size_prefix::MASK).or_default();
+        entry.count += 1;
+        entry.bytes += node.end - node.start;
+        entry.head_bytes += node.end - node.head_start;
+        self.values += 1;
+        self.max_depth = self.max_depth.max(depth);
+        self.max_items = self.max_items.max(node.children.len());
+        for child in &node.children {
+            self.collect(child, depth + 1);
+        }
+    }
+}
+
+fn stats(buf: &[u8]) -> CliResult<String> {
+    let doc = parse_document(buf)?;
+    let mut stats = Stats::default();
+    stats.collect(&doc.root, 0);
+
+    let head_bytes: usize = stats.types.values().map(|t| t.head_bytes).sum();
+    let mut out = String::new();
+    out.push_str(&format!("size:        {} bytes\n", buf.len()));
+    out.push_str(&format!("values:      {}\n", stats.values));
+    out.push_str(&format!("max depth:   {}\n", stats.max_depth));
+    out.push_str(&format!("max items:   {}\n", stats.max_items));
+    out.push_str(&format!(
+        "head bytes:  {} ({:.1}%)\n",
+        head_bytes,
+        head_bytes as f64 * 100.0 / buf.len().max(1) as f64
+    ));
+    out.push_str(&format!("trailer:     {}\n\n", if doc.trailer { "yes" } else { "no" }));
+    out.push_str(&format!("{:<14} {:>10} {:>14} {:>12}\n", "type", "count", "bytes", "head bytes"));
+    for (prefix, t) in &stats.types {
+        out.push_str(&format!("{:<14} {:>10} {:>14} {:>12}\n", type_name(*prefix), t.count, t.bytes, t.head_bytes));
+    }
+    Ok(out)
+}
+
+/// パスの 1 要素
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// `.users[3].address.zip` 形式のパスを分解する
+///
+/// `["key.with.dot"]` のように JSON 文字列でキーを書くこともできる
+fn parse_path(path: &str) -> CliResult<Vec<Segment>> {
+    let mut segments = Vec::new();
+    let mut rest = path.strip_prefix('.').unwrap_or(path);
+    if rest.is_empty() {
+        return Ok(segments);
+    }
+    // 最初のキーは `.` なしで書ける
+    if !rest.starts_with('[') {
+        rest = parse_key_segment(rest, &mut segments);
+    }
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            if after.is_empty() || after.starts_with(['.', '[']) {
+                return Err(format!("empty key in path: {}", path).into());
+            }
+            rest = parse_key_segment(after, &mut segments);
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let close = if after.starts_with('"') {
+                // JSON 文字列の終わりを探す
+                let mut stream = serde_json::Deserializer::from_str(after).into_iter::<String>();
+                let key = match stream.next() {
+                    Some(Ok(key)) => key,
+                    _ => return Err(format!("invalid quoted key in path: {}", path).into()),
+                };
+                segments.push(Segment::Key(key));
+                stream.byte_offset()
+            } else {
+                let close = after.find(']').ok_or_else(|| format!("missing ']' in path: {}", path))?;
+                let index = after[..close]
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("invalid index '{}' in path: {}", &after[..close], path))?;
+                segments.push(Segment::Index(index));
+                close
+            };
+            rest = after[close..]
+                .strip_prefix(']')
+                .ok_or_else(|| format!("missing ']' in path: {}", path))?;
+        } else {
+            return Err(format!("unexpected '{}' in path: {}", rest, path).into());
+        }
+    }
+    Ok(segments)
+}
+
+fn parse_key_segment<'a>(s: &'a str, segments: &mut Vec<Segment>) -> &'a str {
+    let end = s.find(['.', '[']).unwrap_or(s.len());
+    segments.push(Segment::Key(s[..end].to_string()));
+    &s[end..]
+}
+
+fn lookup<'v>(value: &'v Value, path: &str) -> CliResult<&'v Value> {
+    let mut current = value;
+    let mut walked = String::new();
+    for segment in parse_path(path)? {
+        let next = match &segment {
+            Segment::Key(key) => {
+                walked.push('.');
+                walked.push_str(key);
+                current.get(key.as_str())
+            }
+            Segment::Index(index) => {
+                walked.push_str(&format!("[{}]", index));
+                current.get(*index)
+            }
+        };
+        current = next.ok_or_else(|| format!("{} not found ({} is {})", walked, parent_path(&walked), current.type_name()))?;
+    }
+    Ok(current)
+}
+
+fn parent_path(walked: &str) -> &str {
+    match walked.rfind(['.', '[']) {
+        Some(0) | None => ".",
+        Some(index) => &walked[..index],
+    }
+}
+
+/// TON の値を serde の型だけで書き出す
+///
+/// JSON や CBOR にない型は近い型に落とすので型情報は失われる
+/// string_keys が true の場合は Object のキーを文字列にする (JSON 用)
+struct Plain<'a> {
+    value: &'a Value,
+    string_keys: bool,
+}
+
+impl<'a> Plain<'a> {
+    fn new(value: &'a Value, string_keys: bool) -> Self {
+        Self { value, string_keys }
+    }
+}
+
+impl Serialize for Plain<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.value {
+            Value::Undefined | Value::None => serializer.serialize_unit(),
+            Value::Bool(v) => serializer.serialize_bool(*v),
+            Value::Int(Int::I8(v)) => serializer.serialize_i8(*v),
+            Value::Int(Int::I16(v)) => serializer.serialize_i16(*v),
+            Value::Int(Int::I32(v)) => serializer.serialize_i32(*v),
+            Value::Int(Int::I64(v)) => serializer.serialize_i64(*v),
+            Value::UInt(UInt::U8(v)) => serializer.serialize_u8(*v),
+            Value::UInt(UInt::U16(v)) => serializer.serialize_u16(*v),
+            Value::UInt(UInt::U32(v)) => serializer.serialize_u32(*v),
+            Value::UInt(UInt::U64(v)) => serializer.serialize_u64(*v),
+            Value::Float(Float::F16(v)) => serializer.serialize_f32(v.to_f32()),
+            Value::Float(Float::F32(v)) => serializer.serialize_f32(*v),
+            Value::Float(Float::F64(v)) => serializer.serialize_f64(*v),
+            Value::String(v) => serializer.serialize_str(v),
+            Value::Bytes(v) => serializer.serialize_bytes(v),
+            Value::UUID(v) => serializer.collect_str(&v.hyphenated()),
+            Value::DateTime(v) => serializer.serialize_str(&v.to_rfc3339()),
+            Value::Timestamp(v) => serializer.serialize_i64(*v),
+            Value::Duration(v) => match v.num_nanoseconds() {
+                Some(nanos) => serializer.serialize_i64(nanos),
+                None => Err(serde::ser::Error::custom("duration is out of range for nanoseconds")),
+            },
+            Value::Array(vec) => {
+                let mut seq = serializer.serialize_seq(Some(vec.len()))?;
+                for element in vec {
+                    seq.serialize_element(&Plain::new(element, self.string_keys))?;
+                }
+                seq.end()
+            }
+            Value::Object(object) => {
+                let mut map = serializer.serialize_map(Some(object.len()))?;
+                for (key, value) in object.iter() {
+                    map.serialize_entry(&PlainKey { key, string_keys: self.string_keys }, &Plain::new(value, self.string_keys))?;
+                }
+                map.end()
+            }
+            Value::WrappedJSON(json) => json.serialize(serializer),
+            Value::Meta(inner) => Plain::new(inner, self.string_keys).serialize(serializer),
+        }
+    }
+}
+
+struct PlainKey<'a> {
+    key: &'a KeyValue,
+    string_keys: bool,
+}
+
+impl Serialize for PlainKey<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let value = Value::from(self.key.clone());
+        if !self.string_keys {
+            return Plain::new(&value, false).serialize(serializer);
+        }
+        match &value {
+            Value::String(s) => serializer.serialize_str(s),
+            Value::Int(_) | Value::UInt(_) => match value.as_i64() {
+                Some(v) => serializer.collect_str(&v),
+                None => serializer.collect_str(&value.as_u64().unwrap_or_default()),
+            },
+            Value::UUID(v) => serializer.collect_str(&v.hyphenated()),
+            Value::DateTime(v) => serializer.serialize_str(&v.to_rfc3339()),
+            // その他は TON Text の表記を使う
+            value => serializer.collect_str(value),
+        }
+    }
+}
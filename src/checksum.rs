@@ -0,0 +1,319 @@
+//! CRC32C のチェックサム
+//!
+//! 文書全体とコンテナごとにチェックサムを置いて、保存したデータのビット化けを見つける
+//! 形式は `checksum` を参照
+//! シリアライザは書いたバイト列の CRC を持ち続け、コンテナの CRC は書き終えたときに前後の CRC から求める
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+use crate::de::read_head;
+use crate::error::{Error, ErrorCode};
+use crate::raw::TonRef;
+use crate::value::prefix::{checksum, prefix, self_describe, size_prefix};
+
+/// CRC32C (Castagnoli) の多項式 (ビット反転)
+const POLY: u32 = 0x82f6_3b78;
+
+const TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ POLY } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// `X2N[k]` は x^(2^k) mod P
+const X2N: [u32; 32] = {
+    let mut table = [0u32; 32];
+    let mut p = 1u32 << 30;
+    table[0] = p;
+    let mut k = 1;
+    while k < 32 {
+        p = mult(p, p);
+        table[k] = p;
+        k += 1;
+    }
+    table
+};
+
+/// `data` の CRC32C
+pub fn crc32c(data: &[u8]) -> u32 {
+    update(0, data)
+}
+
+/// `crc` の後ろに `data` を続けた CRC32C
+pub(crate) fn update(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &byte in data {
+        crc = TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+/// `a` の後ろに `len` バイトの `b` を続けた CRC32C
+pub(crate) fn combine(a: u32, b: u32, len: u64) -> u32 {
+    mult(shift(len), a) ^ b
+}
+
+/// `a` の後ろに `len` バイトを続けた CRC32C が `ab` の場合の、後ろの `len` バイトだけの CRC32C
+pub(crate) fn suffix(ab: u32, a: u32, len: u64) -> u32 {
+    mult(shift(len), a) ^ ab
+}
+
+/// a * b mod P
+const fn mult(a: u32, mut b: u32) -> u32 {
+    let mut m = 1u32 << 31;
+    let mut p = 0;
+    loop {
+        if a & m != 0 {
+            p ^= b;
+            if a & (m - 1) == 0 {
+                return p;
+            }
+        }
+        m >>= 1;
+        b = if b & 1 != 0 { (b >> 1) ^ POLY } else { b >> 1 };
+    }
+}
+
+/// x^(8 * len) mod P
+fn shift(mut len: u64) -> u32 {
+    let mut p = 1u32 << 31;
+    let mut k = 3;
+    while len != 0 {
+        if len & 1 != 0 {
+            p = mult(X2N[k & 31], p);
+        }
+        len >>= 1;
+        k += 1;
+    }
+    p
+}
+
+/// コンテナのチェックサムの PADDING のバイト数
+pub(crate) const ENTRY_LEN: usize = checksum::BODY_LEN + 2;
+
+/// コンテナのチェックサムの PADDING
+pub(crate) fn container_entry(crc: u32) -> [u8; ENTRY_LEN] {
+    let mut entry = [0u8; ENTRY_LEN];
+    entry[..4].copy_from_slice(&crc.to_le_bytes());
+    entry[4..8].copy_from_slice(&checksum::CONTAINER);
+    entry[8] = checksum::BODY_LEN as u8;
+    entry[9] = prefix::PADDING | size_prefix::SIZE_PREFIX_1BYTE;
+    entry
+}
+
+/// `entry` がコンテナのチェックサムの PADDING なら crc を返す
+pub(crate) fn parse_container_entry(entry: &[u8]) -> Option<u32> {
+    let crc = entry.first_chunk::<4>()?;
+    (entry.len() == ENTRY_LEN && container_entry(u32::from_le_bytes(*crc)) == entry).then(|| u32::from_le_bytes(*crc))
+}
+
+/// `buf[..end]` の最後がコンテナのチェックサムの PADDING なら crc を返す
+fn entry_before(buf: &[u8], end: usize) -> Option<u32> {
+    end.checked_sub(ENTRY_LEN).and_then(|start| parse_container_entry(&buf[start..end]))
+}
+
+/// コンテナの body の最後 (後ろの索引などの PADDING を除く) にあるチェックサムの PADDING の (先頭, crc)
+pub(crate) fn find_container_entry(buf: &[u8], body_start: usize, body_end: usize) -> Option<(usize, u32)> {
+    let mut cursor = body_end;
+    while cursor > body_start {
+        match read_head(buf, cursor) {
+            Ok(head) if head.prefix() == prefix::PADDING && head.start >= body_start => {
+                if let Some(crc) = entry_before(buf, cursor) {
+                    return Some((head.start, crc));
+                }
+                cursor = head.start;
+            }
+            _ => break,
+        }
+    }
+    None
+}
+
+/// Array, Object のチェックサムの PADDING の (先頭, crc)
+fn container_checksum(value: &TonRef<'_>, buf: &[u8]) -> Option<(usize, u32)> {
+    let start = value.range().start;
+    match value.prefix() {
+        prefix::ARRAY | prefix::OBJECT => find_container_entry(buf, start, start + value.body_len()),
+        _ => None,
+    }
+}
+
+/// Array, Object にチェックサムがあれば確かめ、チェックサムがあったかを返す
+///
+/// 中のコンテナの CRC32C を組み合わせて body の CRC32C を求めるので、各バイトは 1 回だけ読む
+/// 一緒に確かめた中のコンテナのチェックサムは、head の位置を `verified` に入れる
+/// 合わない場合は、チェックサムが合わない最も内側のコンテナの範囲をエラーにする
+pub(crate) fn verify_container(buf: &[u8], value: TonRef<'_>, verified: &mut HashSet<usize>) -> Result<bool, Error> {
+    if container_checksum(&value, buf).is_none() {
+        return Ok(false);
+    }
+    let mut checked = Vec::new();
+    if value_crc(buf, value.range().end, 0, &mut checked).is_some() {
+        // 子から先に確かめるので、最初に合わないものが最も内側
+        if let Some((range, _)) = checked.iter().find(|(_, ok)| !ok) {
+            return Err(mismatch(range.clone(), range.end - 1));
+        }
+        verified.extend(checked.iter().map(|(range, _)| range.end - 1));
+        return Ok(true);
+    }
+    // 辿れない場合は body をそのまま数え直す
+    let broken = |value: &TonRef<'_>| {
+        container_checksum(value, buf).is_some_and(|(entry, crc)| crc32c(&buf[value.range().start..entry]) != crc)
+    };
+    if !broken(&value) {
+        return Ok(true);
+    }
+    let mut target = value;
+    // 壊れた子を辿れなくなったところで止める
+    while let Some(child) = target.children_rev().map_while(Result::ok).find(|child| broken(child)) {
+        target = child;
+    }
+    Err(mismatch(target.range(), target.offset()))
+}
+
+/// 子の CRC32C を組み合わせて辿るコンテナの深さの上限 (`validate` と同じ)
+const MAX_WALK_DEPTH: usize = 1024;
+
+/// `buf[..end]` の最後にある値の (先頭, CRC32C)
+///
+/// Array, Object, 表, Meta は子のうちコンテナの CRC32C だけを組み合わせ、それ以外の子はまとめて数える
+/// 途中の Array, Object にチェックサムがあれば (値の範囲, 合うか) を子から順に `checked` に集める
+/// 構造が壊れていて辿れない場合は None
+fn value_crc(buf: &[u8], end: usize, depth: usize, checked: &mut Vec<(Range<usize>, bool)>) -> Option<(usize, u32)> {
+    let head = read_head(buf, end).ok()?;
+    if !matches!(head.prefix(), prefix::ARRAY | prefix::OBJECT | prefix::TABLE | prefix::META) {
+        return Some((head.start, crc32c(&buf[head.start..head.end])));
+    }
+    if depth > MAX_WALK_DEPTH {
+        return None;
+    }
+    // 子の (先頭, 終端, コンテナの CRC32C) を後ろから集める
+    let mut children = Vec::new();
+    let mut cursor = head.head_start;
+    while cursor > head.start {
+        let child = read_head(buf, cursor).ok()?;
+        if child.start < head.start {
+            return None;
+        }
+        let crc = match child.prefix() {
+            prefix::ARRAY | prefix::OBJECT | prefix::TABLE | prefix::META => Some(value_crc(buf, cursor, depth + 1, checked)?.1),
+            _ => None,
+        };
+        children.push((child.start, cursor, crc));
+        cursor = child.start;
+    }
+    let entry = match head.prefix() {
+        prefix::ARRAY | prefix::OBJECT => find_container_entry(buf, head.start, head.head_start),
+        _ => None,
+    };
+    let mut crc = 0;
+    for &(start, end, child_crc) in children.iter().rev() {
+        if let Some((entry, expected)) = entry && entry == start {
+            checked.push((head.start..head.end, crc == expected));
+        }
+        crc = match child_crc {
+            Some(child_crc) => combine(crc, child_crc, (end - start) as u64),
+            None => update(crc, &buf[start..end]),
+        };
+    }
+    Some((head.start, update(crc, &buf[head.head_start..head.end])))
+}
+
+/// 文書のチェックサムの PADDING のバイト数
+pub(crate) const DOCUMENT_LEN: usize = checksum::BODY_LEN + 2;
+
+/// 文書のチェックサムの PADDING
+pub(crate) fn document_trailer(crc: u32) -> [u8; DOCUMENT_LEN] {
+    let mut trailer = [0u8; DOCUMENT_LEN];
+    trailer[..4].copy_from_slice(&crc.to_le_bytes());
+    trailer[4..8].copy_from_slice(&checksum::DOCUMENT);
+    trailer[8] = checksum::BODY_LEN as u8;
+    trailer[9] = prefix::PADDING | size_prefix::SIZE_PREFIX_1BYTE;
+    trailer
+}
+
+/// `buf[..end]` の最後にある文書のチェックサムの (先頭, crc)
+pub(crate) fn find_document_checksum(buf: &[u8], end: usize) -> Option<(usize, u32)> {
+    let start = end.checked_sub(DOCUMENT_LEN)?;
+    let trailer = &buf[start..end];
+    let crc = u32::from_le_bytes(*trailer.first_chunk::<4>()?);
+    (document_trailer(crc) == trailer).then_some((start, crc))
+}
+
+/// self-describe tag を除いた文書の終端
+fn tag_end(buf: &[u8]) -> usize {
+    if buf.ends_with(&self_describe::TON_V1_REV_TAG) {
+        buf.len() - self_describe::TON_V1_REV_TAG.len()
+    } else {
+        buf.len()
+    }
+}
+
+/// self-describe tag と文書のチェックサムを除いた文書の終端
+pub(crate) fn document_end(buf: &[u8]) -> usize {
+    let end = tag_end(buf);
+    match find_document_checksum(buf, end) {
+        Some((start, _)) => start,
+        None => end,
+    }
+}
+
+/// `buf` の文書のチェックサムと、`range` を含むコンテナのチェックサムを書き直す
+///
+/// RAW EDIT で `range` を書き換えた後に使う
+/// 内側のコンテナから順に書き直し、書き直した範囲を返す
+pub(crate) fn refresh(buf: &mut [u8], range: Range<usize>) -> Result<Vec<Range<usize>>, Error> {
+    let mut entries = Vec::new();
+    let end = document_end(buf);
+    let mut cursor = TonRef::new(&buf[..end])?;
+    loop {
+        let (start, body_end) = (cursor.range().start, cursor.range().start + cursor.body_len());
+        if matches!(cursor.prefix(), prefix::ARRAY | prefix::OBJECT)
+            && let Some((entry, _)) = find_container_entry(buf, start, body_end)
+            && range.end <= entry
+        {
+            entries.push((start, entry));
+        }
+        let child = cursor.children_rev().filter_map(Result::ok).find(|child| {
+            let child = child.range();
+            child.start <= range.start && range.end <= child.end
+        });
+        match child {
+            Some(child) => cursor = child,
+            None => break,
+        }
+    }
+    let mut changed = Vec::new();
+    for &(start, entry) in entries.iter().rev() {
+        let crc = crc32c(&buf[start..entry]);
+        buf[entry..entry + ENTRY_LEN].copy_from_slice(&container_entry(crc));
+        changed.push(entry..entry + 4);
+    }
+    changed.extend(refresh_document(buf));
+    Ok(changed)
+}
+
+/// `buf` に文書のチェックサムがあれば書き直し、書き直した範囲を返す
+pub(crate) fn refresh_document(buf: &mut [u8]) -> Option<Range<usize>> {
+    let (start, _) = find_document_checksum(buf, tag_end(buf))?;
+    let crc = crc32c(&buf[..start]);
+    buf[start..start + 4].copy_from_slice(&crc.to_le_bytes());
+    Some(start..start + 4)
+}
+
+/// チェックサムが合わないエラー
+#[cold]
+pub(crate) fn mismatch(range: Range<usize>, pos: usize) -> Error {
+    Error::syntax(ErrorCode::ChecksumMismatch(range), pos)
+}
@@ -0,0 +1,174 @@
+//! フィールドごとの列にまとめた表 (Table)
+//!
+//! 同じフィールドを持つ構造体のシーケンスを、フィールド名を 1 回だけ書いて列ごとに並べる
+//! body は `[column key]* rows:UInt` の形で、Object と同じく列の値の後ろに列の名前 (key) を置く
+//! column は行数と同じ数の要素を持つ Array か型付き配列 (Encoded Array) で、詰められる列は型付き配列になる
+//! 読むときは Object の Array と同じように扱う
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::Error;
+use crate::raw::TonRef;
+use crate::ser::generate_header;
+use crate::value::prefix::prefix;
+
+/// 表で書くことをシリアライザに伝える newtype の名前
+pub(crate) const COLUMNAR_TOKEN: &str = "$serde_ton::private::Columnar";
+
+/// 中身のシーケンスを表 (列ごと) で書き出すラッパー
+///
+/// 要素が全て同じ key を同じ順に持つ Object (構造体) の場合だけ表にし、それ以外は通常の Array になる
+/// 数値などの列は型付き配列で書くので、行ごとの key と head がなくなる
+/// RTON 以外のフォーマットや読み込み時は `T` と同じように扱う
+/// 全ての構造体のシーケンスを表にする場合は `SerializeOptions::columnar` を使う
+///
+/// ```ignore
+/// #[derive(Serialize, Deserialize)]
+/// struct Export {
+///     rows: Columnar<Vec<Record>>,
+/// }
+/// ```
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Columnar<T>(pub T);
+
+impl<T> Columnar<T> {
+    pub fn new(value: T) -> Self {
+        Columnar(value)
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Columnar<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Columnar<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> From<T> for Columnar<T> {
+    fn from(value: T) -> Self {
+        Columnar(value)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Columnar<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T: Serialize> Serialize for Columnar<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(COLUMNAR_TOKEN, &self.0)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Columnar<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Columnar)
+    }
+}
+
+/// 表の列を組み立てる
+///
+/// 行は単体でエンコードした Object で受け取り、value をそのまま列ごとに並べておく
+#[derive(Default)]
+pub(crate) struct TableBuilder {
+    /// エンコードした列の名前
+    keys: Vec<Vec<u8>>,
+    columns: Vec<ColumnBuilder>,
+    rows: usize,
+}
+
+#[derive(Default)]
+pub(crate) struct ColumnBuilder {
+    /// エンコードした value を前から並べたもの
+    data: Vec<u8>,
+    /// 各 value の終端
+    ends: Vec<usize>,
+}
+
+impl ColumnBuilder {
+    /// エンコードした value を並べたバイト列 (Array の body になる)
+    pub(crate) fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// value を前から順に返す
+    pub(crate) fn values(&self) -> impl Iterator<Item = &[u8]> {
+        let starts = std::iter::once(0).chain(self.ends.iter().copied());
+        starts.zip(self.ends.iter().copied()).map(|(start, end)| &self.data[start..end])
+    }
+}
+
+impl TableBuilder {
+    /// 行を追加する
+    ///
+    /// Object でない行や、最初の行と key が違う場合は false を返し、何も追加しない
+    pub(crate) fn push_row(&mut self, encoded: &[u8]) -> bool {
+        let Some(row) = encoded.len().checked_sub(1).and_then(|end| TonRef::at(encoded, end).ok()) else {
+            return false;
+        };
+        if row.range().start != 0 || !row.is_object() {
+            return false;
+        }
+        let Ok(entries) = row.entries() else {
+            return false;
+        };
+        if self.rows == 0 {
+            self.keys = entries.iter().map(|(key, _)| key.raw().to_vec()).collect();
+            self.columns = entries.iter().map(|_| ColumnBuilder::default()).collect();
+        } else if entries.len() != self.keys.len() || entries.iter().zip(&self.keys).any(|((key, _), k)| key.raw() != k.as_slice()) {
+            return false;
+        }
+        for ((_, value), column) in entries.iter().zip(&mut self.columns) {
+            column.data.extend_from_slice(value.raw());
+            column.ends.push(column.data.len());
+        }
+        self.rows += 1;
+        true
+    }
+
+    pub(crate) fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.rows == 0
+    }
+
+    /// (エンコードした列の名前, 列) を前から順に返す
+    pub(crate) fn columns(&self) -> impl Iterator<Item = (&[u8], &ColumnBuilder)> {
+        self.keys.iter().map(Vec::as_slice).zip(&self.columns)
+    }
+
+    /// 追加した行を単体の Object として順に書く
+    pub(crate) fn for_each_row(&self, mut f: impl FnMut(&[u8]) -> Result<(), Error>) -> Result<(), Error> {
+        let mut values: Vec<_> = self.columns.iter().map(ColumnBuilder::values).collect();
+        let mut buf = Vec::new();
+        for _ in 0..self.rows {
+            buf.clear();
+            for (column, key) in values.iter_mut().zip(&self.keys) {
+                buf.extend_from_slice(column.next().unwrap_or_default());
+                buf.extend_from_slice(key);
+            }
+            let (header, header_size) = generate_header(prefix::OBJECT, buf.len() as u64);
+            buf.extend_from_slice(&header[..header_size]);
+            f(&buf)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,289 @@
+//! 大きな String / Bytes / WrappedJSON の body の圧縮 (Compressed)
+//!
+//! 閾値以上の body を codec で圧縮し、展開した型と長さと一緒に Compressed として書く
+//! 形式は `compression` を参照
+//! 読むときは展開した元の型として扱う
+
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::error::{Error, ErrorCode};
+use crate::packed::MAX_DECODED_LEN;
+use crate::value::prefix::{compression, prefix, prefix_str, size_prefix};
+
+/// body の圧縮方式
+///
+/// `id` を Compressed の body に書き、読むときは同じ `id` の codec で展開する
+/// 組み込みの codec は 1 ~ 127 を使うので、アプリケーションの codec は 128 ~ 255 を使う
+/// 読む側は `ReverseDeserializer::add_codec` で同じ codec を登録する
+/// 展開のエラーは `serde::de::Error::custom` で作れる
+pub trait Codec: Send + Sync {
+    /// Compressed の body に書く番号 (0 は使えない)
+    fn id(&self) -> u8;
+
+    fn compress(&self, input: &[u8]) -> Vec<u8>;
+
+    /// `len` バイトに展開する
+    ///
+    /// 展開した結果が `len` バイトでなければエラーになる
+    fn decompress(&self, input: &[u8], len: usize) -> Result<Vec<u8>, Error>;
+}
+
+/// 組み込みの LZ 系の codec (依存なし)
+///
+/// LZ4 のブロック形式と同じ考え方で、リテラルと一致 (2 バイトの距離と長さ) の組を並べる
+/// 速さを優先するので、圧縮率は zstd などより低い
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lz;
+
+/// 一致の最小の長さ
+const MIN_MATCH: usize = 4;
+/// 一致を探すハッシュ表の大きさ (ビット数)
+const HASH_BITS: u32 = 14;
+/// 一致を探す距離の上限
+const MAX_DISTANCE: usize = 0xffff;
+/// 展開する前に確保する大きさの、圧縮したデータに対する倍率
+const EXPECTED_RATIO: usize = 8;
+
+impl Codec for Lz {
+    fn id(&self) -> u8 {
+        compression::LZ
+    }
+
+    fn compress(&self, input: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(input.len() / 2 + 16);
+        let mut table = vec![0usize; 1 << HASH_BITS];
+        let (mut anchor, mut i) = (0, 0);
+        while i + MIN_MATCH <= input.len() {
+            let seq = u32::from_le_bytes(input[i..i + MIN_MATCH].try_into().unwrap());
+            let hash = (seq.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize;
+            // 0 は空きなので位置 + 1 を入れる
+            let candidate = std::mem::replace(&mut table[hash], i + 1);
+            if let Some(candidate) = candidate.checked_sub(1)
+                && i - candidate <= MAX_DISTANCE
+                && input[candidate..candidate + MIN_MATCH] == input[i..i + MIN_MATCH]
+            {
+                let mut len = MIN_MATCH;
+                while i + len < input.len() && input[candidate + len] == input[i + len] {
+                    len += 1;
+                }
+                push_sequence(&mut out, &input[anchor..i], Some((i - candidate, len)));
+                i += len;
+                anchor = i;
+                continue;
+            }
+            i += 1;
+        }
+        push_sequence(&mut out, &input[anchor..], None);
+        out
+    }
+
+    fn decompress(&self, input: &[u8], len: usize) -> Result<Vec<u8>, Error> {
+        // 壊れた文書の長さを信用して大きく確保しないように、入力から見込める分だけ確保して足りなければ伸ばす
+        let mut out = Vec::with_capacity(len.min(input.len().saturating_mul(EXPECTED_RATIO)));
+        let mut i = 0;
+        loop {
+            let token = *input.get(i).ok_or_else(|| lz_error("LZ data ends before a sequence"))?;
+            i += 1;
+            let mut literals = (token >> 4) as usize;
+            if literals == 15 {
+                literals += read_length(input, &mut i)?;
+            }
+            let end = i.checked_add(literals).filter(|&end| end <= input.len()).ok_or_else(|| lz_error("LZ literals overflow the data"))?;
+            if out.len() + literals > len {
+                return Err(lz_error(format!("LZ data expands beyond {} bytes", len)));
+            }
+            out.extend_from_slice(&input[i..end]);
+            i = end;
+            if i == input.len() {
+                break;
+            }
+            let distance = input.get(i..i + 2).ok_or_else(|| lz_error("LZ data ends before a match distance"))?;
+            let distance = u16::from_le_bytes([distance[0], distance[1]]) as usize;
+            i += 2;
+            let mut match_len = (token & 0x0f) as usize + MIN_MATCH;
+            if token & 0x0f == 15 {
+                match_len += read_length(input, &mut i)?;
+            }
+            if distance == 0 || distance > out.len() {
+                return Err(lz_error(format!("LZ match distance {} is out of the output", distance)));
+            }
+            if out.len() + match_len > len {
+                return Err(lz_error(format!("LZ data expands beyond {} bytes", len)));
+            }
+            // 重なる一致は前から 1 バイトずつ写す
+            let start = out.len() - distance;
+            for k in start..start + match_len {
+                out.push(out[k]);
+            }
+        }
+        if out.len() != len {
+            return Err(lz_error(format!("LZ data expands to {} bytes instead of {}", out.len(), len)));
+        }
+        Ok(out)
+    }
+}
+
+/// token, リテラル, 距離, 長さの続きを書く
+fn push_sequence(out: &mut Vec<u8>, literals: &[u8], found: Option<(usize, usize)>) {
+    let match_len = found.map_or(0, |(_, len)| len - MIN_MATCH);
+    out.push(((literals.len().min(15) as u8) << 4) | match_len.min(15) as u8);
+    if literals.len() >= 15 {
+        push_length(out, literals.len() - 15);
+    }
+    out.extend_from_slice(literals);
+    if let Some((distance, _)) = found {
+        out.extend_from_slice(&(distance as u16).to_le_bytes());
+        if match_len >= 15 {
+            push_length(out, match_len - 15);
+        }
+    }
+}
+
+/// 15 を超えた長さは 255 のバイトを続けて書く
+fn push_length(out: &mut Vec<u8>, mut len: usize) {
+    while len >= 255 {
+        out.push(255);
+        len -= 255;
+    }
+    out.push(len as u8);
+}
+
+fn read_length(input: &[u8], i: &mut usize) -> Result<usize, Error> {
+    let mut len = 0usize;
+    loop {
+        let byte = *input.get(*i).ok_or_else(|| lz_error("LZ data ends in a length"))?;
+        *i += 1;
+        len += byte as usize;
+        if byte != 255 {
+            return Ok(len);
+        }
+    }
+}
+
+#[cold]
+fn lz_error(msg: impl Into<String>) -> Error {
+    Error::make(ErrorCode::Other(msg.into()), None)
+}
+
+/// シリアライザの圧縮の設定
+///
+/// `threshold` バイト以上の String / Bytes / WrappedJSON の body を圧縮し、小さくなった場合だけ Compressed で書く
+#[derive(Clone)]
+pub struct Compression {
+    codec: Arc<dyn Codec>,
+    threshold: usize,
+}
+
+impl Compression {
+    /// 組み込みの `Lz` で圧縮する
+    pub fn new(threshold: usize) -> Self {
+        Self::with_codec(Lz, threshold)
+    }
+
+    /// `codec` で圧縮する
+    pub fn with_codec(codec: impl Codec + 'static, threshold: usize) -> Self {
+        Self { codec: Arc::new(codec), threshold }
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// `body` を圧縮した Compressed の body
+    ///
+    /// 閾値より小さいか、圧縮しても小さくならなければ None
+    pub(crate) fn compress(&self, head: u8, body: &[u8]) -> Option<Vec<u8>> {
+        if body.len() < self.threshold.max(1) {
+            return None;
+        }
+        let mut out = self.codec.compress(body);
+        if out.len() + compression::TRAILER_LEN >= body.len() {
+            return None;
+        }
+        out.extend_from_slice(&(body.len() as u64).to_le_bytes());
+        out.push(self.codec.id());
+        out.push(head);
+        Some(out)
+    }
+}
+
+impl Default for Compression {
+    /// 組み込みの `Lz` で 256 バイト以上の body を圧縮する
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+impl fmt::Debug for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Compression").field("codec", &self.codec.id()).field("threshold", &self.threshold).finish()
+    }
+}
+
+/// Compressed の body
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Compressed<'a> {
+    pub(crate) data: &'a [u8],
+    /// 展開したバイト数
+    pub(crate) len: usize,
+    pub(crate) codec: u8,
+    /// 展開したデータを body に持つ型
+    pub(crate) head: u8,
+}
+
+impl<'a> Compressed<'a> {
+    /// Compressed の body を読む
+    ///
+    /// `pos` はエラーに付ける位置 (Compressed の head)
+    pub(crate) fn parse(body: &'a [u8], pos: usize) -> Result<Self, Error> {
+        let Some(split) = body.len().checked_sub(compression::TRAILER_LEN) else {
+            return Err(error("compressed body is shorter than its trailer", pos));
+        };
+        let (data, trailer) = body.split_at(split);
+        let len = u64::from_le_bytes(trailer[..8].try_into().unwrap());
+        let (codec, head) = (trailer[8], trailer[9]);
+        if !matches!(head, prefix::STRING | prefix::BYTES | prefix::WRAPPED_JSON) {
+            return Err(error(format!("compressed body cannot hold head 0x{:02x}", head), pos));
+        }
+        let len = usize::try_from(len).ok().filter(|&len| len <= MAX_DECODED_LEN);
+        let Some(len) = len else {
+            return Err(error(format!("compressed body expands beyond {} bytes", MAX_DECODED_LEN), pos));
+        };
+        Ok(Self { data, len, codec, head })
+    }
+
+    /// 展開する
+    ///
+    /// 組み込みの codec の他に `codecs` から同じ番号のものを探す
+    pub(crate) fn decompress(&self, codecs: &[Arc<dyn Codec>], pos: usize) -> Result<Vec<u8>, Error> {
+        let res = match codecs.iter().find(|codec| codec.id() == self.codec) {
+            Some(codec) => codec.decompress(self.data, self.len),
+            None if self.codec == compression::LZ => Lz.decompress(self.data, self.len),
+            None => return Err(error(format!("unknown codec {}", self.codec), pos)),
+        };
+        let out = res.map_err(|err| err.fix_head(pos, prefix_str::COMPRESSED))?;
+        if out.len() != self.len {
+            return Err(error(format!("compressed body expands to {} bytes instead of {}", out.len(), self.len), pos));
+        }
+        Ok(out)
+    }
+}
+
+/// String / Bytes / WrappedJSON の body
+///
+/// Compressed は組み込みの codec で展開する
+pub(crate) fn plain_body<'a>(head: u8, body: &'a [u8], pos: usize) -> Result<(u8, Cow<'a, [u8]>), Error> {
+    let head = head & !size_prefix::MASK;
+    if head != prefix::COMPRESSED {
+        return Ok((head, Cow::Borrowed(body)));
+    }
+    let compressed = Compressed::parse(body, pos)?;
+    Ok((compressed.head, Cow::Owned(compressed.decompress(&[], pos)?)))
+}
+
+#[cold]
+fn error(msg: impl Into<String>, pos: usize) -> Error {
+    Error::syntax(ErrorCode::Other(msg.into()), pos)
+}
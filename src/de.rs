@@ -1,8 +1,8 @@
-use std::{fs::File, io::{self, Read, Seek, SeekFrom}};
+use std::{fs::File, io, marker::PhantomData, ops::Range, sync::Arc};
 
-use serde::{de::Visitor, Deserialize, Deserializer};
+use serde::{de::{self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess, Visitor}, Deserialize, Deserializer};
 
-use crate::{error::{Error, ErrorCode}, traits::reader::{IOReader, Reader, SliceReader, VecReader}, value::prefix::{prefix, size_prefix}};
+use crate::{checksum::{combine, crc32c, document_trailer, mismatch, parse_container_entry, DOCUMENT_LEN, ENTRY_LEN}, compress::{Codec, Compressed}, error::{Error, ErrorCode, PathSegment}, intern::{reference_index, DocumentKeys, KeyDictionary, KeyTable}, packed::{decode as decode_array, element_width, TypedArray}, raw::{refers_outside, RAW_TOKEN}, ser::generate_header, traits::reader::{BorrowReader, PositionalReader, Reader, ReverseBufReader, SliceReader, VecReader}, value::{de::{ExtendedAccess, VALUE_TOKEN}, prefix::{container_index, key_dictionary, prefix, prefix_str, self_describe, size_prefix}, value::Value}};
 
 
 
@@ -13,19 +13,47 @@ use crate::{error::{Error, ErrorCode}, traits::reader::{IOReader, Reader, SliceR
 /// バッファリングは外部ですべき - 柔軟性
 /// seekトレイとが実装されてる標準型が少ないのが問題
 /// 楽に使うために変換できるように
-/// 
+///
 pub struct ReverseDeserializer<R>
 where R: Reader,
 {
     reader: R,
     deep: u64,
+    /// 次の値を Value 向けの拡張表現で渡すか
+    extended: bool,
+    /// 表 (Table) から読む列の名前 (None は全ての列)
+    projection: Option<Vec<String>>,
+    /// KEYREF を引く key の辞書
+    keys: Option<KeyTable>,
+    /// REF で読んだ参照先の合計バイト数
+    referenced: u64,
+    /// `referenced` の上限
+    reference_limit: u64,
+    /// Compressed を展開する codec (組み込みの codec 以外)
+    codecs: Vec<Arc<dyn Codec>>,
+    /// 文書のチェックサムの位置と値 (`verify_checksum` で確かめる)
+    document_checksum: Option<(u64, u32)>,
+    /// コンテナのチェックサムで確かめた範囲 (中のコンテナは確かめ直さない)
+    verified: Option<Range<u64>>,
 }
 
-impl<'a> ReverseDeserializer<SliceReader<'a>> 
+/// 合わないチェックサムを探すときに潜るコンテナの深さの上限
+const MAX_MISMATCH_DEPTH: usize = 128;
+
+/// REF を展開して値を取り出すときに潜るコンテナの深さの上限 (`validate` と同じ)
+const MAX_CAPTURE_DEPTH: usize = 1024;
+
+/// 値を読むときに潜るコンテナの深さの上限 (`validate` と同じ)
+const MAX_DEPTH: u64 = 1024;
+
+/// REF で読む参照先の合計バイト数の既定の上限
+pub(crate) const DEFAULT_REFERENCE_LIMIT: u64 = 256 << 20;
+
+impl<'a> ReverseDeserializer<SliceReader<'a>>
 {
     pub fn from_slice(slice: &'a [u8]) -> Result<Self, io::Error> {
         let reader = SliceReader::new(slice);
-        Ok(Self { reader, deep: 0 })
+        Self::new(reader)
     }
 
     pub fn into_inner(self) -> &'a [u8] {
@@ -33,24 +61,28 @@ impl<'a> ReverseDeserializer<SliceReader<'a>>
     }
 }
 
-impl<'a> ReverseDeserializer<VecReader<'a>> 
+impl<'a> ReverseDeserializer<VecReader<'a>>
 {
     pub fn from_vec(vec: &'a Vec<u8>) -> Result<Self, io::Error> {
         let reader = VecReader::new(vec);
-        Ok(Self { reader, deep: 0 })
+        Self::new(reader)
     }
 
     pub fn into_inner(self) -> &'a Vec<u8> {
         self.reader.into_inner()
     }
-    
+
 }
 
-impl ReverseDeserializer<IOReader<File>> 
+impl ReverseDeserializer<ReverseBufReader<File>>
 {
-    pub fn from_file(file: File) -> Result<Self,io:: Error> {
-        let reader = IOReader::new(file);
-        Ok(Self { reader, deep: 0 })
+    /// ファイルの終端から読み込むデシリアライザを作る
+    ///
+    /// ブロック単位でバッファリングする `ReverseBufReader` を使う
+    /// バッファなしで読む場合は `ReverseDeserializer::new(IOReader::new(file))`
+    pub fn from_file(file: File) -> Result<Self, io::Error> {
+        let reader = ReverseBufReader::new(file)?;
+        Self::new(reader)
     }
 
     pub fn into_inner(self) -> File {
@@ -58,30 +90,310 @@ impl ReverseDeserializer<IOReader<File>>
     }
 }
 
+impl<'a> ReverseDeserializer<ReverseBufReader<PositionalReader<'a>>>
+{
+    /// 共有しているファイルの `range` にある文書を読むデシリアライザを作る
+    ///
+    /// ファイルのシーク位置を使わないので、1 つの `File` から複数のスレッドで同時に読める
+    /// (Windows ではファイルのシーク位置が動く。`PositionalReader` を参照)
+    pub fn from_shared_file(file: &'a File, range: Range<u64>) -> Result<Self, io::Error> {
+        let capacity = (range.end.saturating_sub(range.start) as usize).min(ReverseBufReader::<PositionalReader>::DEFAULT_CAPACITY);
+        let reader = ReverseBufReader::with_capacity(capacity, PositionalReader::with_range(file, range))?;
+        Self::new(reader)
+    }
+}
+
 impl<R> ReverseDeserializer<R>
 where R: Reader,
 {
-    pub fn new(reader: R) -> Result<Self, io::Error> {
-        Ok(Self { reader, deep: 0 })
+    /// 終端から読み込むデシリアライザを作る
+    ///
+    /// 終端に self-describe tag があれば読み飛ばす
+    pub fn new(mut reader: R) -> Result<Self, io::Error> {
+        let end = reader.seek(io::SeekFrom::End(0))?;
+        let tag_len = self_describe::TON_V1_REV_TAG.len() as u64;
+        if end >= tag_len {
+            let mut tag = [0u8; 4];
+            reader.read_prev(&mut tag)?;
+            if tag != self_describe::TON_V1_REV_TAG {
+                reader.seek(io::SeekFrom::End(0))?;
+            }
+        }
+        let mut de = Self {
+            reader,
+            deep: 0,
+            extended: false,
+            projection: None,
+            keys: None,
+            referenced: 0,
+            reference_limit: DEFAULT_REFERENCE_LIMIT,
+            codecs: Vec::new(),
+            document_checksum: None,
+            verified: None,
+        };
+        de.skip_document_checksum().map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        de.read_key_dictionary().map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Ok(de)
     }
 
-    fn now_pos(&mut self) -> Result<u64, io::Error> {
-        self.reader.stream_position()
+    /// ルートの後ろに文書のチェックサムがあれば覚えておき、シーク位置をチェックサムの前に移動する
+    ///
+    /// 文書全体を読むのは `verify_checksum` を呼んだ場合だけ
+    fn skip_document_checksum(&mut self) -> Result<(), Error> {
+        let end = self.now_pos()?;
+        let mut trailer = [0u8; DOCUMENT_LEN];
+        if end < DOCUMENT_LEN as u64 || self.reader.read_prev(&mut trailer).is_err() {
+            self.reader.seek(io::SeekFrom::Start(end))?;
+            return Ok(());
+        }
+        let crc = u32::from_le_bytes(trailer[..4].try_into().unwrap());
+        if trailer != document_trailer(crc) {
+            self.reader.seek(io::SeekFrom::Start(end))?;
+            return Ok(());
+        }
+        let start = end - DOCUMENT_LEN as u64;
+        self.document_checksum = Some((start, crc));
+        self.reader.seek(io::SeekFrom::Start(start))?;
+        Ok(())
     }
 
-    fn get_size(reader: &mut R) -> io::Result<u64> {
-        let current = reader.stream_position()?;
-        let end = reader.seek(io::SeekFrom::End(0))?;
-        reader.seek(io::SeekFrom::Start(current))?;
-        Ok(end)
+    /// 文書のチェックサムを確かめる
+    ///
+    /// 文書全体を読むので、`new` では確かめない
+    /// 合わなければすぐに ChecksumMismatch エラーを返す
+    /// チェックサムのない文書では false を返す
+    /// シーク位置は変わらない
+    pub fn verify_checksum(&mut self) -> Result<bool, Error> {
+        let Some((start, crc)) = self.document_checksum else {
+            return Ok(false);
+        };
+        let pos = self.now_pos()?;
+        let found = self.checksum(0, start)?;
+        self.reader.seek(io::SeekFrom::Start(pos))?;
+        if found != crc {
+            return Err(mismatch(0..start as usize, start as usize + DOCUMENT_LEN - 1));
+        }
+        Ok(true)
     }
 
-    fn next(&mut self) -> Result<u8, io::Error> {
-        if let Some(i) = self.reader.peek()? {
-            Ok(i)
-        } else {
-            Err(io::Error::new(io::ErrorKind::UnexpectedEof, "No more data to read"))
+    /// `start..end` の CRC32C
+    ///
+    /// 後ろから少しずつ読むので、メモリは文書の大きさによらない
+    fn checksum(&mut self, start: u64, end: u64) -> Result<u32, Error> {
+        const CHUNK: u64 = 64 << 10;
+        let mut buf = vec![0u8; end.saturating_sub(start).min(CHUNK) as usize];
+        self.reader.seek(io::SeekFrom::Start(end))?;
+        let (mut crc, mut len, mut cursor) = (0, 0, end);
+        while cursor > start {
+            let chunk = &mut buf[..(cursor - start).min(CHUNK) as usize];
+            self.reader.read_prev(chunk)?;
+            crc = combine(crc32c(chunk), crc, len);
+            len += chunk.len() as u64;
+            cursor -= chunk.len() as u64;
+        }
+        Ok(crc)
+    }
+
+    /// コンテナの body の最後にチェックサムがあれば確かめる
+    ///
+    /// 確かめた範囲の中のコンテナは確かめ直さないので、各バイトは 1 回だけ読む
+    /// 合わない場合は、中のコンテナから合わない一番内側のものを探してその範囲を返す
+    /// シーク位置は body の終端にある必要があり、シーク位置は変わらない
+    fn verify_container(&mut self, head: u8, len: u64) -> Result<(), Error> {
+        let end = self.now_pos()?;
+        let Some(start) = end.checked_sub(len) else {
+            return Ok(());
+        };
+        if self.verified.as_ref().is_some_and(|verified| verified.start <= start && end <= verified.end) {
+            return Ok(());
+        }
+        match self.container_checksum(start, end)? {
+            Some(true) => self.verified = Some(start..end),
+            Some(false) => {
+                let head_end = end + (1 << (head & size_prefix::MASK)) + 1;
+                let range = match self.find_mismatch(head_end, 0)? {
+                    Some(range) => range,
+                    None => start as usize..head_end as usize,
+                };
+                return Err(mismatch(range.clone(), range.end - 1));
+            }
+            None => {}
+        }
+        self.reader.seek(io::SeekFrom::Start(end))?;
+        Ok(())
+    }
+
+    /// `start..end` の body の最後にあるチェックサムが合うか
+    ///
+    /// チェックサムがなければ None
+    /// シーク位置は body の終端にある必要があり、戻ると body の終端になる
+    fn container_checksum(&mut self, start: u64, end: u64) -> Result<Option<bool>, Error> {
+        // 索引の Padding を読み飛ばしてチェックサムの Padding を探す
+        let mut cursor = end;
+        let mut crc = None;
+        while cursor > start && self.peek_head()? & !size_prefix::MASK == prefix::PADDING {
+            if cursor >= start + ENTRY_LEN as u64 && let Some(found) = self.checksum_entry()? {
+                crc = Some(found);
+                break;
+            }
+            self.skip_value()?;
+            cursor = self.now_pos()?;
+        }
+        let res = match crc {
+            Some(crc) => Some(self.checksum(start, cursor - ENTRY_LEN as u64)? == crc),
+            None => None,
+        };
+        self.reader.seek(io::SeekFrom::Start(end))?;
+        Ok(res)
+    }
+
+    /// `end` で終わる値の中で、チェックサムの合わない一番内側のコンテナの範囲を探す
+    ///
+    /// 壊れた文書でだけ呼ぶので、同じバイトを何度読んでもよい
+    fn find_mismatch(&mut self, end: u64, depth: usize) -> Result<Option<Range<usize>>, Error> {
+        self.reader.seek(io::SeekFrom::Start(end))?;
+        let head = self.prev()?;
+        let container = matches!(head & !size_prefix::MASK, prefix::ARRAY | prefix::OBJECT | prefix::TABLE | prefix::META);
+        if !container || body_kind(head) != BodyKind::Sized || depth >= MAX_MISMATCH_DEPTH {
+            return Ok(None);
+        }
+        let len = self.get_size(head)?;
+        let body_end = self.now_pos()?;
+        let Some(start) = body_end.checked_sub(len) else {
+            return Ok(None);
+        };
+        let mut cursor = body_end;
+        while cursor > start {
+            if let Some(range) = self.find_mismatch(cursor, depth + 1)? {
+                return Ok(Some(range));
+            }
+            self.reader.seek(io::SeekFrom::Start(cursor))?;
+            self.skip_value()?;
+            cursor = self.now_pos()?;
+        }
+        if matches!(head & !size_prefix::MASK, prefix::ARRAY | prefix::OBJECT) {
+            self.reader.seek(io::SeekFrom::Start(body_end))?;
+            if self.container_checksum(start, body_end)? == Some(false) {
+                return Ok(Some(start as usize..end as usize));
+            }
         }
+        Ok(None)
+    }
+
+    /// シーク位置の直前がコンテナのチェックサムの PADDING なら crc を返す
+    ///
+    /// シーク位置は変わらない
+    fn checksum_entry(&mut self) -> Result<Option<u32>, Error> {
+        let end = self.now_pos()?;
+        let mut entry = [0u8; ENTRY_LEN];
+        if end < ENTRY_LEN as u64 || self.peek_head()? != prefix::PADDING | size_prefix::SIZE_PREFIX_1BYTE {
+            return Ok(None);
+        }
+        self.reader.read_prev(&mut entry)?;
+        self.reader.seek(io::SeekFrom::Start(end))?;
+        Ok(parse_container_entry(&entry))
+    }
+
+    /// ルートの後ろに key の辞書があれば読み込み、シーク位置を辞書の前に移動する
+    fn read_key_dictionary(&mut self) -> Result<(), Error> {
+        let end = self.now_pos()?;
+        if end == 0 || self.peek_head()? & !size_prefix::MASK != prefix::PADDING {
+            return Ok(());
+        }
+        let head = self.prev()?;
+        let len = self.get_size(head).unwrap_or_default();
+        let body_end = self.now_pos()?;
+        let mut magic = [0u8; 4];
+        if len < key_dictionary::TRAILER_LEN as u64 || len > body_end || self.reader.read_prev(&mut magic).is_err() || magic != key_dictionary::MAGIC {
+            self.reader.seek(io::SeekFrom::Start(end))?;
+            return Ok(());
+        }
+        self.reader.seek(io::SeekFrom::Start(body_end))?;
+        let body = self.read_body(len)?;
+        let pos = end as usize - 1;
+        if let Some(keys) = DocumentKeys::parse(&body, pos)? {
+            self.keys = Some(KeyTable::from_document(&keys, pos)?);
+        }
+        Ok(())
+    }
+
+    /// 共有した key の辞書を使う
+    ///
+    /// `SerializeOptions::key_dictionary` で書いた文書を読むときに、書いたときと同じ辞書を渡す
+    /// 文書が違う辞書で書かれていればエラー
+    pub fn use_key_dictionary(&mut self, dictionary: &KeyDictionary) -> Result<(), Error> {
+        match &mut self.keys {
+            Some(keys) => keys.set_external(dictionary),
+            None => {
+                self.keys = Some(KeyTable::external_only(dictionary));
+                Ok(())
+            }
+        }
+    }
+
+    /// REF で読む参照先の合計バイト数の上限を設定する (既定は 256 MiB)
+    ///
+    /// 参照先の中の REF も数えるので、参照を重ねて小さな文書を巨大な値に展開させる入力を止められる
+    pub fn set_reference_limit(&mut self, bytes: u64) {
+        self.reference_limit = bytes;
+    }
+
+    /// Compressed を展開する codec を登録する
+    ///
+    /// 組み込みの `Lz` は登録しなくても使える
+    /// 同じ番号の codec を登録した場合は後から登録したものを使う
+    pub fn add_codec(&mut self, codec: impl Codec + 'static) {
+        self.codecs.insert(0, Arc::new(codec));
+    }
+
+    /// KEYREF を引く辞書を設定する (文書の一部だけを読む場合)
+    pub(crate) fn set_keys(&mut self, keys: KeyTable) {
+        self.keys = Some(keys);
+    }
+
+    /// 表 (Table) から読む列を `columns` の名前に絞る
+    ///
+    /// 他の列は head だけを見て飛ばし、body を読まない
+    /// 絞った列にないフィールドは `Option` や `#[serde(default)]` でなければ missing field エラーになる
+    pub fn select_columns(&mut self, columns: &[&str]) {
+        self.projection = Some(columns.iter().map(|column| column.to_string()).collect());
+    }
+
+    /// ルートの値を読んだ後に、入力が全て使われたか確かめる
+    ///
+    /// ルートの値より前に Padding 以外のデータが残っていれば TrailingData エラー
+    /// 文書のチェックサムは確かめない (`verify_checksum` を使う)
+    pub fn end(&mut self) -> Result<(), Error> {
+        let remaining = self.remaining()?;
+        if remaining > 0 {
+            return Err(Error::new(ErrorCode::TrailingData(remaining as usize), remaining as usize - 1));
+        }
+        Ok(())
+    }
+
+    /// `end` で確かめてから reader を返す
+    pub fn finish(mut self) -> Result<R, Error> {
+        self.end()?;
+        Ok(self.reader)
+    }
+
+    /// 先頭側に残っている読んでいないバイト数
+    ///
+    /// ルートの値の直前にある Padding は読み飛ばす
+    fn remaining(&mut self) -> Result<u64, Error> {
+        let mut pos = self.now_pos()?;
+        while pos > 0 {
+            if self.peek_head()? & !size_prefix::MASK != prefix::PADDING || self.skip_value().is_err() {
+                self.reader.seek(io::SeekFrom::Start(pos))?;
+                break;
+            }
+            pos = self.now_pos()?;
+        }
+        Ok(pos)
+    }
+
+    fn now_pos(&mut self) -> Result<u64, io::Error> {
+        self.reader.stream_position()
     }
 
     fn prev(&mut self) -> Result<u8, io::Error> {
@@ -92,16 +404,26 @@ where R: Reader,
         }
     }
 
-    fn peek(&mut self) -> Result<u8, io::Error> {
-        if let Some(i) = self.reader.peek()? {
-            Ok(i)
-        } else {
-            Err(io::Error::new(io::ErrorKind::UnexpectedEof, "No more data to read"))
+    /// コンテナの中に入ってネストを 1 つ深くする
+    ///
+    /// `pos` はコンテナの head の位置で、上限を超える場合はそこのエラー
+    fn enter(&mut self, pos: u64) -> Result<(), Error> {
+        if self.deep > MAX_DEPTH {
+            return Err(Error::new(ErrorCode::Other(format!("nesting is deeper than {}", MAX_DEPTH)), pos as usize));
         }
+        self.deep += 1;
+        Ok(())
+    }
+
+    /// 次に読む head を読み込み、シーク位置を戻します
+    fn peek_head(&mut self) -> Result<u8, io::Error> {
+        let head = self.prev()?;
+        self.reader.seek(io::SeekFrom::Current(1))?;
+        Ok(head)
     }
 }
 
-impl<'de, R> ReverseDeserializer<R>
+impl<R> ReverseDeserializer<R>
 where
     R: Reader,
 {
@@ -111,24 +433,276 @@ where
     }
 
     fn get_size_16(&mut self) -> Result<u16, Error> {
-        self.reader.seek(io::SeekFrom::Current(-2))?; // 2 byte戻す
-        let size = self.reader.read_u16()?;
+        let size = self.reader.prev_u16()?;
         Ok(size)
     }
 
     fn get_size_32(&mut self) -> Result<u32, Error> {
-        self.reader.seek(io::SeekFrom::Current(-4))?; // 4 byte戻す
-        let size = self.reader.read_u32()?;
+        let size = self.reader.prev_u32()?;
         Ok(size)
     }
 
     fn get_size_64(&mut self) -> Result<u64, Error> {
-        self.reader.seek(io::SeekFrom::Current(-8))?; // 8 byte戻す
-        let size = self.reader.read_u64()?;
+        let size = self.reader.prev_u64()?;
         Ok(size)
     }
 
+    /// head の size prefix に従ってデータ長を読み込む
+    fn get_size(&mut self, head: u8) -> Result<u64, Error> {
+        match head & size_prefix::MASK {
+            size_prefix::SIZE_PREFIX_1BYTE => self.get_size_8().map(|v| v as u64),
+            size_prefix::SIZE_PREFIX_2BYTE => self.get_size_16().map(|v| v as u64),
+            size_prefix::SIZE_PREFIX_4BYTE => self.get_size_32().map(|v| v as u64),
+            _ => self.get_size_64(),
+        }
+    }
+
+    /// シーク位置の直前にある `len` バイトの body を読み込む
+    fn read_body(&mut self, len: u64) -> Result<Vec<u8>, Error> {
+        let pos = self.now_pos()?;
+        if len > pos {
+            return Err(Error::new(ErrorCode::Eof("body length exceeds the beginning of the data".to_string()), pos as usize));
+        }
+        let mut buf = vec![0u8; len as usize];
+        self.reader.read_prev(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn read_string(&mut self, len: u64) -> Result<String, Error> {
+        let buf = self.read_body(len)?;
+        let pos = self.now_pos()?;
+        String::from_utf8(buf).map_err(|_| Error::new(ErrorCode::Other("invalid UTF-8 string".to_string()), pos as usize))
+    }
+
+    /// 値を 1 つ読み飛ばし、シーク位置を値の先頭に移動する
+    fn skip_value(&mut self) -> Result<(), Error> {
+        let head = self.prev()?;
+        let len = match body_kind(head) {
+            BodyKind::Fixed(len) => len,
+            BodyKind::Sized => self.get_size(head)?,
+            BodyKind::Invalid => {
+                let pos = self.now_pos()?;
+                return Err(Error::new(ErrorCode::UnknownPrefix(head), pos as usize));
+            }
+        };
+        let pos = self.now_pos()?;
+        if len > pos {
+            return Err(Error::new(ErrorCode::Eof("body length exceeds the beginning of the data".to_string()), pos as usize));
+        }
+        self.reader.seek(io::SeekFrom::Start(pos - len))?;
+        Ok(())
+    }
+
+    /// コンテナの body に含まれる子要素の終端位置を前から順に集める
+    ///
+    /// シーク位置はコンテナ body の終端にある必要がある
+    /// 戻るとシーク位置は body の先頭になる
+    /// PADDING (コンテナのチェックサムを含む) は読み飛ばす
+    /// keep_meta が false の場合は META も読み飛ばす
+    fn scan_children(&mut self, len: u64, keep_meta: bool) -> Result<Vec<u64>, Error> {
+        let end = self.now_pos()?;
+        if len > end {
+            return Err(Error::new(ErrorCode::Eof("container length exceeds the beginning of the data".to_string()), end as usize));
+        }
+        let start = end - len;
+        let mut ends = Vec::new();
+        let mut cursor = end;
+        while cursor > start {
+            let head = self.peek_head()?;
+            let skip = match head & !size_prefix::MASK {
+                prefix::PADDING => true,
+                prefix::META => !keep_meta,
+                _ => false,
+            };
+            if !skip {
+                ends.push(cursor);
+            }
+            self.skip_value()?;
+            cursor = self.now_pos()?;
+            if cursor < start {
+                return Err(Error::new(ErrorCode::Other("child value overflows its container".to_string()), cursor as usize));
+            }
+        }
+        ends.reverse();
+        Ok(ends)
+    }
+
+    /// ルートの Array の要素を 1 つずつデシリアライズするイテレータを作る
+    ///
+    /// 要素は格納順 (最後の要素から先頭へ) に返す
+    /// Array 全体を読み込まないので、メモリの使用量は要素 1 つ分で済む
+    pub fn iter_array<T>(&mut self) -> Result<ArrayIter<'_, R, T>, Error> {
+        self.iter_array_ordered(ArrayOrder::Reverse)
+    }
+
+    /// 順序を指定して `iter_array` を作る
+    ///
+    /// `ArrayOrder::Forward` はオフセット索引から要素の位置をその都度読むので、メモリの使用量は変わらない
+    /// 索引のない Array は前から辿れないのでエラーになる (`SerializeOptions::index` で索引を付ける)
+    pub fn iter_array_ordered<T>(&mut self, order: ArrayOrder) -> Result<ArrayIter<'_, R, T>, Error> {
+        // ルートの後ろにある Padding を読み飛ばす
+        let head = loop {
+            let head = self.peek_head()?;
+            if head & !size_prefix::MASK != prefix::PADDING {
+                break head;
+            }
+            self.skip_value()?;
+        };
+        let end = self.now_pos()?;
+        if head & !size_prefix::MASK != prefix::ARRAY {
+            return Err(Error::new(
+                ErrorCode::InvalidType { expected: "an array".to_string(), found: type_name(head).to_string() },
+                end as usize - 1,
+            ));
+        }
+        self.prev()?;
+        let len = self.get_size(head)?;
+        let body_end = self.now_pos()?;
+        if len > body_end {
+            return Err(Error::new(ErrorCode::Eof("body length exceeds the beginning of the data".to_string()), body_end as usize));
+        }
+        let start = body_end - len;
+        self.verify_container(head, len)?;
+        let positions = match order {
+            ArrayOrder::Reverse => Positions::Back(body_end),
+            ArrayOrder::Forward => match self.read_index(start, body_end)? {
+                Some(positions) => positions,
+                None => {
+                    return Err(Error::new(
+                        ErrorCode::Other("forward iteration needs an array with a container index".to_string()),
+                        end as usize - 1,
+                    ));
+                }
+            },
+        };
+        Ok(ArrayIter {
+            de: self,
+            start,
+            positions,
+            index: 0,
+            done: false,
+            _marker: PhantomData,
+        })
+    }
+
+    /// コンテナの body の最後にあるオフセット索引の trailer だけを読む
+    fn read_index(&mut self, start: u64, body_end: u64) -> Result<Option<Positions>, Error> {
+        if body_end == start {
+            return Ok(None);
+        }
+        self.reader.seek(io::SeekFrom::Start(body_end))?;
+        let head = self.peek_head()?;
+        if head & !size_prefix::MASK != prefix::PADDING || body_kind(head) != BodyKind::Sized {
+            return Ok(None);
+        }
+        self.prev()?;
+        let len = self.get_size(head)?;
+        let pad_end = self.now_pos()?;
+        if len < container_index::TRAILER_LEN as u64 || pad_end < start + len {
+            return Ok(None);
+        }
+        let mut trailer = [0u8; container_index::TRAILER_LEN];
+        self.reader.read_prev(&mut trailer)?;
+        if trailer[10..] != container_index::MAGIC {
+            return Ok(None);
+        }
+        let count = u64::from_le_bytes(trailer[..8].try_into().unwrap());
+        let width = trailer[8];
+        let table = pad_end - len;
+        if !matches!(width, 4 | 8) || count.checked_mul(width as u64) != Some(len - container_index::TRAILER_LEN as u64) {
+            return Ok(None);
+        }
+        Ok(Some(Positions::Index { table, width, count: count as usize, limit: table }))
+    }
+
+}
+
+impl<'de, R> ReverseDeserializer<R>
+where
+    R: BorrowReader<'de>,
+{
+    /// 次の値が REF なら参照先の終端に移動し、参照先を読み終えた後に戻る位置 (REF の先頭) を返す
+    fn enter_reference(&mut self) -> Result<Option<u64>, Error> {
+        let head = self.peek_head()?;
+        if head & !size_prefix::MASK != prefix::REF {
+            return Ok(None);
+        }
+        let pos = self.now_pos()? as usize - 1;
+        self.prev()?;
+        let body = self.read_body(1 << (head & size_prefix::MASK))?;
+        let back = self.now_pos()?;
+        let target = reference_index(&body);
+        if target >= back {
+            return Err(Error::new(ErrorCode::Other(format!("reference to {} does not point before itself", target)), pos));
+        }
+        self.reader.seek(io::SeekFrom::Start(target + 1))?;
+        self.skip_value()?;
+        self.referenced = self.referenced.saturating_add(target + 1 - self.now_pos()?);
+        if self.referenced > self.reference_limit {
+            return Err(Error::new(ErrorCode::Other(format!("references expand beyond {} bytes", self.reference_limit)), pos));
+        }
+        self.reader.seek(io::SeekFrom::Start(target + 1))?;
+        Ok(Some(back))
+    }
+
+    /// 次の値が REF なら参照先を `read` で読み、シーク位置を REF の先頭に移動する
+    ///
+    /// REF でなければそのまま `read` で読む
+    fn read_reference<T>(&mut self, read: impl FnOnce(&mut Self) -> Result<T, Error>) -> Result<T, Error> {
+        match self.enter_reference()? {
+            Some(back) => {
+                let res = read(self);
+                self.reader.seek(io::SeekFrom::Start(back))?;
+                res
+            }
+            None => read(self),
+        }
+    }
+
+    /// 位置の決まっていないエラーに `end` で終わる値の head の位置と型を付ける
+    #[cold]
+    fn fix_error(&mut self, err: Error, end: u64) -> Error {
+        if !err.needs_position() || end == 0 {
+            return err;
+        }
+        let head = self.reader.seek(io::SeekFrom::Start(end)).and_then(|_| self.prev());
+        match head {
+            Ok(head) => err.fix_head(end as usize - 1, type_name(head)),
+            Err(_) => err,
+        }
+    }
+
+    /// `end` で終わる Object の key をパスの要素として読む
+    #[cold]
+    fn key_segment(&mut self, key_end: u64) -> Option<PathSegment> {
+        self.reader.seek(io::SeekFrom::Start(key_end)).ok()?;
+        match Value::deserialize(&mut *self).ok()? {
+            Value::String(key) => Some(PathSegment::Key(key)),
+            key => Some(PathSegment::Other(key.to_string())),
+        }
+    }
+
+    /// Object の value や enum の中身のエラーに key をパスとして付ける
+    fn with_key<T>(&mut self, res: Result<T, Error>, key_end: u64) -> Result<T, Error> {
+        res.map_err(|err| match self.key_segment(key_end) {
+            Some(segment) => err.push_path(segment),
+            None => err,
+        })
+    }
+
     fn parse_value<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let end = self.now_pos()?;
+        match self.parse_head(visitor) {
+            Ok(value) => Ok(value),
+            Err(err) => Err(self.fix_error(err, end)),
+        }
+    }
+
+    /// head を読んで型ごとに visitor に渡す
+    fn parse_head<V>(&mut self, visitor: V) -> Result<V::Value, Error>
     where
         V: Visitor<'de>,
     {
@@ -147,429 +721,1461 @@ where
         const FLOAT16: u8 = prefix::FLOAT | size_prefix::SIZE_PREFIX_2BYTE;
         const FLOAT32: u8 = prefix::FLOAT | size_prefix::SIZE_PREFIX_4BYTE;
         const FLOAT64: u8 = prefix::FLOAT | size_prefix::SIZE_PREFIX_8BYTE;
-        const STRING8: u8 = prefix::STRING | size_prefix::SIZE_PREFIX_1BYTE;
-        const STRING16: u8 = prefix::STRING | size_prefix::SIZE_PREFIX_2BYTE;
-        const STRING32: u8 = prefix::STRING | size_prefix::SIZE_PREFIX_4BYTE;
-        const STRING64: u8 = prefix::STRING | size_prefix::SIZE_PREFIX_8BYTE;
-        const BYTES8: u8 = prefix::BYTES | size_prefix::SIZE_PREFIX_1BYTE;
-        const BYTES16: u8 = prefix::BYTES | size_prefix::SIZE_PREFIX_2BYTE;
-        const BYTES32: u8 = prefix::BYTES | size_prefix::SIZE_PREFIX_4BYTE;
-        const BYTES64: u8 = prefix::BYTES | size_prefix::SIZE_PREFIX_8BYTE;
         const UUID: u8 = prefix::UUID;
-        const DATETIME: u8 = prefix::DATETIME;
-        const TIMESTAMP: u8 = prefix::TIMESTAMP;
-        const DURATION: u8 = prefix::DURATION;
-        const ARRAY8: u8 = prefix::ARRAY | size_prefix::SIZE_PREFIX_1BYTE;
-        const ARRAY16: u8 = prefix::ARRAY | size_prefix::SIZE_PREFIX_2BYTE;
-        const ARRAY32: u8 = prefix::ARRAY | size_prefix::SIZE_PREFIX_4BYTE;
-        const ARRAY64: u8 = prefix::ARRAY | size_prefix::SIZE_PREFIX_8BYTE;
-        const OBJECT8: u8 = prefix::OBJECT | size_prefix::SIZE_PREFIX_1BYTE;
-        const OBJECT16: u8 = prefix::OBJECT | size_prefix::SIZE_PREFIX_2BYTE;
-        const OBJECT32: u8 = prefix::OBJECT | size_prefix::SIZE_PREFIX_4BYTE;
-        const OBJECT64: u8 = prefix::OBJECT | size_prefix::SIZE_PREFIX_8BYTE;
-        const WRAPPED_JSON8: u8 = prefix::WRAPPED_JSON | size_prefix::SIZE_PREFIX_1BYTE;
-        const WRAPPED_JSON16: u8 = prefix::WRAPPED_JSON | size_prefix::SIZE_PREFIX_2BYTE;
-        const WRAPPED_JSON32: u8 = prefix::WRAPPED_JSON | size_prefix::SIZE_PREFIX_4BYTE;
-        const WRAPPED_JSON64: u8 = prefix::WRAPPED_JSON | size_prefix::SIZE_PREFIX_8BYTE;
-        const META8: u8 = prefix::META | size_prefix::SIZE_PREFIX_1BYTE;
-        const META16: u8 = prefix::META | size_prefix::SIZE_PREFIX_2BYTE;
-        const META32: u8 = prefix::META | size_prefix::SIZE_PREFIX_4BYTE;
-        const META64: u8 = prefix::META | size_prefix::SIZE_PREFIX_8BYTE;
-        const PADDING8: u8 = prefix::PADDING | size_prefix::SIZE_PREFIX_1BYTE;
-        const PADDING16: u8 = prefix::PADDING | size_prefix::SIZE_PREFIX_2BYTE;
-        const PADDING32: u8 = prefix::PADDING | size_prefix::SIZE_PREFIX_4BYTE;
-        const PADDING64: u8 = prefix::PADDING | size_prefix::SIZE_PREFIX_8BYTE;
-        let header = self.peek()?;
+        const TIMESTAMP: u8 = prefix::TIMESTAMP | size_prefix::SIZE_PREFIX_8BYTE;
+        const DURATION: u8 = prefix::DURATION | size_prefix::SIZE_PREFIX_8BYTE;
+
+        let extended = std::mem::take(&mut self.extended);
+        let header = self.prev()?;
         match header {
             UNDEFINED => {
+                if extended {
+                    return visitor.visit_map(ExtendedAccess::new(prefix_str::UNDEFINED, de::value::UnitDeserializer::new()));
+                }
                 let pos = self.now_pos()?;
-                return Err(Error::new(ErrorCode::Other("Cant perse UNDEFINED TYPE".to_string()), pos as usize));
-            },
-            NONE => return visitor.visit_none(),
-            BOOLF => {
-                return visitor.visit_bool(false);
-            },
-            BOOLT => {
-                return visitor.visit_bool(true);
+                Err(Error::new(ErrorCode::Other("Cant perse UNDEFINED TYPE".to_string()), pos as usize))
             },
+            NONE => visitor.visit_none(),
+            BOOLF => visitor.visit_bool(false),
+            BOOLT => visitor.visit_bool(true),
             UINT8 => {
-                self.reader.seek(io::SeekFrom::Current(-1))?; // 1 byte戻す
-                let u8_val = self.reader.read_u8()?;
-                return visitor.visit_u8(u8_val);
+                let u8_val = self.prev()?;
+                visitor.visit_u8(u8_val)
             },
             UINT16 => {
-                self.reader.seek(io::SeekFrom::Current(-2))?; // 2 byte戻す
-                let u16_val = self.reader.read_u16()?;
-                return visitor.visit_u16(u16_val);
+                let u16_val = self.reader.prev_u16()?;
+                visitor.visit_u16(u16_val)
             },
             UINT32 => {
-                self.reader.seek(io::SeekFrom::Current(-4))?; // 4 byte戻す
-                let u32_val = self.reader.read_u32()?;
-                return visitor.visit_u32(u32_val);
+                let u32_val = self.reader.prev_u32()?;
+                visitor.visit_u32(u32_val)
             },
             UINT64 => {
-                self.reader.seek(io::SeekFrom::Current(-8))?; // 8 byte戻す
-                let u64_val = self.reader.read_u64()?;
-                return visitor.visit_u64(u64_val);
+                let u64_val = self.reader.prev_u64()?;
+                visitor.visit_u64(u64_val)
             },
             INT8 => {
-                self.reader.seek(io::SeekFrom::Current(-1))?; // 1 byte戻す
-                let as_u8_val = self.reader.read_u8()?;
-                return visitor.visit_i8(as_u8_val as i8);
+                let as_u8_val = self.prev()?;
+                visitor.visit_i8(as_u8_val as i8)
             },
             INT16 => {
-                self.reader.seek(io::SeekFrom::Current(-2))?; // 2 byte戻す
-                let as_u16_val = self.reader.read_u16()?;
-                return visitor.visit_i16(as_u16_val as i16);
+                let as_u16_val = self.reader.prev_u16()?;
+                visitor.visit_i16(as_u16_val as i16)
             },
             INT32 => {
-                self.reader.seek(io::SeekFrom::Current(-4))?; // 4 byte戻す
-                let as_u32_val = self.reader.read_u32()?;
-                return visitor.visit_i32(as_u32_val as i32);
+                let as_u32_val = self.reader.prev_u32()?;
+                visitor.visit_i32(as_u32_val as i32)
             },
             INT64 => {
-                self.reader.seek(io::SeekFrom::Current(-8))?; // 8 byte戻す
-                let as_u64_val = self.reader.read_u64()?;
-                return visitor.visit_i64(as_u64_val as i64);
+                let as_u64_val = self.reader.prev_u64()?;
+                visitor.visit_i64(as_u64_val as i64)
             },
             FLOAT16 => {
-                self.reader.seek(io::SeekFrom::Current(-2))?; // 2 byte戻す
-                let as_u16_val = self.reader.read_u16()?;
-                return visitor.visit_f32(f32::from_bits(as_u16_val as u32));
+                let as_u16_val = self.reader.prev_u16()?;
+                let val = half::f16::from_bits(as_u16_val).to_f32();
+                if extended {
+                    return visitor.visit_map(ExtendedAccess::new(prefix_str::FLOAT, de::value::F32Deserializer::new(val)));
+                }
+                visitor.visit_f32(val)
             },
             FLOAT32 => {
-                self.reader.seek(io::SeekFrom::Current(-4))?; // 4 byte戻す
-                let as_u32_val = self.reader.read_u32()?;
-                return visitor.visit_f32(f32::from_bits(as_u32_val));
+                let as_u32_val = self.reader.prev_u32()?;
+                visitor.visit_f32(f32::from_bits(as_u32_val))
             },
             FLOAT64 => {
-                self.reader.seek(io::SeekFrom::Current(-8))?; // 8 byte戻す
-                let as_u64_val = self.reader.read_u64()?;
-                return visitor.visit_f64(f64::from_bits(as_u64_val));
-            },
-            STRING8 => {
-                let size = self.get_size_8()?;
-                let mut buf: Vec<u8> = vec![0; size as usize];
-
-                visitor.s
-            },
-            STRING16 => {
-                let size = self.get_size_16()?;
-            },
-            STRING32 => {
-                let size = self.get_size_32()?;
-            },
-            STRING64 => {
-                let size = self.get_size_64()?;
-            },
-            BYTES8 => {
-                let size = self.get_size_8()?;
-            },
-            BYTES16 => {
-                let size = self.get_size_16()?;
-            },
-            BYTES32 => {
-                let size = self.get_size_32()?;
-            },
-            BYTES64 => {
-                let size = self.get_size_64()?;
-            },
-            UUID => {},
-            DATETIME => {},
-            TIMESTAMP => {},
-            DURATION => {},
-            ARRAY8 => {
-                let size = self.get_size_8()?;
-            },
-            ARRAY16 => {
-                let size = self.get_size_16()?;
-            },
-            ARRAY32 => {
-                let size = self.get_size_32()?;
-            },
-            ARRAY64 => {
-                let size = self.get_size_64()?;
+                let as_u64_val = self.reader.prev_u64()?;
+                visitor.visit_f64(f64::from_bits(as_u64_val))
             },
-            OBJECT8 => {
-                let size = self.get_size_8()?;
+            UUID => {
+                let buf = self.read_body(16)?;
+                if extended {
+                    return visitor.visit_map(ExtendedAccess::new(prefix_str::UUID, de::value::BytesDeserializer::new(&buf)));
+                }
+                let uuid = uuid::Uuid::from_slice(&buf).map_err(<Error as de::Error>::custom)?;
+                visitor.visit_string(uuid.hyphenated().to_string())
             },
-            OBJECT16 => {
-                let size = self.get_size_16()?;
+            TIMESTAMP => {
+                let val = self.reader.prev_u64()? as i64;
+                if extended {
+                    return visitor.visit_map(ExtendedAccess::new(prefix_str::TIMESTAMP, de::value::I64Deserializer::new(val)));
+                }
+                visitor.visit_i64(val)
             },
-            OBJECT32 => {
-                let size = self.get_size_32()?;
+            DURATION => {
+                let val = self.reader.prev_u64()? as i64;
+                if extended {
+                    return visitor.visit_map(ExtendedAccess::new(prefix_str::DURATION, de::value::I64Deserializer::new(val)));
+                }
+                visitor.visit_i64(val)
             },
-            OBJECT64 => {
-                let size = self.get_size_64()?
+            _ if header & !size_prefix::MASK == prefix::REF => {
+                self.reader.seek(io::SeekFrom::Current(1))?;
+                self.extended = extended;
+                self.read_reference(|de| de.parse_head(visitor))
             },
-            WRAPPED_JSON8 => {
-                let size = self.get_size_8()?;
+            _ if header & !size_prefix::MASK == prefix::KEYREF => {
+                let width = 1usize << (header & size_prefix::MASK);
+                let body = self.read_body(width as u64)?;
+                let pos = self.now_pos()? as usize + width;
+                let key = match &self.keys {
+                    Some(keys) => keys.resolve(reference_index(&body), pos)?,
+                    None => return Err(Error::new(ErrorCode::Other("key reference without key dictionary".to_string()), pos)),
+                };
+                visitor.visit_str(key)
             },
-            WRAPPED_JSON16 => {
-                let size = self.get_size_16()?;
-            },
-            WRAPPED_JSON32 => {
-                let size = self.get_size_32()?;
+            _ => self.parse_sized(header, extended, visitor),
+        }
+    }
+
+    /// データ長を持つ型 (可変長グループ) を読み込む
+    ///
+    /// シーク位置は head の位置にある必要がある
+    fn parse_sized<V>(&mut self, header: u8, extended: bool, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let head_pos = self.now_pos()?;
+        if body_kind(header) != BodyKind::Sized {
+            return Err(Error::new(ErrorCode::UnknownPrefix(header), head_pos as usize));
+        }
+        let size = self.get_size(header)?;
+        match header & !size_prefix::MASK {
+            prefix::STRING => {
+                let s = self.read_string(size)?;
+                visitor.visit_string(s)
             },
-            WRAPPED_JSON64 => {
-                let size = self.get_size_64()?;
+            prefix::BYTES => {
+                let buf = self.read_body(size)?;
+                visitor.visit_byte_buf(buf)
             },
-            META8 => {
-                let size = self.get_size_8()?;
+            prefix::DATETIME => {
+                let s = self.read_string(size)?;
+                if extended {
+                    return visitor.visit_map(ExtendedAccess::new(prefix_str::DATETIME, de::value::StrDeserializer::new(&s)));
+                }
+                visitor.visit_string(s)
             },
-            META16 => {
-                let size = self.get_size_16()?;
+            prefix::WRAPPED_JSON => {
+                let s = self.read_string(size)?;
+                visit_json(s, extended, visitor)
             },
-            META32 => {
-                let size = self.get_size_32()?;
+            prefix::COMPRESSED => {
+                let pos = self.now_pos()? as usize;
+                let body = self.read_body(size)?;
+                let compressed = Compressed::parse(&body, pos)?;
+                let data = compressed.decompress(&self.codecs, pos)?;
+                if compressed.head == prefix::BYTES {
+                    return visitor.visit_byte_buf(data);
+                }
+                let s = String::from_utf8(data).map_err(|_| Error::new(ErrorCode::Other("invalid UTF-8 string".to_string()), pos))?;
+                match compressed.head {
+                    prefix::STRING => visitor.visit_string(s),
+                    _ => visit_json(s, extended, visitor),
+                }
             },
-            META64 => {
-                let size = self.get_size_64()?;
+            prefix::ARRAY => {
+                self.verify_container(header, size)?;
+                let ends = self.scan_children(size, extended)?;
+                let start = self.now_pos()?;
+                self.enter(head_pos)?;
+                let res = visitor.visit_seq(ReverseSeqAccess::new(self, ends));
+                self.deep -= 1;
+                self.reader.seek(io::SeekFrom::Start(start))?;
+                res
             },
-            PADDING8 => {
-                let size = self.get_size_8()?;
+            prefix::TYPED_ARRAY | prefix::ENCODED_ARRAY => {
+                let elements = self.read_packed(header, size)?;
+                visitor.visit_seq(PackedSeqAccess { elements, index: 0 })
             },
-            PADDING16 => {
-                let size = self.get_size_16()?;
+            prefix::TABLE => {
+                let ends = self.scan_children(size, false)?;
+                let start = self.now_pos()?;
+                let (columns, rows) = self.read_table(&ends, start)?;
+                self.enter(head_pos)?;
+                let res = visitor.visit_seq(TableSeqAccess { de: &mut *self, columns, rows, row: 0 });
+                self.deep -= 1;
+                self.reader.seek(io::SeekFrom::Start(start))?;
+                res
             },
-            PADDING32 => {
-                let size = self.get_size_32()?;
+            prefix::OBJECT => {
+                self.verify_container(header, size)?;
+                let ends = self.scan_children(size, extended)?;
+                let start = self.now_pos()?;
+                if ends.len() % 2 != 0 {
+                    return Err(Error::new(ErrorCode::Other("object has a key without value".to_string()), start as usize));
+                }
+                self.enter(head_pos)?;
+                let res = visitor.visit_map(ReverseMapAccess::new(self, ends));
+                self.deep -= 1;
+                self.reader.seek(io::SeekFrom::Start(start))?;
+                res
             },
-            PADDING64 => {
-                let size = self.get_size_64()?;
+            prefix::META => {
+                let body_end = self.now_pos()?;
+                if size > body_end {
+                    return Err(Error::new(ErrorCode::Eof("body length exceeds the beginning of the data".to_string()), body_end as usize));
+                }
+                let start = body_end - size;
+                self.enter(head_pos)?;
+                let res = if extended {
+                    visitor.visit_map(ExtendedAccess::new(prefix_str::META, &mut *self))
+                } else {
+                    // Value 以外では中身をそのまま渡す
+                    self.parse_value(visitor)
+                };
+                self.deep -= 1;
+                self.reader.seek(io::SeekFrom::Start(start))?;
+                res
             },
             _ => {
+                // 続く PADDING もまとめて読み飛ばして次の値を読む
                 let pos = self.now_pos()?;
-                Err(Error::new(ErrorCode::InvalidType, pos as usize))
+                if size > pos {
+                    return Err(Error::new(ErrorCode::Eof("body length exceeds the beginning of the data".to_string()), pos as usize));
+                }
+                self.reader.seek(io::SeekFrom::Start(pos - size))?;
+                while self.peek_head()? & !size_prefix::MASK == prefix::PADDING {
+                    self.skip_value()?;
+                }
+                self.extended = extended;
+                self.parse_value(visitor)
             },
         }
     }
 }
 
-impl<'de, R> Deserializer<'de> for ReverseDeserializer<R>
-where R: Reader,
+impl<'de, R> ReverseDeserializer<R>
+where
+    R: BorrowReader<'de>,
 {
-    type Error = Error;
+    /// 型付き配列か Encoded Array の body を読んで要素を取り出す
+    ///
+    /// シーク位置は body の終端にある必要がある
+    fn read_packed(&mut self, header: u8, size: u64) -> Result<PackedElements, Error> {
+        let body = self.read_body(size)?;
+        let start = self.now_pos()?;
+        let pos = (start + size) as usize;
+        if header & !size_prefix::MASK == prefix::TYPED_ARRAY {
+            let array = TypedArray::parse(&body, pos)?;
+            return Ok(PackedElements {
+                element: array.element,
+                width: array.width,
+                count: array.count,
+                data: array.data.to_vec(),
+                start,
+                in_place: true,
+            });
+        }
+        let (element, data) = decode_array(&body, pos)?;
+        let width = element_width(element).unwrap_or(1);
+        Ok(PackedElements { element, width, count: data.len() / width, data, start, in_place: false })
+    }
 
-    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: Visitor<'de>,
-    {
-        // ここでデシリアライズのロジックを実装
-        // 例: visitor.visit_i64(42)
-        Err(serde::de::Error::custom("deserialize_any is not implemented"))
+    /// 表の子の終端から列と行数を読む
+    ///
+    /// `select_columns` で絞った場合は、それ以外の列の中身を読まない
+    fn read_table(&mut self, ends: &[u64], start: u64) -> Result<(Vec<TableColumn>, usize), Error> {
+        let Some((&rows_end, pairs)) = ends.split_last() else {
+            return Err(Error::new(ErrorCode::Other("table has no row count".to_string()), start as usize));
+        };
+        if pairs.len() % 2 != 0 {
+            return Err(Error::new(ErrorCode::Other("table has a column without name".to_string()), start as usize));
+        }
+        self.reader.seek(io::SeekFrom::Start(rows_end))?;
+        let head = self.peek_head()?;
+        if head & !size_prefix::MASK != prefix::UINT {
+            return Err(Error::new(
+                ErrorCode::InvalidType { expected: "a row count".to_string(), found: type_name(head).to_string() },
+                rows_end as usize - 1,
+            ));
+        }
+        let rows = u64::deserialize(&mut *self)? as usize;
+        let mut columns = Vec::with_capacity(pairs.len() / 2);
+        for pair in pairs.chunks_exact(2) {
+            let (value_end, key_end) = (pair[0], pair[1]);
+            if self.projection.is_some() {
+                self.reader.seek(io::SeekFrom::Start(key_end))?;
+                let selected = match String::deserialize(&mut *self) {
+                    Ok(key) => self.projection.as_ref().is_some_and(|projection| projection.contains(&key)),
+                    Err(_) => false,
+                };
+                if !selected {
+                    continue;
+                }
+            }
+            self.reader.seek(io::SeekFrom::Start(value_end))?;
+            let head = self.prev()?;
+            let values = match head & !size_prefix::MASK {
+                prefix::ARRAY => {
+                    let size = self.get_size(head)?;
+                    ColumnValues::Ends(self.scan_children(size, false)?)
+                }
+                prefix::TYPED_ARRAY | prefix::ENCODED_ARRAY => {
+                    let size = self.get_size(head)?;
+                    ColumnValues::Packed(self.read_packed(head, size)?)
+                }
+                _ => {
+                    return Err(Error::new(
+                        ErrorCode::InvalidType { expected: "a table column".to_string(), found: type_name(head).to_string() },
+                        value_end as usize - 1,
+                    ));
+                }
+            };
+            let count = match &values {
+                ColumnValues::Ends(ends) => ends.len(),
+                ColumnValues::Packed(elements) => elements.count,
+            };
+            if count != rows {
+                return Err(Error::new(
+                    ErrorCode::Other(format!("table column has {} values for {} rows", count, rows)),
+                    value_end as usize - 1,
+                ));
+            }
+            columns.push(TableColumn { key_end, values });
+        }
+        Ok((columns, rows))
     }
+}
 
-    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        let _ = visitor;
-        Err(serde::de::Error::custom("i128 is not supported"))
+/// head から body の長さの決まり方を判定する
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyKind {
+    /// body の長さが head だけで決まる
+    Fixed(u64),
+    /// head の直前にデータ長がある
+    Sized,
+    /// 未知の head
+    Invalid,
+}
+
+/// head 1 バイトから body の長さの決まり方を返す
+///
+/// Sized の場合、データ長は head の size prefix が示すバイト数で head の直前に置かれる
+pub fn body_kind(head: u8) -> BodyKind {
+    const SIZES: [u64; 4] = [1, 2, 4, 8];
+    let size_bits = (head & size_prefix::MASK) as usize;
+    match head & !size_prefix::MASK {
+        prefix::UNDEFINED => if head == prefix::UNDEFINED { BodyKind::Fixed(0) } else { BodyKind::Invalid },
+        prefix::NONE => if head == prefix::NONE { BodyKind::Fixed(0) } else { BodyKind::Invalid },
+        prefix::BOOL => if size_bits <= 1 { BodyKind::Fixed(0) } else { BodyKind::Invalid },
+        prefix::INT | prefix::UINT | prefix::KEYREF | prefix::REF => BodyKind::Fixed(SIZES[size_bits]),
+        prefix::FLOAT => if size_bits == 0 { BodyKind::Invalid } else { BodyKind::Fixed(SIZES[size_bits]) },
+        prefix::UUID => if size_bits == 0 { BodyKind::Fixed(16) } else { BodyKind::Invalid },
+        prefix::TIMESTAMP | prefix::DURATION => if size_bits == 3 { BodyKind::Fixed(8) } else { BodyKind::Invalid },
+        prefix::STRING | prefix::BYTES | prefix::DATETIME | prefix::ARRAY | prefix::OBJECT
+        | prefix::WRAPPED_JSON | prefix::TYPED_ARRAY | prefix::ENCODED_ARRAY | prefix::TABLE | prefix::META
+        | prefix::COMPRESSED | prefix::PADDING => BodyKind::Sized,
+        _ => BodyKind::Invalid,
     }
-    
-    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: serde::de::Visitor<'de>,
-    {
-        let _ = visitor;
-        Err(serde::de::Error::custom("u128 is not supported"))
+}
+
+/// WrappedJSON の中身を渡す
+///
+/// Value 向けには JSON の文字列のまま渡す
+fn visit_json<'de, V>(s: String, extended: bool, visitor: V) -> Result<V::Value, Error>
+where
+    V: Visitor<'de>,
+{
+    if extended {
+        return visitor.visit_map(ExtendedAccess::new(prefix_str::WRAPPED_JSON, de::value::StrDeserializer::new(&s)));
     }
-    
-    fn is_human_readable(&self) -> bool {
-        true
+    let json: serde_json::Value = serde_json::from_str(&s).map_err(<Error as de::Error>::custom)?;
+    json.deserialize_any(visitor).map_err(de::Error::custom)
+}
+
+/// head の型名を `prefix_str` の表記で返す
+pub fn type_name(head: u8) -> &'static str {
+    match head & !size_prefix::MASK {
+        prefix::UNDEFINED => prefix_str::UNDEFINED,
+        prefix::NONE => prefix_str::NONE,
+        prefix::BOOL => prefix_str::BOOL,
+        prefix::INT => prefix_str::INT,
+        prefix::UINT => prefix_str::UINT,
+        prefix::FLOAT => prefix_str::FLOAT,
+        prefix::STRING => prefix_str::STRING,
+        prefix::BYTES => prefix_str::BYTES,
+        prefix::UUID => prefix_str::UUID,
+        prefix::DATETIME => prefix_str::DATETIME,
+        prefix::TIMESTAMP => prefix_str::TIMESTAMP,
+        prefix::DURATION => prefix_str::DURATION,
+        prefix::ARRAY => prefix_str::ARRAY,
+        prefix::OBJECT => prefix_str::OBJECT,
+        prefix::WRAPPED_JSON => prefix_str::WRAPPED_JSON,
+        prefix::TYPED_ARRAY => prefix_str::TYPED_ARRAY,
+        prefix::ENCODED_ARRAY => prefix_str::ENCODED_ARRAY,
+        prefix::TABLE => prefix_str::TABLE,
+        prefix::KEYREF => prefix_str::KEYREF,
+        prefix::REF => prefix_str::REF,
+        prefix::COMPRESSED => prefix_str::COMPRESSED,
+        prefix::META => prefix_str::META,
+        prefix::PADDING => prefix_str::PADDING,
+        _ => "$unknown",
     }
-    
-    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: Visitor<'de> {
-        todo!()
+}
+
+/// バイト列上の 1 値の位置
+///
+/// `start..head_start` が body, `head_start..end` がデータ長と head
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Head {
+    /// head 1 バイト
+    pub head: u8,
+    /// body の先頭
+    pub start: usize,
+    /// データ長を含めた head の先頭
+    pub head_start: usize,
+    /// 値の終端 (head の次)
+    pub end: usize,
+}
+
+impl Head {
+    /// size prefix を除いた型
+    #[inline]
+    pub fn prefix(&self) -> u8 {
+        self.head & !size_prefix::MASK
     }
-    
-    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: Visitor<'de> {
-        todo!()
+
+    /// body のバイト数
+    #[inline]
+    pub fn body_len(&self) -> usize {
+        self.head_start - self.start
     }
-    
-    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: Visitor<'de> {
-        todo!()
+}
+
+/// `buf[..end]` の末尾にある値の head を読む
+///
+/// body の中身は検査しない
+pub fn read_head(buf: &[u8], end: usize) -> Result<Head, Error> {
+    if end == 0 || end > buf.len() {
+        return Err(Error::new(ErrorCode::Eof("unexpected beginning of data while reading a head".to_string()), end));
     }
-    
-    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: Visitor<'de> {
-        todo!()
+    let head = buf[end - 1];
+    let mut head_start = end - 1;
+    let len = match body_kind(head) {
+        BodyKind::Fixed(len) => len,
+        BodyKind::Sized => {
+            let size_len = 1usize << (head & size_prefix::MASK);
+            if head_start < size_len {
+                return Err(Error::new(ErrorCode::Eof(format!("size field of {} is truncated", type_name(head))), head_start));
+            }
+            head_start -= size_len;
+            let mut size = [0u8; 8];
+            size[..size_len].copy_from_slice(&buf[head_start..head_start + size_len]);
+            u64::from_le_bytes(size)
+        }
+        BodyKind::Invalid => {
+            return Err(Error::new(ErrorCode::UnknownPrefix(head), end - 1));
+        }
+    };
+    if len > head_start as u64 {
+        return Err(Error::new(
+            ErrorCode::Eof(format!("body of {} is {} bytes but only {} bytes precede it", type_name(head), len, head_start)),
+            head_start,
+        ));
     }
-    
-    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: Visitor<'de> {
-        todo!()
+    Ok(Head {
+        head,
+        start: head_start - len as usize,
+        head_start,
+        end,
+    })
+}
+
+/// `iter_array` が要素を返す順序
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArrayOrder {
+    /// 格納順 (最後の要素から先頭へ)
+    Reverse,
+    /// 論理順 (先頭の要素から最後へ)
+    ///
+    /// オフセット索引のある Array だけ
+    Forward,
+}
+
+/// 次に読む要素の位置
+enum Positions {
+    /// Reverse: まだ読んでいない範囲の終端
+    Back(u64),
+    /// Forward: オフセット索引の表の位置
+    Index { table: u64, width: u8, count: usize, limit: u64 },
+}
+
+/// ルートの Array の要素を 1 つずつデシリアライズするイテレータ
+///
+/// `ReverseDeserializer::iter_array` で作る
+/// 要素のデシリアライズに失敗しても、その要素を飛ばして続きを読める
+/// `nth` や `skip` は要素をデシリアライズせずにサイズだけで読み飛ばす
+pub struct ArrayIter<'a, R, T>
+where R: Reader,
+{
+    de: &'a mut ReverseDeserializer<R>,
+    /// Array の body の先頭
+    start: u64,
+    positions: Positions,
+    /// 次に返す要素の順番
+    index: usize,
+    done: bool,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<R, T> ArrayIter<'_, R, T>
+where R: Reader,
+{
+    /// 次の要素の終端を返し、その要素を読み終えた状態にする
+    ///
+    /// Padding と Meta は読み飛ばす
+    fn advance(&mut self) -> Result<Option<u64>, Error> {
+        match &mut self.positions {
+            Positions::Back(back) => loop {
+                if *back <= self.start {
+                    return Ok(None);
+                }
+                let end = *back;
+                self.de.reader.seek(io::SeekFrom::Start(end))?;
+                let head = self.de.peek_head()?;
+                self.de.skip_value()?;
+                let child_start = self.de.now_pos()?;
+                if child_start < self.start {
+                    return Err(Error::new(ErrorCode::Other("child value overflows its container".to_string()), child_start as usize));
+                }
+                *back = child_start;
+                if !matches!(head & !size_prefix::MASK, prefix::PADDING | prefix::META) {
+                    return Ok(Some(end));
+                }
+            },
+            Positions::Index { table, width, count, limit } => {
+                if self.index >= *count {
+                    return Ok(None);
+                }
+                let mut bytes = [0u8; 8];
+                self.de.reader.seek(io::SeekFrom::Start(*table + (self.index * *width as usize) as u64))?;
+                self.de.reader.read_exact(&mut bytes[..*width as usize])?;
+                let end = self.start.saturating_add(u64::from_le_bytes(bytes));
+                if end > *limit {
+                    return Err(Error::new(ErrorCode::Other(format!("index offset {} is out of the container", end)), *limit as usize));
+                }
+                Ok(Some(end))
+            }
+        }
     }
-    
-    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: Visitor<'de> {
-        todo!()
+
+    fn finish(&mut self) {
+        self.done = true;
+        // 読み終えたら Array の前に移動して `end` で確かめられるようにする
+        let _ = self.de.reader.seek(io::SeekFrom::Start(self.start));
     }
-    
-    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: Visitor<'de> {
-        todo!()
+}
+
+impl<'de, R, T> Iterator for ArrayIter<'_, R, T>
+where
+    R: BorrowReader<'de>,
+    T: Deserialize<'de>,
+{
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let end = match self.advance() {
+            Ok(Some(end)) => end,
+            Ok(None) => {
+                self.finish();
+                return None;
+            }
+            Err(err) => {
+                // 要素の位置が分からなくなったので続きは読めない
+                self.finish();
+                return Some(Err(err));
+            }
+        };
+        let index = self.index;
+        self.index += 1;
+        let res = self
+            .de
+            .reader
+            .seek(io::SeekFrom::Start(end))
+            .map_err(Error::io)
+            .and_then(|_| T::deserialize(&mut *self.de));
+        // 格納順では先頭からの添字が分からないのでパスを付けない
+        Some(res.map_err(|err| match self.positions {
+            Positions::Back(_) => err,
+            Positions::Index { .. } => err.push_path(PathSegment::Index(index)),
+        }))
     }
-    
-    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: Visitor<'de> {
-        todo!()
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        for _ in 0..n {
+            if self.done {
+                return None;
+            }
+            match self.advance() {
+                Ok(Some(_)) => self.index += 1,
+                Ok(None) => {
+                    self.finish();
+                    return None;
+                }
+                Err(err) => {
+                    self.finish();
+                    return Some(Err(err));
+                }
+            }
+        }
+        self.next()
     }
-    
-    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: Visitor<'de> {
-        todo!()
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.positions {
+            _ if self.done => (0, Some(0)),
+            Positions::Back(_) => (0, None),
+            Positions::Index { count, .. } => (count - self.index, Some(count - self.index)),
+        }
     }
-    
-    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: Visitor<'de> {
-        todo!()
+}
+
+/// 配列の要素を前から順に渡す
+struct ReverseSeqAccess<'a, R>
+where R: Reader,
+{
+    de: &'a mut ReverseDeserializer<R>,
+    ends: std::vec::IntoIter<u64>,
+    /// 次に渡す要素の添字
+    index: usize,
+}
+
+impl<'a, R> ReverseSeqAccess<'a, R>
+where R: Reader,
+{
+    fn new(de: &'a mut ReverseDeserializer<R>, ends: Vec<u64>) -> Self {
+        Self { de, ends: ends.into_iter(), index: 0 }
     }
-    
-    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+}
+
+impl<'de, R> SeqAccess<'de> for ReverseSeqAccess<'_, R>
+where R: BorrowReader<'de>,
+{
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
     where
-        V: Visitor<'de> {
-        todo!()
+        T: DeserializeSeed<'de>,
+    {
+        match self.ends.next() {
+            Some(end) => {
+                let index = self.index;
+                self.index += 1;
+                self.de.reader.seek(io::SeekFrom::Start(end))?;
+                seed.deserialize(&mut *self.de)
+                    .map(Some)
+                    .map_err(|err| err.push_path(PathSegment::Index(index)))
+            }
+            None => Ok(None),
+        }
     }
-    
-    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: Visitor<'de> {
-        todo!()
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.ends.len())
     }
-    
-    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+}
+
+/// 型付き配列 (Encoded Array) の要素
+///
+/// 要素は `ElementDeserializer` で data から直接読む
+struct PackedElements {
+    element: u8,
+    width: usize,
+    count: usize,
+    data: Vec<u8>,
+    /// body の先頭の位置
+    start: u64,
+    /// data が body にそのまま置かれているか (符号化されていれば要素の位置は body の先頭にする)
+    in_place: bool,
+}
+
+impl PackedElements {
+    /// index 番目の要素をデシリアライズする
+    fn deserialize<'de, T>(&self, index: usize, seed: T) -> Result<T::Value, Error>
     where
-        V: Visitor<'de> {
-        todo!()
+        T: DeserializeSeed<'de>,
+    {
+        let (bytes, bit) = match self.width {
+            0 => (&[][..], self.data[index / 8] >> (index % 8) & 1 != 0),
+            width => (&self.data[index * width..(index + 1) * width], false),
+        };
+        // エラーには body の中の要素の位置を付ける
+        let offset = self.start as usize + match (self.in_place, self.width) {
+            (false, _) => 0,
+            (true, 0) => index / 8,
+            (true, width) => index * width,
+        };
+        let de = ElementDeserializer { element: self.element, bytes, bit, extended: false };
+        seed.deserialize(de).map_err(|err| err.fix_head(offset + self.width, type_name(self.element)))
     }
-    
-    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+}
+
+/// 型付き配列の要素 1 つを、head のない要素のバイト列から直接読むデシリアライザ
+struct ElementDeserializer<'a> {
+    /// 要素の head (Bool は false の head)
+    element: u8,
+    /// 要素のバイト列 (Bool は空)
+    bytes: &'a [u8],
+    /// Bool の値
+    bit: bool,
+    /// Value 向けの拡張表現で渡すか
+    extended: bool,
+}
+
+impl ElementDeserializer<'_> {
+    /// 要素のバイト列 (`element_width` で幅は決まっている)
+    fn le_bytes<const N: usize>(&self) -> [u8; N] {
+        let mut buf = [0u8; N];
+        buf.copy_from_slice(self.bytes);
+        buf
+    }
+
+    /// 要素を単体の値としてエンコードする
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.bytes.len() + 1);
+        buf.extend_from_slice(self.bytes);
+        buf.push(self.element | self.bit as u8);
+        buf
+    }
+}
+
+impl<'de> Deserializer<'de> for ElementDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
-        V: Visitor<'de> {
-        todo!()
+        V: Visitor<'de>,
+    {
+        match (self.element & !size_prefix::MASK, self.bytes.len()) {
+            (prefix::BOOL, _) => visitor.visit_bool(self.bit),
+            (prefix::UINT, 1) => visitor.visit_u8(self.bytes[0]),
+            (prefix::UINT, 2) => visitor.visit_u16(u16::from_le_bytes(self.le_bytes())),
+            (prefix::UINT, 4) => visitor.visit_u32(u32::from_le_bytes(self.le_bytes())),
+            (prefix::UINT, 8) => visitor.visit_u64(u64::from_le_bytes(self.le_bytes())),
+            (prefix::INT, 1) => visitor.visit_i8(self.bytes[0] as i8),
+            (prefix::INT, 2) => visitor.visit_i16(i16::from_le_bytes(self.le_bytes())),
+            (prefix::INT, 4) => visitor.visit_i32(i32::from_le_bytes(self.le_bytes())),
+            (prefix::INT, 8) => visitor.visit_i64(i64::from_le_bytes(self.le_bytes())),
+            (prefix::FLOAT, 2) => {
+                let val = half::f16::from_bits(u16::from_le_bytes(self.le_bytes())).to_f32();
+                if self.extended {
+                    return visitor.visit_map(ExtendedAccess::new(prefix_str::FLOAT, de::value::F32Deserializer::new(val)));
+                }
+                visitor.visit_f32(val)
+            },
+            (prefix::FLOAT, 4) => visitor.visit_f32(f32::from_le_bytes(self.le_bytes())),
+            (prefix::FLOAT, 8) => visitor.visit_f64(f64::from_le_bytes(self.le_bytes())),
+            (prefix::UUID, _) => {
+                if self.extended {
+                    return visitor.visit_map(ExtendedAccess::new(prefix_str::UUID, de::value::BytesDeserializer::new(self.bytes)));
+                }
+                let uuid = uuid::Uuid::from_slice(self.bytes).map_err(<Error as de::Error>::custom)?;
+                visitor.visit_string(uuid.hyphenated().to_string())
+            },
+            (prefix::TIMESTAMP, _) => {
+                let val = i64::from_le_bytes(self.le_bytes());
+                if self.extended {
+                    return visitor.visit_map(ExtendedAccess::new(prefix_str::TIMESTAMP, de::value::I64Deserializer::new(val)));
+                }
+                visitor.visit_i64(val)
+            },
+            _ => Err(Error::make(ErrorCode::UnknownPrefix(self.element), None)),
+        }
     }
-    
+
     fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
-        V: Visitor<'de> {
-        todo!()
+        V: Visitor<'de>,
+    {
+        self.deserialize_byte_buf(visitor)
     }
-    
+
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
-        V: Visitor<'de> {
-        todo!()
+        V: Visitor<'de>,
+    {
+        match self.element {
+            prefix::UUID => visitor.visit_byte_buf(self.bytes.to_vec()),
+            _ => self.deserialize_any(visitor),
+        }
     }
-    
+
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
-        V: Visitor<'de> {
-        todo!()
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
     }
-    
-    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+
+    fn deserialize_newtype_struct<V>(mut self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
     where
-        V: Visitor<'de> {
-        todo!()
+        V: Visitor<'de>,
+    {
+        match name {
+            VALUE_TOKEN => self.extended = true,
+            RAW_TOKEN => return visitor.visit_byte_buf(self.encode()),
+            _ => {}
+        }
+        visitor.visit_newtype_struct(self)
     }
-    
-    fn deserialize_unit_struct<V>(
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    fn is_human_readable(&self) -> bool {
+        true
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        unit unit_struct seq tuple tuple_struct map struct enum identifier
+    }
+}
+
+/// 型付き配列の要素を前から順に渡す
+struct PackedSeqAccess {
+    elements: PackedElements,
+    index: usize,
+}
+
+impl<'de> SeqAccess<'de> for PackedSeqAccess {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.index >= self.elements.count {
+            return Ok(None);
+        }
+        let index = self.index;
+        self.index += 1;
+        self.elements.deserialize(index, seed)
+            .map(Some)
+            .map_err(|err| err.push_path(PathSegment::Index(index)))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.elements.count - self.index)
+    }
+}
+
+/// 表の列の値の位置
+enum ColumnValues {
+    /// Array の要素の終端
+    Ends(Vec<u64>),
+    Packed(PackedElements),
+}
+
+/// 表の列
+struct TableColumn {
+    /// 列の名前の終端
+    key_end: u64,
+    values: ColumnValues,
+}
+
+/// 表の行を前から順に Object として渡す
+struct TableSeqAccess<'a, R>
+where R: Reader,
+{
+    de: &'a mut ReverseDeserializer<R>,
+    columns: Vec<TableColumn>,
+    rows: usize,
+    /// 次に渡す行
+    row: usize,
+}
+
+impl<'de, R> SeqAccess<'de> for TableSeqAccess<'_, R>
+where R: BorrowReader<'de>,
+{
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.row >= self.rows {
+            return Ok(None);
+        }
+        let row = self.row;
+        self.row += 1;
+        seed.deserialize(TableRow { de: &mut *self.de, columns: &self.columns, row })
+            .map(Some)
+            .map_err(|err| err.push_path(PathSegment::Index(row)))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.rows - self.row)
+    }
+}
+
+/// 表の 1 行を Object として読むデシリアライザ
+struct TableRow<'a, R>
+where R: Reader,
+{
+    de: &'a mut ReverseDeserializer<R>,
+    columns: &'a [TableColumn],
+    row: usize,
+}
+
+impl<'de, R> Deserializer<'de> for TableRow<'_, R>
+where R: BorrowReader<'de>,
+{
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_map(TableRowAccess { de: self.de, columns: self.columns.iter(), row: self.row, column: None })
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct enum identifier
+    }
+}
+
+/// 表の 1 行の列を前から順に渡す
+struct TableRowAccess<'a, R>
+where R: Reader,
+{
+    de: &'a mut ReverseDeserializer<R>,
+    columns: std::slice::Iter<'a, TableColumn>,
+    row: usize,
+    /// 次に読む value の列
+    column: Option<&'a TableColumn>,
+}
+
+impl<'de, R> MapAccess<'de> for TableRowAccess<'_, R>
+where R: BorrowReader<'de>,
+{
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        let Some(column) = self.columns.next() else {
+            return Ok(None);
+        };
+        self.column = Some(column);
+        self.de.reader.seek(io::SeekFrom::Start(column.key_end))?;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let Some(column) = self.column.take() else {
+            return Err(de::Error::custom("next_value_seed called before next_key_seed"));
+        };
+        let res = match &column.values {
+            ColumnValues::Ends(ends) => {
+                self.de.reader.seek(io::SeekFrom::Start(ends[self.row]))?;
+                seed.deserialize(&mut *self.de)
+            }
+            ColumnValues::Packed(elements) => elements.deserialize(self.row, seed),
+        };
+        self.de.with_key(res, column.key_end)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.columns.len())
+    }
+}
+
+/// Object の要素を前から順に渡す
+///
+/// RTON の Object は value -> key の順で並んでいる
+struct ReverseMapAccess<'a, R>
+where R: Reader,
+{
+    de: &'a mut ReverseDeserializer<R>,
+    ends: std::vec::IntoIter<u64>,
+    /// 次に読む value と、その key の終端
+    value_end: Option<(u64, u64)>,
+}
+
+impl<'a, R> ReverseMapAccess<'a, R>
+where R: Reader,
+{
+    fn new(de: &'a mut ReverseDeserializer<R>, ends: Vec<u64>) -> Self {
+        Self { de, ends: ends.into_iter(), value_end: None }
+    }
+}
+
+impl<'de, R> MapAccess<'de> for ReverseMapAccess<'_, R>
+where R: BorrowReader<'de>,
+{
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        let (value_end, key_end) = match (self.ends.next(), self.ends.next()) {
+            (Some(value_end), Some(key_end)) => (value_end, key_end),
+            _ => return Ok(None),
+        };
+        self.value_end = Some((value_end, key_end));
+        self.de.reader.seek(io::SeekFrom::Start(key_end))?;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        match self.value_end.take() {
+            Some((value_end, key_end)) => {
+                self.de.reader.seek(io::SeekFrom::Start(value_end))?;
+                let res = seed.deserialize(&mut *self.de);
+                self.de.with_key(res, key_end)
+            }
+            None => Err(de::Error::custom("next_value_seed called before next_key_seed")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.ends.len() / 2)
+    }
+}
+
+/// enum を読むための EnumAccess
+///
+/// Object { variant: value } の形になっている
+struct ReverseEnumAccess<'a, R>
+where R: Reader,
+{
+    de: &'a mut ReverseDeserializer<R>,
+    value_end: u64,
+    key_end: u64,
+}
+
+impl<'de, 'a, R> EnumAccess<'de> for ReverseEnumAccess<'a, R>
+where R: BorrowReader<'de>,
+{
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        self.de.reader.seek(io::SeekFrom::Start(self.key_end))?;
+        let variant = seed.deserialize(&mut *self.de)?;
+        self.de.reader.seek(io::SeekFrom::Start(self.value_end))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, R> VariantAccess<'de> for ReverseEnumAccess<'_, R>
+where R: BorrowReader<'de>,
+{
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        let key_end = self.key_end;
+        let res = de::Deserialize::deserialize(&mut *self.de);
+        self.de.with_key(res, key_end)
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let res = seed.deserialize(&mut *self.de);
+        self.de.with_key(res, self.key_end)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let res = self.de.deserialize_seq(visitor);
+        self.de.with_key(res, self.key_end)
+    }
+
+    fn struct_variant<V>(
         self,
-        name: &'static str,
+        _fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let res = self.de.deserialize_map(visitor);
+        self.de.with_key(res, self.key_end)
+    }
+}
+
+impl<'de, R> Deserializer<'de> for &mut ReverseDeserializer<R>
+where R: BorrowReader<'de>,
+{
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.parse_value(visitor)
+    }
+
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let _ = visitor;
+        Err(serde::de::Error::custom("i128 is not supported"))
+    }
+
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let _ = visitor;
+        Err(serde::de::Error::custom("u128 is not supported"))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        true
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char
+        seq tuple tuple_struct map struct identifier
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de> {
-        todo!()
+        self.deserialize_string(visitor)
     }
-    
-    fn deserialize_newtype_struct<V>(
-        self,
-        name: &'static str,
-        visitor: V,
-    ) -> Result<V::Value, Self::Error>
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de> {
-        todo!()
+        // UUID はハイフン区切りの文字列として渡す
+        self.parse_value(visitor)
     }
-    
-    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de> {
-        todo!()
+        self.deserialize_byte_buf(visitor)
     }
-    
-    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de> {
-        todo!()
+        let end = self.now_pos()?;
+        let res = match self.peek_head()? {
+            prefix::UUID => {
+                self.prev()?;
+                let buf = self.read_body(16)?;
+                visitor.visit_byte_buf(buf)
+            },
+            head if head & !size_prefix::MASK == prefix::STRING => {
+                self.prev()?;
+                let size = self.get_size(head)?;
+                let buf = self.read_body(size)?;
+                visitor.visit_byte_buf(buf)
+            },
+            head if head & !size_prefix::MASK == prefix::REF => self.read_reference(|de| de.deserialize_byte_buf(visitor)),
+            _ => return self.parse_value(visitor),
+        };
+        res.map_err(|err| self.fix_error(err, end))
     }
-    
-    fn deserialize_tuple_struct<V>(
-        self,
-        name: &'static str,
-        len: usize,
-        visitor: V,
-    ) -> Result<V::Value, Self::Error>
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de> {
-        todo!()
+        if self.peek_head()? == prefix::NONE {
+            self.prev()?;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
     }
-    
-    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de> {
-        todo!()
+        if self.peek_head()? == prefix::NONE {
+            self.prev()?;
+            visitor.visit_unit()
+        } else {
+            self.parse_value(visitor)
+        }
     }
-    
-    fn deserialize_struct<V>(
+
+    fn deserialize_unit_struct<V>(
         self,
-        name: &'static str,
-        fields: &'static [&'static str],
+        _name: &'static str,
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de> {
-        todo!()
+        self.deserialize_unit(visitor)
     }
-    
-    fn deserialize_enum<V>(
+
+    fn deserialize_newtype_struct<V>(
         self,
         name: &'static str,
-        variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de> {
-        todo!()
+        if name == VALUE_TOKEN {
+            self.extended = true;
+        }
+        if name == RAW_TOKEN {
+            let end = self.now_pos()?;
+            return self.read_reference(|de| de.parse_raw(visitor)).map_err(|err| self.fix_error(err, end));
+        }
+        visitor.visit_newtype_struct(self)
     }
-    
-    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de> {
-        todo!()
+        let end = self.now_pos()?;
+        match self.read_reference(|de| de.parse_enum(visitor)) {
+            Ok(value) => Ok(value),
+            Err(err) => Err(self.fix_error(err, end)),
+        }
     }
-    
+
     fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de> {
-        todo!()
+        self.skip_value()?;
+        visitor.visit_unit()
     }
-    
-    // 他の必要なメソッドを実装
-}
\ No newline at end of file
+}
+
+impl<'de, R> ReverseDeserializer<R>
+where
+    R: BorrowReader<'de>,
+{
+    /// 値 1 つ分のバイト列をデコードせずに渡す
+    ///
+    /// 入力を借用できる場合は借用して渡す
+    /// 中の REF は文書の先頭からの位置を、KEYREF は文書の辞書を指すので、
+    /// これらを含む値は参照先の値と key の String に置き換えたバイト列を渡す
+    fn parse_raw<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let end = self.now_pos()?;
+        self.skip_value()?;
+        let start = self.now_pos()?;
+        let bytes = match self.reader.borrow_range(start..end) {
+            Some(bytes) if !refers_outside(bytes)? => return visitor.visit_borrowed_bytes(bytes),
+            Some(_) => None,
+            None => {
+                self.reader.seek(io::SeekFrom::Start(end))?;
+                let bytes = self.read_body(end - start)?;
+                if refers_outside(&bytes)? { None } else { Some(bytes) }
+            }
+        };
+        if let Some(bytes) = bytes {
+            return visitor.visit_byte_buf(bytes);
+        }
+        self.reader.seek(io::SeekFrom::Start(end))?;
+        let mut bytes = Vec::with_capacity((end - start) as usize);
+        self.capture(&mut bytes, 0)?;
+        self.reader.seek(io::SeekFrom::Start(start))?;
+        visitor.visit_byte_buf(bytes)
+    }
+
+    /// シーク位置で終わる値を、中の REF を参照先の値に、KEYREF を key の String に置き換えて `out` に書き出す
+    ///
+    /// 書き直したコンテナの Padding (オフセット索引とチェックサム) は位置が変わるので書かない
+    /// 戻るとシーク位置は値の先頭になる
+    fn capture(&mut self, out: &mut Vec<u8>, depth: usize) -> Result<(), Error> {
+        let end = self.now_pos()?;
+        if depth > MAX_CAPTURE_DEPTH {
+            return Err(Error::new(ErrorCode::Other(format!("nesting is deeper than {}", MAX_CAPTURE_DEPTH)), end as usize - 1));
+        }
+        let head = self.peek_head()?;
+        match head & !size_prefix::MASK {
+            prefix::REF => self.read_reference(|de| de.capture(out, depth)),
+            prefix::KEYREF => {
+                let key = String::deserialize(&mut *self)?;
+                let (header, header_size) = generate_header(prefix::STRING, key.len() as u64);
+                out.extend_from_slice(key.as_bytes());
+                out.extend_from_slice(&header[..header_size]);
+                Ok(())
+            }
+            container @ (prefix::ARRAY | prefix::OBJECT | prefix::TABLE | prefix::META) => {
+                self.prev()?;
+                let size = self.get_size(head)?;
+                let ends = self.scan_children(size, true)?;
+                let start = self.now_pos()?;
+                let body_start = out.len();
+                for child in ends {
+                    self.reader.seek(io::SeekFrom::Start(child))?;
+                    self.capture(out, depth + 1)?;
+                }
+                let (header, header_size) = generate_header(container, (out.len() - body_start) as u64);
+                out.extend_from_slice(&header[..header_size]);
+                self.reader.seek(io::SeekFrom::Start(start))?;
+                Ok(())
+            }
+            _ => {
+                self.skip_value()?;
+                let start = self.now_pos()?;
+                self.reader.seek(io::SeekFrom::Start(end))?;
+                out.extend_from_slice(&self.read_body(end - start)?);
+                Ok(())
+            }
+        }
+    }
+
+    /// enum は unit variant なら String, それ以外は 1 要素の Object
+    fn parse_enum<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let head = self.peek_head()?;
+        match head & !size_prefix::MASK {
+            prefix::STRING => {
+                // unit variant
+                let variant = String::deserialize(&mut *self)?;
+                visitor.visit_enum(variant.into_deserializer())
+            },
+            prefix::OBJECT => {
+                self.prev()?;
+                let head_pos = self.now_pos()?;
+                let size = self.get_size(head)?;
+                let ends = self.scan_children(size, false)?;
+                let start = self.now_pos()?;
+                if ends.len() != 2 {
+                    return Err(Error::new(ErrorCode::Other("enum must be an object with exactly one entry".to_string()), start as usize));
+                }
+                self.enter(head_pos)?;
+                let res = visitor.visit_enum(ReverseEnumAccess { de: &mut *self, value_end: ends[0], key_end: ends[1] });
+                self.deep -= 1;
+                self.reader.seek(io::SeekFrom::Start(start))?;
+                res
+            },
+            _ => {
+                let pos = self.now_pos()?;
+                Err(Error::new(
+                    ErrorCode::InvalidType { expected: "enum".to_string(), found: type_name(head).to_string() },
+                    pos.saturating_sub(1) as usize,
+                ))
+            },
+        }
+    }
+}
+
+/// RTON のバイト列から値をデシリアライズする
+///
+/// ルートの値がバイト列の全体を覆っていない場合は TrailingData エラー
+/// 文書のチェックサムがあれば確かめる
+pub fn from_slice<'a, T>(slice: &'a [u8]) -> Result<T, Error>
+where
+    T: Deserialize<'a>,
+{
+    let mut de = ReverseDeserializer::from_slice(slice)?;
+    let value = deserialize_checked(&mut de)?;
+    de.end()?;
+    Ok(value)
+}
+
+/// 値をデシリアライズしてから文書のチェックサムを確かめる
+///
+/// 先に値を読むので、壊れたコンテナはコンテナのチェックサムの範囲で分かる
+/// 値のデシリアライズに失敗した場合も、文書のチェックサムが合わなければそのエラーを返す
+fn deserialize_checked<'de, R, T>(de: &mut ReverseDeserializer<R>) -> Result<T, Error>
+where
+    R: BorrowReader<'de>,
+    T: Deserialize<'de>,
+{
+    match T::deserialize(&mut *de) {
+        Ok(value) => {
+            de.verify_checksum()?;
+            Ok(value)
+        }
+        Err(err) if err.is_checksum_mismatch() => Err(err),
+        Err(err) => Err(de.verify_checksum().err().unwrap_or(err)),
+    }
+}
+
+/// バイト列の末尾にある値を 1 つデシリアライズし、残りの先頭側の範囲と一緒に返す
+///
+/// 複数の値を詰めたバッファを後ろから順に読むためのもの
+/// 残りの範囲に self-describe tag は含まれない
+pub fn from_slice_partial<'a, T>(slice: &'a [u8]) -> Result<(T, Range<usize>), Error>
+where
+    T: Deserialize<'a>,
+{
+    let mut de = ReverseDeserializer::from_slice(slice)?;
+    let value = deserialize_checked(&mut de)?;
+    let start = de.now_pos()? as usize;
+    Ok((value, 0..start))
+}
+
+/// 表 (Table) の `columns` の列だけを読んでデシリアライズする
+///
+/// 他の列は中身を読まないので、列の多い表から一部のフィールドだけを取り出すのに使う
+/// `T` は絞った列だけをフィールドに持つ構造体の `Vec` などにする
+pub fn from_slice_columns<'a, T>(slice: &'a [u8], columns: &[&str]) -> Result<T, Error>
+where
+    T: Deserialize<'a>,
+{
+    let mut de = ReverseDeserializer::from_slice(slice)?;
+    de.select_columns(columns);
+    let value = deserialize_checked(&mut de)?;
+    de.end()?;
+    Ok(value)
+}
+
+/// 共有した key の辞書を使って書いた文書をデシリアライズする
+pub fn from_slice_with_keys<'a, T>(slice: &'a [u8], dictionary: &KeyDictionary) -> Result<T, Error>
+where
+    T: Deserialize<'a>,
+{
+    let mut de = ReverseDeserializer::from_slice(slice)?;
+    de.use_key_dictionary(dictionary)?;
+    let value = deserialize_checked(&mut de)?;
+    de.end()?;
+    Ok(value)
+}
@@ -0,0 +1,100 @@
+//! 同じ値の重複を除く (back-reference)
+//!
+//! 同じ String や同じ部分木を 2 回目以降は前に書いた値への参照 (REF) で書く
+//! REF の body は参照先の head の位置 (文書の先頭から数えたバイト数) で、参照先は必ず REF より前にある
+//! 書いた値は固定の数の枠を持つハッシュ表で覚え、同じハッシュの値が来たら古い方を忘れる
+
+/// ハッシュ表の枠の数
+const SLOTS: usize = 4096;
+
+/// 覚える値の最大のバイト数 (ハッシュ表の大きさを抑える)
+const MAX_LEN: usize = 4096;
+
+/// 参照で置き換える値の最小のバイト数
+///
+/// 参照は 2 バイト以上なので、これより短い値は覚えない
+const MIN_LEN: usize = 4;
+
+struct Entry {
+    /// head の位置
+    offset: u64,
+    bytes: Box<[u8]>,
+}
+
+/// シリアライザが書いた値を覚える
+pub(crate) struct Deduper {
+    slots: Vec<Option<Entry>>,
+    /// 外側の値を書き終えるまで書き出しが決まらない値の (枠, head の位置)
+    ///
+    /// 外側の値が参照になった場合は、その中で覚えた値を忘れる
+    pending: Vec<(usize, u64)>,
+    /// 外側の値を別のバッファにエンコードしている深さ
+    depth: usize,
+}
+
+impl Deduper {
+    pub(crate) fn new() -> Self {
+        Self { slots: (0..SLOTS).map(|_| None).collect(), pending: Vec::new(), depth: 0 }
+    }
+
+    /// 値のエンコードを始める
+    ///
+    /// 戻り値は `leave` に渡す
+    pub(crate) fn enter(&mut self) -> usize {
+        self.depth += 1;
+        self.pending.len()
+    }
+
+    /// 値のエンコードを終える
+    ///
+    /// `replaced` の場合は `enter` から後に覚えた値を忘れる
+    pub(crate) fn leave(&mut self, mark: usize, replaced: bool) {
+        self.depth -= 1;
+        if replaced {
+            for (slot, offset) in self.pending.drain(mark..) {
+                if self.slots[slot].as_ref().is_some_and(|entry| entry.offset == offset) {
+                    self.slots[slot] = None;
+                }
+            }
+        }
+        if self.depth == 0 {
+            self.pending.clear();
+        }
+    }
+
+    /// 前に書いた同じ値の head の位置
+    pub(crate) fn find(&self, bytes: &[u8]) -> Option<u64> {
+        if !(MIN_LEN..=MAX_LEN).contains(&bytes.len()) {
+            return None;
+        }
+        match &self.slots[slot(bytes)] {
+            Some(entry) if *entry.bytes == *bytes => Some(entry.offset),
+            _ => None,
+        }
+    }
+
+    /// 書いた値を覚える
+    ///
+    /// `offset` は値の head の位置
+    pub(crate) fn insert(&mut self, bytes: &[u8], offset: u64) {
+        if !(MIN_LEN..=MAX_LEN).contains(&bytes.len()) {
+            return;
+        }
+        let slot = slot(bytes);
+        self.slots[slot] = Some(Entry { offset, bytes: bytes.into() });
+        if self.depth > 0 {
+            self.pending.push((slot, offset));
+        }
+    }
+}
+
+/// FNV-1a で枠を選ぶ
+fn slot(bytes: &[u8]) -> usize {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    (hash % SLOTS as u64) as usize
+}
+
@@ -0,0 +1,644 @@
+//! RAW EDIT
+//!
+//! エンコード済みの RTON を組み立て直さずに書き換える
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+
+use serde::Serialize;
+
+use crate::checksum::{container_entry, crc32c, find_container_entry, refresh, refresh_document};
+use crate::de::{read_head, type_name, DEFAULT_REFERENCE_LIMIT};
+use crate::error::{Error, ErrorCode};
+use crate::intern::reference_index;
+use crate::raw::{not_navigable, RawTonRef, TonRef};
+use crate::ser::{generate_header, to_vec, value_to_vec};
+use crate::value::index::parse_array_index;
+use crate::value::prefix::{prefix, size_prefix};
+use crate::value::value::Value;
+
+/// `RawEditor` が REF を展開できるコンテナの深さの上限
+const MAX_SHARED_DEPTH: usize = 1024;
+
+/// `buf` の `pointer` (RFC 6901) にある値を `value` で上書きする
+///
+/// 元の値と同じ型の値だけ書ける
+/// Bool, Int, UInt, Float, UUID, Timestamp, Duration は同じバイト数の場合だけ書き換える
+/// String や Array などの長さが変わる値は、直前にある Padding (`Slack`) を使って大きくしたり、
+/// 余ったバイトを Padding にして小さくしたりする
+/// 親のコンテナのサイズは変わらないので、文書全体の長さも変わらない
+/// 収まらない場合は `Error::is_size_mismatch` のエラーになり、`buf` は変わらない
+/// 文書と親のコンテナのチェックサムは書き直す (書き込んだ値にはチェックサムを付けない)
+/// 重複排除の REF を通る値と REF に参照されている値は、他の値と共有しているのでエラーになる
+/// (`RawEditor` は REF を展開して書き換える)
+///
+/// 書き換えた範囲 (使った Padding を含む) を返す
+pub fn edit_in_place<T>(buf: &mut [u8], pointer: &str, value: &T) -> Result<Range<usize>, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let encoded = to_vec(value)?;
+    write_in_place(buf, pointer, &encoded).map(|(range, _)| range)
+}
+
+/// `edit_in_place` の Value 版
+///
+/// UUID, Timestamp, Duration などの拡張型を書く場合に使う
+pub fn edit_value_in_place(buf: &mut [u8], pointer: &str, value: &Value) -> Result<Range<usize>, Error> {
+    let encoded = value_to_vec(value)?;
+    write_in_place(buf, pointer, &encoded).map(|(range, _)| range)
+}
+
+/// ファイルに対する `edit_in_place`
+///
+/// 値の場所を探すためにファイル全体を読むが、書き込むのは書き換えた範囲とチェックサムだけ
+/// 書き換えた範囲を返す
+pub fn edit_file_in_place<T>(file: &mut File, pointer: &str, value: &T) -> Result<Range<u64>, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let encoded = to_vec(value)?;
+    let mut buf = Vec::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_end(&mut buf)?;
+    let (range, checksums) = write_in_place(&mut buf, pointer, &encoded)?;
+    for written in std::iter::once(range.clone()).chain(checksums) {
+        file.seek(SeekFrom::Start(written.start as u64))?;
+        file.write_all(&buf[written])?;
+    }
+    Ok(range.start as u64..range.end as u64)
+}
+
+/// `pointer` の Array の最後に `value` を追加する
+///
+/// Array の直前にある Padding (`Slack`) を使うので、文書全体の長さは変わらない
+/// 空きが足りない場合は `Error::is_size_mismatch` のエラーになり、`buf` は変わらない
+/// オフセット索引を持つ Array には追加できない
+/// Array にチェックサムがあれば、追加した要素の後ろに付け直す
+///
+/// 書き換えた範囲を返す
+pub fn append_in_place<T>(buf: &mut [u8], pointer: &str, value: &T) -> Result<Range<usize>, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let element = to_vec(value)?;
+    let (target, lower) = locate(buf, pointer)?;
+    if !target.is_array() {
+        return Err(Error::new(
+            ErrorCode::InvalidType { expected: type_name(prefix::ARRAY).to_string(), found: target.type_name().to_string() },
+            target.offset(),
+        ));
+    }
+    if target.has_index() {
+        return Err(Error::new(
+            ErrorCode::Other("cannot append to an indexed array in place".to_string()),
+            target.offset(),
+        ));
+    }
+    let (range, offset) = (target.range(), target.offset());
+    check_unshared(buf, &range, offset)?;
+    let entry = find_container_entry(buf, range.start, range.start + target.body_len());
+    let mut encoded = buf[range.start..entry.map_or(range.start + target.body_len(), |(entry, _)| entry)].to_vec();
+    encoded.extend_from_slice(&element);
+    if entry.is_some() {
+        encoded.extend_from_slice(&container_entry(crc32c(&encoded)));
+    }
+    let (header, header_size) = generate_header(prefix::ARRAY, encoded.len() as u64);
+    encoded.extend_from_slice(&header[..header_size]);
+    let range = replace_with_slack(buf, range, lower, &encoded, offset)?;
+    refresh(buf, range.clone())?;
+    Ok(range)
+}
+
+/// `pointer` にある値と、その値を含む親の body の先頭を探す
+///
+/// Meta は外す (親の body の先頭は一番内側の Meta の body の先頭になる)
+/// ルートの場合は 0
+pub(crate) fn locate<'a>(buf: &'a [u8], pointer: &str) -> Result<(TonRef<'a>, usize), Error> {
+    let mut target = TonRef::new(buf)?;
+    let mut lower = 0;
+    let mut tokens = parse_pointer(pointer)?.into_iter();
+    loop {
+        while target.prefix() == prefix::META {
+            lower = target.range().start;
+            target = match target.children_rev().next() {
+                Some(child) => child?,
+                None => return Err(Error::syntax(ErrorCode::Other("missing meta value".to_string()), target.offset())),
+            };
+        }
+        let token = match tokens.next() {
+            Some(token) => token,
+            None => return Ok((target, lower)),
+        };
+        lower = target.range().start;
+        let next = if target.is_table() {
+            // 表は行の番号と列の名前で列の要素を指す
+            let row = parse_array_index(&token).ok_or_else(|| not_found(pointer))?;
+            let name = tokens.next().ok_or_else(|| not_navigable("table row", target.offset()))?;
+            let column = target.column(&name)?.ok_or_else(|| not_found(pointer))?;
+            lower = column.range().start;
+            match target.cell(row, &name)? {
+                Some(_) => column.index_unresolved(row)?,
+                None => None,
+            }
+        } else if target.is_array() {
+            match parse_array_index(&token) {
+                Some(index) => target.index_unresolved(index)?,
+                None => None,
+            }
+        } else {
+            target.get_unresolved(&token)?
+        };
+        target = next.ok_or_else(|| not_found(pointer))?;
+        // 参照先は他の値と共有しているので、書き換えると他の値も変わる
+        if target.prefix() == prefix::REF {
+            return Err(Error::new(ErrorCode::Other("value is a back-reference".to_string()), target.offset()));
+        }
+    }
+}
+
+/// `range` の中を指す REF があればエラーにする
+///
+/// REF は自分より前しか指さないので、`range` より前で終わる値の中は探さない
+/// `range` の中にある REF は書き換えで消えるので数えない
+fn check_unshared(buf: &[u8], range: &Range<usize>, offset: usize) -> Result<(), Error> {
+    let mut stack = vec![TonRef::new(buf)?];
+    while let Some(value) = stack.pop() {
+        let within = value.range();
+        if within.end <= range.start || (range.start <= within.start && within.end <= range.end) {
+            continue;
+        }
+        if value.prefix() == prefix::REF {
+            if range.contains(&(reference_index(value.body()) as usize)) {
+                return Err(Error::new(
+                    ErrorCode::Other(format!("value is shared with the back-reference at {}", value.offset())),
+                    offset,
+                ));
+            }
+            continue;
+        }
+        for child in value.children_rev() {
+            stack.push(child?);
+        }
+    }
+    Ok(())
+}
+
+/// エンコード済みの値を `pointer` の値に上書きする
+///
+/// 書き換えた範囲と、書き直したチェックサムの範囲を返す
+pub(crate) fn write_in_place(buf: &mut [u8], pointer: &str, encoded: &[u8]) -> Result<(Range<usize>, Vec<Range<usize>>), Error> {
+    let (target, lower) = locate(buf, pointer)?;
+    let new_head = read_head(encoded, encoded.len())?;
+    if new_head.start != 0 {
+        return Err(Error::new(ErrorCode::TrailingData(new_head.start), new_head.start - 1));
+    }
+    if new_head.prefix() != target.prefix() {
+        return Err(Error::new(
+            ErrorCode::InvalidType {
+                expected: target.type_name().to_string(),
+                found: type_name(new_head.head).to_string(),
+            },
+            target.offset(),
+        ));
+    }
+    let (range, offset) = (target.range(), target.offset());
+    if is_fixed_scalar(target.prefix()) && encoded.len() != range.len() {
+        return Err(Error::new(ErrorCode::SizeMismatch { expected: range.len(), found: encoded.len() }, offset));
+    }
+    check_unshared(buf, &range, offset)?;
+    let range = replace_with_slack(buf, range, lower, encoded, offset)?;
+    let checksums = refresh(buf, range.clone())?;
+    Ok((range, checksums))
+}
+
+/// `range` の値を `encoded` で置き換える
+///
+/// 長さが違う場合は `lower` より後ろで `range` の直前に続く Padding を空きとして使い、
+/// 余ったバイトを Padding で埋める
+fn replace_with_slack(
+    buf: &mut [u8],
+    range: Range<usize>,
+    lower: usize,
+    encoded: &[u8],
+    offset: usize,
+) -> Result<Range<usize>, Error> {
+    if encoded.len() == range.len() {
+        buf[range.clone()].copy_from_slice(encoded);
+        return Ok(range);
+    }
+    let mut start = range.start;
+    while start > lower {
+        match read_head(buf, start) {
+            Ok(head) if head.prefix() == prefix::PADDING && head.start >= lower => start = head.start,
+            _ => break,
+        }
+    }
+    let available = range.end - start;
+    let filler = match available.checked_sub(encoded.len()) {
+        Some(filler) if filler != 1 => filler,
+        _ => {
+            return Err(Error::new(ErrorCode::SizeMismatch { expected: available, found: encoded.len() }, offset));
+        }
+    };
+    write_padding(&mut buf[start..start + filler]);
+    buf[start + filler..range.end].copy_from_slice(encoded);
+    Ok(start..range.end)
+}
+
+/// `out` 全体をちょうど埋める Padding を書く
+///
+/// 1 バイトの Padding は作れないので、`out` は空か 2 バイト以上
+fn write_padding(out: &mut [u8]) {
+    if out.is_empty() {
+        return;
+    }
+    let (width, size_prefix) = match out.len() {
+        len if len - 2 <= u8::MAX as usize => (1, size_prefix::SIZE_PREFIX_1BYTE),
+        len if len - 3 <= u16::MAX as usize => (2, size_prefix::SIZE_PREFIX_2BYTE),
+        len if len - 5 <= u32::MAX as usize => (4, size_prefix::SIZE_PREFIX_4BYTE),
+        _ => (8, size_prefix::SIZE_PREFIX_8BYTE),
+    };
+    let body = out.len() - 1 - width;
+    let (head, rest) = out.split_last_mut().unwrap();
+    *head = prefix::PADDING | size_prefix;
+    rest[..body].fill(0);
+    rest[body..].copy_from_slice(&(body as u64).to_le_bytes()[..width]);
+}
+
+/// 長さが head だけで決まり、同じ長さで書き換えられる型か
+fn is_fixed_scalar(prefix: u8) -> bool {
+    matches!(
+        prefix,
+        prefix::BOOL | prefix::INT | prefix::UINT | prefix::FLOAT | prefix::UUID | prefix::TIMESTAMP | prefix::DURATION
+    )
+}
+
+/// 複数の編集をまとめて適用して、新しいバッファを作る
+///
+/// 編集のない部分木はバイト列のままコピーし、編集した値の祖先のコンテナだけ
+/// 新しいサイズで head を書き直す
+/// 値を Value に組み立てないので、大きな文書の 1 つのフィールドを変える場合もほぼコピーだけで済む
+///
+/// パスは JSON Pointer (RFC 6901) で、Array の添字は全て元の文書での位置を指す
+/// 編集したコンテナの中の Padding (オフセット索引を含む) は書き出さない
+/// チェックサムのあったコンテナと文書のチェックサムは書き直す
+/// 重複排除の REF は参照先の位置が変わるので、参照先の値に展開して書き出す
+/// (REF を通るパスの編集は展開した値だけを変える)
+pub struct RawEditor<'a> {
+    buf: &'a [u8],
+    root: EditNode,
+}
+
+/// パスの 1 要素ごとの編集
+#[derive(Default)]
+struct EditNode {
+    op: Option<Op>,
+    /// Array でこの位置の前に挿入する値
+    inserts: Vec<Vec<u8>>,
+    children: BTreeMap<String, EditNode>,
+}
+
+enum Op {
+    /// 置き換える (なければ追加する)
+    Set(Vec<u8>),
+    Delete,
+}
+
+impl<'a> RawEditor<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        RawEditor { buf, root: EditNode::default() }
+    }
+
+    /// `pointer` の値を置き換える
+    ///
+    /// Object に key がなければ追加し、Array の `-` は末尾に追加する
+    pub fn set<T>(&mut self, pointer: &str, value: &T) -> Result<&mut Self, Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let encoded = to_vec(value)?;
+        self.push(pointer, Op::Set(encoded))
+    }
+
+    /// `set` の Value 版
+    pub fn set_value(&mut self, pointer: &str, value: &Value) -> Result<&mut Self, Error> {
+        let encoded = value_to_vec(value)?;
+        self.push(pointer, Op::Set(encoded))
+    }
+
+    /// エンコード済みの値で `pointer` の値を置き換える
+    pub fn set_raw(&mut self, pointer: &str, value: &RawTonRef) -> Result<&mut Self, Error> {
+        self.push(pointer, Op::Set(value.as_bytes().to_vec()))
+    }
+
+    /// `pointer` に値を挿入する
+    ///
+    /// Array では元の文書でその添字にある要素の前に挿入し、`-` や要素数と同じ添字は末尾に追加する
+    /// Object では key を追加し、既にあればエラーになる
+    pub fn insert<T>(&mut self, pointer: &str, value: &T) -> Result<&mut Self, Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let encoded = to_vec(value)?;
+        let (parent, last) = split_pointer(pointer)?;
+        let node = self.node(&parent, pointer)?;
+        let node = node.children.entry(last).or_default();
+        // Object の key の追加か Array の挿入かは適用するときに決める
+        node.inserts.push(encoded);
+        Ok(self)
+    }
+
+    /// `pointer` の値を削除する
+    pub fn delete(&mut self, pointer: &str) -> Result<&mut Self, Error> {
+        self.push(pointer, Op::Delete)
+    }
+
+    /// 編集を適用した新しいバッファを作る
+    ///
+    /// 元の文書のルートの前後にあるバイト (Padding や self-describe tag) はそのまま残す
+    pub fn apply(&self) -> Result<Vec<u8>, Error> {
+        let root = TonRef::new(self.buf)?;
+        let range = root.range();
+        let mut out = Vec::with_capacity(self.buf.len());
+        out.extend_from_slice(&self.buf[..range.start]);
+        match &self.root.op {
+            Some(Op::Set(encoded)) => out.extend_from_slice(encoded),
+            Some(Op::Delete) => {
+                return Err(Error::make(ErrorCode::Other("cannot delete the root value".to_string()), None));
+            }
+            None => {
+                let limit = self.buf.len() + DEFAULT_REFERENCE_LIMIT as usize;
+                let mut shared = Shared { containers: HashSet::new(), limit };
+                collect_shared(root, &mut shared.containers)?;
+                emit(root, &self.root, "", &shared, &mut out)?
+            }
+        }
+        out.extend_from_slice(&self.buf[range.end..]);
+        refresh_document(&mut out);
+        Ok(out)
+    }
+
+    fn push(&mut self, pointer: &str, op: Op) -> Result<&mut Self, Error> {
+        let tokens = parse_pointer(pointer)?;
+        let node = self.node(&tokens, pointer)?;
+        if node.op.is_some() || !node.children.is_empty() {
+            return Err(conflict(pointer));
+        }
+        node.op = Some(op);
+        Ok(self)
+    }
+
+    /// パスの位置の編集を作る
+    ///
+    /// 途中に置き換えや削除の編集があればエラー
+    fn node(&mut self, tokens: &[String], pointer: &str) -> Result<&mut EditNode, Error> {
+        let mut node = &mut self.root;
+        for token in tokens {
+            if node.op.is_some() {
+                return Err(conflict(pointer));
+            }
+            node = node.children.entry(token.clone()).or_default();
+        }
+        if node.op.is_some() {
+            return Err(conflict(pointer));
+        }
+        Ok(node)
+    }
+}
+
+/// REF の展開に使う情報
+struct Shared {
+    /// REF を含むコンテナの head の位置
+    containers: HashSet<usize>,
+    /// 展開した文書の長さの上限
+    limit: usize,
+}
+
+/// REF を含むコンテナの head の位置を集める
+///
+/// 深い文書でもスタックを使い切らないように、明示的なスタックで辿る
+/// 展開は REF を含むコンテナを再帰で書き出すので、REF が `MAX_SHARED_DEPTH` より深ければエラー
+fn collect_shared(root: TonRef, shared: &mut HashSet<usize>) -> Result<(), Error> {
+    let mut stack = vec![(root, 0)];
+    // 辿っている値の祖先の head の位置
+    let mut ancestors = Vec::new();
+    while let Some((value, depth)) = stack.pop() {
+        ancestors.truncate(depth);
+        if value.prefix() == prefix::REF {
+            if depth > MAX_SHARED_DEPTH {
+                return Err(Error::new(
+                    ErrorCode::Other(format!("back-reference is nested deeper than {}", MAX_SHARED_DEPTH)),
+                    value.offset(),
+                ));
+            }
+            // 祖先が集めてあれば、それより外側も集めてある
+            for &offset in ancestors.iter().rev() {
+                if !shared.insert(offset) {
+                    break;
+                }
+            }
+            continue;
+        }
+        ancestors.push(value.offset());
+        for child in value.children_rev() {
+            stack.push((child?, depth + 1));
+        }
+    }
+    Ok(())
+}
+
+/// 編集のない値を書き出す
+///
+/// REF を含まない値はバイト列のままコピーし、REF は参照先の値に展開する
+fn copy(value: TonRef, shared: &Shared, out: &mut Vec<u8>) -> Result<(), Error> {
+    let (pos, value) = (value.offset(), value.resolve()?);
+    if out.len() + value.range().len() > shared.limit {
+        return Err(Error::new(ErrorCode::Other(format!("references expand beyond {} bytes", shared.limit)), pos));
+    }
+    if !shared.containers.contains(&value.offset()) {
+        out.extend_from_slice(value.raw());
+        return Ok(());
+    }
+    let body_start = out.len();
+    for child in value.children()? {
+        copy(child, shared, out)?;
+    }
+    close_container(&value, body_start, out);
+    Ok(())
+}
+
+/// 書き出したコンテナの body の後ろにチェックサムと head を付ける
+fn close_container(value: &TonRef, body_start: usize, out: &mut Vec<u8>) {
+    if matches!(value.prefix(), prefix::ARRAY | prefix::OBJECT)
+        && find_container_entry(value.raw(), 0, value.body_len()).is_some()
+    {
+        let crc = crc32c(&out[body_start..]);
+        out.extend_from_slice(&container_entry(crc));
+    }
+    let (header, size) = generate_header(value.prefix(), (out.len() - body_start) as u64);
+    out.extend_from_slice(&header[..size]);
+}
+
+/// 編集のある値を書き出す
+fn emit(value: TonRef, node: &EditNode, pointer: &str, shared: &Shared, out: &mut Vec<u8>) -> Result<(), Error> {
+    if node.children.is_empty() {
+        return copy(value, shared, out);
+    }
+    let value = value.resolve()?;
+    let body_start = out.len();
+    match value.prefix() {
+        prefix::META => {
+            // Meta は中の値をそのまま辿る
+            let inner = value.children()?.pop().ok_or_else(|| not_found(pointer))?;
+            emit(inner, node, pointer, shared, out)?;
+        }
+        prefix::ARRAY => emit_array(value, node, pointer, shared, out)?,
+        prefix::OBJECT => emit_object(value, node, pointer, shared, out)?,
+        prefix::TABLE => return Err(not_navigable("table row", value.offset())),
+        _ => {
+            let token = node.children.keys().next().map(String::as_str).unwrap_or_default();
+            return Err(not_found(&child_pointer(pointer, token)));
+        }
+    }
+    close_container(&value, body_start, out);
+    Ok(())
+}
+
+fn emit_array(
+    value: TonRef,
+    node: &EditNode,
+    pointer: &str,
+    shared: &Shared,
+    out: &mut Vec<u8>,
+) -> Result<(), Error> {
+    let children = value.children()?;
+    let len = children.len();
+    let mut edits: Vec<Option<&EditNode>> = vec![None; len + 1];
+    for (token, child) in &node.children {
+        let index = match token.as_str() {
+            "-" => len,
+            _ => parse_array_index(token).filter(|&i| i <= len).ok_or_else(|| not_found(&child_pointer(pointer, token)))?,
+        };
+        if edits[index].is_some() {
+            // `-` と要素数の添字は同じ位置を指す
+            return Err(conflict(&child_pointer(pointer, token)));
+        }
+        edits[index] = Some(child);
+    }
+    for (index, edit) in edits.into_iter().enumerate() {
+        let child_value = children.get(index).copied();
+        let Some(edit) = edit else {
+            if let Some(child_value) = child_value {
+                copy(child_value, shared, out)?;
+            }
+            continue;
+        };
+        for inserted in &edit.inserts {
+            out.extend_from_slice(inserted);
+        }
+        let child_pointer = child_pointer(pointer, &index.to_string());
+        match (&edit.op, child_value) {
+            (Some(Op::Set(encoded)), _) => out.extend_from_slice(encoded),
+            (Some(Op::Delete), Some(_)) => {}
+            (None, Some(child_value)) => emit(child_value, edit, &child_pointer, shared, out)?,
+            (None, None) if edit.children.is_empty() => {}
+            _ => return Err(not_found(&child_pointer)),
+        }
+    }
+    Ok(())
+}
+
+fn emit_object(
+    value: TonRef,
+    node: &EditNode,
+    pointer: &str,
+    shared: &Shared,
+    out: &mut Vec<u8>,
+) -> Result<(), Error> {
+    let entries = value.entries()?;
+    // 同じ key が複数ある場合は `TonRef::get` と同じく後ろのものを編集する
+    let mut targets = BTreeMap::new();
+    for (i, (key, _)) in entries.iter().enumerate() {
+        if let Some(key) = key.as_str() {
+            targets.insert(key, i);
+        }
+    }
+    let mut edits: Vec<Option<&EditNode>> = vec![None; entries.len()];
+    let mut added = Vec::new();
+    for (token, child) in &node.children {
+        match targets.get(token.as_str()) {
+            Some(&i) => {
+                if !child.inserts.is_empty() {
+                    return Err(Error::new(
+                        ErrorCode::Other(format!("key {} already exists", child_pointer(pointer, token))),
+                        entries[i].0.offset(),
+                    ));
+                }
+                edits[i] = Some(child);
+            }
+            None => match (&child.op, child.inserts.as_slice()) {
+                (Some(Op::Set(encoded)), []) | (None, [encoded]) if child.children.is_empty() => {
+                    added.push((token, encoded))
+                }
+                _ => return Err(not_found(&child_pointer(pointer, token))),
+            },
+        }
+    }
+    for ((key, child_value), edit) in entries.into_iter().zip(edits) {
+        match edit.map(|edit| (edit, &edit.op)) {
+            None => {
+                copy(child_value, shared, out)?;
+                out.extend_from_slice(key.raw());
+            }
+            Some((_, Some(Op::Set(encoded)))) => {
+                out.extend_from_slice(encoded);
+                out.extend_from_slice(key.raw());
+            }
+            Some((_, Some(Op::Delete))) => {}
+            Some((edit, _)) => {
+                emit(child_value, edit, &child_pointer(pointer, key.as_str().unwrap_or_default()), shared, out)?;
+                out.extend_from_slice(key.raw());
+            }
+        }
+    }
+    for (key, encoded) in added {
+        out.extend_from_slice(encoded);
+        out.extend_from_slice(&to_vec(key.as_str())?);
+    }
+    Ok(())
+}
+
+fn parse_pointer(pointer: &str) -> Result<Vec<String>, Error> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    match pointer.strip_prefix('/') {
+        Some(rest) => Ok(rest.split('/').map(|token| token.replace("~1", "/").replace("~0", "~")).collect()),
+        None => Err(Error::make(ErrorCode::Other(format!("invalid JSON pointer: {:?}", pointer)), None)),
+    }
+}
+
+fn split_pointer(pointer: &str) -> Result<(Vec<String>, String), Error> {
+    let mut tokens = parse_pointer(pointer)?;
+    match tokens.pop() {
+        Some(last) => Ok((tokens, last)),
+        None => Err(conflict(pointer)),
+    }
+}
+
+fn child_pointer(pointer: &str, token: &str) -> String {
+    format!("{}/{}", pointer, token.replace('~', "~0").replace('/', "~1"))
+}
+
+#[cold]
+fn not_found(pointer: &str) -> Error {
+    Error::make(ErrorCode::NotFoundTarget(pointer.to_string()), None)
+}
+
+#[cold]
+fn conflict(pointer: &str) -> Error {
+    Error::make(ErrorCode::Other(format!("conflicting edits at {:?}", pointer)), None)
+}
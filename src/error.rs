@@ -1,5 +1,5 @@
 use core::fmt;
-use std::{fmt::{Debug, Display}, io, result};
+use std::{fmt::{Debug, Display}, io, ops::Range, result};
 use std::str::FromStr;
 
 use serde::{de, ser};
@@ -11,35 +11,97 @@ pub struct Error {
 pub type Result<T> = result::Result<T, Error>;
 
 impl Error {
-    pub fn new(code: ErrorCode, pos: usize) -> Self {
+    pub(crate) fn new(code: ErrorCode, pos: usize) -> Self {
+        Self::make(code, Some(pos))
+    }
+
+    #[inline]
+    pub(crate) fn make(code: ErrorCode, pos: Option<usize>) -> Self {
         Error {
             err: Box::new(ErrorImpl {
                 code,
                 pos,
+                line: 0,
+                column: 0,
+                path: Vec::new(),
             }),
         }
     }
 
+    /// エラーの位置
+    ///
+    /// 位置の情報がない場合は 0
     pub fn pos(&self) -> usize {
+        self.err.pos.unwrap_or(0)
+    }
+
+    /// エラーの原因になった head の絶対位置
+    ///
+    /// テキスト形式の場合は文字列上のバイト位置
+    pub fn offset(&self) -> Option<usize> {
         self.err.pos
     }
 
+    /// テキスト形式のエラー行 (1始まり)
+    ///
+    /// 行の情報がない場合は 0
+    pub fn line(&self) -> usize {
+        self.err.line
+    }
+
+    /// テキスト形式のエラー列 (1始まり)
+    ///
+    /// 列の情報がない場合は 0
+    pub fn column(&self) -> usize {
+        self.err.column
+    }
+
+    /// エラーが起きた値の論理パス
+    ///
+    /// `.users[3].address.zip` の形式
+    /// ルートの値やパスの情報がない場合は None
+    pub fn path(&self) -> Option<String> {
+        if self.err.path.is_empty() {
+            return None;
+        }
+        let mut out = String::new();
+        // 内側から積んでいるので逆順に並べる
+        for segment in self.err.path.iter().rev() {
+            segment.write_to(&mut out);
+        }
+        Some(out)
+    }
+
+    /// 型が合わない場合に期待していた Rust 側の型
+    pub fn expected(&self) -> Option<&str> {
+        match &self.err.code {
+            ErrorCode::InvalidType { expected, .. } => Some(expected),
+            _ => None,
+        }
+    }
+
+    /// 型が合わない場合に実際にあった型
+    ///
+    /// バイナリの場合は `prefix_str` の型名 (`$string` など)
+    pub fn found(&self) -> Option<&str> {
+        match &self.err.code {
+            ErrorCode::InvalidType { found, .. } => Some(found),
+            _ => None,
+        }
+    }
+
     pub fn classify(&self) -> Category {
         match &self.err.code {
             ErrorCode::Message(_) => Category::InvalidType,
             ErrorCode::Io(_) => Category::Io,
-            ErrorCode::NotFoundTarget => Category::Syntax,
-            ErrorCode::InvalidType => Category::InvalidType,
-            ErrorCode::Other(str) => {
-                // 適当
-                if str.contains("EOF") || str.contains("eof") {
-                    Category::Eof
-                } else if str.contains("unknown format") {
-                    Category::UnknownFormat
-                } else {
-                    Category::Syntax
-                }
-            }
+            ErrorCode::Eof(_) => Category::Eof,
+            ErrorCode::UnknownPrefix(_) => Category::UnknownFormat,
+            ErrorCode::NotFoundTarget(_) => Category::Syntax,
+            ErrorCode::InvalidType { .. } => Category::InvalidType,
+            ErrorCode::SizeMismatch { .. } => Category::InvalidType,
+            ErrorCode::TrailingData(_) => Category::Syntax,
+            ErrorCode::ChecksumMismatch(_) => Category::Syntax,
+            ErrorCode::Other(_) => Category::Syntax,
         }
     }
 
@@ -63,21 +125,115 @@ impl Error {
         self.classify() == Category::UnknownFormat
     }
 
+    /// RAW EDIT で書く値のサイズが元の値と違うか
+    ///
+    /// この場合は文書を組み立て直して書く必要がある
+    pub fn is_size_mismatch(&self) -> bool {
+        matches!(self.err.code, ErrorCode::SizeMismatch { .. })
+    }
+
+    /// RAW EDIT で指定した場所に値がないか
+    pub fn is_not_found(&self) -> bool {
+        matches!(self.err.code, ErrorCode::NotFoundTarget(_))
+    }
+
+    /// ルートの値の前に余分なデータが残っているか
+    pub fn is_trailing_data(&self) -> bool {
+        matches!(self.err.code, ErrorCode::TrailingData(_))
+    }
+
+    /// チェックサムが合わないか (保存したデータが壊れている)
+    pub fn is_checksum_mismatch(&self) -> bool {
+        matches!(self.err.code, ErrorCode::ChecksumMismatch(_))
+    }
+
+    /// チェックサムが合わなかった文書やコンテナのバイト列の範囲
+    pub fn checksum_range(&self) -> Option<Range<usize>> {
+        match &self.err.code {
+            ErrorCode::ChecksumMismatch(range) => Some(range.clone()),
+            _ => None,
+        }
+    }
+
 }
 
 pub struct ErrorImpl {
     code: ErrorCode,
-    pos: usize,
+    pos: Option<usize>,
+    line: usize,
+    column: usize,
+    /// 内側の値から順に積む
+    path: Vec<PathSegment>,
 }
 
 pub(crate) enum ErrorCode {
     Message(String),
     Io(io::Error),
-    NotFoundTarget,
-    InvalidType,
+    /// データの終わりに達した
+    Eof(String),
+    /// 未知の head
+    UnknownPrefix(u8),
+    /// RAW EDIT で指定した場所に値がない
+    NotFoundTarget(String),
+    /// 期待した型と実際の型が違う
+    InvalidType {
+        expected: String,
+        found: String,
+    },
+    /// RAW EDIT で書く値のサイズが元の値と違う
+    SizeMismatch {
+        expected: usize,
+        found: usize,
+    },
+    /// ルートの値の前に残ったバイト数
+    ///
+    /// 後ろから読むので、余分なデータはルートの値より前にある
+    TrailingData(usize),
+    /// チェックサムが合わないバイト列の範囲
+    ChecksumMismatch(Range<usize>),
     Other(String),
 }
 
+impl ErrorCode {
+    #[cold]
+    pub(crate) fn eof() -> Self {
+        ErrorCode::Eof("unexpected end of data".to_string())
+    }
+}
+
+/// 論理パスの 1 要素
+pub(crate) enum PathSegment {
+    /// Array の添字
+    Index(usize),
+    /// Object の String キー
+    Key(String),
+    /// String 以外のキー (TON Text の表記)
+    Other(String),
+}
+
+impl PathSegment {
+    fn write_to(&self, out: &mut String) {
+        match self {
+            PathSegment::Index(index) => out.push_str(&format!("[{}]", index)),
+            PathSegment::Key(key) if is_identifier(key) => {
+                out.push('.');
+                out.push_str(key);
+            }
+            PathSegment::Key(key) => {
+                let quoted = serde_json::to_string(key).unwrap_or_default();
+                out.push_str(&format!("[{}]", quoted));
+            }
+            PathSegment::Other(key) => out.push_str(&format!("[{}]", key)),
+        }
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Category {
     /// IOエラー
@@ -90,12 +246,12 @@ pub enum Category {
     InvalidType,
 
     /// 不明なフォーマット
-    /// 
+    ///
     /// 基本のフォーマットには従ってるがprefixなどが不明な場合
     UnknownFormat,
 
     /// 終端エラー
-    /// 
+    ///
     /// ファイルの終端に達した場合
     Eof,
 }
@@ -103,34 +259,79 @@ pub enum Category {
 impl Error {
     #[cold]
     pub(crate) fn syntax(code: ErrorCode, pos: usize) -> Self {
-        Self {
-            err: Box::new(ErrorImpl {
-                code,
-                pos,
-            }),
-        }
+        Self::make(code, Some(pos))
+    }
+
+    /// テキスト形式の位置情報付きでエラーを作る
+    #[cold]
+    pub(crate) fn syntax_at(code: ErrorCode, pos: usize, line: usize, column: usize) -> Self {
+        let mut err = Self::make(code, Some(pos));
+        err.err.line = line;
+        err.err.column = column;
+        err
     }
 
     #[cold]
     pub(crate) fn io(error: io::Error) -> Self {
-        Error {
-            err: Box::new(ErrorImpl {
-                code: ErrorCode::Io(error),
-                pos: 0,
-            }),
+        // ReverseDeserializer::new などが io::Error に包んだエラーは元に戻す
+        if error.get_ref().is_some_and(|inner| inner.is::<Error>()) {
+            return *error.into_inner().unwrap().downcast::<Error>().unwrap();
         }
+        if error.kind() == io::ErrorKind::UnexpectedEof {
+            return Self::make(ErrorCode::eof(), None);
+        }
+        Self::make(ErrorCode::Io(error), None)
+    }
+
+    /// 位置が決まっていないか
+    ///
+    /// visitor から返ってきたエラーなど、どの値で起きたか分かっていない場合
+    #[inline]
+    pub(crate) fn needs_position(&self) -> bool {
+        self.err.pos.is_none() && self.err.line == 0
     }
 
+    /// 位置が決まっていないエラーに値の head の位置と型を付ける
+    ///
+    /// 型が合わないエラーの found は TON の型名に置き換える
     #[cold]
-    pub(crate) fn fix_position<F>(self, f: F) -> Self
-    where
-        F: FnOnce(ErrorCode) -> Error,
-    {
-        if self.err.pos == 0 {
-            f(self.err.code)
-        } else {
-            self
+    pub(crate) fn fix_head(mut self, pos: usize, found: &str) -> Self {
+        if !self.needs_position() {
+            return self;
+        }
+        self.err.pos = Some(pos);
+        if let ErrorCode::InvalidType { found: f, .. } = &mut self.err.code {
+            *f = found.to_string();
         }
+        self
+    }
+
+    /// 位置が決まっていないエラーにテキストの位置を付ける
+    #[cold]
+    pub(crate) fn fix_text_position(mut self, pos: usize, line: usize, column: usize) -> Self {
+        if !self.needs_position() {
+            return self;
+        }
+        self.err.pos = Some(pos);
+        self.err.line = line;
+        self.err.column = column;
+        self
+    }
+
+    /// 部分的なバッファで起きたエラーの位置を元のバッファの位置に直す
+    #[cold]
+    pub(crate) fn shift(mut self, base: usize) -> Self {
+        if let (0, Some(pos)) = (self.err.line, &mut self.err.pos) {
+            *pos += base;
+        }
+        self
+    }
+
+    /// 外側のコンテナでの位置をパスに積む
+    #[cold]
+    pub(crate) fn push_path(mut self, segment: PathSegment) -> Self {
+        self.err.path.push(segment);
+        self
     }
 }
 
@@ -139,8 +340,15 @@ impl Display for ErrorCode {
         match self {
             ErrorCode::Message(msg) => f.write_str(msg),
             ErrorCode::Io(err) => Display::fmt(err, f),
-            ErrorCode::NotFoundTarget => f.write_str("Target not found"),
-            ErrorCode::InvalidType => f.write_str("Invalid type"),
+            ErrorCode::Eof(msg) => f.write_str(msg),
+            ErrorCode::UnknownPrefix(head) => write!(f, "unknown head {:#04x}", head),
+            ErrorCode::NotFoundTarget(target) => write!(f, "target {} not found", target),
+            ErrorCode::InvalidType { expected, found } => write!(f, "invalid type: expected {}, found {}", expected, found),
+            ErrorCode::SizeMismatch { expected, found } => {
+                write!(f, "size mismatch: the new value needs {} bytes but the existing value has {} bytes", found, expected)
+            }
+            ErrorCode::TrailingData(len) => write!(f, "trailing data: {} bytes before the root value", len),
+            ErrorCode::ChecksumMismatch(range) => write!(f, "checksum mismatch in bytes {}..{}", range.start, range.end),
             ErrorCode::Other(msg) => f.write_str(msg),
         }
     }
@@ -150,9 +358,12 @@ impl Debug for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Error {{ code: {:?}, pos: {} }}",
+            "Error {{ code: {:?}, pos: {:?}, line: {}, column: {}, path: {:?} }}",
             self.err.code.to_string(),
-            self.err.pos
+            self.err.pos,
+            self.err.line,
+            self.err.column,
+            self.path().unwrap_or_default()
         )
     }
 }
@@ -163,7 +374,14 @@ impl From<std::io::Error> for Error {
     }
 }
 
-impl serde::de::StdError for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.err.code {
+            ErrorCode::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
 impl Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -172,11 +390,22 @@ impl Display for Error {
 }
 
 impl Display for ErrorImpl {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {  
-        if self.pos == 0 {
-            Display::fmt(&self.code, f)
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.code, f)?;
+        if !self.path.is_empty() {
+            f.write_str(" at ")?;
+            let mut path = String::new();
+            for segment in self.path.iter().rev() {
+                segment.write_to(&mut path);
+            }
+            f.write_str(&path)?;
+        }
+        if self.line != 0 {
+            write!(f, " at line {} column {}", self.line, self.column)
+        } else if let Some(pos) = self.pos {
+            write!(f, " at offset {}", pos)
         } else {
-            write!(f, "{} at position {}", self.code, self.pos)
+            Ok(())
         }
     }
 }
@@ -186,6 +415,17 @@ impl de::Error for Error {
     fn custom<T: Display>(msg: T) -> Self {
         make_error(msg.to_string())
     }
+
+    #[cold]
+    fn invalid_type(unexp: de::Unexpected, exp: &dyn de::Expected) -> Self {
+        Self::make(
+            ErrorCode::InvalidType {
+                expected: exp.to_string(),
+                found: unexp.to_string(),
+            },
+            None,
+        )
+    }
 }
 
 impl ser::Error for Error {
@@ -196,20 +436,13 @@ impl ser::Error for Error {
 }
 
 fn make_error(mut msg: String) -> Error {
-    let pos = parse_pos(&mut msg).unwrap_or(0);
-    Error {
-        err: Box::new(ErrorImpl {
-            code: ErrorCode::Message(msg),
-            pos,
-        }),
-    }
+    let pos = parse_pos(&mut msg);
+    Error::make(ErrorCode::Message(msg), pos)
 }
 
 fn parse_pos(msg: &mut String) -> Option<usize> {
-    let start_of_suffix = match msg.rfind(" at pos ") {
-        Some(index) => index,
-        None => return None, // " at pos " が見つからなければ解析できない
-    };
+    // " at pos " が見つからなければ解析できない
+    let start_of_suffix = msg.rfind(" at pos ")?;
 
     // " at pos " の直後にある数値を探す
     let start_of_pos = start_of_suffix + " at pos ".len();
@@ -228,4 +461,4 @@ fn parse_pos(msg: &mut String) -> Option<usize> {
     // `msg` から " at pos X" を削除
     msg.truncate(start_of_suffix);
     Some(pos)
-}
\ No newline at end of file
+}
@@ -0,0 +1,368 @@
+//! 追記専用のレコードファイル
+//!
+//! 独立したレコードを 1 つのファイルに追記していき、番号で引けるようにする
+//! 形式は `record_file` を参照
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::de::ReverseDeserializer;
+use crate::error::{Error, ErrorCode};
+use crate::ser::ReverseSerializer;
+use crate::stream::frame;
+use crate::value::prefix::record_file::{FOOTER, FOOTER_TRAILER_LEN, HEADER};
+use crate::value::prefix::stream_frame::{self, EDGE_LEN};
+
+/// レコードの番号
+///
+/// 追記した順に 0 から振る
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RecordId(pub u64);
+
+impl RecordId {
+    pub fn index(self) -> u64 {
+        self.0
+    }
+}
+
+/// 追記専用のレコードファイル
+///
+/// `append` したレコードはすぐにファイルに書くが、
+/// レコードの位置の索引 (footer) は `sync` まで書かない
+/// footer のないファイルを開いた場合は、各レコードの枠の trailer を確かめながら辿り直し、
+/// 途中で切れたレコードは捨てる
+pub struct TonFile {
+    file: File,
+    /// 各レコードの枠の先頭
+    offsets: Vec<u64>,
+    /// 最後のレコードの終端
+    data_end: u64,
+    /// ファイルの長さ (footer や切れたレコードを含む)
+    file_len: u64,
+    /// ファイル上の footer が offsets と一致しているか
+    synced: bool,
+    buf: Vec<u8>,
+}
+
+impl TonFile {
+    /// ファイルを開く
+    ///
+    /// ファイルがなければ作る
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+        Self::from_file(file)
+    }
+
+    /// 空のファイルを作る
+    ///
+    /// 既にあれば中身を消す
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(path)?;
+        Self::from_file(file)
+    }
+
+    /// 読み書きできる `File` から作る
+    pub fn from_file(mut file: File) -> Result<Self, Error> {
+        let file_len = file.seek(SeekFrom::End(0))?;
+        let header_len = HEADER.len() as u64;
+        if file_len == 0 {
+            file.write_all(&HEADER)?;
+            return Ok(TonFile {
+                file,
+                offsets: Vec::new(),
+                data_end: header_len,
+                file_len: header_len,
+                synced: false,
+                buf: Vec::new(),
+            });
+        }
+        let mut header = [0u8; HEADER.len()];
+        if file_len < header_len || read_at(&mut file, 0, &mut header).is_err() || header != HEADER {
+            return Err(Error::syntax(ErrorCode::Other("not a TON record file".to_string()), 0));
+        }
+
+        let mut this = TonFile {
+            file,
+            offsets: Vec::new(),
+            data_end: header_len,
+            file_len,
+            synced: false,
+            buf: Vec::new(),
+        };
+        if !this.read_footer()? {
+            this.recover()?;
+        }
+        Ok(this)
+    }
+
+    /// footer を読む
+    ///
+    /// footer がないか、レコードと合わない場合は false
+    fn read_footer(&mut self) -> Result<bool, Error> {
+        let trailer_len = FOOTER_TRAILER_LEN as u64;
+        if self.file_len < HEADER.len() as u64 + trailer_len {
+            return Ok(false);
+        }
+        let mut trailer = [0u8; FOOTER_TRAILER_LEN];
+        read_at(&mut self.file, self.file_len - trailer_len, &mut trailer)?;
+        if trailer[8..] != FOOTER {
+            return Ok(false);
+        }
+        let count = u64::from_le_bytes(trailer[..8].try_into().unwrap());
+        let footer_start = match count
+            .checked_mul(8)
+            .and_then(|table| (self.file_len - trailer_len).checked_sub(table))
+        {
+            Some(start) if start >= HEADER.len() as u64 => start,
+            _ => return Ok(false),
+        };
+        let mut table = vec![0u8; (count * 8) as usize];
+        read_at(&mut self.file, footer_start, &mut table)?;
+        let offsets: Vec<u64> = table.chunks_exact(8).map(|c| u64::from_le_bytes(c.try_into().unwrap())).collect();
+
+        // 索引が前から順に並んでいて、最後のレコードが footer の直前で終わっているか
+        let ordered = offsets.first().is_none_or(|&first| first == HEADER.len() as u64)
+            && offsets.windows(2).all(|w| w[0].checked_add((EDGE_LEN * 2) as u64).is_some_and(|end| end <= w[1]));
+        if !ordered {
+            return Ok(false);
+        }
+        let data_end = match offsets.last() {
+            Some(&last) => self.frame_at(last)?,
+            None => Some(HEADER.len() as u64),
+        };
+        if data_end != Some(footer_start) {
+            return Ok(false);
+        }
+        self.offsets = offsets;
+        self.data_end = footer_start;
+        self.synced = true;
+        Ok(true)
+    }
+
+    /// 先頭からレコードの枠を辿り直す
+    ///
+    /// 枠が壊れているか途中で切れている所から後ろは捨てる
+    fn recover(&mut self) -> Result<(), Error> {
+        let mut pos = HEADER.len() as u64;
+        self.offsets.clear();
+        while let Some(end) = self.frame_at(pos)? {
+            self.offsets.push(pos);
+            pos = end;
+        }
+        self.data_end = pos;
+        self.synced = false;
+        Ok(())
+    }
+
+    /// pos から始まるレコードの枠の終端
+    ///
+    /// 先頭と末尾 (trailer) の magic と長さが揃っていなければ None
+    fn frame_at(&mut self, pos: u64) -> Result<Option<u64>, Error> {
+        let edge = EDGE_LEN as u64;
+        if self.file_len.saturating_sub(pos) < edge * 2 {
+            return Ok(None);
+        }
+        let mut head = [0u8; EDGE_LEN];
+        read_at(&mut self.file, pos, &mut head)?;
+        if head[..4] != stream_frame::HEAD {
+            return Ok(None);
+        }
+        let len = u64::from_le_bytes(head[4..].try_into().unwrap());
+        if self.file_len - pos - edge * 2 < len {
+            return Ok(None);
+        }
+        let end = pos + edge * 2 + len;
+        let mut tail = [0u8; EDGE_LEN];
+        read_at(&mut self.file, end - edge, &mut tail)?;
+        if tail[8..] != stream_frame::TAIL || tail[..8] != head[4..] {
+            return Ok(None);
+        }
+        Ok(Some(end))
+    }
+
+    /// 値を 1 レコードとして追記する
+    pub fn append<T>(&mut self, value: &T) -> Result<RecordId, Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut buf = std::mem::take(&mut self.buf);
+        buf.clear();
+        let mut ser = ReverseSerializer::new(buf);
+        let res = value.serialize(&mut ser);
+        let buf = ser.into_inner();
+        let res = res.and_then(|_| self.append_raw(&buf));
+        self.buf = buf;
+        res
+    }
+
+    /// エンコード済みの RTON を 1 レコードとして追記する
+    pub fn append_raw(&mut self, document: &[u8]) -> Result<RecordId, Error> {
+        // 古い footer や切れたレコードは上書きする前に切り詰める
+        if self.file_len > self.data_end {
+            self.file.set_len(self.data_end)?;
+            self.file_len = self.data_end;
+        }
+        let frame = frame(document);
+        self.file.seek(SeekFrom::Start(self.data_end))?;
+        self.file.write_all(&frame)?;
+
+        let id = RecordId(self.offsets.len() as u64);
+        self.offsets.push(self.data_end);
+        self.data_end += frame.len() as u64;
+        self.file_len = self.data_end;
+        self.synced = false;
+        Ok(id)
+    }
+
+    /// footer を書いてファイルをディスクに同期する
+    pub fn sync(&mut self) -> Result<(), Error> {
+        if !self.synced {
+            let mut footer = Vec::with_capacity(self.offsets.len() * 8 + FOOTER_TRAILER_LEN);
+            for offset in &self.offsets {
+                footer.extend_from_slice(&offset.to_le_bytes());
+            }
+            footer.extend_from_slice(&(self.offsets.len() as u64).to_le_bytes());
+            footer.extend_from_slice(&FOOTER);
+            self.file.seek(SeekFrom::Start(self.data_end))?;
+            self.file.write_all(&footer)?;
+            let end = self.data_end + footer.len() as u64;
+            if self.file_len > end {
+                self.file.set_len(end)?;
+            }
+            self.file_len = end;
+            self.synced = true;
+        }
+        self.file.sync_all()?;
+        Ok(())
+    }
+
+    /// レコード数
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// レコードの document がある範囲
+    pub fn range(&self, id: RecordId) -> Option<std::ops::Range<u64>> {
+        let index = usize::try_from(id.0).ok()?;
+        let start = *self.offsets.get(index)? + EDGE_LEN as u64;
+        let end = self.offsets.get(index + 1).copied().unwrap_or(self.data_end) - EDGE_LEN as u64;
+        Some(start..end)
+    }
+
+    /// レコードをデシリアライズする
+    ///
+    /// ファイルのシーク位置を使わないので `&self` で読める
+    /// Windows ではファイルのシーク位置が動くが、`append` と `sync` は書く前に必ずシークするので影響しない
+    pub fn get<T>(&self, id: RecordId) -> Result<Option<T>, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let Some(range) = self.range(id) else {
+            return Ok(None);
+        };
+        let start = range.start as usize;
+        let decode = || -> Result<T, Error> {
+            let mut de = ReverseDeserializer::from_shared_file(&self.file, range)?;
+            let value = T::deserialize(&mut de)?;
+            de.end()?;
+            Ok(value)
+        };
+        decode().map(Some).map_err(|err| err.shift(start))
+    }
+
+    /// 先頭のレコードから順にデシリアライズするイテレータ
+    pub fn iter<T>(&self) -> Records<'_, T>
+    where
+        T: DeserializeOwned,
+    {
+        Records {
+            file: self,
+            front: 0,
+            back: self.offsets.len(),
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// 最後のレコードから順にデシリアライズするイテレータ
+    pub fn iter_rev<T>(&self) -> std::iter::Rev<Records<'_, T>>
+    where
+        T: DeserializeOwned,
+    {
+        self.iter().rev()
+    }
+
+    /// `get` はファイルのシーク位置を使わないので、シーク位置を使う場合は先にシークする
+    /// (Windows では `get` がシーク位置を動かす)
+    pub fn get_ref(&self) -> &File {
+        &self.file
+    }
+
+    pub fn into_inner(self) -> File {
+        self.file
+    }
+}
+
+/// `TonFile` のレコードを順にデシリアライズするイテレータ
+///
+/// レコードごとに `Result` を返すので、壊れたレコードがあっても続きを読める
+pub struct Records<'a, T> {
+    file: &'a TonFile,
+    front: usize,
+    back: usize,
+    _marker: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> Iterator for Records<'_, T> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        let id = RecordId(self.front as u64);
+        self.front += 1;
+        Some(self.file.get(id).and_then(|value| value.ok_or_else(missing)))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.front = self.front.saturating_add(n).min(self.back);
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<T: DeserializeOwned> DoubleEndedIterator for Records<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        self.back -= 1;
+        let id = RecordId(self.back as u64);
+        Some(self.file.get(id).and_then(|value| value.ok_or_else(missing)))
+    }
+}
+
+impl<T: DeserializeOwned> ExactSizeIterator for Records<'_, T> {}
+
+#[cold]
+fn missing() -> Error {
+    Error::syntax(ErrorCode::Other("record not found".to_string()), 0)
+}
+
+fn read_at(file: &mut File, pos: u64, buf: &mut [u8]) -> io::Result<()> {
+    file.seek(SeekFrom::Start(pos))?;
+    file.read_exact(buf)
+}
@@ -0,0 +1,318 @@
+//! Object の key の辞書 (key interning)
+//!
+//! 同じ形の Object が並ぶ文書では同じ key の文字列が何度も書かれるので、key を辞書の番号 (KEYREF) で書く
+//! 辞書はルートの値の後ろに PADDING として置く (形式は `key_dictionary` を参照)
+//! 書く側と読む側で共有する外部の辞書 (`KeyDictionary`) を使うと、そこにある key は文書に書かない
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::checksum::document_end;
+use crate::de::read_head;
+use crate::error::{Error, ErrorCode};
+use crate::value::prefix::{key_dictionary, prefix, size_prefix};
+
+/// 書く側と読む側で共有する key の辞書
+///
+/// key には追加した順に 0 から番号が付く
+/// 同じ辞書を `SerializeOptions::key_dictionary` と `ReverseDeserializer::use_key_dictionary` に渡す
+/// 辞書の内容から作った指紋を文書に書くので、違う辞書で読むとエラーになる
+///
+/// ```ignore
+/// let dict = KeyDictionary::new(["id", "name", "email"]);
+/// let bytes = to_vec_with(&users, &SerializeOptions::new().key_dictionary(&dict))?;
+/// let users: Vec<User> = from_slice_with_keys(&bytes, &dict)?;
+/// ```
+#[derive(Clone, Default)]
+pub struct KeyDictionary {
+    inner: Arc<DictionaryInner>,
+}
+
+#[derive(Default)]
+struct DictionaryInner {
+    keys: Vec<String>,
+    positions: HashMap<String, u64>,
+    fingerprint: u64,
+}
+
+impl KeyDictionary {
+    /// `keys` の順に番号を付けた辞書を作る
+    ///
+    /// 重複した key は最初の番号を使う
+    pub fn new<I, S>(keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let keys: Vec<String> = keys.into_iter().map(Into::into).collect();
+        let mut positions = HashMap::with_capacity(keys.len());
+        for (i, key) in keys.iter().enumerate() {
+            positions.entry(key.clone()).or_insert(i as u64);
+        }
+        let fingerprint = fingerprint(&keys);
+        Self { inner: Arc::new(DictionaryInner { keys, positions, fingerprint }) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.keys.is_empty()
+    }
+
+    /// 番号の key
+    pub fn get(&self, index: u64) -> Option<&str> {
+        usize::try_from(index).ok().and_then(|index| self.inner.keys.get(index)).map(String::as_str)
+    }
+
+    /// key の番号
+    pub fn position(&self, key: &str) -> Option<u64> {
+        self.inner.positions.get(key).copied()
+    }
+
+    pub fn keys(&self) -> &[String] {
+        &self.inner.keys
+    }
+
+    /// 辞書の内容から作った指紋 (0 にはならない)
+    pub fn fingerprint(&self) -> u64 {
+        self.inner.fingerprint
+    }
+}
+
+impl PartialEq for KeyDictionary {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner.keys == other.inner.keys
+    }
+}
+
+impl Eq for KeyDictionary {}
+
+impl fmt::Debug for KeyDictionary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("KeyDictionary").field(&self.inner.keys).finish()
+    }
+}
+
+/// key の並びの FNV-1a (64 bit)
+///
+/// 0 は外部の辞書を使わない文書を表すので使わない
+fn fingerprint(keys: &[String]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for key in keys {
+        for &byte in (key.len() as u64).to_le_bytes().iter().chain(key.as_bytes()) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash.max(1)
+}
+
+/// シリアライザが key に番号を振る
+pub(crate) struct Interner {
+    external: Option<KeyDictionary>,
+    /// 文書の辞書に書く key
+    keys: Vec<String>,
+    positions: HashMap<String, u64>,
+}
+
+impl Interner {
+    pub(crate) fn new(external: Option<KeyDictionary>) -> Self {
+        Self { external, keys: Vec::new(), positions: HashMap::new() }
+    }
+
+    fn base(&self) -> u64 {
+        self.external.as_ref().map_or(0, |external| external.len() as u64)
+    }
+
+    /// key の番号
+    ///
+    /// 外部の辞書になければ文書の辞書に追加する
+    pub(crate) fn intern(&mut self, key: &str) -> u64 {
+        if let Some(index) = self.external.as_ref().and_then(|external| external.position(key)) {
+            return index;
+        }
+        if let Some(&index) = self.positions.get(key) {
+            return index;
+        }
+        let index = self.base() + self.keys.len() as u64;
+        self.keys.push(key.to_string());
+        self.positions.insert(key.to_string(), index);
+        index
+    }
+
+    /// 文書の辞書 (PADDING の body)
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let keys_len: usize = self.keys.iter().map(String::len).sum();
+        let mut body = Vec::with_capacity(keys_len + self.keys.len() * 4 + key_dictionary::TRAILER_LEN);
+        for key in &self.keys {
+            body.extend_from_slice(key.as_bytes());
+        }
+        let mut end = 0u32;
+        for key in &self.keys {
+            end += key.len() as u32;
+            body.extend_from_slice(&end.to_le_bytes());
+        }
+        body.extend_from_slice(&self.base().to_le_bytes());
+        body.extend_from_slice(&(self.keys.len() as u64).to_le_bytes());
+        body.extend_from_slice(&self.external.as_ref().map_or(0, KeyDictionary::fingerprint).to_le_bytes());
+        body.extend_from_slice(&key_dictionary::MAGIC);
+        body
+    }
+}
+
+/// KEYREF や REF のように番号だけを body に持つ値を 1 つエンコードする
+///
+/// 番号は入る一番小さい幅で書く
+pub(crate) fn encode_reference(prefix: u8, index: u64) -> ([u8; 9], usize) {
+    let (width, size_bits) = match index {
+        0..=0xff => (1, size_prefix::SIZE_PREFIX_1BYTE),
+        0x100..=0xffff => (2, size_prefix::SIZE_PREFIX_2BYTE),
+        0x1_0000..=0xffff_ffff => (4, size_prefix::SIZE_PREFIX_4BYTE),
+        _ => (8, size_prefix::SIZE_PREFIX_8BYTE),
+    };
+    let mut buf = [0u8; 9];
+    buf[..width].copy_from_slice(&index.to_le_bytes()[..width]);
+    buf[width] = prefix | size_bits;
+    (buf, width + 1)
+}
+
+/// KEYREF や REF の body (リトルエンディアンの番号) を読む
+pub(crate) fn reference_index(body: &[u8]) -> u64 {
+    let mut buf = [0u8; 8];
+    buf[..body.len()].copy_from_slice(body);
+    u64::from_le_bytes(buf)
+}
+
+/// 文書の辞書 (借用)
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DocumentKeys<'a> {
+    keys: &'a [u8],
+    ends: &'a [u8],
+    base: u64,
+    count: usize,
+    fingerprint: u64,
+}
+
+impl<'a> DocumentKeys<'a> {
+    /// PADDING の body を辞書として読む
+    ///
+    /// MAGIC がなければ None、辞書の形が壊れていればエラー
+    /// `pos` はエラーに付ける位置 (PADDING の head)
+    pub(crate) fn parse(body: &'a [u8], pos: usize) -> Result<Option<Self>, Error> {
+        if body.len() < key_dictionary::TRAILER_LEN || !body.ends_with(&key_dictionary::MAGIC) {
+            return Ok(None);
+        }
+        let (rest, trailer) = body.split_at(body.len() - key_dictionary::TRAILER_LEN);
+        let base = u64::from_le_bytes(trailer[..8].try_into().unwrap());
+        let count = u64::from_le_bytes(trailer[8..16].try_into().unwrap());
+        let fingerprint = u64::from_le_bytes(trailer[16..24].try_into().unwrap());
+        let table_len = usize::try_from(count).ok().and_then(|count| count.checked_mul(4)).filter(|&len| len <= rest.len());
+        let Some(table_len) = table_len else {
+            return Err(error(format!("key dictionary has {} keys but only {} bytes", count, rest.len()), pos));
+        };
+        let (keys, ends) = rest.split_at(rest.len() - table_len);
+        let mut prev = 0;
+        for end in ends.chunks_exact(4) {
+            let end = u32::from_le_bytes(end.try_into().unwrap()) as usize;
+            if end < prev || end > keys.len() {
+                return Err(error(format!("key dictionary has an invalid key end {}", end), pos));
+            }
+            prev = end;
+        }
+        if prev != keys.len() {
+            return Err(error("key dictionary has bytes after its last key", pos));
+        }
+        Ok(Some(Self { keys, ends, base, count: count as usize, fingerprint }))
+    }
+
+    /// 文書の末尾 (self-describe tag と文書のチェックサムの前) にある辞書を探す
+    pub(crate) fn find(buf: &'a [u8]) -> Result<Option<Self>, Error> {
+        match read_head(buf, document_end(buf)) {
+            Ok(head) if head.prefix() == prefix::PADDING => Self::parse(&buf[head.start..head.head_start], head.end - 1),
+            _ => Ok(None),
+        }
+    }
+
+    /// 外部の辞書の key の数 (文書の key の番号の始まり)
+    pub(crate) fn base(&self) -> u64 {
+        self.base
+    }
+
+    /// 文書の辞書にある番号の key
+    ///
+    /// 外部の辞書の番号や範囲外の番号は None
+    pub(crate) fn get(&self, index: u64) -> Option<&'a [u8]> {
+        let i = usize::try_from(index.checked_sub(self.base)?).ok().filter(|&i| i < self.count)?;
+        let end = |i: usize| u32::from_le_bytes(self.ends[i * 4..i * 4 + 4].try_into().unwrap()) as usize;
+        let start = if i == 0 { 0 } else { end(i - 1) };
+        Some(&self.keys[start..end(i)])
+    }
+
+    /// 番号の上限 (外部の辞書と文書の辞書の key の合計)
+    pub(crate) fn limit(&self) -> u64 {
+        self.base.saturating_add(self.count as u64)
+    }
+}
+
+/// デシリアライザが KEYREF を引くための辞書
+#[derive(Debug, Clone, Default)]
+pub(crate) struct KeyTable {
+    base: u64,
+    fingerprint: u64,
+    keys: Vec<String>,
+    external: Option<KeyDictionary>,
+}
+
+impl KeyTable {
+    /// 文書の辞書の key を UTF-8 として読み込む
+    pub(crate) fn from_document(keys: &DocumentKeys, pos: usize) -> Result<Self, Error> {
+        let mut table = Self { base: keys.base, fingerprint: keys.fingerprint, keys: Vec::with_capacity(keys.count), external: None };
+        for i in 0..keys.count as u64 {
+            let key = keys.get(keys.base + i).unwrap_or_default();
+            let key = std::str::from_utf8(key).map_err(|_| error("key dictionary has a key that is not valid UTF-8", pos))?;
+            table.keys.push(key.to_string());
+        }
+        Ok(table)
+    }
+
+    /// 外部の辞書を使う
+    ///
+    /// 文書が外部の辞書を使って書かれている場合は、指紋が一致しなければエラー
+    pub(crate) fn set_external(&mut self, external: &KeyDictionary) -> Result<(), Error> {
+        if self.fingerprint != 0 && self.fingerprint != external.fingerprint() {
+            return Err(Error::make(ErrorCode::Other("key dictionary does not match the document".to_string()), None));
+        }
+        self.external = Some(external.clone());
+        Ok(())
+    }
+
+    /// 文書に辞書がない場合に外部の辞書だけを使う
+    pub(crate) fn external_only(external: &KeyDictionary) -> Self {
+        Self { base: external.len() as u64, fingerprint: external.fingerprint(), keys: Vec::new(), external: Some(external.clone()) }
+    }
+
+    /// KEYREF の番号の key
+    ///
+    /// `pos` はエラーに付ける位置 (KEYREF の head)
+    pub(crate) fn resolve(&self, index: u64, pos: usize) -> Result<&str, Error> {
+        if index < self.base {
+            return match &self.external {
+                Some(external) => external.get(index).ok_or_else(|| error(format!("key reference {} is out of the key dictionary", index), pos)),
+                None => Err(error("document needs an external key dictionary", pos)),
+            };
+        }
+        usize::try_from(index - self.base)
+            .ok()
+            .and_then(|i| self.keys.get(i))
+            .map(String::as_str)
+            .ok_or_else(|| error(format!("key reference {} is out of the key dictionary", index), pos))
+    }
+}
+
+#[cold]
+fn error(msg: impl Into<String>, pos: usize) -> Error {
+    Error::syntax(ErrorCode::Other(msg.into()), pos)
+}
@@ -2,6 +2,30 @@ pub mod value;
 pub mod error;
 pub mod ser;
 pub mod de;
+pub mod text;
 pub mod stream;
-pub mod bin;
-pub mod traits;
\ No newline at end of file
+pub mod traits;
+pub mod validate;
+pub mod raw;
+pub mod file;
+pub mod recover;
+pub mod edit;
+pub mod slack;
+pub mod packed;
+pub mod columnar;
+pub mod intern;
+pub mod compress;
+pub mod checksum;
+mod dedup;
+
+pub use validate::{validate, ValidationReport};
+pub use raw::{to_raw_ton, Lazy, Primitive, RawTon, RawTonRef, TonRef};
+pub use file::{RecordId, TonFile};
+pub use recover::{recover, RecoveredValue, RecoveryReport};
+pub use edit::{append_in_place, edit_file_in_place, edit_in_place, edit_value_in_place, RawEditor};
+pub use slack::Slack;
+pub use packed::{ArrayEncoding, Packed};
+pub use columnar::Columnar;
+pub use intern::KeyDictionary;
+pub use compress::{Codec, Compression, Lz};
+pub use checksum::crc32c;
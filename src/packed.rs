@@ -0,0 +1,424 @@
+//! 要素ごとの head を持たない型付き配列 (Typed Array)
+//!
+//! body は `[要素; count] element_head` の形で、要素はリトルエンディアンで前から詰めて並べる
+//! element_head は要素を単体で書いた場合の head で、要素の型と幅を表す
+//! Bool は 1 要素 1 ビット (下位ビットから) で、`[ビット列] 最後のバイトのビット数:u8 element_head` になる
+//!
+//! 要素を差分やランレングスで符号化した Encoded Array もここで扱う (形式は `array_encoding` を参照)
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::de::read_head;
+use crate::error::{Error, ErrorCode};
+use crate::value::prefix::{array_encoding, prefix, size_prefix};
+
+/// 型付き配列で書くことをシリアライザに伝える newtype の名前
+pub(crate) const PACKED_TOKEN: &str = "$serde_ton::private::Packed";
+
+/// 中身のシーケンスを型付き配列で書き出すラッパー
+///
+/// 要素が全て同じ型の数値, Bool, UUID, Timestamp の場合だけ詰めて書き、それ以外は通常の Array になる
+/// RTON 以外のフォーマットや読み込み時は `T` と同じように扱う
+/// 全てのシーケンスを詰める場合は `SerializeOptions::packed_arrays` を使う
+///
+/// ```ignore
+/// #[derive(Serialize, Deserialize)]
+/// struct Embedding {
+///     id: u64,
+///     vector: Packed<Vec<f32>>,
+/// }
+/// ```
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Packed<T>(pub T);
+
+impl<T> Packed<T> {
+    pub fn new(value: T) -> Self {
+        Packed(value)
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Packed<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Packed<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T> From<T> for Packed<T> {
+    fn from(value: T) -> Self {
+        Packed(value)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Packed<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T: Serialize> Serialize for Packed<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(PACKED_TOKEN, &self.0)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Packed<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Packed)
+    }
+}
+
+/// 型付き配列の要素にできる head の要素 1 つのバイト数
+///
+/// Bool は 0 (ビット単位)
+pub(crate) fn element_width(head: u8) -> Option<usize> {
+    const BOOL: u8 = prefix::BOOL | size_prefix::SIZE_PREFIX_1BYTE;
+    const TIMESTAMP: u8 = prefix::TIMESTAMP | size_prefix::SIZE_PREFIX_8BYTE;
+    const WIDTHS: [usize; 4] = [1, 2, 4, 8];
+    let size_bits = (head & size_prefix::MASK) as usize;
+    match head & !size_prefix::MASK {
+        _ if head == BOOL => Some(0),
+        prefix::INT | prefix::UINT => Some(WIDTHS[size_bits]),
+        prefix::FLOAT if size_bits != 0 => Some(WIDTHS[size_bits]),
+        _ if head == prefix::UUID => Some(16),
+        _ if head == TIMESTAMP => Some(8),
+        _ => None,
+    }
+}
+
+/// 型付き配列の body
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TypedArray<'a> {
+    /// 要素の head (Bool は false の head)
+    pub(crate) element: u8,
+    /// 要素 1 つのバイト数 (Bool は 0)
+    pub(crate) width: usize,
+    pub(crate) count: usize,
+    /// 要素を詰めたバイト列
+    pub(crate) data: &'a [u8],
+}
+
+impl<'a> TypedArray<'a> {
+    /// body を読む
+    ///
+    /// `pos` はエラーに付ける位置 (値の head)
+    pub(crate) fn parse(body: &'a [u8], pos: usize) -> Result<Self, Error> {
+        let (&element, rest) = body.split_last().ok_or_else(|| error("typed array has no element type", pos))?;
+        let width = element_width(element)
+            .ok_or_else(|| error(format!("invalid typed array element head 0x{:02x}", element), pos))?;
+        if width == 0 {
+            let (&last_bits, data) = rest.split_last().ok_or_else(|| error("bool array has no bit count", pos))?;
+            let count = match (data.len(), last_bits) {
+                (0, 0) => 0,
+                (len, 1..=8) if len > 0 => (len - 1) * 8 + last_bits as usize,
+                _ => return Err(error(format!("bool array has an invalid bit count {}", last_bits), pos)),
+            };
+            return Ok(Self { element, width, count, data });
+        }
+        if !rest.len().is_multiple_of(width) {
+            return Err(error(format!("typed array body of {} bytes is not a multiple of {} bytes", rest.len(), width), pos));
+        }
+        Ok(Self { element, width, count: rest.len() / width, data: rest })
+    }
+
+    /// i 番目の要素を単体の値として `out` に書く
+    pub(crate) fn encode_element(&self, i: usize, out: &mut Vec<u8>) {
+        if self.width == 0 {
+            let bit = self.data[i / 8] >> (i % 8) & 1;
+            out.push(self.element | bit);
+        } else {
+            out.extend_from_slice(&self.data[i * self.width..(i + 1) * self.width]);
+            out.push(self.element);
+        }
+    }
+}
+
+/// 型付き配列の body を組み立てる
+///
+/// 要素は単体でエンコードしたバイト列で受け取る
+#[derive(Default)]
+pub(crate) struct PackedBuilder {
+    element: Option<u8>,
+    count: usize,
+    data: Vec<u8>,
+}
+
+impl PackedBuilder {
+    /// 単体でエンコードした要素を追加する
+    ///
+    /// 詰められない要素や、前の要素と型が違う場合は false を返し、何も追加しない
+    pub(crate) fn push(&mut self, encoded: &[u8]) -> bool {
+        let head = match read_head(encoded, encoded.len()) {
+            Ok(head) if head.start == 0 => head.head,
+            _ => return false,
+        };
+        // Bool は true の head も false の head にまとめる
+        let (element, bit) = match element_width(head & !1) {
+            Some(0) => (head & !1, head & 1),
+            _ => (head, 0),
+        };
+        let width = match element_width(element) {
+            Some(width) if self.element.is_none_or(|e| e == element) => width,
+            _ => return false,
+        };
+        self.element = Some(element);
+        if width == 0 {
+            if self.count.is_multiple_of(8) {
+                self.data.push(0);
+            }
+            *self.data.last_mut().unwrap() |= bit << (self.count % 8);
+        } else {
+            self.data.extend_from_slice(&encoded[..width]);
+        }
+        self.count += 1;
+        true
+    }
+
+    /// 要素の head (Bool は false の head)
+    pub(crate) fn element(&self) -> Option<u8> {
+        self.element
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// 要素を詰めたバイト列
+    pub(crate) fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// 要素のあとに置く body の末尾
+    pub(crate) fn trailer(&self) -> Vec<u8> {
+        let mut trailer = Vec::with_capacity(2);
+        if let Some(element) = self.element {
+            if element_width(element) == Some(0) {
+                trailer.push(match self.count % 8 {
+                    0 if self.count > 0 => 8,
+                    bits => bits as u8,
+                });
+            }
+            trailer.push(element);
+        }
+        trailer
+    }
+
+    /// 追加した要素を単体の値として順に書く
+    pub(crate) fn for_each_element(&self, mut f: impl FnMut(&[u8]) -> Result<(), Error>) -> Result<(), Error> {
+        let Some(element) = self.element else {
+            return Ok(());
+        };
+        let array = TypedArray { element, width: element_width(element).unwrap_or(0), count: self.count, data: &self.data };
+        let mut buf = Vec::new();
+        for i in 0..array.count {
+            buf.clear();
+            array.encode_element(i, &mut buf);
+            f(&buf)?;
+        }
+        Ok(())
+    }
+}
+
+/// 型付き配列の要素を符号化する方針
+///
+/// どの方針でも、符号化しない場合より小さくならなければ型付き配列のまま書く
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ArrayEncoding {
+    /// 符号化しない
+    #[default]
+    Plain,
+    /// 前の要素との差分 (整数と Timestamp)
+    Delta,
+    /// 前の差分との差分 (整数と Timestamp)
+    DeltaOfDelta,
+    /// 同じ値の連続をまとめる (Bool 以外)
+    RunLength,
+    /// 使える並べ方のうち一番小さくなるもの
+    Smallest,
+}
+
+/// 符号化した要素の展開後の上限 (バイト)
+///
+/// ランレングスは小さな body から大きな配列を作れるので、これを超える場合はエラーにする
+pub(crate) const MAX_DECODED_LEN: usize = 256 << 20;
+
+/// 要素を符号化する
+///
+/// 型付き配列の body (`data` と element_head) より小さくなる場合だけ `(encoding, payload)` を返す
+pub(crate) fn encode(element: u8, data: &[u8], policy: ArrayEncoding) -> Option<(u8, Vec<u8>)> {
+    let width = element_width(element).filter(|&width| width > 0)?;
+    let candidates: &[u8] = match policy {
+        ArrayEncoding::Plain => &[],
+        ArrayEncoding::Delta => &[array_encoding::DELTA],
+        ArrayEncoding::DeltaOfDelta => &[array_encoding::DELTA_OF_DELTA],
+        ArrayEncoding::RunLength => &[array_encoding::RUN_LENGTH],
+        ArrayEncoding::Smallest => &[array_encoding::DELTA, array_encoding::DELTA_OF_DELTA, array_encoding::RUN_LENGTH],
+    };
+    let mut best: Option<(u8, Vec<u8>)> = None;
+    for &encoding in candidates {
+        let payload = match encoding {
+            array_encoding::RUN_LENGTH => run_length(data, width),
+            _ if !is_integer(element) => continue,
+            array_encoding::DELTA => deltas(element, data, width, 1),
+            _ => deltas(element, data, width, 2),
+        };
+        // body は encoding と element_head の 2 バイトが付く
+        if payload.len() + 2 < best.as_ref().map_or(data.len() + 1, |(_, best)| best.len() + 2) {
+            best = Some((encoding, payload));
+        }
+    }
+    best
+}
+
+/// Encoded Array の body を展開して `(element_head, 型付き配列の data)` を返す
+///
+/// `pos` はエラーに付ける位置 (値の head)
+pub(crate) fn decode(body: &[u8], pos: usize) -> Result<(u8, Vec<u8>), Error> {
+    let [payload @ .., encoding, element] = body else {
+        return Err(error("encoded array has no element type", pos));
+    };
+    let (encoding, element) = (*encoding, *element);
+    let width = element_width(element)
+        .filter(|&width| width > 0)
+        .ok_or_else(|| error(format!("invalid encoded array element head 0x{:02x}", element), pos))?;
+    let mut data = Vec::new();
+    let mut cursor = payload;
+    match encoding {
+        array_encoding::DELTA | array_encoding::DELTA_OF_DELTA if is_integer(element) => {
+            let order = if encoding == array_encoding::DELTA { 1 } else { 2 };
+            let (mut prev, mut prev_delta) = (0u64, 0u64);
+            while !cursor.is_empty() {
+                let mut delta = unzigzag(read_varint(&mut cursor).ok_or_else(|| error("broken varint in encoded array", pos))?);
+                if order == 2 {
+                    delta = delta.wrapping_add(prev_delta);
+                    prev_delta = delta;
+                }
+                prev = prev.wrapping_add(delta);
+                data.extend_from_slice(&prev.to_le_bytes()[..width]);
+            }
+        }
+        array_encoding::RUN_LENGTH => {
+            while !cursor.is_empty() {
+                if cursor.len() < width {
+                    return Err(error("broken run in encoded array", pos));
+                }
+                let (value, rest) = cursor.split_at(width);
+                cursor = rest;
+                let run = read_varint(&mut cursor).ok_or_else(|| error("broken varint in encoded array", pos))?;
+                // data.len() は MAX_DECODED_LEN を超えないので引き算は溢れない
+                let len = usize::try_from(run)
+                    .ok()
+                    .and_then(|run| run.checked_mul(width))
+                    .filter(|&len| len <= MAX_DECODED_LEN - data.len());
+                let Some(len) = len else {
+                    return Err(error(format!("encoded array expands beyond {} bytes", MAX_DECODED_LEN), pos));
+                };
+                data.reserve(len);
+                for _ in 0..run {
+                    data.extend_from_slice(value);
+                }
+            }
+        }
+        _ => {
+            return Err(error(
+                format!("unknown array encoding 0x{:02x} for {}", encoding, crate::de::type_name(element)),
+                pos,
+            ));
+        }
+    }
+    Ok((element, data))
+}
+
+/// 差分を取れる要素か
+fn is_integer(element: u8) -> bool {
+    matches!(element & !size_prefix::MASK, prefix::INT | prefix::UINT | prefix::TIMESTAMP)
+}
+
+/// 要素を 64 bit に拡張して読む
+fn widen(element: u8, bytes: &[u8]) -> u64 {
+    let mut buf = [0u8; 8];
+    buf[..bytes.len()].copy_from_slice(bytes);
+    let value = u64::from_le_bytes(buf);
+    let shift = 64 - bytes.len() as u32 * 8;
+    if element & !size_prefix::MASK == prefix::INT && shift > 0 {
+        ((value << shift) as i64 >> shift) as u64
+    } else {
+        value
+    }
+}
+
+/// `order` 回差分を取って zigzag varint で並べる
+fn deltas(element: u8, data: &[u8], width: usize, order: usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    let (mut prev, mut prev_delta) = (0u64, 0u64);
+    for chunk in data.chunks_exact(width) {
+        let value = widen(element, chunk);
+        let delta = value.wrapping_sub(prev);
+        let written = if order == 1 { delta } else { delta.wrapping_sub(prev_delta) };
+        write_varint(&mut out, zigzag(written));
+        prev = value;
+        prev_delta = delta;
+    }
+    out
+}
+
+fn run_length(data: &[u8], width: usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut chunks = data.chunks_exact(width).peekable();
+    while let Some(value) = chunks.next() {
+        let mut run = 1u64;
+        while chunks.next_if_eq(&value).is_some() {
+            run += 1;
+        }
+        out.extend_from_slice(value);
+        write_varint(&mut out, run);
+    }
+    out
+}
+
+fn zigzag(value: u64) -> u64 {
+    (value << 1) ^ ((value as i64 >> 63) as u64)
+}
+
+fn unzigzag(value: u64) -> u64 {
+    (value >> 1) ^ (value & 1).wrapping_neg()
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(cursor: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for (i, &byte) in cursor.iter().enumerate().take(10) {
+        value |= ((byte & 0x7f) as u64).checked_shl(7 * i as u32)?;
+        if byte & 0x80 == 0 {
+            *cursor = &cursor[i + 1..];
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cold]
+fn error(msg: impl Into<String>, pos: usize) -> Error {
+    Error::syntax(ErrorCode::Other(msg.into()), pos)
+}
@@ -0,0 +1,1216 @@
+//! エンコードされたままの RTON を扱う API
+//!
+//! コンテナの長さは head に入っているので、値を組み立てずにバッファ上を移動して目的の値だけを読める
+//! 大きな文書から少しのフィールドだけを取り出す場合に使う
+//!
+//! `RawTon` / `RawTonRef` はエンコード済みの部分木をそのまま持ち運ぶための型で、
+//! `Lazy` は最初に使うときまでデコードを遅らせる
+
+use std::borrow::{Borrow, Cow};
+use std::fmt;
+use std::ops::{Deref, Range};
+use std::sync::OnceLock;
+
+use serde::de::{self, DeserializeOwned, Visitor};
+use serde::{ser, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::compress::plain_body;
+use crate::de::{read_head, type_name, Head, ReverseDeserializer};
+use crate::error::{Error, ErrorCode};
+use crate::intern::{reference_index, DocumentKeys, KeyTable};
+use crate::packed::{decode as decode_array, element_width, TypedArray};
+use crate::traits::reader::SliceReader;
+use crate::value::index::parse_array_index;
+use crate::value::prefix::{container_index, prefix, self_describe, size_prefix};
+use crate::value::value::Value;
+
+/// RawTon を serializer / deserializer に伝えるための newtype struct 名
+pub(crate) const RAW_TOKEN: &str = "$serde_ton::private::RawTon";
+
+/// バッファ上の 1 つの値を指す参照
+///
+/// コピーしても元のバッファを借用するだけ
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct TonRef<'a> {
+    buf: &'a [u8],
+    head: Head,
+}
+
+impl<'a> TonRef<'a> {
+    /// 文書のルートの値を指す
+    ///
+    /// 末尾の self-describe tag と、ルートの後ろにある Padding は読み飛ばす
+    pub fn new(buf: &'a [u8]) -> Result<Self, Error> {
+        let mut end = buf.len();
+        if buf.ends_with(&self_describe::TON_V1_REV_TAG) {
+            end -= self_describe::TON_V1_REV_TAG.len();
+        }
+        loop {
+            let head = read_head(buf, end)?;
+            if head.prefix() != prefix::PADDING {
+                return Ok(Self { buf, head });
+            }
+            end = head.start;
+        }
+    }
+
+    /// `offset` にある head の値を指す
+    ///
+    /// `offset` は head 1 バイトの位置 (`Error::offset` と同じ)
+    pub fn at(buf: &'a [u8], offset: usize) -> Result<Self, Error> {
+        let head = read_head(buf, offset.saturating_add(1))?;
+        Ok(Self { buf, head })
+    }
+
+    /// head 1 バイト
+    #[inline]
+    pub fn head(&self) -> u8 {
+        self.head.head
+    }
+
+    /// size prefix を除いた型
+    #[inline]
+    pub fn prefix(&self) -> u8 {
+        self.head.prefix()
+    }
+
+    /// 型名 (`prefix_str` の表記)
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        type_name(self.head.head)
+    }
+
+    /// head 1 バイトの位置
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.head.end - 1
+    }
+
+    /// 値全体 (body, データ長, head) の範囲
+    #[inline]
+    pub fn range(&self) -> Range<usize> {
+        self.head.start..self.head.end
+    }
+
+    /// 値全体のバイト列
+    ///
+    /// そのまま 1 つの文書としてデシリアライズできる
+    #[inline]
+    pub fn raw(&self) -> &'a [u8] {
+        &self.buf[self.head.start..self.head.end]
+    }
+
+    /// 値全体を RawTonRef として借用する
+    ///
+    /// 中身は検査しない
+    #[inline]
+    pub fn as_raw_ton(&self) -> &'a RawTonRef {
+        RawTonRef::from_bytes_unchecked(self.raw())
+    }
+
+    /// body のバイト列
+    #[inline]
+    pub fn body(&self) -> &'a [u8] {
+        &self.buf[self.head.start..self.head.head_start]
+    }
+
+    /// body のバイト数
+    #[inline]
+    pub fn body_len(&self) -> usize {
+        self.head.body_len()
+    }
+
+    pub fn is_array(&self) -> bool {
+        self.prefix() == prefix::ARRAY
+    }
+
+    pub fn is_object(&self) -> bool {
+        self.prefix() == prefix::OBJECT
+    }
+
+    pub fn is_table(&self) -> bool {
+        self.prefix() == prefix::TABLE
+    }
+
+    /// 圧縮した body (Compressed) か
+    ///
+    /// `as_str` では読めないので `to_str` や `to_bytes` を使う
+    pub fn is_compressed(&self) -> bool {
+        self.prefix() == prefix::COMPRESSED
+    }
+
+    /// Meta を外した中身を返す
+    ///
+    /// REF は参照先に移動する
+    /// Meta でも REF でもなければそのまま返す
+    pub fn unwrap_meta(self) -> Result<Self, Error> {
+        let mut target = self.resolve()?;
+        while target.prefix() == prefix::META {
+            let mut children = target.children_rev();
+            target = match children.next() {
+                Some(child) => child?.resolve()?,
+                None => return Err(error("missing meta value", target.offset())),
+            };
+        }
+        Ok(target)
+    }
+
+    /// REF なら参照先の値を返す
+    ///
+    /// REF でなければそのまま返す
+    pub fn resolve(self) -> Result<Self, Error> {
+        let mut target = self;
+        while target.prefix() == prefix::REF {
+            let offset = reference_index(target.body());
+            if offset >= target.head.start as u64 {
+                return Err(error(format!("reference to {} does not point before itself", offset), target.offset()));
+            }
+            target = TonRef::at(self.buf, offset as usize)?;
+        }
+        Ok(target)
+    }
+
+    /// 子の値を後ろから順に返す
+    ///
+    /// Padding とコンテナのチェックサムは読み飛ばす
+    /// Object の場合は key, value の順に交互に並ぶ
+    /// 表の場合は行数のあとに key, 列の順に交互に並ぶ
+    pub fn children_rev(&self) -> ChildrenRev<'a> {
+        let (start, cursor) = match self.prefix() {
+            prefix::ARRAY | prefix::OBJECT | prefix::TABLE | prefix::META => (self.head.start, self.head.head_start),
+            _ => (0, 0),
+        };
+        ChildrenRev { buf: self.buf, start, cursor }
+    }
+
+    /// 子の値を前から順に集める
+    ///
+    /// コンテナでなければ空
+    pub fn children(&self) -> Result<Vec<TonRef<'a>>, Error> {
+        let mut children = self.children_rev().collect::<Result<Vec<_>, _>>()?;
+        children.reverse();
+        Ok(children)
+    }
+
+    /// オフセット索引を持つコンテナか
+    pub fn has_index(&self) -> bool {
+        self.container_index().is_some()
+    }
+
+    /// コンテナの body の最後にあるオフセット索引
+    fn container_index(&self) -> Option<ContainerIndex<'a>> {
+        match self.prefix() {
+            prefix::ARRAY | prefix::OBJECT => parse_index(self.buf, self.head.start, self.head.head_start),
+            _ => None,
+        }
+    }
+
+    /// 索引の i 番目の終端で終わる子
+    fn indexed_child(&self, index: &ContainerIndex<'a>, i: usize) -> Result<TonRef<'a>, Error> {
+        let end = index.end(self.head.start, i);
+        if end > index.start {
+            return Err(error(format!("index offset {} is out of the container", end), self.offset()));
+        }
+        let head = read_head(self.buf, end)?;
+        if head.start < self.head.start {
+            return Err(error(format!("index offset {} is out of the container", end), self.offset()));
+        }
+        Ok(TonRef { buf: self.buf, head })
+    }
+
+    /// コンテナの要素数
+    ///
+    /// Object はエントリ数、Array と Meta は子の数、型付き配列は要素数、表は行数
+    pub fn count(&self) -> Result<usize, Error> {
+        let target = self.unwrap_meta()?;
+        if target.is_table() {
+            return target.table_rows();
+        }
+        if target.prefix() == prefix::TYPED_ARRAY {
+            return Ok(TypedArray::parse(target.body(), target.offset())?.count);
+        }
+        if target.prefix() == prefix::ENCODED_ARRAY {
+            let (element, data) = decode_array(target.body(), target.offset())?;
+            return Ok(data.len() / element_width(element).unwrap_or(1));
+        }
+        if let Some(index) = target.container_index() {
+            return Ok(index.count);
+        }
+        let mut count = 0;
+        for child in target.children_rev() {
+            child?;
+            count += 1;
+        }
+        if target.is_object() {
+            Ok(count / 2)
+        } else {
+            Ok(count)
+        }
+    }
+
+    /// Object の (key, value) を前から順に集める
+    ///
+    /// Object でなければ空
+    pub fn entries(&self) -> Result<Vec<(TonRef<'a>, TonRef<'a>)>, Error> {
+        let target = self.unwrap_meta()?;
+        if !target.is_object() {
+            return Ok(Vec::new());
+        }
+        let mut entries = Vec::new();
+        let mut children = target.children_rev();
+        while let Some(key) = children.next() {
+            let key = key?;
+            match children.next() {
+                Some(value) => entries.push((key, value?)),
+                None => return Err(error("object has a key without a value", target.offset())),
+            }
+        }
+        entries.reverse();
+        Ok(entries)
+    }
+
+    /// Object の key を前から順に集める
+    pub fn keys(&self) -> Result<Vec<TonRef<'a>>, Error> {
+        Ok(self.entries()?.into_iter().map(|(key, _)| key).collect())
+    }
+
+    /// Object の String キーで値を引く
+    ///
+    /// Object でない場合や key がない場合は None
+    /// 同じ key が複数ある場合は後ろのものを返す
+    pub fn get(&self, key: &str) -> Result<Option<TonRef<'a>>, Error> {
+        self.get_unresolved(key)?.map(TonRef::resolve).transpose()
+    }
+
+    /// REF を参照先に移動しない `get`
+    pub(crate) fn get_unresolved(&self, key: &str) -> Result<Option<TonRef<'a>>, Error> {
+        let target = self.unwrap_meta()?;
+        if !target.is_object() {
+            return Ok(None);
+        }
+        if let Some(index) = target.container_index().filter(|index| index.sorted) {
+            // key が整列しているので二分探索する
+            let (mut low, mut high) = (0, index.count);
+            while low < high {
+                let mid = low + (high - low) / 2;
+                let k = target.indexed_child(&index, mid)?;
+                match k.body().cmp(key.as_bytes()) {
+                    std::cmp::Ordering::Less => low = mid + 1,
+                    std::cmp::Ordering::Greater => high = mid,
+                    std::cmp::Ordering::Equal => {
+                        let value = read_head(self.buf, k.head.start)?;
+                        if value.start < target.head.start {
+                            return Err(error("object has a key without a value", target.offset()));
+                        }
+                        return Ok(Some(TonRef { buf: self.buf, head: value }));
+                    }
+                }
+            }
+            return Ok(None);
+        }
+        let keys = DocumentKeys::find(self.buf)?;
+        let mut children = target.children_rev();
+        while let Some(k) = children.next() {
+            let k = k?;
+            let value = match children.next() {
+                Some(value) => value?,
+                None => return Err(error("object has a key without a value", target.offset())),
+            };
+            if k.key_body(keys.as_ref()) == Some(key.as_bytes()) {
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+
+    /// 表の列を名前で引く
+    ///
+    /// 列は行数と同じ数の要素を持つ Array か型付き配列で、他の列は読まない
+    /// 表でない場合や列がない場合は None
+    pub fn column(&self, name: &str) -> Result<Option<TonRef<'a>>, Error> {
+        let target = self.unwrap_meta()?;
+        if !target.is_table() {
+            return Ok(None);
+        }
+        let keys = DocumentKeys::find(self.buf)?;
+        let mut children = target.children_rev();
+        children.next().transpose()?;
+        while let Some(key) = children.next() {
+            let key = key?;
+            let column = match children.next() {
+                Some(column) => column?,
+                None => return Err(error("table has a column without name", target.offset())),
+            };
+            if key.key_body(keys.as_ref()) == Some(name.as_bytes()) {
+                return Ok(Some(column));
+            }
+        }
+        Ok(None)
+    }
+
+    /// 表の列の名前を前から順に集める
+    pub fn column_names(&self) -> Result<Vec<&'a str>, Error> {
+        let target = self.unwrap_meta()?;
+        if !target.is_table() {
+            return Ok(Vec::new());
+        }
+        let mut names = Vec::new();
+        for (i, child) in target.children_rev().enumerate().skip(1) {
+            let child = child?;
+            if i % 2 == 1 {
+                names.push(child.as_str().ok_or_else(|| error("table column name is not a string", child.offset()))?);
+            }
+        }
+        names.reverse();
+        Ok(names)
+    }
+
+    /// 表の最後にある行数
+    fn table_rows(&self) -> Result<usize, Error> {
+        match self.children_rev().next().transpose()? {
+            Some(rows) if rows.prefix() == prefix::UINT => Ok(rows.as_u64().unwrap_or_default() as usize),
+            _ => Err(error("table has no row count", self.offset())),
+        }
+    }
+
+    /// 表の `row` 行目の `name` 列の値
+    ///
+    /// 表の行は 1 つの値として書かれていないので、行ではなく列の要素を指す
+    /// 列が型付き配列の場合は要素も値として書かれていないのでエラーになる
+    /// 表でない場合や範囲外の場合、列がない場合は None
+    pub fn cell(&self, row: usize, name: &str) -> Result<Option<TonRef<'a>>, Error> {
+        let target = self.unwrap_meta()?;
+        if !target.is_table() || row >= target.table_rows()? {
+            return Ok(None);
+        }
+        let Some(column) = target.column(name)? else {
+            return Ok(None);
+        };
+        if !column.is_array() {
+            return Err(not_navigable(&format!("{} column {:?}", column.type_name(), name), column.offset()));
+        }
+        column.index(row)
+    }
+
+    /// Array の要素を前からの添字で引く
+    ///
+    /// Array でない場合や範囲外の場合は None
+    /// 表の行は 1 つの値として書かれていないのでエラーになる (`cell` を使う)
+    pub fn index(&self, index: usize) -> Result<Option<TonRef<'a>>, Error> {
+        self.index_unresolved(index)?.map(TonRef::resolve).transpose()
+    }
+
+    /// REF を参照先に移動しない `index`
+    pub(crate) fn index_unresolved(&self, index: usize) -> Result<Option<TonRef<'a>>, Error> {
+        let target = self.unwrap_meta()?;
+        if target.is_table() {
+            return Err(not_navigable("table row", target.offset()));
+        }
+        if !target.is_array() {
+            return Ok(None);
+        }
+        if let Some(container_index) = target.container_index() {
+            if index >= container_index.count {
+                return Ok(None);
+            }
+            return target.indexed_child(&container_index, index).map(Some);
+        }
+        let children = target.children_rev().collect::<Result<Vec<_>, _>>()?;
+        Ok(children.len().checked_sub(index + 1).map(|i| children[i]))
+    }
+
+    /// JSON Pointer (RFC 6901) で値を引く
+    ///
+    /// `Value::pointer` と同じく Array は数値、Object は String キーとして辿る
+    /// 表は行の番号と列の名前の 2 つで `cell` を引く
+    pub fn pointer(&self, pointer: &str) -> Result<Option<TonRef<'a>>, Error> {
+        if pointer.is_empty() {
+            return Ok(Some(*self));
+        }
+        let rest = match pointer.strip_prefix('/') {
+            Some(rest) => rest,
+            None => return Ok(None),
+        };
+        let mut target = *self;
+        let mut tokens = rest.split('/').map(|token| token.replace("~1", "/").replace("~0", "~"));
+        while let Some(token) = tokens.next() {
+            target = target.unwrap_meta()?;
+            let next = if target.is_table() {
+                let Some(row) = parse_array_index(&token) else {
+                    return Ok(None);
+                };
+                match tokens.next() {
+                    Some(name) => target.cell(row, &name)?,
+                    None => return Err(not_navigable("table row", target.offset())),
+                }
+            } else if target.is_array() {
+                match parse_array_index(&token) {
+                    Some(index) => target.index(index)?,
+                    None => None,
+                }
+            } else {
+                target.get(&token)?
+            };
+            match next {
+                Some(next) => target = next,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(target))
+    }
+
+    /// この値だけをデシリアライズする
+    pub fn decode<T>(&self) -> Result<T, Error>
+    where
+        T: Deserialize<'a>,
+    {
+        // REF は文書の先頭からの位置なので、値より前を含めて読む
+        let mut de = ReverseDeserializer::new(SliceReader::new(&self.buf[..self.head.end]))?;
+        if let Some(keys) = DocumentKeys::find(self.buf)? {
+            de.set_keys(KeyTable::from_document(&keys, self.buf.len() - 1)?);
+        }
+        T::deserialize(&mut de)
+    }
+
+    /// この値を Value に組み立てる
+    pub fn to_value(&self) -> Result<Value, Error> {
+        self.decode()
+    }
+
+    /// String の中身
+    ///
+    /// KEYREF の key は文書の辞書から引く
+    pub fn as_str(&self) -> Option<&'a str> {
+        let keys = match self.prefix() {
+            prefix::KEYREF => DocumentKeys::find(self.buf).ok()?,
+            _ => None,
+        };
+        std::str::from_utf8(self.key_body(keys.as_ref())?).ok()
+    }
+
+    /// String の中身
+    ///
+    /// `as_str` と違い、Compressed の String も組み込みの codec で展開して読む
+    pub fn to_str(&self) -> Result<Cow<'a, str>, Error> {
+        if let Some(s) = self.as_str() {
+            return Ok(Cow::Borrowed(s));
+        }
+        let body = self.plain_body(prefix::STRING)?;
+        let invalid = || error("invalid UTF-8 string", self.offset());
+        match body {
+            Cow::Borrowed(body) => std::str::from_utf8(body).map(Cow::Borrowed).map_err(|_| invalid()),
+            Cow::Owned(body) => String::from_utf8(body).map(Cow::Owned).map_err(|_| invalid()),
+        }
+    }
+
+    /// Bytes の body
+    ///
+    /// Compressed の Bytes は組み込みの codec で展開して読む
+    pub fn to_bytes(&self) -> Result<Cow<'a, [u8]>, Error> {
+        self.plain_body(prefix::BYTES)
+    }
+
+    /// `expected` の型の body (Compressed なら展開したもの)
+    fn plain_body(&self, expected: u8) -> Result<Cow<'a, [u8]>, Error> {
+        let (head, body) = plain_body(self.head(), self.body(), self.offset())?;
+        if head != expected {
+            return Err(Error::new(
+                ErrorCode::InvalidType { expected: type_name(expected).to_string(), found: type_name(head).to_string() },
+                self.offset(),
+            ));
+        }
+        Ok(body)
+    }
+
+    /// String の body か、KEYREF が指す文書の辞書の key
+    fn key_body(&self, keys: Option<&DocumentKeys<'a>>) -> Option<&'a [u8]> {
+        match self.prefix() {
+            prefix::STRING => Some(self.body()),
+            prefix::KEYREF => keys?.get(reference_index(self.body())),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.head() {
+            h if h == prefix::BOOL | size_prefix::SIZE_PREFIX_1BYTE => Some(false),
+            h if h == prefix::BOOL | size_prefix::SIZE_PREFIX_2BYTE => Some(true),
+            _ => None,
+        }
+    }
+
+    /// 整数を i64 で返す
+    ///
+    /// UInt は i64 に収まる場合だけ返す
+    pub fn as_i64(&self) -> Option<i64> {
+        let body = self.body();
+        match self.prefix() {
+            prefix::INT => Some(match body.len() {
+                1 => body[0] as i8 as i64,
+                2 => i16::from_le_bytes(body.try_into().ok()?) as i64,
+                4 => i32::from_le_bytes(body.try_into().ok()?) as i64,
+                _ => i64::from_le_bytes(body.try_into().ok()?),
+            }),
+            prefix::UINT => self.as_u64().and_then(|v| i64::try_from(v).ok()),
+            _ => None,
+        }
+    }
+
+    /// 整数を u64 で返す
+    ///
+    /// Int は負でない場合だけ返す
+    pub fn as_u64(&self) -> Option<u64> {
+        let body = self.body();
+        match self.prefix() {
+            prefix::UINT => Some(match body.len() {
+                1 => body[0] as u64,
+                2 => u16::from_le_bytes(body.try_into().ok()?) as u64,
+                4 => u32::from_le_bytes(body.try_into().ok()?) as u64,
+                _ => u64::from_le_bytes(body.try_into().ok()?),
+            }),
+            prefix::INT => self.as_i64().and_then(|v| u64::try_from(v).ok()),
+            _ => None,
+        }
+    }
+
+    /// Float を f64 で返す
+    pub fn as_f64(&self) -> Option<f64> {
+        let body = self.body();
+        match self.prefix() {
+            prefix::FLOAT => Some(match body.len() {
+                2 => half::f16::from_bits(u16::from_le_bytes(body.try_into().ok()?)).to_f64(),
+                4 => f32::from_bits(u32::from_le_bytes(body.try_into().ok()?)) as f64,
+                _ => f64::from_bits(u64::from_le_bytes(body.try_into().ok()?)),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Bytes の body か型付き配列の要素を `T` のスライスとして借用する
+    ///
+    /// body のバイト数が `T` の倍数で、メモリ上で `T` の境界に揃っていて、
+    /// リトルエンディアンの環境の場合だけ借用できる (それ以外はエラー)
+    /// 揃えて書くには `SerializeOptions::alignment` を使う
+    pub fn as_slice<T: Primitive>(&self) -> Result<&'a [T], Error> {
+        let body = self.primitive_body::<T>()?;
+        if cfg!(target_endian = "big") {
+            return Err(error("zero-copy slices need a little-endian target", self.offset()));
+        }
+        // SAFETY: Primitive はどのビット列も有効な値になる数値型にだけ実装している
+        let (head, values, tail) = unsafe { body.align_to::<T>() };
+        if !head.is_empty() || !tail.is_empty() {
+            return Err(error(
+                format!("{} body is not aligned to {} bytes in memory", self.type_name(), std::mem::align_of::<T>()),
+                self.offset(),
+            ));
+        }
+        Ok(values)
+    }
+
+    /// Bytes の body か型付き配列の要素を `T` のスライスとして読む
+    ///
+    /// `as_slice` で借用できればそのまま返し、できなければコピーして返す
+    /// 符号化された型付き配列と Compressed の Bytes は展開して返す
+    pub fn to_slice<T: Primitive>(&self) -> Result<Cow<'a, [T]>, Error> {
+        if self.prefix() == prefix::ENCODED_ARRAY {
+            let (element, data) = decode_array(self.body(), self.offset())?;
+            check_element::<T>(element, self.offset())?;
+            return Ok(Cow::Owned(data.chunks_exact(std::mem::size_of::<T>()).map(T::from_le_slice).collect()));
+        }
+        if self.is_compressed() {
+            let data = self.to_bytes()?;
+            if !data.len().is_multiple_of(std::mem::size_of::<T>()) {
+                return Err(error(
+                    format!("body of {} bytes is not a multiple of {} bytes", data.len(), std::mem::size_of::<T>()),
+                    self.offset(),
+                ));
+            }
+            return Ok(Cow::Owned(data.chunks_exact(std::mem::size_of::<T>()).map(T::from_le_slice).collect()));
+        }
+        if let Ok(values) = self.as_slice() {
+            return Ok(Cow::Borrowed(values));
+        }
+        let body = self.primitive_body::<T>()?;
+        Ok(Cow::Owned(body.chunks_exact(std::mem::size_of::<T>()).map(T::from_le_slice).collect()))
+    }
+
+    /// `T` の倍数の長さを持つ Bytes の body か、要素の型が `T` の型付き配列の要素
+    fn primitive_body<T: Primitive>(&self) -> Result<&'a [u8], Error> {
+        if self.prefix() == prefix::TYPED_ARRAY {
+            let array = TypedArray::parse(self.body(), self.offset())?;
+            check_element::<T>(array.element, self.offset())?;
+            return Ok(array.data);
+        }
+        if self.prefix() != prefix::BYTES {
+            return Err(Error::new(
+                ErrorCode::InvalidType { expected: type_name(prefix::BYTES).to_string(), found: self.type_name().to_string() },
+                self.offset(),
+            ));
+        }
+        let body = self.body();
+        if !body.len().is_multiple_of(std::mem::size_of::<T>()) {
+            return Err(error(
+                format!("body of {} bytes is not a multiple of {} bytes", body.len(), std::mem::size_of::<T>()),
+                self.offset(),
+            ));
+        }
+        Ok(body)
+    }
+}
+
+/// 型付き配列の要素を `T` として読めるか
+fn check_element<T: Primitive>(element: u8, pos: usize) -> Result<(), Error> {
+    if T::ELEMENT != Some(element) {
+        return Err(error(
+            format!("typed array of {} cannot be read as {}", type_name(element), std::any::type_name::<T>()),
+            pos,
+        ));
+    }
+    Ok(())
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// `TonRef::as_slice` で読める数値型
+///
+/// リトルエンディアンで詰めて並べた body から読む
+pub trait Primitive: Copy + private::Sealed {
+    /// 型付き配列の要素にした場合の head
+    const ELEMENT: Option<u8>;
+
+    /// `size_of::<Self>()` バイトのリトルエンディアンの値を読む
+    fn from_le_slice(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_primitive {
+    ($($ty:ty => $element:expr),*) => {
+        $(
+            impl private::Sealed for $ty {}
+
+            impl Primitive for $ty {
+                const ELEMENT: Option<u8> = $element;
+
+                #[inline]
+                fn from_le_slice(bytes: &[u8]) -> Self {
+                    let mut buf = [0u8; std::mem::size_of::<$ty>()];
+                    buf.copy_from_slice(bytes);
+                    <$ty>::from_le_bytes(buf)
+                }
+            }
+        )*
+    };
+}
+
+impl_primitive!(
+    u8 => Some(prefix::UINT | size_prefix::SIZE_PREFIX_1BYTE),
+    u16 => Some(prefix::UINT | size_prefix::SIZE_PREFIX_2BYTE),
+    u32 => Some(prefix::UINT | size_prefix::SIZE_PREFIX_4BYTE),
+    u64 => Some(prefix::UINT | size_prefix::SIZE_PREFIX_8BYTE),
+    u128 => None,
+    i8 => Some(prefix::INT | size_prefix::SIZE_PREFIX_1BYTE),
+    i16 => Some(prefix::INT | size_prefix::SIZE_PREFIX_2BYTE),
+    i32 => Some(prefix::INT | size_prefix::SIZE_PREFIX_4BYTE),
+    i64 => Some(prefix::INT | size_prefix::SIZE_PREFIX_8BYTE),
+    i128 => None,
+    f32 => Some(prefix::FLOAT | size_prefix::SIZE_PREFIX_4BYTE),
+    f64 => Some(prefix::FLOAT | size_prefix::SIZE_PREFIX_8BYTE)
+);
+
+impl std::fmt::Debug for TonRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TonRef")
+            .field("type", &self.type_name())
+            .field("range", &self.range())
+            .finish()
+    }
+}
+
+/// コンテナのオフセット索引
+///
+/// 形式は `container_index` を参照
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ContainerIndex<'a> {
+    /// offset の並び
+    table: &'a [u8],
+    /// offset 1 つのバイト数
+    width: usize,
+    /// 要素数 (Object はエントリ数)
+    pub(crate) count: usize,
+    /// key が整列しているか
+    pub(crate) sorted: bool,
+    /// 索引の PADDING の先頭 (子はここより前にある)
+    pub(crate) start: usize,
+}
+
+impl ContainerIndex<'_> {
+    /// i 番目の子の終端
+    pub(crate) fn end(&self, body_start: usize, i: usize) -> usize {
+        let mut bytes = [0u8; 8];
+        bytes[..self.width].copy_from_slice(&self.table[i * self.width..(i + 1) * self.width]);
+        body_start.saturating_add(u64::from_le_bytes(bytes) as usize)
+    }
+}
+
+/// コンテナの body `body_start..body_end` の最後にある索引を読む
+///
+/// 索引がない場合や形式が合わない場合は None
+pub(crate) fn parse_index(buf: &[u8], body_start: usize, body_end: usize) -> Option<ContainerIndex<'_>> {
+    if body_end <= body_start {
+        return None;
+    }
+    let head = read_head(buf, body_end).ok()?;
+    if head.prefix() != prefix::PADDING || head.start < body_start {
+        return None;
+    }
+    let body = &buf[head.start..head.head_start];
+    let trailer = body.len().checked_sub(container_index::TRAILER_LEN)?;
+    if !body.ends_with(&container_index::MAGIC) {
+        return None;
+    }
+    let count = u64::from_le_bytes(body[trailer..trailer + 8].try_into().ok()?) as usize;
+    let width = body[trailer + 8] as usize;
+    let flags = body[trailer + 9];
+    if !matches!(width, 4 | 8) || count.checked_mul(width)? != trailer {
+        return None;
+    }
+    Some(ContainerIndex {
+        table: &body[..trailer],
+        width,
+        count,
+        sorted: flags & container_index::FLAG_SORTED_KEYS != 0,
+        start: head.start,
+    })
+}
+
+/// コンテナの子を後ろから順に返すイテレータ
+///
+/// 壊れたデータに当たるとエラーを返して止まる
+pub struct ChildrenRev<'a> {
+    buf: &'a [u8],
+    /// 親の body の先頭
+    start: usize,
+    /// 次に読む値の終端
+    cursor: usize,
+}
+
+impl<'a> Iterator for ChildrenRev<'a> {
+    type Item = Result<TonRef<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.cursor > self.start {
+            let head = match read_head(self.buf, self.cursor) {
+                Ok(head) if head.start >= self.start => head,
+                Ok(head) => {
+                    self.cursor = self.start;
+                    return Some(Err(error(
+                        format!("{} overflows its parent by {} bytes", type_name(head.head), self.start - head.start),
+                        head.end - 1,
+                    )));
+                }
+                Err(err) => {
+                    self.cursor = self.start;
+                    return Some(Err(err));
+                }
+            };
+            self.cursor = head.start;
+            if head.prefix() != prefix::PADDING {
+                return Some(Ok(TonRef { buf: self.buf, head }));
+            }
+        }
+        None
+    }
+}
+
+/// エンコード済みの 1 つの値 (借用)
+///
+/// `serde_json::value::RawValue` と同じく、構造体のフィールドに使うとその部分木のバイト列をデコードせずに受け取る
+/// 受け取るときは `from_slice` と同じく構造を検査する
+/// シリアライズするとバイト列をそのまま書き込み、親のコンテナのサイズにも数えられる
+///
+/// 借用できるのは `from_slice` などスライスから読む場合だけ
+/// ファイルから読む場合と、重複排除の REF や辞書の KEYREF を含む値
+/// (`SerializeOptions::dedup`, `SerializeOptions::interned_keys`) は `RawTon` を使う
+#[repr(transparent)]
+pub struct RawTonRef([u8]);
+
+impl RawTonRef {
+    #[inline]
+    fn from_bytes_unchecked(bytes: &[u8]) -> &RawTonRef {
+        // repr(transparent) なので [u8] と同じ表現
+        unsafe { &*(bytes as *const [u8] as *const RawTonRef) }
+    }
+
+    /// バイト列が 1 つの値として正しいか検査して RawTonRef にする
+    ///
+    /// self-describe tag は付けられない
+    pub fn from_slice(bytes: &[u8]) -> Result<&RawTonRef, Error> {
+        check_raw(bytes)?;
+        Ok(Self::from_bytes_unchecked(bytes))
+    }
+
+    /// エンコード済みのバイト列
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// バイト列を辿る TonRef を作る
+    pub fn to_ton_ref(&self) -> Result<TonRef<'_>, Error> {
+        TonRef::new(&self.0)
+    }
+
+    /// 中身をデシリアライズする
+    pub fn decode<'a, T>(&'a self) -> Result<T, Error>
+    where
+        T: Deserialize<'a>,
+    {
+        crate::de::from_slice(&self.0)
+    }
+
+    /// 中身を Value に組み立てる
+    pub fn to_value(&self) -> Result<Value, Error> {
+        self.decode()
+    }
+}
+
+impl ToOwned for RawTonRef {
+    type Owned = RawTon;
+
+    fn to_owned(&self) -> RawTon {
+        RawTon { bytes: self.0.to_vec() }
+    }
+}
+
+impl PartialEq for RawTonRef {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for RawTonRef {}
+
+impl std::hash::Hash for RawTonRef {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+/// TON Text で表示する
+///
+/// デコードできない場合はバイト列を表示する
+impl fmt::Debug for RawTonRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.to_value() {
+            Ok(value) => write!(f, "RawTon({})", value),
+            Err(_) => write!(f, "RawTon({:02x?})", &self.0),
+        }
+    }
+}
+
+impl Serialize for RawTonRef {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(RAW_TOKEN, &RawBytes(&self.0))
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for &'a RawTonRef {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct RawTonRefVisitor;
+
+        impl<'de> Visitor<'de> for RawTonRefVisitor {
+            type Value = &'de RawTonRef;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("TON bytes borrowed from the input")
+            }
+
+            fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                RawTonRef::from_slice(v).map_err(de::Error::custom)
+            }
+
+            /// 入力を借用できないか、REF や KEYREF を置き換えた値
+            fn visit_byte_buf<E>(self, _v: Vec<u8>) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Err(de::Error::custom(
+                    "raw TON value is not borrowed from the input (read from a reader or containing back-references or interned keys), use RawTon",
+                ))
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(RAW_TOKEN, RawTonRefVisitor)
+    }
+}
+
+/// エンコード済みの 1 つの値 (所有)
+///
+/// `RawTonRef` の所有版で、どの reader から読んでも受け取れる
+/// 中の REF と KEYREF は文書の他の場所を指すので、参照先の値と key の String に置き換えたバイト列を持つ
+/// TON 以外の deserializer から読んだ場合は Value を経由してエンコードする
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct RawTon {
+    bytes: Vec<u8>,
+}
+
+impl RawTon {
+    /// バイト列が 1 つの値として正しいか検査して RawTon にする
+    pub fn from_vec(bytes: Vec<u8>) -> Result<Self, Error> {
+        check_raw(&bytes)?;
+        Ok(Self { bytes })
+    }
+
+    /// Value をエンコードして RawTon にする
+    pub fn from_value(value: &Value) -> Result<Self, Error> {
+        Ok(Self { bytes: crate::ser::value_to_vec(value)? })
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// 値をエンコードして RawTon にする
+pub fn to_raw_ton<T>(value: &T) -> Result<RawTon, Error>
+where
+    T: ?Sized + Serialize,
+{
+    Ok(RawTon { bytes: crate::ser::to_vec(value)? })
+}
+
+impl Deref for RawTon {
+    type Target = RawTonRef;
+
+    #[inline]
+    fn deref(&self) -> &RawTonRef {
+        RawTonRef::from_bytes_unchecked(&self.bytes)
+    }
+}
+
+impl Borrow<RawTonRef> for RawTon {
+    fn borrow(&self) -> &RawTonRef {
+        self
+    }
+}
+
+impl AsRef<[u8]> for RawTon {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl fmt::Debug for RawTon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl Serialize for RawTon {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        (**self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for RawTon {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct RawTonVisitor;
+
+        impl<'de> Visitor<'de> for RawTonVisitor {
+            type Value = RawTon;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("any TON value")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                RawTon::from_vec(v.to_vec()).map_err(de::Error::custom)
+            }
+
+            fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                RawTon::from_vec(v).map_err(de::Error::custom)
+            }
+
+            /// TON 以外の deserializer
+            fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                let value = Value::deserialize(deserializer)?;
+                RawTon::from_value(&value).map_err(de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(RAW_TOKEN, RawTonVisitor)
+    }
+}
+
+/// 最初に使うときにデコードする値
+///
+/// デシリアライズ時は `RawTon` としてバイト列だけを受け取る
+/// シリアライズ時はデコードしたかどうかに関係なく元のバイト列を書き込む
+pub struct Lazy<T> {
+    raw: RawTon,
+    value: OnceLock<T>,
+}
+
+impl<T> Lazy<T> {
+    /// エンコード済みの値から作る
+    pub fn from_raw(raw: RawTon) -> Self {
+        Self { raw, value: OnceLock::new() }
+    }
+
+    /// エンコード済みのバイト列
+    pub fn raw(&self) -> &RawTonRef {
+        &self.raw
+    }
+
+    /// もうデコードしたか
+    pub fn is_decoded(&self) -> bool {
+        self.value.get().is_some()
+    }
+}
+
+impl<T> Lazy<T>
+where
+    T: Serialize,
+{
+    /// 値をエンコードして作る
+    ///
+    /// 渡した値はデコード済みとして持つ
+    pub fn new(value: T) -> Result<Self, Error> {
+        let raw = to_raw_ton(&value)?;
+        Ok(Self { raw, value: OnceLock::from(value) })
+    }
+}
+
+impl<T> Lazy<T>
+where
+    T: DeserializeOwned,
+{
+    /// 値を返す
+    ///
+    /// 最初の呼び出しでデコードし、以降はデコード済みの値を返す
+    pub fn get(&self) -> Result<&T, Error> {
+        if let Some(value) = self.value.get() {
+            return Ok(value);
+        }
+        let value = self.raw.decode()?;
+        Ok(self.value.get_or_init(|| value))
+    }
+
+    /// 値を取り出す
+    pub fn into_inner(self) -> Result<T, Error> {
+        match self.value.into_inner() {
+            Some(value) => Ok(value),
+            None => self.raw.decode(),
+        }
+    }
+}
+
+impl<T> Clone for Lazy<T>
+where
+    T: Clone,
+{
+    fn clone(&self) -> Self {
+        Self { raw: self.raw.clone(), value: self.value.clone() }
+    }
+}
+
+impl<T> fmt::Debug for Lazy<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value.get() {
+            Some(value) => f.debug_tuple("Lazy").field(value).finish(),
+            None => f.debug_tuple("Lazy").field(&self.raw).finish(),
+        }
+    }
+}
+
+impl<T> Serialize for Lazy<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.raw.serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Lazy<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        RawTon::deserialize(deserializer).map(Self::from_raw)
+    }
+}
+
+/// serialize_bytes で書き出すためのラッパー
+struct RawBytes<'a>(&'a [u8]);
+
+impl ser::Serialize for RawBytes<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+/// RawTon に入れられるバイト列か検査する
+fn check_raw(bytes: &[u8]) -> Result<(), Error> {
+    let report = crate::validate(bytes)?;
+    if report.self_describe {
+        return Err(error("raw TON value cannot contain a self-describe tag", bytes.len() - 1));
+    }
+    Ok(())
+}
+
+/// 値 1 つ分のバイト列の中に、文書の他の場所を指す値 (REF と、文書の辞書を引く KEYREF) があるか
+pub(crate) fn refers_outside(bytes: &[u8]) -> Result<bool, Error> {
+    let mut stack = vec![TonRef::at(bytes, bytes.len().saturating_sub(1))?];
+    while let Some(value) = stack.pop() {
+        if matches!(value.prefix(), prefix::REF | prefix::KEYREF) {
+            return Ok(true);
+        }
+        for child in value.children_rev() {
+            stack.push(child?);
+        }
+    }
+    Ok(false)
+}
+
+/// 1 つの値として書かれていないので TonRef で指せないエラー
+#[cold]
+pub(crate) fn not_navigable(what: &str, pos: usize) -> Error {
+    error(format!("{} is not an encoded value and cannot be navigated", what), pos)
+}
+
+#[cold]
+fn error(msg: impl Into<String>, pos: usize) -> Error {
+    Error::syntax(ErrorCode::Other(msg.into()), pos)
+}
@@ -0,0 +1,121 @@
+//! 壊れた RTON からの値の回収
+//!
+//! 途中で切れたり一部が上書きされたりしたデータを後ろから走査し、
+//! それ単体で正しい値として読める範囲を集める
+
+use std::ops::Range;
+
+use crate::de::read_head;
+use crate::raw::TonRef;
+use crate::validate::validate;
+use crate::value::prefix::{prefix, self_describe};
+
+/// 回収した値
+#[derive(Debug, Clone)]
+pub struct RecoveredValue<'a> {
+    /// 値の範囲 (self-describe trailer を含む)
+    pub range: Range<usize>,
+    /// 末尾に self-describe trailer があったか
+    ///
+    /// trailer 付きの値は文書のルートだった可能性が高い
+    pub self_describe: bool,
+    /// 回収した値
+    pub value: TonRef<'a>,
+}
+
+/// 回収の結果
+#[derive(Debug, Clone, Default)]
+pub struct RecoveryReport<'a> {
+    /// 回収した値 (前から順)
+    pub values: Vec<RecoveredValue<'a>>,
+    /// どの値にも含まれなかった範囲 (前から順)
+    pub damaged: Vec<Range<usize>>,
+}
+
+impl RecoveryReport<'_> {
+    /// 壊れた範囲がないか
+    pub fn is_clean(&self) -> bool {
+        self.damaged.is_empty()
+    }
+
+    /// 壊れた範囲のバイト数の合計
+    pub fn damaged_bytes(&self) -> usize {
+        self.damaged.iter().map(|range| range.len()).sum()
+    }
+}
+
+/// 壊れた RTON から読める値を回収する
+///
+/// 末尾から、そこで終わる値が `validate` で正しいと確かめられるか試す
+/// 正しければその値を回収して値の先頭に進み、正しくなければ 1 バイト戻って試す
+/// ルートの head が失われている場合は、残っている子の値を 1 つずつ回収する
+///
+/// 壊れた範囲の中の数バイトが偶然正しい値として読めることがあるので、
+/// 小さな値をどこまで信用するかは呼び出し側で判断する
+pub fn recover(buf: &[u8]) -> RecoveryReport<'_> {
+    let mut report = RecoveryReport::default();
+    let mut end = buf.len();
+    // 今見ている壊れた範囲の終端
+    let mut damaged_end = None;
+    while end > 0 {
+        match recover_at(buf, end) {
+            Some(found) => {
+                if let Some(damaged_end) = damaged_end.take() {
+                    report.damaged.push(end..damaged_end);
+                }
+                end = found.as_ref().map_or_else(|| padding_start(buf, end), |v| v.range.start);
+                report.values.extend(found);
+            }
+            None => {
+                damaged_end.get_or_insert(end);
+                end -= 1;
+            }
+        }
+    }
+    if let Some(damaged_end) = damaged_end {
+        report.damaged.push(0..damaged_end);
+    }
+    report.values.reverse();
+    report.damaged.reverse();
+    report
+}
+
+/// end で終わる正しい値を探す
+///
+/// Padding だった場合は Some(None)
+fn recover_at(buf: &[u8], end: usize) -> Option<Option<RecoveredValue<'_>>> {
+    let tag = self_describe::TON_V1_REV_TAG;
+    if buf[..end].ends_with(&tag)
+        && let Some(value) = value_at(buf, end - tag.len())
+    {
+        return Some(Some(RecoveredValue {
+            range: value.range().start..end,
+            self_describe: true,
+            value,
+        }));
+    }
+    let head = read_head(buf, end).ok()?;
+    if head.prefix() == prefix::PADDING {
+        return Some(None);
+    }
+    let value = value_at(buf, end)?;
+    Some(Some(RecoveredValue {
+        range: value.range(),
+        self_describe: false,
+        value,
+    }))
+}
+
+/// end で終わる値が単体で正しければ返す
+fn value_at(buf: &[u8], end: usize) -> Option<TonRef<'_>> {
+    let value = TonRef::at(buf, end.checked_sub(1)?).ok()?;
+    if value.prefix() == prefix::PADDING {
+        return None;
+    }
+    validate(value.raw()).ok()?;
+    Some(value)
+}
+
+fn padding_start(buf: &[u8], end: usize) -> usize {
+    read_head(buf, end).map_or(end - 1, |head| head.start)
+}
@@ -4,9 +4,154 @@ use serde::{ser, Serialize, Serializer};
 
 use crate::traits::ser::{ExtendSerialize, ExtendSerializeMap, ExtendSerializeSeq, ExtendSerializeStruct, ExtendSerializeStructVariant, ExtendSerializeTuple, ExtendSerializeTupleStruct, ExtendSerializeTupleVariant, ExtendedSerializer};
 use crate::value::prefix::self_describe;
+use crate::value::value::Value;
+use crate::raw::{TonRef, RAW_TOKEN};
+use crate::slack::slack_len;
+use crate::packed::{encode, ArrayEncoding, PackedBuilder, PACKED_TOKEN};
+use crate::columnar::{TableBuilder, COLUMNAR_TOKEN};
+use crate::dedup::Deduper;
+use crate::compress::Compression;
+use crate::checksum::{container_entry, document_trailer, suffix, update};
+use crate::intern::{encode_reference, Interner, KeyDictionary};
+use crate::de::read_head;
+use crate::value::prefix::container_index;
 use crate::{error::Error, value::prefix::prefix};
 use crate::value::prefix::size_prefix::{SIZE_PREFIX_1BYTE, SIZE_PREFIX_2BYTE, SIZE_PREFIX_4BYTE, SIZE_PREFIX_8BYTE};
 
+/// シリアライザの設定
+///
+/// 設定はそれぞれ組み合わせられる
+///
+/// ```ignore
+/// let options = SerializeOptions::new().dedup().compression(Compression::new(64)).container_checksums(256);
+/// let bytes = to_vec_with(&value, &options)?;
+/// ```
+#[derive(Clone, Default)]
+pub struct SerializeOptions {
+    index_threshold: Option<usize>,
+    alignment: Option<u64>,
+    packed_arrays: bool,
+    array_encoding: ArrayEncoding,
+    columnar: bool,
+    interned_keys: bool,
+    key_dictionary: Option<KeyDictionary>,
+    dedup: bool,
+    compression: Option<Compression>,
+    checksum: bool,
+    checksum_threshold: Option<usize>,
+}
+
+impl SerializeOptions {
+    /// 何もしない設定 (`to_vec` と同じ)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 子が `threshold` 個以上あるコンテナにオフセット索引を付ける
+    ///
+    /// 索引は PADDING として書くので、索引を知らない reader でもそのまま読める
+    /// `TonRef` は索引を使って Array の要素へ直接移動し、key が整列した Object を二分探索する
+    pub fn index(mut self, threshold: usize) -> Self {
+        self.index_threshold = Some(threshold);
+        self
+    }
+
+    /// Bytes と型付き配列の body が文書の先頭から `align` バイトの境界で始まるように PADDING を挟む
+    ///
+    /// `align` は 128 以下の 2 の累乗 (4, 8, 16 など)
+    /// 揃えたバッファからは `TonRef::as_slice` で `&[f32]` などをコピーせずに読める
+    ///
+    /// # Panics
+    ///
+    /// `align` が 128 以下の 2 の累乗でない場合
+    pub fn alignment(mut self, align: usize) -> Self {
+        assert!(align.is_power_of_two() && align <= 128, "alignment must be a power of two up to 128: {}", align);
+        self.alignment = Some(align as u64).filter(|&align| align > 1);
+        self
+    }
+
+    /// 同じ型の数値, Bool, UUID, Timestamp だけのシーケンスを型付き配列で書く
+    ///
+    /// 要素ごとの head を書かないので、`Vec<f64>` は 1 要素 8 バイトになる
+    /// 一部のシーケンスだけを詰める場合は `Packed` を使う
+    pub fn packed_arrays(mut self) -> Self {
+        self.packed_arrays = true;
+        self
+    }
+
+    /// `packed_arrays` に加えて、小さくなる場合は型付き配列の要素を `encoding` で符号化する
+    ///
+    /// 一定間隔の Timestamp や少しずつ増えるカウンタは差分、同じ値が続く列はランレングスが小さくなる
+    /// 読む側は型付き配列と同じように扱えるが、`TonRef::as_slice` では借用できない
+    pub fn array_encoding(mut self, encoding: ArrayEncoding) -> Self {
+        self.packed_arrays = true;
+        self.array_encoding = encoding;
+        self
+    }
+
+    /// 同じフィールドを持つ構造体のシーケンスを表 (列ごと) で書く
+    ///
+    /// フィールド名は 1 回だけ書き、数値などの列は型付き配列で詰める
+    /// 行は一度メモリに溜めるので、表の全体を書き終えるまで出力されない
+    /// 一部のシーケンスだけを表にする場合は `Columnar` を使う
+    pub fn columnar(mut self) -> Self {
+        self.columnar = true;
+        self
+    }
+
+    /// Object の String key を辞書の番号 (KEYREF) で書く
+    ///
+    /// 同じ key は 2 回目以降も番号だけになる
+    /// 辞書はルートの値の後ろに書く
+    pub fn interned_keys(mut self) -> Self {
+        self.interned_keys = true;
+        self
+    }
+
+    /// `interned_keys` に加えて、`dictionary` にある key は文書に書かずに共有した辞書の番号で書く
+    ///
+    /// 読む側は `ReverseDeserializer::use_key_dictionary` で同じ辞書を渡す
+    pub fn key_dictionary(mut self, dictionary: &KeyDictionary) -> Self {
+        self.interned_keys = true;
+        self.key_dictionary = Some(dictionary.clone());
+        self
+    }
+
+    /// コンテナの子が前に書いた値と同じ場合に、その値への参照 (REF) で書く
+    ///
+    /// 繰り返し現れる String や同じ部分木が 2 回目以降は数バイトになる
+    /// 参照は文書の先頭からの位置なので、文書の一部を切り出すと参照先を失う
+    pub fn dedup(mut self) -> Self {
+        self.dedup = true;
+        self
+    }
+
+    /// `compression` の閾値以上の String, Bytes, WrappedJSON の body を圧縮 (Compressed) する
+    ///
+    /// 圧縮して小さくなる場合だけ Compressed で書くので、圧縮が効かない body はそのまま
+    /// 読む側は展開した元の型として扱う
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// 書いたバイト列の CRC32C を数え、文書のチェックサムを書く
+    pub fn checksum(mut self) -> Self {
+        self.checksum = true;
+        self
+    }
+
+    /// `checksum` に加えて、body が `threshold` バイト以上の Array, Object にチェックサムを付ける
+    ///
+    /// コンテナのチェックサムは body の最後の PADDING なので、チェックサムを知らない reader は読み飛ばす
+    /// 壊れたコンテナの範囲が分かるので、壊れていない部分だけを読み直せる
+    pub fn container_checksums(mut self, threshold: usize) -> Self {
+        self.checksum = true;
+        self.checksum_threshold = Some(threshold);
+        self
+    }
+}
+
 /// A structure for serializing Rust values to RTON.
 pub struct ReverseSerializer<W>
 where
@@ -15,6 +160,32 @@ where
     writer: W,
     size: u64,
     deep: u64,
+    /// 次の bytes を RawTon としてそのまま書き込むか
+    raw: bool,
+    /// この数以上の子を持つコンテナにオフセット索引を付ける
+    index_threshold: Option<usize>,
+    /// Bytes と型付き配列の body をこのバイト境界に揃える
+    alignment: Option<u64>,
+    /// 全てのシーケンスを型付き配列で書けるか試す
+    packed_arrays: bool,
+    /// 次のシーケンスを型付き配列で書けるか試す (`Packed`)
+    pack_next: bool,
+    /// 型付き配列の要素を符号化する方針
+    array_encoding: ArrayEncoding,
+    /// 全ての構造体のシーケンスを表で書けるか試す
+    columnar: bool,
+    /// 次のシーケンスを表で書けるか試す (`Columnar`)
+    columnar_next: bool,
+    /// Object の String key を辞書の番号で書く
+    interner: Option<Interner>,
+    /// 前に書いた値と同じ子を参照 (REF) で書く
+    dedup: Option<Deduper>,
+    /// 大きな String / Bytes / WrappedJSON の body を圧縮する
+    compression: Option<Compression>,
+    /// ここまでに書いたバイト列の CRC32C (チェックサムを書く場合)
+    crc: Option<u32>,
+    /// body がこのバイト数以上の Array, Object にチェックサムを付ける
+    checksum_threshold: Option<usize>,
 }
 
 impl<W> ReverseSerializer<W>
@@ -28,9 +199,66 @@ where
             writer,
             size: 0,
             deep: 0,
+            raw: false,
+            index_threshold: None,
+            alignment: None,
+            packed_arrays: false,
+            pack_next: false,
+            array_encoding: ArrayEncoding::Plain,
+            columnar: false,
+            columnar_next: false,
+            interner: None,
+            dedup: None,
+            compression: None,
+            crc: None,
+            checksum_threshold: None,
+        }
+    }
+
+    /// `options` の設定で書くシリアライザを作る
+    ///
+    /// key の辞書とチェックサムを使う場合は、ルートの値を書いた後に `write_key_dictionary` と `write_checksum` を呼ぶ
+    pub fn with_options(writer: W, options: &SerializeOptions) -> Self {
+        let mut ser = Self::new(writer);
+        ser.index_threshold = options.index_threshold;
+        ser.alignment = options.alignment;
+        ser.packed_arrays = options.packed_arrays;
+        ser.array_encoding = options.array_encoding;
+        ser.columnar = options.columnar;
+        ser.interner = options.interned_keys.then(|| Interner::new(options.key_dictionary.clone()));
+        ser.dedup = options.dedup.then(Deduper::new);
+        ser.compression = options.compression.clone();
+        ser.crc = options.checksum.then_some(0);
+        ser.checksum_threshold = options.checksum_threshold;
+        ser
+    }
+
+    /// 文書のチェックサムを書く
+    ///
+    /// ここまでに書いた全てのバイト列 (ルートの値と key の辞書) の CRC32C を PADDING として書く
+    /// `write_key_dictionary` の後、`write_self_describe` の前に書く
+    /// チェックサムを使わないシリアライザでは何もしない
+    pub fn write_checksum(&mut self) -> Result<(), Error> {
+        match self.crc {
+            Some(crc) => self.write_encoded(&document_trailer(crc)),
+            None => Ok(()),
         }
     }
 
+    /// key の辞書をルートの値の後ろに書く
+    ///
+    /// 辞書は PADDING なので、ルートの値と self-describe tag の間に置く
+    /// key の辞書を使わないシリアライザでは何もしない
+    pub fn write_key_dictionary(&mut self) -> Result<(), Error> {
+        let body = match &self.interner {
+            Some(interner) => interner.encode(),
+            None => return Ok(()),
+        };
+        let (header, header_size) = generate_header(prefix::PADDING, body.len() as u64);
+        self.write_encoded(&body)?;
+        self.write_encoded(&header[..header_size])
+    }
+
     /// Unwrap the `Writer` from the `Serializer`.
     #[inline]
     pub fn into_inner(self) -> W {
@@ -55,9 +283,207 @@ where
     #[inline]
     fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
         self.writer.write_all(bytes).map_err(Error::io)?;
+        if let Some(crc) = &mut self.crc {
+            *crc = update(*crc, bytes);
+        }
+        Ok(())
+    }
+
+    /// エンコード済みのバイト列をそのまま書き込む
+    #[inline]
+    fn write_encoded(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.write_bytes(bytes)?;
+        self.size += bytes.len() as u64;
         Ok(())
     }
 
+    /// 次に書く body が境界から始まるように PADDING を書く
+    ///
+    /// PADDING は 2 バイト以上なので、1 バイト足りない場合は 1 つ先の境界まで進める
+    fn align_body(&mut self) -> Result<(), Error> {
+        let align = match self.alignment {
+            Some(align) => align,
+            None => return Ok(()),
+        };
+        let mut pad = (align - self.size % align) % align;
+        if pad == 1 {
+            pad += align;
+        }
+        if pad == 0 {
+            return Ok(());
+        }
+        let body = pad as usize - 2;
+        self.write_bytes(&[0u8; 128][..body])?;
+        self.write_bytes(&[body as u8, prefix::PADDING | SIZE_PREFIX_1BYTE])?;
+        self.size += pad;
+        Ok(())
+    }
+
+    /// 型付き配列を書く
+    ///
+    /// 符号化して小さくなる場合は Encoded Array にする
+    fn write_packed(&mut self, packed: &PackedBuilder) -> Result<(), Error> {
+        let element = packed.element().unwrap_or_default();
+        if let Some((encoding, payload)) = encode(element, packed.data(), self.array_encoding) {
+            self.write_encoded(&payload)?;
+            self.write_encoded(&[encoding, element])?;
+            let (header, header_size) = generate_header(prefix::ENCODED_ARRAY, payload.len() as u64 + 2);
+            return self.write_encoded(&header[..header_size]);
+        }
+        self.align_body()?;
+        let trailer = packed.trailer();
+        self.write_encoded(packed.data())?;
+        self.write_encoded(&trailer)?;
+        let (header, header_size) = generate_header(prefix::TYPED_ARRAY, (packed.data().len() + trailer.len()) as u64);
+        self.write_encoded(&header[..header_size])
+    }
+
+    /// 溜める要素を別にエンコードするシリアライザを作る
+    ///
+    /// 境界の揃えと参照以外の設定を引き継ぐ (書き出す位置が決まっていないため)
+    /// 溜めた要素を通常の子として書き出す場合は `write_reencoded` で付け直す
+    /// key の辞書は書き終えたら `interner` に戻す
+    fn scratch<'b>(&mut self, buf: &'b mut Vec<u8>) -> ReverseSerializer<&'b mut Vec<u8>> {
+        let mut ser = ReverseSerializer::new(buf);
+        ser.index_threshold = self.index_threshold;
+        ser.packed_arrays = self.packed_arrays;
+        ser.array_encoding = self.array_encoding;
+        ser.columnar = self.columnar;
+        ser.interner = self.interner.take();
+        ser.compression = self.compression.clone();
+        ser.crc = self.crc.map(|_| 0);
+        ser.checksum_threshold = self.checksum_threshold;
+        ser
+    }
+
+    /// 別にエンコードした値を、この位置で書いた場合と同じになるように書き直す
+    ///
+    /// Array と Object は開き直して、子ごとに境界の揃えと参照、コンテナごとに索引とチェックサムを付け直す
+    /// 元の索引とチェックサムの Padding は読み飛ばす
+    fn write_reencoded(&mut self, encoded: &[u8]) -> Result<(), Error> {
+        let value = TonRef::new(encoded)?;
+        match value.prefix() {
+            prefix::ARRAY => {
+                let mut compound = Compound::new(self);
+                for child in value.children()? {
+                    compound.write_encoded_child(child.raw())?;
+                    compound.mark();
+                }
+                ser::SerializeSeq::end(compound)
+            }
+            prefix::OBJECT => {
+                let mut compound = Compound::new(self);
+                for (key, value) in value.entries()? {
+                    compound.write_encoded_child(value.raw())?;
+                    compound.write_encoded_key(key.raw().to_vec())?;
+                }
+                ser::SerializeMap::end(compound)
+            }
+            prefix::BYTES | prefix::TYPED_ARRAY if value.body_len() != 0 => {
+                self.align_body()?;
+                self.write_encoded(encoded)
+            }
+            _ => self.write_encoded(encoded),
+        }
+    }
+
+    /// 子の値を別にエンコードし、前に書いた同じ値があれば参照を、なければエンコードした値を書く
+    ///
+    /// 別のシリアライザは位置を含めて全ての設定を引き継ぐので、中の参照や境界の揃えはそのまま使える
+    /// `dedup` は `self.dedup` から取り出したもので、書き終えたら戻す
+    fn write_deduped(
+        &mut self,
+        mut dedup: Deduper,
+        encode: impl FnOnce(&mut ReverseSerializer<&mut Vec<u8>>) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let mark = dedup.enter();
+        let mut buf = Vec::new();
+        let mut ser = ReverseSerializer::new(&mut buf);
+        ser.size = self.size;
+        ser.index_threshold = self.index_threshold;
+        ser.alignment = self.alignment;
+        ser.packed_arrays = self.packed_arrays;
+        ser.array_encoding = self.array_encoding;
+        ser.columnar = self.columnar;
+        ser.interner = self.interner.take();
+        ser.compression = self.compression.clone();
+        ser.crc = self.crc.map(|_| 0);
+        ser.checksum_threshold = self.checksum_threshold;
+        ser.dedup = Some(dedup);
+        let res = encode(&mut ser);
+        self.interner = ser.interner.take();
+        let mut dedup = ser.dedup.take().unwrap_or_else(Deduper::new);
+        let target = res.as_ref().ok().and_then(|_| dedup.find(&buf));
+        let reference = target.map(|target| encode_reference(prefix::REF, target)).filter(|(_, len)| *len < buf.len());
+        dedup.leave(mark, reference.is_some());
+        let res = res.and_then(|_| match reference {
+            Some((reference, len)) => self.write_encoded(&reference[..len]),
+            None => {
+                self.write_encoded(&buf)?;
+                dedup.insert(&buf, self.size - 1);
+                Ok(())
+            }
+        });
+        self.dedup = Some(dedup);
+        res
+    }
+
+    /// 圧縮する設定で、圧縮して小さくなる場合は body を Compressed で書き、true を返す
+    ///
+    /// `prefix` は展開した body を持つ型
+    fn write_compressed(&mut self, prefix: u8, body: &[u8]) -> Result<bool, Error> {
+        let Some(compressed) = self.compression.as_ref().and_then(|compression| compression.compress(prefix, body)) else {
+            return Ok(false);
+        };
+        let (header, header_size) = generate_header(prefix::COMPRESSED, compressed.len() as u64);
+        self.write_encoded(&compressed)?;
+        self.write_encoded(&header[..header_size])?;
+        Ok(true)
+    }
+
+    /// 圧縮せずに String を書く (Object の key と enum の variant 名)
+    fn write_plain_str(&mut self, v: &str) -> Result<(), Error> {
+        let bytes = v.as_bytes();
+        let size = bytes.len();
+        let (header, header_size) = generate_header(prefix::STRING, size as u64);
+        // 文字列データを逆順に格納
+        self.write_bytes(bytes)?;
+        self.write_bytes(&header[..header_size])?;
+        self.size += (size + header_size) as u64;
+        Ok(())
+    }
+
+    /// key の辞書を使う場合は key を番号で書き、true を返す
+    fn write_interned_key(&mut self, key: &str) -> Result<bool, Error> {
+        let Some(interner) = &mut self.interner else {
+            return Ok(false);
+        };
+        let (keyref, len) = encode_reference(prefix::KEYREF, interner.intern(key));
+        self.write_encoded(&keyref[..len])?;
+        Ok(true)
+    }
+
+    /// 表を書く
+    ///
+    /// 全ての値を詰められる列は型付き配列、それ以外の列は Array にする
+    fn write_table(&mut self, table: &TableBuilder) -> Result<(), Error> {
+        let start_pos = self.size;
+        for (key, column) in table.columns() {
+            let mut packed = PackedBuilder::default();
+            if column.values().all(|value| packed.push(value)) {
+                self.write_packed(&packed)?;
+            } else {
+                self.write_encoded(column.data())?;
+                let (header, header_size) = generate_header(prefix::ARRAY, column.data().len() as u64);
+                self.write_encoded(&header[..header_size])?;
+            }
+            self.write_encoded(key)?;
+        }
+        (&mut *self).serialize_u64(table.rows() as u64)?;
+        let (header, header_size) = generate_header(prefix::TABLE, self.size - start_pos);
+        self.write_encoded(&header[..header_size])
+    }
+
     /// シリアライズしたサイズを取得する
     /// 
     /// return: u64
@@ -100,13 +526,13 @@ where
     #[inline]
     fn serialize_datetime<Tz>(self, v: &chrono::DateTime<Tz>) -> Result<Self::Ok, Self::Error>
     where
-        Tz: chrono::TimeZone + ?Sized {
+        Tz: chrono::TimeZone {
         let rfc_str = v.to_rfc3339();
         let bytes = rfc_str.as_bytes();
         let size = bytes.len();
         let (header, header_size) = generate_header(prefix::DATETIME, size as u64);
         // 日時データを逆順に格納
-        self.write_bytes(&bytes)?;
+        self.write_bytes(bytes)?;
         self.write_bytes(&header[..header_size])?;
         self.size += (size + header_size) as u64;
         Ok(())
@@ -139,17 +565,20 @@ where
     ) -> Result<Self::Ok, Self::Error> {
         let json_str = v.to_string();
         let bytes = json_str.as_bytes();
+        if self.write_compressed(prefix::WRAPPED_JSON, bytes)? {
+            return Ok(());
+        }
         let size = bytes.len();
         let (header, header_size) = generate_header(prefix::WRAPPED_JSON, size as u64);
         // JSONデータを逆順に格納
-        self.write_bytes(&bytes)?;
+        self.write_bytes(bytes)?;
         self.write_bytes(&header[..header_size])?;
         self.size += (size + header_size) as u64;
         Ok(())
     }
     
     #[inline]
-    fn serialize_meta(self, v: &Box<crate::value::value::Value>) -> Result<Self::Ok, Self::Error> {
+    fn serialize_meta(self, v: &crate::value::value::Value) -> Result<Self::Ok, Self::Error> {
         let start_pos = self.size;
         v.ex_serialize(&mut *self)?;
         let (header, header_size) = generate_header(prefix::META, self.size - start_pos);
@@ -172,9 +601,17 @@ where
         Ok(())
     }
 
+    #[inline]
+    fn serialize_undefined(self) -> Result<Self::Ok, Self::Error> {
+        let value = [prefix::UNDEFINED];
+        self.write_bytes(&value)?;
+        self.size += 1;
+        Ok(())
+    }
+
     #[inline]
     fn ex_serialize_seq(self, _len: Option<usize>) -> Result<Self::ExtendSerializeSeq, Self::Error> {
-        Ok(Compound::new(self))
+        Ok(Compound::new_seq(self))
     }
 
     #[inline]
@@ -316,19 +753,26 @@ where
 
     #[inline]
     fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
-        let bytes = v.as_bytes();
-        let size = bytes.len();
-        let (header, header_size) = generate_header(prefix::STRING, size as u64);
-        // 文字列データを逆順に格納
-        self.write_bytes(bytes)?;
-        self.write_bytes(&header[..header_size])?;
-        self.size += (size + header_size) as u64;
-        Ok(())
+        if self.write_compressed(prefix::STRING, v.as_bytes())? {
+            return Ok(());
+        }
+        self.write_plain_str(v)
     }
     
     #[inline]
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        if std::mem::take(&mut self.raw) {
+            // エンコード済みの値なのでそのまま書き込む
+            return self.write_encoded(v);
+        }
+        // 圧縮した body は境界に揃えない (借用できないため)
+        if self.write_compressed(prefix::BYTES, v)? {
+            return Ok(());
+        }
         let size = v.len();
+        if size != 0 {
+            self.align_body()?;
+        }
         let (header, header_size) = generate_header(prefix::BYTES, size as u64);
         // バイトデータを逆順に格納
         self.write_bytes(v)?;
@@ -366,16 +810,32 @@ where
         _variant_index: u32,
         variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
-        self.serialize_str(variant)
+        self.write_plain_str(variant)
     }
 
     fn serialize_newtype_struct<T>(
         self,
-        _name: &'static str,
+        name: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
         T: ?Sized + ser::Serialize {
+        if name == RAW_TOKEN {
+            self.raw = true;
+        } else if name == PACKED_TOKEN {
+            self.pack_next = true;
+            let res = value.serialize(&mut *self);
+            self.pack_next = false;
+            return res;
+        } else if name == COLUMNAR_TOKEN {
+            self.columnar_next = true;
+            let res = value.serialize(&mut *self);
+            self.columnar_next = false;
+            return res;
+        } else if let Some(len) = slack_len(name) {
+            // 値の直前に空きを置くので、値を大きくするときは前に広げられる
+            (&mut *self).serialize_padding(len)?;
+        }
         value.serialize(&mut *self)
     }
 
@@ -390,7 +850,7 @@ where
         T: ?Sized + ser::Serialize {
         let start_pos = self.size;
         self.serialize_some(value)?;
-        self.serialize_str(variant)?;
+        self.write_plain_str(variant)?;
         let (header, header_size) = generate_header(prefix::OBJECT, self.size - start_pos);
         self.write_bytes(&header[..header_size])?;
         self.size += header_size as u64;
@@ -398,7 +858,7 @@ where
     }
 
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
-        Ok(Compound::new(self))
+        Ok(Compound::new_seq(self))
     }
 
     fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
@@ -452,7 +912,17 @@ where
 {
     ser: &'a mut ReverseSerializer<W>,
     start_pos: u64,
+    /// 子を書く前の CRC32C
+    start_crc: u32,
     variant_name: Option<&'static str>,
+    /// 索引を付ける場合の子の終端 (start_pos から)
+    index: Option<IndexBuilder>,
+    /// 型付き配列で書く場合の要素
+    packed: Option<PackedBuilder>,
+    /// 表で書く場合の行
+    table: Option<TableBuilder>,
+    /// 溜める要素をエンコードするためのバッファ
+    scratch: Vec<u8>,
 }
 
 impl<'a, W> Compound<'a, W>
@@ -464,11 +934,38 @@ where
         let start_pos = ser.size;
         // ネストの深さを増やす
         ser.deep += 1;
+        let index = ser.index_threshold.map(|_| IndexBuilder::default());
+        // `Packed` と `Columnar` はすぐ内側のシーケンスにだけ使う
+        ser.pack_next = false;
+        ser.columnar_next = false;
+        let start_crc = ser.crc.unwrap_or_default();
         Self {
             ser,
             start_pos,
+            start_crc,
             variant_name: None,
+            index,
+            packed: None,
+            table: None,
+            scratch: Vec::new(),
+        }
+    }
+
+    /// シーケンスを書く Compound を作る
+    ///
+    /// 型付き配列や表を使う場合は、要素が詰められなくなるまで書かずに溜める
+    #[inline]
+    pub(crate) fn new_seq(ser: &'a mut ReverseSerializer<W>) -> Self {
+        let pack = ser.packed_arrays || ser.pack_next;
+        let columnar = ser.columnar || ser.columnar_next;
+        let mut compound = Self::new(ser);
+        if pack {
+            compound.packed = Some(PackedBuilder::default());
+        }
+        if columnar {
+            compound.table = Some(TableBuilder::default());
         }
+        compound
     }
 
     #[inline]
@@ -476,12 +973,252 @@ where
         let start_pos = ser.size;
         // ネストの深さを増やす
         ser.deep += 2;
+        let index = ser.index_threshold.map(|_| IndexBuilder::default());
+        ser.pack_next = false;
+        ser.columnar_next = false;
+        let start_crc = ser.crc.unwrap_or_default();
         Self {
             ser,
             start_pos,
+            start_crc,
             variant_name: Some(variant_name),
+            index,
+            packed: None,
+            table: None,
+            scratch: Vec::new(),
         }
     }
+
+    /// 書き終えた要素の終端を記録する
+    #[inline]
+    fn mark(&mut self) {
+        if let Some(index) = &mut self.index {
+            index.ends.push(self.ser.size - self.start_pos);
+        }
+    }
+
+    /// 書き終えた Object のエントリの終端を記録する
+    ///
+    /// key は String の場合の中身、それ以外は None
+    #[inline]
+    fn mark_entry(&mut self, key: Option<&[u8]>) {
+        if let Some(index) = &mut self.index {
+            index.ends.push(self.ser.size - self.start_pos);
+            index.push_key(key);
+        }
+    }
+
+    /// 子の値を書く
+    ///
+    /// 重複を除く場合は、前に書いた同じ値への参照になることがある
+    fn write_child<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        match self.ser.dedup.take() {
+            Some(dedup) => self.ser.write_deduped(dedup, |ser| value.serialize(ser)),
+            None => value.serialize(&mut *self.ser),
+        }
+    }
+
+    /// 別にエンコードした子の値を書き直す
+    ///
+    /// 重複を除く場合は、前に書いた同じ値への参照になることがある
+    fn write_encoded_child(&mut self, encoded: &[u8]) -> Result<(), Error> {
+        match self.ser.dedup.take() {
+            Some(dedup) => self.ser.write_deduped(dedup, |ser| ser.write_reencoded(encoded)),
+            None => self.ser.write_reencoded(encoded),
+        }
+    }
+
+    /// `write_child` の ExtendSerialize 版
+    fn write_ex_child<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + ExtendSerialize,
+    {
+        match self.ser.dedup.take() {
+            Some(dedup) => self.ser.write_deduped(dedup, |ser| value.ex_serialize(ser)),
+            None => value.ex_serialize(&mut *self.ser),
+        }
+    }
+
+    /// 別にエンコードした key を書き込んでエントリの終端を記録する
+    ///
+    /// 索引を付ける場合は key が整列しているか調べ、key の辞書を使う場合は String の key を番号で書くため、
+    /// key だけは一度 Vec にエンコードする
+    fn write_encoded_key(&mut self, key: Vec<u8>) -> Result<(), Error> {
+        let text = string_body(&key).and_then(|body| std::str::from_utf8(body).ok());
+        if let Some(text) = text && self.ser.write_interned_key(text)? {
+            self.mark_entry(None);
+            return Ok(());
+        }
+        self.ser.write_encoded(&key)?;
+        self.mark_entry(string_body(&key));
+        Ok(())
+    }
+
+    /// 構造体のフィールド名を書いてエントリの終端を記録する
+    ///
+    /// key の辞書を使う場合は番号で書く (番号の key は整列を調べられない)
+    fn write_field_key(&mut self, key: &'static str) -> Result<(), Error> {
+        if self.ser.write_interned_key(key)? {
+            self.mark_entry(None);
+            return Ok(());
+        }
+        self.ser.write_plain_str(key)?;
+        self.mark_entry(Some(key.as_bytes()));
+        Ok(())
+    }
+
+    /// 型付き配列や表に要素を溜める
+    ///
+    /// 要素を別のバッファにエンコードしてみて、溜められれば true を返す
+    /// 溜められない場合は溜めていた要素を通常の子として書き出し、この要素はまだ書かずに false を返す
+    fn buffer_element(
+        &mut self,
+        encode: impl FnOnce(&mut ReverseSerializer<&mut Vec<u8>>) -> Result<(), Error>,
+    ) -> Result<bool, Error> {
+        if self.packed.is_none() && self.table.is_none() {
+            return Ok(false);
+        }
+        let mut scratch = std::mem::take(&mut self.scratch);
+        scratch.clear();
+        let mut ser = self.ser.scratch(&mut scratch);
+        let res = encode(&mut ser);
+        self.ser.interner = ser.interner.take();
+        res?;
+        let res = self.buffer_encoded(&scratch);
+        self.scratch = scratch;
+        res
+    }
+
+    fn buffer_encoded(&mut self, encoded: &[u8]) -> Result<bool, Error> {
+        if let Some(packed) = &mut self.packed {
+            if packed.push(encoded) {
+                return Ok(true);
+            }
+            let packed = self.packed.take().unwrap_or_default();
+            if !packed.is_empty() {
+                // 溜めていた要素は Object ではないので表にもできない
+                self.table = None;
+                packed.for_each_element(|encoded| {
+                    self.write_encoded_child(encoded)?;
+                    self.mark();
+                    Ok(())
+                })?;
+                return Ok(false);
+            }
+        }
+        if let Some(table) = &mut self.table {
+            if table.push_row(encoded) {
+                return Ok(true);
+            }
+            if let Some(table) = self.table.take() {
+                table.for_each_row(|encoded| {
+                    self.write_encoded_child(encoded)?;
+                    self.mark();
+                    Ok(())
+                })?;
+            }
+        }
+        Ok(false)
+    }
+
+    /// 溜めた要素があれば型付き配列か表として書き、true を返す
+    fn write_buffered(&mut self) -> Result<bool, Error> {
+        if let Some(packed) = self.packed.take().filter(|packed| !packed.is_empty()) {
+            self.ser.write_packed(&packed)?;
+            return Ok(true);
+        }
+        if let Some(table) = self.table.take().filter(|table| !table.is_empty()) {
+            self.ser.write_table(&table)?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// body が閾値以上ならチェックサムの PADDING を書く
+    ///
+    /// 索引は body の最後に置くので、索引より先に書く
+    fn write_checksum_entry(&mut self) -> Result<(), Error> {
+        let (Some(crc), Some(threshold)) = (self.ser.crc, self.ser.checksum_threshold) else {
+            return Ok(());
+        };
+        let len = self.ser.size - self.start_pos;
+        if len < threshold as u64 {
+            return Ok(());
+        }
+        self.ser.write_encoded(&container_entry(suffix(crc, self.start_crc, len)))
+    }
+
+    /// 記録した終端から索引を書き込む
+    fn write_index(&mut self) -> Result<(), Error> {
+        let index = match self.index.take() {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+        if index.ends.len() < self.ser.index_threshold.unwrap_or(usize::MAX) {
+            return Ok(());
+        }
+        let body = index.encode(self.ser.size - self.start_pos);
+        let (header, header_size) = generate_header(prefix::PADDING, body.len() as u64);
+        self.ser.write_encoded(&body)?;
+        self.ser.write_encoded(&header[..header_size])
+    }
+}
+
+/// エンコードされた 1 つの String の中身
+fn string_body(bytes: &[u8]) -> Option<&[u8]> {
+    let head = read_head(bytes, bytes.len()).ok()?;
+    if head.prefix() == prefix::STRING && head.start == 0 {
+        Some(&bytes[..head.head_start])
+    } else {
+        None
+    }
+}
+
+/// コンテナの索引を組み立てる
+#[derive(Default)]
+struct IndexBuilder {
+    ends: Vec<u64>,
+    /// 直前の key (String の中身)
+    last_key: Option<Vec<u8>>,
+    /// String 以外の key か、順序の崩れた key があった
+    unsorted: bool,
+}
+
+impl IndexBuilder {
+    fn push_key(&mut self, key: Option<&[u8]>) {
+        match key {
+            Some(key) if !self.unsorted => {
+                if self.last_key.as_deref().is_some_and(|last| last >= key) {
+                    self.unsorted = true;
+                }
+                self.last_key = Some(key.to_vec());
+            }
+            _ => self.unsorted = true,
+        }
+    }
+
+    /// `[offset; count] count width flags MAGIC` の body を作る
+    ///
+    /// body_len は索引を除いたコンテナの body の長さ
+    fn encode(&self, body_len: u64) -> Vec<u8> {
+        let width: usize = if body_len <= u32::MAX as u64 { 4 } else { 8 };
+        let mut body = Vec::with_capacity(self.ends.len() * width + container_index::TRAILER_LEN);
+        for end in &self.ends {
+            body.extend_from_slice(&end.to_le_bytes()[..width]);
+        }
+        body.extend_from_slice(&(self.ends.len() as u64).to_le_bytes());
+        body.push(width as u8);
+        let mut flags = 0;
+        if self.last_key.is_some() && !self.unsorted {
+            flags |= container_index::FLAG_SORTED_KEYS;
+        }
+        body.push(flags);
+        body.extend_from_slice(&container_index::MAGIC);
+        body
+    }
 }
 
 impl<'a, W> ser::SerializeSeq for Compound<'a, W>
@@ -497,14 +1234,24 @@ where
     fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
         T: ?Sized + ser::Serialize {
-        value.serialize(&mut *self.ser)?;
+        if self.buffer_element(|ser| value.serialize(ser))? {
+            return Ok(());
+        }
+        self.write_child(value)?;
+        self.mark();
         Ok(())
     }
 
     #[inline]
-    fn end(self) -> Result<Self::Ok, Self::Error> {
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
         // ネストを抜ける
         self.ser.deep -= 1;
+        if self.write_buffered()? {
+            return Ok(());
+        }
+        // 索引を付ける場合は子の後ろに書く
+        self.write_checksum_entry()?;
+        self.write_index()?;
         // シーケンスの合計サイズを計算
         let seq_size = self.ser.size - self.start_pos;
         // ヘッダを生成
@@ -528,14 +1275,24 @@ where
     fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
         T: ?Sized + ExtendSerialize {
-        value.ex_serialize(&mut *self.ser)?;
+        if self.buffer_element(|ser| value.ex_serialize(ser))? {
+            return Ok(());
+        }
+        self.write_ex_child(value)?;
+        self.mark();
         Ok(())
     }
 
     #[inline]
-    fn end(self) -> Result<Self::Ok, Self::Error> {
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
         // ネストを抜ける
         self.ser.deep -= 1;
+        if self.write_buffered()? {
+            return Ok(());
+        }
+        // 索引を付ける場合は子の後ろに書く
+        self.write_checksum_entry()?;
+        self.write_index()?;
         // シーケンスの合計サイズを計算
         let seq_size = self.ser.size - self.start_pos;
         // ヘッダを生成
@@ -561,14 +1318,18 @@ where
     fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
         T: ?Sized + ser::Serialize {
-        value.serialize(&mut *self.ser)?;
+        self.write_child(value)?;
+        self.mark();
         Ok(())
     }
 
     #[inline]
-    fn end(self) -> Result<Self::Ok, Self::Error> {
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
         // ネストを抜ける
         self.ser.deep -= 1;
+        // 索引を付ける場合は子の後ろに書く
+        self.write_checksum_entry()?;
+        self.write_index()?;
         // シーケンスの合計サイズを計算
         let seq_size = self.ser.size - self.start_pos;
         // ヘッダを生成
@@ -592,14 +1353,18 @@ where
     fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
         T: ?Sized + ExtendSerialize {
-        value.ex_serialize(&mut *self.ser)?;
+        self.write_ex_child(value)?;
+        self.mark();
         Ok(())
     }
 
     #[inline]
-    fn end(self) -> Result<Self::Ok, Self::Error> {
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
         // ネストを抜ける
         self.ser.deep -= 1;
+        // 索引を付ける場合は子の後ろに書く
+        self.write_checksum_entry()?;
+        self.write_index()?;
         // シーケンスの合計サイズを計算
         let seq_size = self.ser.size - self.start_pos;
         // ヘッダを生成
@@ -624,14 +1389,18 @@ where
     fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
         T: ?Sized + ser::Serialize {
-        value.serialize(&mut *self.ser)?;
+        self.write_child(value)?;
+        self.mark();
         Ok(())
     }
 
     #[inline]
-    fn end(self) -> Result<Self::Ok, Self::Error> {
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
         // ネストを抜ける
         self.ser.deep -= 1;
+        // 索引を付ける場合は子の後ろに書く
+        self.write_checksum_entry()?;
+        self.write_index()?;
         // シーケンスの合計サイズを計算
         let seq_size = self.ser.size - self.start_pos;
         // ヘッダを生成
@@ -655,14 +1424,18 @@ where
     fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
         T: ?Sized + ExtendSerialize {
-        value.ex_serialize(&mut *self.ser)?;
+        self.write_ex_child(value)?;
+        self.mark();
         Ok(())
     }
 
     #[inline]
-    fn end(self) -> Result<Self::Ok, Self::Error> {
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
         // ネストを抜ける
         self.ser.deep -= 1;
+        // 索引を付ける場合は子の後ろに書く
+        self.write_checksum_entry()?;
+        self.write_index()?;
         // シーケンスの合計サイズを計算
         let seq_size = self.ser.size - self.start_pos;
         // ヘッダを生成
@@ -688,14 +1461,18 @@ where
     fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
         T: ?Sized + ser::Serialize {
-        value.serialize(&mut *self.ser)?;
+        self.write_child(value)?;
+        self.mark();
         Ok(())
     }
 
     #[inline]
-    fn end(self) -> Result<Self::Ok, Self::Error> {
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
         // ネストを抜ける(seq と map 分)
         self.ser.deep -= 2;
+        // 索引を付ける場合は子の後ろに書く
+        self.write_checksum_entry()?;
+        self.write_index()?;
         // シーケンスの合計サイズを計算
         let seq_size = self.ser.size - self.start_pos;
         // seqヘッダを生成
@@ -704,7 +1481,7 @@ where
         self.ser.write_bytes(&array_header[..array_header_size])?;
         self.ser.size += array_header_size as u64;
         // mapのkeyをシリアライズ
-        self.ser.serialize_str(self.variant_name.unwrap())?;
+        self.ser.write_plain_str(self.variant_name.unwrap())?;
         // マップの合計サイズを計算
         let map_size = self.ser.size - self.start_pos;
         // mapヘッダを生成
@@ -727,14 +1504,18 @@ where
     fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
         T: ?Sized + ExtendSerialize {
-        value.ex_serialize(&mut *self.ser)?;
+        self.write_ex_child(value)?;
+        self.mark();
         Ok(())
     }
 
     #[inline]
-    fn end(self) -> Result<Self::Ok, Self::Error> {
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
         // ネストを抜ける(seq と map 分)
         self.ser.deep -= 2;
+        // 索引を付ける場合は子の後ろに書く
+        self.write_checksum_entry()?;
+        self.write_index()?;
         // シーケンスの合計サイズを計算
         let seq_size = self.ser.size - self.start_pos;
         // seqヘッダを生成
@@ -743,7 +1524,7 @@ where
         self.ser.write_bytes(&array_header[..array_header_size])?;
         self.ser.size += array_header_size as u64;
         // mapのkeyをシリアライズ
-        self.ser.serialize_str(self.variant_name.unwrap())?;
+        self.ser.write_plain_str(self.variant_name.unwrap())?;
         // マップの合計サイズを計算
         let map_size = self.ser.size - self.start_pos;
         // mapヘッダを生成
@@ -771,7 +1552,12 @@ where
             V: ?Sized + ser::Serialize, {
         
         // 逆順のため、valueを先にシリアライズ
-        value.serialize(&mut *self.ser)?;
+        self.write_child(value)?;
+        if self.index.is_some() || self.ser.interner.is_some() || self.ser.compression.is_some() {
+            let mut key_ser = ReverseSerializer::new(Vec::new());
+            key.serialize(&mut key_ser)?;
+            return self.write_encoded_key(key_ser.into_inner());
+        }
         key.serialize(&mut *self.ser)?;
 
         Ok(())
@@ -781,22 +1567,29 @@ where
     fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
     where
         T: ?Sized + ser::Serialize {
-        key.serialize(&mut *self.ser)?;
-        Ok(())
+        // key は圧縮しない
+        let compression = self.ser.compression.take();
+        let res = key.serialize(&mut *self.ser);
+        self.ser.compression = compression;
+        res
     }
 
     #[inline]
     fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
         T: ?Sized + ser::Serialize {
-        value.serialize(&mut *self.ser)?;
+        self.write_child(value)?;
+        self.mark_entry(None);
         Ok(())
     }
 
     #[inline]
-    fn end(self) -> Result<Self::Ok, Self::Error> {
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
         // ネストを抜ける
         self.ser.deep -= 1;
+        // 索引を付ける場合は子の後ろに書く
+        self.write_checksum_entry()?;
+        self.write_index()?;
         // Mapの合計サイズを計算
         let seq_size = self.ser.size - self.start_pos;
         // ヘッダを生成
@@ -823,7 +1616,12 @@ where
             V: ?Sized + ExtendSerialize, {
         
         // 逆順のため、valueを先にシリアライズ
-        value.ex_serialize(&mut *self.ser)?;
+        self.write_ex_child(value)?;
+        if self.index.is_some() || self.ser.interner.is_some() || self.ser.compression.is_some() {
+            let mut key_ser = ReverseSerializer::new(Vec::new());
+            key.ex_serialize(&mut key_ser)?;
+            return self.write_encoded_key(key_ser.into_inner());
+        }
         key.ex_serialize(&mut *self.ser)?;
 
         Ok(())
@@ -833,22 +1631,29 @@ where
     fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
     where
         T: ?Sized + ExtendSerialize {
-        key.ex_serialize(&mut *self.ser)?;
-        Ok(())
+        // key は圧縮しない
+        let compression = self.ser.compression.take();
+        let res = key.ex_serialize(&mut *self.ser);
+        self.ser.compression = compression;
+        res
     }
 
     #[inline]
     fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
         T: ?Sized + ExtendSerialize {
-        value.ex_serialize(&mut *self.ser)?;
+        self.write_ex_child(value)?;
+        self.mark_entry(None);
         Ok(())
     }
 
     #[inline]
-    fn end(self) -> Result<Self::Ok, Self::Error> {
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
         // ネストを抜ける
         self.ser.deep -= 1;
+        // 索引を付ける場合は子の後ろに書く
+        self.write_checksum_entry()?;
+        self.write_index()?;
         // Mapの合計サイズを計算
         let seq_size = self.ser.size - self.start_pos;
         // ヘッダを生成
@@ -874,15 +1679,17 @@ where
     where
         T: ?Sized + ser::Serialize {
         // 逆順のため、valueを先にシリアライズ
-        value.serialize(&mut *self.ser)?;
-        key.serialize(&mut *self.ser)?;
-        Ok(())
+        self.write_child(value)?;
+        self.write_field_key(key)
     }
 
     #[inline]
-    fn end(self) -> Result<Self::Ok, Self::Error> {
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
         // ネストを抜ける
         self.ser.deep -= 1;
+        // 索引を付ける場合は子の後ろに書く
+        self.write_checksum_entry()?;
+        self.write_index()?;
         // Structの合計サイズを計算
         let seq_size = self.ser.size - self.start_pos;
         // ヘッダを生成
@@ -907,15 +1714,17 @@ where
     where
         T: ?Sized + ExtendSerialize {
         // 逆順のため、valueを先にシリアライズ
-        value.ex_serialize(&mut *self.ser)?;
-        key.serialize(&mut *self.ser)?;
-        Ok(())
+        self.write_ex_child(value)?;
+        self.write_field_key(key)
     }
 
     #[inline]
-    fn end(self) -> Result<Self::Ok, Self::Error> {
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
         // ネストを抜ける
         self.ser.deep -= 1;
+        // 索引を付ける場合は子の後ろに書く
+        self.write_checksum_entry()?;
+        self.write_index()?;
         // Structの合計サイズを計算
         let seq_size = self.ser.size - self.start_pos;
         // ヘッダを生成
@@ -941,15 +1750,17 @@ where
     where
         T: ?Sized + ser::Serialize {
         // 逆順のため、valueを先にシリアライズ
-        value.serialize(&mut *self.ser)?;
-        key.serialize(&mut *self.ser)?;
-        Ok(())
+        self.write_child(value)?;
+        self.write_field_key(key)
     }
 
     #[inline]
-    fn end(self) -> Result<Self::Ok, Self::Error> {
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
         // ネストを抜ける(map と map 分)
         self.ser.deep -= 2;
+        // 索引を付ける場合は子の後ろに書く
+        self.write_checksum_entry()?;
+        self.write_index()?;
         // structの合計サイズを計算
         let seq_size = self.ser.size - self.start_pos;
         // structのヘッダを生成
@@ -959,7 +1770,7 @@ where
         // ヘッダ分のサイズを加算
         self.ser.size += header_size as u64;
         // mapのkeyをシリアライズ
-        self.ser.serialize_str(self.variant_name.unwrap())?;
+        self.ser.write_plain_str(self.variant_name.unwrap())?;
         // outer_structの合計サイズを計算
         let outer_struct_size = self.ser.size - self.start_pos;
         // outer_structのヘッダを生成
@@ -984,15 +1795,17 @@ where
     where
         T: ?Sized + ExtendSerialize {
         // 逆順のため、valueを先にシリアライズ
-        value.ex_serialize(&mut *self.ser)?;
-        key.serialize(&mut *self.ser)?;
-        Ok(())
+        self.write_ex_child(value)?;
+        self.write_field_key(key)
     }
 
     #[inline]
-    fn end(self) -> Result<Self::Ok, Self::Error> {
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
         // ネストを抜ける(map と map 分)
         self.ser.deep -= 2;
+        // 索引を付ける場合は子の後ろに書く
+        self.write_checksum_entry()?;
+        self.write_index()?;
         // structの合計サイズを計算
         let seq_size = self.ser.size - self.start_pos;
         // structのヘッダを生成
@@ -1002,7 +1815,7 @@ where
         // ヘッダ分のサイズを加算
         self.ser.size += header_size as u64;
         // mapのkeyをシリアライズ
-        self.ser.serialize_str(self.variant_name.unwrap())?;
+        self.ser.write_plain_str(self.variant_name.unwrap())?;
         // outer_structの合計サイズを計算
         let outer_struct_size = self.ser.size - self.start_pos;
         // outer_structのヘッダを生成
@@ -1016,6 +1829,49 @@ where
     
 }
 
+/// 値を RTON にシリアライズして `Vec<u8>` で返す
+pub fn to_vec<T>(value: &T) -> Result<Vec<u8>, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let mut ser = ReverseSerializer::new(Vec::new());
+    value.serialize(&mut ser)?;
+    Ok(ser.into_inner())
+}
+
+/// 値を RTON にシリアライズして writer に書き込む
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<(), Error>
+where
+    W: Write,
+    T: ?Sized + Serialize,
+{
+    let mut ser = ReverseSerializer::new(writer);
+    value.serialize(&mut ser)
+}
+
+/// `options` の設定で値をシリアライズする
+///
+/// key の辞書とチェックサムを使う設定では、ルートの値の後ろに辞書と文書のチェックサムも書く
+pub fn to_vec_with<T>(value: &T, options: &SerializeOptions) -> Result<Vec<u8>, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let mut ser = ReverseSerializer::with_options(Vec::new(), options);
+    value.serialize(&mut ser)?;
+    ser.write_key_dictionary()?;
+    ser.write_checksum()?;
+    Ok(ser.into_inner())
+}
+
+/// Value を RTON にシリアライズして `Vec<u8>` で返す
+///
+/// UUID や DateTime などの拡張型もそのまま書き出す
+pub fn value_to_vec(value: &Value) -> Result<Vec<u8>, Error> {
+    let mut ser = ReverseSerializer::new(Vec::new());
+    value.ex_serialize(&mut ser)?;
+    Ok(ser.into_inner())
+}
+
 /// Generate reverse serialization header.
 /// 
 /// prefix: u8 // header prefix
@@ -1044,7 +1900,7 @@ pub fn generate_header(prefix: u8, size_of_byte: u64) -> ([u8; 9], usize) {
         return (buf, 5);
     }
 
-    buf[0..8].copy_from_slice(&(size_of_byte as u64).to_le_bytes());
+    buf[0..8].copy_from_slice(&size_of_byte.to_le_bytes());
     buf[8] = prefix | SIZE_PREFIX_8BYTE;
     (buf, 9)
 }
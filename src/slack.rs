@@ -0,0 +1,116 @@
+//! 値の前に空き (Padding) を確保する
+//!
+//! RAW EDIT で値を大きくするときに、親のコンテナのサイズを変えずに空きを使って書き換えるためのもの
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// RTON のシリアライザに空きのサイズを伝える newtype の名前の先頭
+///
+/// 後ろに body のバイト数を 10 進数で付ける
+pub(crate) const SLACK_TOKEN: &str = "$serde_ton::private::Slack::";
+
+/// 値の直前に body が `N` バイトの Padding を置いて書き出すラッパー
+///
+/// RTON 以外のフォーマットや読み込み時は `T` と同じように扱う
+/// 書き出した後は `edit_in_place` や `append_in_place` が空きを使って値を大きくできる
+///
+/// ```ignore
+/// #[derive(Serialize, Deserialize)]
+/// struct Counter {
+///     name: Slack<String, 32>,
+///     history: Slack<Vec<u32>, 256>,
+/// }
+/// ```
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Slack<T, const N: usize>(pub T);
+
+impl<T, const N: usize> Slack<T, N> {
+    pub fn new(value: T) -> Self {
+        Slack(value)
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T, const N: usize> Deref for Slack<T, N> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T, const N: usize> DerefMut for Slack<T, N> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T, const N: usize> From<T> for Slack<T, N> {
+    fn from(value: T) -> Self {
+        Slack(value)
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for Slack<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<T: Serialize, const N: usize> Serialize for Slack<T, N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(Self::NAME, &self.0)
+    }
+}
+
+impl<'de, T: Deserialize<'de>, const N: usize> Deserialize<'de> for Slack<T, N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Padding はデシリアライザが読み飛ばす
+        T::deserialize(deserializer).map(Slack)
+    }
+}
+
+/// 空きのサイズを付けた newtype の名前の最大の長さ (usize は 10 進数で 20 桁まで)
+const NAME_CAPACITY: usize = SLACK_TOKEN.len() + 20;
+
+impl<T, const N: usize> Slack<T, N> {
+    /// 空きのサイズを付けた newtype の名前 (コンパイル時に作る)
+    const NAME: &'static str = {
+        let (bytes, len) = &slack_name(N);
+        match std::str::from_utf8(bytes.split_at(*len).0) {
+            Ok(name) => name,
+            Err(_) => panic!("slack name is not UTF-8"),
+        }
+    };
+}
+
+/// `SLACK_TOKEN` の後ろに `len` を 10 進数で付けた名前と、その長さ
+const fn slack_name(len: usize) -> ([u8; NAME_CAPACITY], usize) {
+    let mut name = [0u8; NAME_CAPACITY];
+    let token = SLACK_TOKEN.as_bytes();
+    let mut i = 0;
+    while i < token.len() {
+        name[i] = token[i];
+        i += 1;
+    }
+    let digits = if len == 0 { 1 } else { len.ilog10() as usize + 1 };
+    let mut rest = len;
+    let mut k = digits;
+    while k > 0 {
+        k -= 1;
+        name[i + k] = b'0' + (rest % 10) as u8;
+        rest /= 10;
+    }
+    (name, i + digits)
+}
+
+/// newtype の名前から空きのサイズを読む
+pub(crate) fn slack_len(name: &str) -> Option<usize> {
+    name.strip_prefix(SLACK_TOKEN)?.parse().ok()
+}
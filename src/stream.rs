@@ -0,0 +1,315 @@
+//! 複数のドキュメントを 1 つのストリームに並べる
+//!
+//! ログのように独立したレコードを追記していくためのもの
+//! 各レコードは `stream_frame` の枠で囲むので、前からも後ろからも辿れる
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::de::from_slice;
+use crate::error::{Error, ErrorCode};
+use crate::ser::ReverseSerializer;
+use crate::value::prefix::stream_frame::{EDGE_LEN, HEAD, TAIL};
+
+/// magic を探すときに一度に読むバイト数
+const SCAN_CHUNK: usize = 64 * 1024;
+
+/// ドキュメントを枠付きで `Write` に追記していく
+pub struct StreamSerializer<W> {
+    writer: W,
+    /// 書いたレコード数
+    count: u64,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> StreamSerializer<W> {
+    pub fn new(writer: W) -> Self {
+        StreamSerializer { writer, count: 0, buf: Vec::new() }
+    }
+
+    /// 値を 1 レコードとして書く
+    pub fn write<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut buf = std::mem::take(&mut self.buf);
+        buf.clear();
+        let mut ser = ReverseSerializer::new(buf);
+        let res = value.serialize(&mut ser);
+        let buf = ser.into_inner();
+        let res = res.and_then(|_| self.write_raw(&buf));
+        self.buf = buf;
+        res
+    }
+
+    /// エンコード済みの RTON を 1 レコードとして書く
+    pub fn write_raw(&mut self, document: &[u8]) -> Result<(), Error> {
+        // レコード単位で 1 回の write にして、途中で切れる範囲を小さくする
+        self.writer.write_all(&frame(document))?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// 書いたレコード数
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// ストリームのレコードを順にデシリアライズするイテレータ
+///
+/// `next` は先頭から、`next_back` は末尾 (新しいもの) から読む
+/// 後ろから読む場合は `.rev()` を使う
+///
+/// レコードごとに `Result` を返し、壊れたレコードがあってもその先を読み続ける
+/// 枠が壊れていた場合は magic を探して次のレコードから読み直す
+pub struct StreamDeserializer<R, T> {
+    reader: R,
+    /// まだ読んでいない範囲
+    front: u64,
+    back: u64,
+    buf: Vec<u8>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<R: Read + Seek, T> StreamDeserializer<R, T> {
+    /// reader 全体をストリームとして読む
+    pub fn new(mut reader: R) -> Result<Self, Error> {
+        let len = reader.seek(SeekFrom::End(0))?;
+        Ok(Self::with_range(reader, 0..len))
+    }
+
+    /// reader の `range` の範囲をストリームとして読む
+    pub fn with_range(reader: R, range: std::ops::Range<u64>) -> Self {
+        StreamDeserializer {
+            reader,
+            front: range.start,
+            back: range.end.max(range.start),
+            buf: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// まだ読んでいない範囲
+    pub fn remaining(&self) -> std::ops::Range<u64> {
+        self.front..self.back
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn read_at(&mut self, pos: u64, buf: &mut [u8]) -> io::Result<()> {
+        self.reader.seek(SeekFrom::Start(pos))?;
+        self.reader.read_exact(buf)
+    }
+
+    /// pos から始まる枠の端を読んで len を返す
+    ///
+    /// 先頭は `HEAD len`、末尾は `len TAIL` の順
+    fn read_len(&mut self, pos: u64, magic: [u8; 4], magic_first: bool) -> io::Result<Option<u64>> {
+        let mut edge = [0u8; EDGE_LEN];
+        self.read_at(pos, &mut edge)?;
+        let (found, len) = if magic_first {
+            (&edge[..4], &edge[4..])
+        } else {
+            (&edge[8..], &edge[..8])
+        };
+        if found != magic {
+            return Ok(None);
+        }
+        Ok(Some(u64::from_le_bytes(len.try_into().unwrap())))
+    }
+
+    /// 末尾のレコードの document の範囲
+    ///
+    /// 枠が壊れている場合は None
+    fn frame_back(&mut self) -> io::Result<Option<(u64, u64)>> {
+        let edge = EDGE_LEN as u64;
+        if self.back - self.front < edge * 2 {
+            return Ok(None);
+        }
+        let Some(len) = self.read_len(self.back - edge, TAIL, false)? else {
+            return Ok(None);
+        };
+        let Some(start) = (self.back - edge * 2 - self.front).checked_sub(len).map(|s| s + self.front) else {
+            return Ok(None);
+        };
+        if self.read_len(start, HEAD, true)? != Some(len) {
+            return Ok(None);
+        }
+        Ok(Some((start + edge, start + edge + len)))
+    }
+
+    /// 先頭のレコードの document の範囲
+    ///
+    /// 枠が壊れている場合は None
+    fn frame_front(&mut self) -> io::Result<Option<(u64, u64)>> {
+        let edge = EDGE_LEN as u64;
+        if self.back - self.front < edge * 2 {
+            return Ok(None);
+        }
+        let Some(len) = self.read_len(self.front, HEAD, true)? else {
+            return Ok(None);
+        };
+        if (self.back - self.front - edge * 2) < len {
+            return Ok(None);
+        }
+        let end = self.front + edge + len;
+        if self.read_len(end, TAIL, false)? != Some(len) {
+            return Ok(None);
+        }
+        Ok(Some((self.front + edge, end)))
+    }
+
+    /// `front..before` で最後に現れる TAIL の終端
+    fn find_tail(&mut self, before: u64) -> io::Result<Option<u64>> {
+        let mut end = before;
+        let mut chunk = vec![0u8; SCAN_CHUNK];
+        while end - self.front >= TAIL.len() as u64 {
+            let start = end.saturating_sub(SCAN_CHUNK as u64).max(self.front);
+            let chunk = &mut chunk[..(end - start) as usize];
+            self.read_at(start, chunk)?;
+            if let Some(i) = chunk.windows(TAIL.len()).rposition(|w| w == TAIL) {
+                return Ok(Some(start + (i + TAIL.len()) as u64));
+            }
+            if start == self.front {
+                break;
+            }
+            // 境界をまたぐ magic を見落とさないように少し重ねる
+            end = start + TAIL.len() as u64 - 1;
+        }
+        Ok(None)
+    }
+
+    /// `after..back` で最初に現れる HEAD の位置
+    fn find_head(&mut self, after: u64) -> io::Result<Option<u64>> {
+        let mut start = after;
+        let mut chunk = vec![0u8; SCAN_CHUNK];
+        while self.back.saturating_sub(start) >= HEAD.len() as u64 {
+            let end = start.saturating_add(SCAN_CHUNK as u64).min(self.back);
+            let chunk = &mut chunk[..(end - start) as usize];
+            self.read_at(start, chunk)?;
+            if let Some(i) = chunk.windows(HEAD.len()).position(|w| w == HEAD) {
+                return Ok(Some(start + i as u64));
+            }
+            if end == self.back {
+                break;
+            }
+            start = end - HEAD.len() as u64 + 1;
+        }
+        Ok(None)
+    }
+
+    fn decode(&mut self, start: u64, end: u64) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let mut buf = std::mem::take(&mut self.buf);
+        buf.resize((end - start) as usize, 0);
+        let res = self
+            .read_at(start, &mut buf)
+            .map_err(Error::io)
+            .and_then(|_| from_slice::<T>(&buf).map_err(|err| err.shift(start as usize)));
+        self.buf = buf;
+        res
+    }
+}
+
+impl<R: Read + Seek, T: DeserializeOwned> Iterator for StreamDeserializer<R, T> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        match self.frame_front() {
+            Ok(Some((start, end))) => {
+                self.front = end + EDGE_LEN as u64;
+                Some(self.decode(start, end))
+            }
+            Ok(None) => {
+                let pos = self.front;
+                // 読み直す位置が見つからなければ残りは全て捨てる
+                self.front = match self.find_head(pos + 1) {
+                    Ok(next) => next.unwrap_or(self.back),
+                    Err(err) => {
+                        self.front = self.back;
+                        return Some(Err(Error::io(err)));
+                    }
+                };
+                Some(Err(frame_error(pos)))
+            }
+            Err(err) => {
+                self.front = self.back;
+                Some(Err(Error::io(err)))
+            }
+        }
+    }
+}
+
+impl<R: Read + Seek, T: DeserializeOwned> DoubleEndedIterator for StreamDeserializer<R, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+        match self.frame_back() {
+            Ok(Some((start, end))) => {
+                self.back = start - EDGE_LEN as u64;
+                Some(self.decode(start, end))
+            }
+            Ok(None) => {
+                let pos = self.back - 1;
+                self.back = match self.find_tail(pos) {
+                    Ok(prev) => prev.unwrap_or(self.front),
+                    Err(err) => {
+                        self.back = self.front;
+                        return Some(Err(Error::io(err)));
+                    }
+                };
+                Some(Err(frame_error(pos)))
+            }
+            Err(err) => {
+                self.back = self.front;
+                Some(Err(Error::io(err)))
+            }
+        }
+    }
+}
+
+/// document を `stream_frame` の枠で囲む
+pub(crate) fn frame(document: &[u8]) -> Vec<u8> {
+    let len = (document.len() as u64).to_le_bytes();
+    let mut frame = Vec::with_capacity(document.len() + EDGE_LEN * 2);
+    frame.extend_from_slice(&HEAD);
+    frame.extend_from_slice(&len);
+    frame.extend_from_slice(document);
+    frame.extend_from_slice(&len);
+    frame.extend_from_slice(&TAIL);
+    frame
+}
+
+#[cold]
+fn frame_error(pos: u64) -> Error {
+    Error::syntax(ErrorCode::Other("broken stream frame".to_string()), pos as usize)
+}
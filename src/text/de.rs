@@ -0,0 +1,803 @@
+use chrono::DateTime;
+use serde::de::{self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+
+use crate::error::{Error, ErrorCode};
+use crate::value::de::{ExtendedAccess, VALUE_TOKEN};
+use crate::value::prefix::prefix_str;
+
+/// TON Text のデシリアライザ
+pub struct TextDeserializer<'de> {
+    input: &'de str,
+    pos: usize,
+    deep: usize,
+    /// 次の値を Value 向けの拡張表現で渡すか
+    extended: bool,
+}
+
+/// ネストの上限 (serde_json と同じ)
+const MAX_DEPTH: usize = 128;
+
+/// 数値リテラル
+enum Number {
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F16(f32),
+    F32(f32),
+    F64(f64),
+}
+
+impl<'de> TextDeserializer<'de> {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(input: &'de str) -> Self {
+        Self {
+            input,
+            pos: 0,
+            deep: 0,
+            extended: false,
+        }
+    }
+
+    /// 残りが空白とコメントだけであることを確認する
+    pub fn end(&mut self) -> Result<(), Error> {
+        self.skip_ws()?;
+        if self.pos < self.input.len() {
+            return Err(self.error("trailing characters"));
+        }
+        Ok(())
+    }
+
+    /// 現在位置の行と列を付けてエラーを作る
+    #[cold]
+    fn error(&self, msg: &str) -> Error {
+        self.error_at(msg, self.pos)
+    }
+
+    #[cold]
+    fn error_at(&self, msg: &str, pos: usize) -> Error {
+        let (line, column) = self.line_column(pos);
+        Error::syntax_at(ErrorCode::Other(msg.to_string()), pos, line, column)
+    }
+
+    /// 括弧を読んでネストを 1 つ深くする
+    ///
+    /// 上限を超える場合は括弧の位置のエラー
+    fn enter(&mut self) -> Result<(), Error> {
+        if self.deep >= MAX_DEPTH {
+            return Err(self.error(&format!("nesting is deeper than {}", MAX_DEPTH)));
+        }
+        self.pos += 1;
+        self.deep += 1;
+        Ok(())
+    }
+
+    /// 入力の終わりに達したエラーを作る
+    #[cold]
+    fn eof(&self, msg: &str) -> Error {
+        let (line, column) = self.line_column(self.pos);
+        Error::syntax_at(ErrorCode::Eof(msg.to_string()), self.pos, line, column)
+    }
+
+    /// バイト位置から行と列 (どちらも 1 始まり) を求める
+    fn line_column(&self, pos: usize) -> (usize, usize) {
+        let consumed = &self.input[..pos.min(self.input.len())];
+        let line = consumed.matches('\n').count() + 1;
+        let column = match consumed.rfind('\n') {
+            Some(index) => consumed[index + 1..].chars().count() + 1,
+            None => consumed.chars().count() + 1,
+        };
+        (line, column)
+    }
+
+    /// 値のエラーに位置を付ける
+    fn fix_error(&self, err: Error, pos: usize) -> Error {
+        if err.needs_position() {
+            let (line, column) = self.line_column(pos);
+            err.fix_text_position(pos, line, column)
+        } else {
+            err
+        }
+    }
+
+    #[inline]
+    fn rest(&self) -> &'de str {
+        &self.input[self.pos..]
+    }
+
+    #[inline]
+    fn peek_char(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// 空白とコメント (`//`, `/* */`) を読み飛ばす
+    fn skip_ws(&mut self) -> Result<(), Error> {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if trimmed.starts_with("//") {
+                match trimmed.find('\n') {
+                    Some(index) => self.pos += index + 1,
+                    None => self.pos = self.input.len(),
+                }
+            } else if let Some(rest) = trimmed.strip_prefix("/*") {
+                match rest.find("*/") {
+                    Some(index) => self.pos += index + 4,
+                    None => return Err(self.eof("unterminated comment")),
+                }
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    /// 空白を読み飛ばして次の文字を見る
+    fn peek_token(&mut self) -> Result<Option<char>, Error> {
+        self.skip_ws()?;
+        Ok(self.peek_char())
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), Error> {
+        if self.peek_token()? == Some(c) {
+            self.pos += c.len_utf8();
+            Ok(())
+        } else if self.pos >= self.input.len() {
+            Err(self.eof(&format!("expected `{}` but reached end of input", c)))
+        } else {
+            Err(self.error(&format!("expected `{}`", c)))
+        }
+    }
+
+    /// 識別子 `[A-Za-z_][A-Za-z0-9_]*` を読む
+    fn parse_ident(&mut self) -> &'de str {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    /// JSON と同じエスケープの文字列を読む
+    fn parse_string(&mut self) -> Result<String, Error> {
+        self.expect('"')?;
+        let mut out = String::new();
+        loop {
+            let rest = self.rest();
+            let index = match rest.find(['"', '\\']) {
+                Some(index) => index,
+                None => {
+                    self.pos = self.input.len();
+                    return Err(self.eof("unterminated string"));
+                }
+            };
+            out.push_str(&rest[..index]);
+            self.pos += index;
+            if rest.as_bytes()[index] == b'"' {
+                self.pos += 1;
+                return Ok(out);
+            }
+            // エスケープ
+            self.pos += 1;
+            let escape_pos = self.pos;
+            let c = match self.peek_char() {
+                Some(c) => c,
+                None => return Err(self.eof("unterminated string")),
+            };
+            self.pos += c.len_utf8();
+            match c {
+                '"' => out.push('"'),
+                '\\' => out.push('\\'),
+                '/' => out.push('/'),
+                'b' => out.push('\u{08}'),
+                'f' => out.push('\u{0C}'),
+                'n' => out.push('\n'),
+                'r' => out.push('\r'),
+                't' => out.push('\t'),
+                'u' => {
+                    let high = self.parse_hex4()?;
+                    let code = if (0xD800..0xDC00).contains(&high) {
+                        if !self.rest().starts_with("\\u") {
+                            return Err(self.error_at("lone surrogate in string", escape_pos));
+                        }
+                        self.pos += 2;
+                        let low = self.parse_hex4()?;
+                        if !(0xDC00..0xE000).contains(&low) {
+                            return Err(self.error_at("invalid surrogate pair in string", escape_pos));
+                        }
+                        0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                    } else {
+                        high
+                    };
+                    match char::from_u32(code) {
+                        Some(c) => out.push(c),
+                        None => return Err(self.error_at("invalid unicode escape", escape_pos)),
+                    }
+                }
+                _ => return Err(self.error_at("invalid escape", escape_pos)),
+            }
+        }
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, Error> {
+        let rest = self.rest();
+        match rest.get(..4).and_then(|hex| u32::from_str_radix(hex, 16).ok()) {
+            Some(v) => {
+                self.pos += 4;
+                Ok(v)
+            }
+            None => Err(self.error("invalid unicode escape")),
+        }
+    }
+
+    /// 数値リテラルを読む
+    ///
+    /// サフィックスがない場合、整数は i64 (収まらなければ u64)、小数は f64
+    fn parse_number(&mut self) -> Result<Number, Error> {
+        let start = self.pos;
+        let bytes = self.input.as_bytes();
+        let mut end = self.pos;
+        if bytes.get(end) == Some(&b'-') {
+            end += 1;
+        }
+        let mut is_float = false;
+        // nan / inf
+        let ident_start = end;
+        let number = if bytes.get(end).is_some_and(|b| b.is_ascii_alphabetic()) {
+            self.pos = end;
+            let ident = self.parse_ident();
+            let (word, suffix) = ident.split_at(ident.len().min(3));
+            let v = match word {
+                "nan" => f64::NAN,
+                "inf" => f64::INFINITY,
+                _ => return Err(self.error_at("invalid number", ident_start)),
+            };
+            let v = if start != ident_start { -v } else { v };
+            return self.float_with_suffix(v, suffix, start);
+        } else {
+            while bytes.get(end).is_some_and(|b| b.is_ascii_digit()) {
+                end += 1;
+            }
+            if bytes.get(end) == Some(&b'.') && bytes.get(end + 1).is_some_and(|b| b.is_ascii_digit()) {
+                is_float = true;
+                end += 1;
+                while bytes.get(end).is_some_and(|b| b.is_ascii_digit()) {
+                    end += 1;
+                }
+            }
+            if matches!(bytes.get(end), Some(b'e') | Some(b'E')) {
+                let mut exp_end = end + 1;
+                if matches!(bytes.get(exp_end), Some(b'+') | Some(b'-')) {
+                    exp_end += 1;
+                }
+                if bytes.get(exp_end).is_some_and(|b| b.is_ascii_digit()) {
+                    is_float = true;
+                    end = exp_end;
+                    while bytes.get(end).is_some_and(|b| b.is_ascii_digit()) {
+                        end += 1;
+                    }
+                }
+            }
+            &self.input[start..end]
+        };
+        if number.is_empty() || number == "-" {
+            return Err(self.error_at("invalid number", start));
+        }
+        self.pos = end;
+        let suffix = self.parse_ident();
+        let invalid = || self.error_at(&format!("invalid number `{}{}`", number, suffix), start);
+        if is_float {
+            let v: f64 = number.parse().map_err(|_| invalid())?;
+            return self.float_with_suffix(v, suffix, start);
+        }
+        let res = match suffix {
+            "" => match number.parse::<i64>() {
+                Ok(v) => Ok(Number::I64(v)),
+                Err(_) => number.parse().map(Number::U64),
+            },
+            "i8" => number.parse().map(Number::I8),
+            "i16" => number.parse().map(Number::I16),
+            "i32" => number.parse().map(Number::I32),
+            "i64" => number.parse().map(Number::I64),
+            "u8" => number.parse().map(Number::U8),
+            "u16" => number.parse().map(Number::U16),
+            "u32" => number.parse().map(Number::U32),
+            "u64" => number.parse().map(Number::U64),
+            "f16" | "f32" | "f64" => {
+                let v: f64 = number.parse().map_err(|_| invalid())?;
+                return self.float_with_suffix(v, suffix, start);
+            }
+            _ => return Err(invalid()),
+        };
+        res.map_err(|_| invalid())
+    }
+
+    fn float_with_suffix(&self, v: f64, suffix: &str, start: usize) -> Result<Number, Error> {
+        match suffix {
+            "" | "f64" => Ok(Number::F64(v)),
+            "f32" => Ok(Number::F32(v as f32)),
+            "f16" => Ok(Number::F16(half::f16::from_f64(v).to_f32())),
+            _ => Err(self.error_at(&format!("invalid float suffix `{}`", suffix), start)),
+        }
+    }
+
+    /// `bytes("...")` の中身の16進数を読む
+    fn parse_hex_bytes(&mut self) -> Result<Vec<u8>, Error> {
+        let start = self.pos;
+        let hex = self.parse_string()?;
+        let digits: Vec<u8> = hex.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+        if !digits.len().is_multiple_of(2) {
+            return Err(self.error_at("odd number of hex digits", start));
+        }
+        digits
+            .chunks(2)
+            .map(|pair| {
+                std::str::from_utf8(pair)
+                    .ok()
+                    .and_then(|s| u8::from_str_radix(s, 16).ok())
+                    .ok_or_else(|| self.error_at("invalid hex digit", start))
+            })
+            .collect()
+    }
+
+    /// `timestamp(...)` などの中身の整数を読む
+    fn parse_i64_arg(&mut self) -> Result<i64, Error> {
+        self.skip_ws()?;
+        let start = self.pos;
+        match self.parse_number()? {
+            Number::I64(v) => Ok(v),
+            _ => Err(self.error_at("expected an i64 integer", start)),
+        }
+    }
+
+    /// `json(...)` の中身を読む
+    fn parse_json(&mut self) -> Result<serde_json::Value, Error> {
+        self.skip_ws()?;
+        let start = self.pos;
+        let mut stream = serde_json::Deserializer::from_str(self.rest()).into_iter::<serde_json::Value>();
+        match stream.next() {
+            Some(Ok(json)) => {
+                self.pos += stream.byte_offset();
+                Ok(json)
+            }
+            Some(Err(e)) => Err(self.error_at(&format!("invalid json: {}", e), start)),
+            None => Err(self.error_at("expected json", start)),
+        }
+    }
+
+    fn visit_number<V>(&mut self, number: Number, extended: bool, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match number {
+            Number::I8(v) => visitor.visit_i8(v),
+            Number::I16(v) => visitor.visit_i16(v),
+            Number::I32(v) => visitor.visit_i32(v),
+            Number::I64(v) => visitor.visit_i64(v),
+            Number::U8(v) => visitor.visit_u8(v),
+            Number::U16(v) => visitor.visit_u16(v),
+            Number::U32(v) => visitor.visit_u32(v),
+            Number::U64(v) => visitor.visit_u64(v),
+            Number::F16(v) => {
+                if extended {
+                    return visitor.visit_map(ExtendedAccess::new(prefix_str::FLOAT, de::value::F32Deserializer::new(v)));
+                }
+                visitor.visit_f32(v)
+            }
+            Number::F32(v) => visitor.visit_f32(v),
+            Number::F64(v) => visitor.visit_f64(v),
+        }
+    }
+
+    fn parse_value<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let extended = std::mem::take(&mut self.extended);
+        let start = match self.peek_token()? {
+            Some(_) => self.pos,
+            None => return Err(self.eof("expected value but reached end of input")),
+        };
+        let res = match self.peek_char() {
+            Some('"') => {
+                let s = self.parse_string()?;
+                visitor.visit_string(s)
+            }
+            Some('[') => {
+                self.enter()?;
+                let res = visitor.visit_seq(TextSeqAccess { de: &mut *self, first: true });
+                self.deep -= 1;
+                res
+            }
+            Some('{') => {
+                self.enter()?;
+                let res = visitor.visit_map(TextMapAccess { de: &mut *self, first: true });
+                self.deep -= 1;
+                res
+            }
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let number = self.parse_number()?;
+                self.visit_number(number, extended, visitor)
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let ident = self.parse_ident();
+                self.parse_keyword(ident, start, extended, visitor)
+            }
+            _ => Err(self.error("expected value")),
+        };
+        res.map_err(|e| self.fix_error(e, start))
+    }
+
+    /// 識別子から始まる値を読む
+    fn parse_keyword<V>(&mut self, ident: &str, start: usize, extended: bool, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match ident {
+            "true" => visitor.visit_bool(true),
+            "false" => visitor.visit_bool(false),
+            "none" | "null" => visitor.visit_none(),
+            "undefined" => {
+                if extended {
+                    return visitor.visit_map(ExtendedAccess::new(prefix_str::UNDEFINED, de::value::UnitDeserializer::new()));
+                }
+                Err(self.error_at("Cant perse UNDEFINED TYPE", start))
+            }
+            _ if ident.starts_with("nan") || ident.starts_with("inf") => {
+                self.pos = start;
+                let number = self.parse_number()?;
+                self.visit_number(number, extended, visitor)
+            }
+            "uuid" => {
+                self.expect('(')?;
+                self.skip_ws()?;
+                let arg_pos = self.pos;
+                let s = self.parse_string()?;
+                self.expect(')')?;
+                let uuid = uuid::Uuid::parse_str(&s).map_err(|e| self.error_at(&format!("invalid uuid: {}", e), arg_pos))?;
+                if extended {
+                    return visitor.visit_map(ExtendedAccess::new(prefix_str::UUID, de::value::BytesDeserializer::new(uuid.as_bytes())));
+                }
+                visitor.visit_string(uuid.hyphenated().to_string())
+            }
+            "datetime" => {
+                self.expect('(')?;
+                self.skip_ws()?;
+                let arg_pos = self.pos;
+                let s = self.parse_string()?;
+                self.expect(')')?;
+                DateTime::parse_from_rfc3339(&s).map_err(|e| self.error_at(&format!("invalid datetime: {}", e), arg_pos))?;
+                if extended {
+                    return visitor.visit_map(ExtendedAccess::new(prefix_str::DATETIME, de::value::StrDeserializer::new(&s)));
+                }
+                visitor.visit_string(s)
+            }
+            "timestamp" | "duration" => {
+                self.expect('(')?;
+                let v = self.parse_i64_arg()?;
+                self.expect(')')?;
+                if extended {
+                    let name = if ident == "timestamp" { prefix_str::TIMESTAMP } else { prefix_str::DURATION };
+                    return visitor.visit_map(ExtendedAccess::new(name, de::value::I64Deserializer::new(v)));
+                }
+                visitor.visit_i64(v)
+            }
+            "bytes" => {
+                self.expect('(')?;
+                self.skip_ws()?;
+                let buf = self.parse_hex_bytes()?;
+                self.expect(')')?;
+                visitor.visit_byte_buf(buf)
+            }
+            "json" => {
+                self.expect('(')?;
+                let json = self.parse_json()?;
+                self.expect(')')?;
+                if extended {
+                    let s = json.to_string();
+                    return visitor.visit_map(ExtendedAccess::new(prefix_str::WRAPPED_JSON, de::value::StrDeserializer::new(&s)));
+                }
+                json.deserialize_any(visitor).map_err(de::Error::custom)
+            }
+            "meta" => {
+                self.expect('(')?;
+                let res = if extended {
+                    visitor.visit_map(ExtendedAccess::new(prefix_str::META, &mut *self))?
+                } else {
+                    // Value 以外では中身をそのまま渡す
+                    self.parse_value(visitor)?
+                };
+                self.expect(')')?;
+                Ok(res)
+            }
+            _ => Err(self.error_at(&format!("unknown identifier `{}`", ident), start)),
+        }
+    }
+
+    /// object の key を読む
+    ///
+    /// キーワード以外の識別子はそのまま文字列の key として扱う
+    fn parse_key<K>(&mut self, seed: K) -> Result<K::Value, Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        self.skip_ws()?;
+        let start = self.pos;
+        if self.peek_char().is_some_and(|c| c.is_ascii_alphabetic() || c == '_') {
+            let ident = self.parse_ident();
+            let is_keyword = matches!(ident, "true" | "false" | "none" | "null" | "undefined")
+                || ident.starts_with("nan")
+                || ident.starts_with("inf");
+            if !is_keyword && self.peek_token()? != Some('(') {
+                return seed.deserialize(de::value::BorrowedStrDeserializer::<Error>::new(ident));
+            }
+            self.pos = start;
+        }
+        seed.deserialize(&mut *self)
+    }
+}
+
+struct TextSeqAccess<'a, 'de> {
+    de: &'a mut TextDeserializer<'de>,
+    first: bool,
+}
+
+impl<'de> SeqAccess<'de> for TextSeqAccess<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.de.peek_token()? == Some(']') {
+            self.de.pos += 1;
+            return Ok(None);
+        }
+        if !std::mem::take(&mut self.first) {
+            self.de.expect(',')?;
+            // 末尾のカンマ
+            if self.de.peek_token()? == Some(']') {
+                self.de.pos += 1;
+                return Ok(None);
+            }
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+struct TextMapAccess<'a, 'de> {
+    de: &'a mut TextDeserializer<'de>,
+    first: bool,
+}
+
+impl<'de> MapAccess<'de> for TextMapAccess<'_, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if self.de.peek_token()? == Some('}') {
+            self.de.pos += 1;
+            return Ok(None);
+        }
+        if !std::mem::take(&mut self.first) {
+            self.de.expect(',')?;
+            // 末尾のカンマ
+            if self.de.peek_token()? == Some('}') {
+                self.de.pos += 1;
+                return Ok(None);
+            }
+        }
+        let key = self.de.parse_key(seed)?;
+        self.de.expect(':')?;
+        Ok(Some(key))
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+struct TextEnumAccess<'a, 'de> {
+    de: &'a mut TextDeserializer<'de>,
+}
+
+impl<'de, 'a> EnumAccess<'de> for TextEnumAccess<'a, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = self.de.parse_key(seed)?;
+        self.de.expect(':')?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for TextEnumAccess<'_, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        de::Deserialize::deserialize(self.de)
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.de.deserialize_seq(visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.de.deserialize_map(visitor)
+    }
+}
+
+impl<'de> Deserializer<'de> for &mut TextDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.parse_value(visitor)
+    }
+
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let _ = visitor;
+        Err(serde::de::Error::custom("i128 is not supported"))
+    }
+
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        let _ = visitor;
+        Err(serde::de::Error::custom("u128 is not supported"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string
+        bytes byte_buf seq tuple tuple_struct map struct identifier ignored_any
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de> {
+        self.skip_ws()?;
+        let rest = self.rest();
+        for word in ["none", "null"] {
+            if rest.starts_with(word) && !rest[word.len()..].starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_') {
+                self.pos += word.len();
+                return visitor.visit_none();
+            }
+        }
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de> {
+        self.parse_value(UnitVisitor(visitor))
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de> {
+        if name == VALUE_TOKEN {
+            self.extended = true;
+        }
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de> {
+        match self.peek_token()? {
+            Some('"') => {
+                // unit variant
+                let variant = self.parse_string()?;
+                visitor.visit_enum(variant.into_deserializer())
+            }
+            Some('{') => {
+                self.enter()?;
+                let res = visitor.visit_enum(TextEnumAccess { de: &mut *self })?;
+                self.deep -= 1;
+                // 末尾のカンマ
+                if self.peek_token()? == Some(',') {
+                    self.pos += 1;
+                }
+                self.expect('}')?;
+                Ok(res)
+            }
+            _ => Err(self.error("expected enum")),
+        }
+    }
+}
+
+/// `none` を unit として渡す visitor
+struct UnitVisitor<V>(V);
+
+impl<'de, V> Visitor<'de> for UnitVisitor<V>
+where
+    V: Visitor<'de>,
+{
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.0.expecting(formatter)
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.0.visit_unit()
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.0.visit_unit()
+    }
+}
+
+/// TON Text から値をデシリアライズする
+pub fn from_str<'a, T>(s: &'a str) -> Result<T, Error>
+where
+    T: Deserialize<'a>,
+{
+    let mut de = TextDeserializer::from_str(s);
+    let value = T::deserialize(&mut de)?;
+    de.end()?;
+    Ok(value)
+}
@@ -0,0 +1,9 @@
+//! TON Text
+//!
+//! TON の全ての型を表現できる人間向けのテキスト形式
+//! 書式は docs/ton_text_format.md を参照
+pub mod ser;
+pub mod de;
+
+pub use de::{from_str, TextDeserializer};
+pub use ser::{to_string, to_string_pretty, value_to_string, PrettyConfig, TextSerializer};
@@ -0,0 +1,850 @@
+use std::io::Write;
+
+use serde::{ser, Serialize};
+
+use crate::error::Error;
+use crate::traits::ser::{ExtendSerialize, ExtendSerializeMap, ExtendSerializeSeq, ExtendSerializeStruct, ExtendSerializeStructVariant, ExtendSerializeTuple, ExtendSerializeTupleStruct, ExtendSerializeTupleVariant, ExtendedSerializer};
+use crate::value::value::Value;
+use crate::raw::RAW_TOKEN;
+
+/// 整形出力の設定
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrettyConfig {
+    /// 1 段分のインデント
+    pub indent: String,
+    /// 改行文字
+    pub new_line: String,
+    /// コンテナの最後の要素にもカンマを付けるか
+    pub trailing_comma: bool,
+}
+
+impl Default for PrettyConfig {
+    fn default() -> Self {
+        Self {
+            indent: "  ".to_string(),
+            new_line: "\n".to_string(),
+            trailing_comma: false,
+        }
+    }
+}
+
+impl PrettyConfig {
+    /// インデントを指定して設定を作る
+    pub fn with_indent(indent: &str) -> Self {
+        Self {
+            indent: indent.to_string(),
+            ..Self::default()
+        }
+    }
+}
+
+/// A structure for serializing Rust values to TON Text.
+pub struct TextSerializer<W>
+where
+    W: Write,
+{
+    writer: W,
+    pretty: Option<PrettyConfig>,
+    deep: usize,
+    /// 次の bytes を RawTon としてデコードして書き込むか
+    raw: bool,
+}
+
+impl<W> TextSerializer<W>
+where
+    W: Write,
+{
+    /// 改行なしで出力するシリアライザを作る
+    #[inline]
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            pretty: None,
+            deep: 0,
+            raw: false,
+        }
+    }
+
+    /// デフォルト設定で整形出力するシリアライザを作る
+    #[inline]
+    pub fn pretty(writer: W) -> Self {
+        Self::with_config(writer, PrettyConfig::default())
+    }
+
+    /// 設定を指定して整形出力するシリアライザを作る
+    #[inline]
+    pub fn with_config(writer: W, config: PrettyConfig) -> Self {
+        Self {
+            writer,
+            pretty: Some(config),
+            deep: 0,
+            raw: false,
+        }
+    }
+
+    /// Unwrap the `Writer` from the `Serializer`.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Wrap Writer
+    #[inline]
+    fn write_str(&mut self, s: &str) -> Result<(), Error> {
+        self.writer.write_all(s.as_bytes()).map_err(Error::io)
+    }
+
+    /// 改行してインデントを書く (整形出力のときのみ)
+    fn write_new_line(&mut self) -> Result<(), Error> {
+        if let Some(config) = &self.pretty {
+            let mut buf = config.new_line.clone();
+            for _ in 0..self.deep {
+                buf.push_str(&config.indent);
+            }
+            self.write_str(&buf)?;
+        }
+        Ok(())
+    }
+
+    /// `key: ` の区切りを書く
+    fn write_colon(&mut self) -> Result<(), Error> {
+        if self.pretty.is_some() {
+            self.write_str(": ")
+        } else {
+            self.write_str(":")
+        }
+    }
+
+    /// コンテナを開く
+    fn begin(&mut self, open: &str) -> Result<(), Error> {
+        self.write_str(open)?;
+        self.deep += 1;
+        Ok(())
+    }
+
+    /// 要素の前の区切りを書く
+    fn begin_element(&mut self, first: bool) -> Result<(), Error> {
+        if !first {
+            self.write_str(",")?;
+        }
+        self.write_new_line()
+    }
+
+    /// コンテナを閉じる
+    fn end(&mut self, close: &str, has_element: bool) -> Result<(), Error> {
+        self.deep -= 1;
+        if has_element && self.pretty.is_some() {
+            if self.pretty.as_ref().is_some_and(|config| config.trailing_comma) {
+                self.write_str(",")?;
+            }
+            self.write_new_line()?;
+        }
+        self.write_str(close)
+    }
+
+    /// `{"variant": ` まで書く
+    fn begin_variant(&mut self, variant: &str) -> Result<(), Error> {
+        self.begin("{")?;
+        self.begin_element(true)?;
+        ser::Serializer::serialize_str(&mut *self, variant)?;
+        self.write_colon()
+    }
+
+    /// 数値を型のサフィックス付きで書く
+    fn write_number<T: std::fmt::Display>(&mut self, v: T, suffix: &str) -> Result<(), Error> {
+        self.write_str(&format!("{}{}", v, suffix))
+    }
+
+    /// 浮動小数点数を書く
+    ///
+    /// Debug 表記は必ず小数点か指数を含むので整数と区別できる
+    fn write_float<T: std::fmt::Debug>(&mut self, v: T, is_nan: bool, is_infinite: bool, is_negative: bool, suffix: &str) -> Result<(), Error> {
+        let body = if is_nan {
+            "nan".to_string()
+        } else if is_infinite {
+            if is_negative { "-inf".to_string() } else { "inf".to_string() }
+        } else {
+            format!("{:?}", v)
+        };
+        self.write_str(&body)?;
+        self.write_str(suffix)
+    }
+}
+
+/// Implement the `ExtendedSerializer` trait for `TextSerializer`.
+impl<'a, W> ExtendedSerializer for &'a mut TextSerializer<W>
+where
+    W: Write,
+{
+    type ExtendSerializeSeq = Compound<'a, W>;
+    type ExtendSerializeMap = Compound<'a, W>;
+
+    #[inline]
+    fn serialize_f16(self, v: half::f16) -> Result<Self::Ok, Self::Error> {
+        let v = v.to_f32();
+        self.write_float(v, v.is_nan(), v.is_infinite(), v.is_sign_negative(), "f16")
+    }
+
+    #[inline]
+    fn serialize_uuid(self, v: &uuid::Uuid) -> Result<Self::Ok, Self::Error> {
+        self.write_str(&format!("uuid(\"{}\")", v.hyphenated()))
+    }
+
+    #[inline]
+    fn serialize_datetime<Tz>(self, v: &chrono::DateTime<Tz>) -> Result<Self::Ok, Self::Error>
+    where
+        Tz: chrono::TimeZone {
+        self.write_str(&format!("datetime(\"{}\")", v.to_rfc3339()))
+    }
+
+    #[inline]
+    fn serialize_timestamp(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.write_str(&format!("timestamp({})", v))
+    }
+
+    #[inline]
+    fn serialize_duration(self, v: &chrono::Duration) -> Result<Self::Ok, Self::Error> {
+        self.write_str(&format!("duration({})", v.num_nanoseconds().unwrap_or(0)))
+    }
+
+    #[inline]
+    fn serialize_wrapped_json(
+        self,
+        v: &serde_json::Value,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.write_str(&format!("json({})", v))
+    }
+
+    #[inline]
+    fn serialize_meta(self, v: &Value) -> Result<Self::Ok, Self::Error> {
+        self.write_str("meta(")?;
+        v.ex_serialize(&mut *self)?;
+        self.write_str(")")
+    }
+
+    #[inline]
+    fn serialize_padding(self, v: usize) -> Result<Self::Ok, Self::Error> {
+        // テキストではパディングは意味を持たない
+        let _ = v;
+        Ok(())
+    }
+
+    #[inline]
+    fn serialize_undefined(self) -> Result<Self::Ok, Self::Error> {
+        self.write_str("undefined")
+    }
+
+    #[inline]
+    fn ex_serialize_seq(self, _len: Option<usize>) -> Result<Self::ExtendSerializeSeq, Self::Error> {
+        Compound::new(self, "[", "]", false)
+    }
+
+    #[inline]
+    fn ex_serialize_map(self, _len: Option<usize>) -> Result<Self::ExtendSerializeMap, Self::Error> {
+        Compound::new(self, "{", "}", false)
+    }
+}
+
+/// Implement the `Serializer` trait for `TextSerializer`.
+impl<'a, W> ser::Serializer for &'a mut TextSerializer<W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Compound<'a, W>;
+    type SerializeTuple = Compound<'a, W>;
+    type SerializeTupleStruct = Compound<'a, W>;
+    type SerializeTupleVariant = Compound<'a, W>;
+    type SerializeMap = Compound<'a, W>;
+    type SerializeStruct = Compound<'a, W>;
+    type SerializeStructVariant = Compound<'a, W>;
+
+    #[inline]
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        self.write_str(if v { "true" } else { "false" })
+    }
+
+    #[inline]
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.write_number(v, "i8")
+    }
+
+    #[inline]
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.write_number(v, "i16")
+    }
+
+    #[inline]
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.write_number(v, "i32")
+    }
+
+    #[inline]
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        // サフィックスなしの整数は i64
+        self.write_number(v, "")
+    }
+
+    #[inline]
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.write_number(v, "u8")
+    }
+
+    #[inline]
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.write_number(v, "u16")
+    }
+
+    #[inline]
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.write_number(v, "u32")
+    }
+
+    #[inline]
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.write_number(v, "u64")
+    }
+
+    #[inline]
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.write_float(v, v.is_nan(), v.is_infinite(), v.is_sign_negative(), "f32")
+    }
+
+    #[inline]
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        // サフィックスなしの小数は f64
+        self.write_float(v, v.is_nan(), v.is_infinite(), v.is_sign_negative(), "")
+    }
+
+    #[inline]
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        self.serialize_str(v.encode_utf8(&mut [0u8; 4]))
+    }
+
+    #[inline]
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        // エスケープは JSON と同じ
+        let quoted = serde_json::to_string(v).map_err(<Error as ser::Error>::custom)?;
+        self.write_str(&quoted)
+    }
+
+    #[inline]
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        if std::mem::take(&mut self.raw) {
+            let value: Value = crate::de::from_slice(v)?;
+            return value.ex_serialize(self);
+        }
+        let mut buf = String::with_capacity(v.len() * 2 + 9);
+        buf.push_str("bytes(\"");
+        for byte in v {
+            buf.push_str(&format!("{:02x}", byte));
+        }
+        buf.push_str("\")");
+        self.write_str(&buf)
+    }
+
+    #[inline]
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        self.write_str("none")
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + ser::Serialize {
+        value.serialize(&mut *self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.serialize_none()
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.serialize_none()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + ser::Serialize {
+        if name == RAW_TOKEN {
+            self.raw = true;
+        }
+        value.serialize(&mut *self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + ser::Serialize {
+        self.begin_variant(variant)?;
+        value.serialize(&mut *self)?;
+        self.end("}", true)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Compound::new(self, "[", "]", false)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Compound::new(self, "[", "]", false)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Compound::new(self, "[", "]", false)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        self.begin_variant(variant)?;
+        Compound::new(self, "[", "]", true)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Compound::new(self, "{", "}", false)
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Compound::new(self, "{", "}", false)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        self.begin_variant(variant)?;
+        Compound::new(self, "{", "}", true)
+    }
+}
+
+pub struct Compound<'a, W>
+where
+    W: Write,
+{
+    ser: &'a mut TextSerializer<W>,
+    close: &'static str,
+    first: bool,
+    /// variant の外側の `}` も閉じるか
+    variant: bool,
+}
+
+impl<'a, W> Compound<'a, W>
+where
+    W: Write,
+{
+    #[inline]
+    fn new(ser: &'a mut TextSerializer<W>, open: &'static str, close: &'static str, variant: bool) -> Result<Self, Error> {
+        ser.begin(open)?;
+        Ok(Self {
+            ser,
+            close,
+            first: true,
+            variant,
+        })
+    }
+
+    #[inline]
+    fn begin_element(&mut self) -> Result<(), Error> {
+        self.ser.begin_element(self.first)?;
+        self.first = false;
+        Ok(())
+    }
+
+    #[inline]
+    fn field_name(&mut self, key: &str) -> Result<(), Error> {
+        self.begin_element()?;
+        ser::Serializer::serialize_str(&mut *self.ser, key)?;
+        self.ser.write_colon()
+    }
+
+    #[inline]
+    fn finish(self) -> Result<(), Error> {
+        self.ser.end(self.close, !self.first)?;
+        if self.variant {
+            self.ser.end("}", true)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W> ser::SerializeSeq for Compound<'_, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + ser::Serialize {
+        self.begin_element()?;
+        value.serialize(&mut *self.ser)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<W> ExtendSerializeSeq for Compound<'_, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + ExtendSerialize {
+        self.begin_element()?;
+        value.ex_serialize(&mut *self.ser)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<W> ser::SerializeTuple for Compound<'_, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + ser::Serialize {
+        self.begin_element()?;
+        value.serialize(&mut *self.ser)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<W> ExtendSerializeTuple for Compound<'_, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + ExtendSerialize {
+        self.begin_element()?;
+        value.ex_serialize(&mut *self.ser)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<W> ser::SerializeTupleStruct for Compound<'_, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + ser::Serialize {
+        self.begin_element()?;
+        value.serialize(&mut *self.ser)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<W> ExtendSerializeTupleStruct for Compound<'_, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + ExtendSerialize {
+        self.begin_element()?;
+        value.ex_serialize(&mut *self.ser)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<W> ser::SerializeTupleVariant for Compound<'_, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + ser::Serialize {
+        self.begin_element()?;
+        value.serialize(&mut *self.ser)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<W> ExtendSerializeTupleVariant for Compound<'_, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + ExtendSerialize {
+        self.begin_element()?;
+        value.ex_serialize(&mut *self.ser)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<W> ser::SerializeMap for Compound<'_, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + ser::Serialize {
+        self.begin_element()?;
+        key.serialize(&mut *self.ser)?;
+        self.ser.write_colon()
+    }
+
+    #[inline]
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + ser::Serialize {
+        value.serialize(&mut *self.ser)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<W> ExtendSerializeMap for Compound<'_, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + ExtendSerialize {
+        self.begin_element()?;
+        key.ex_serialize(&mut *self.ser)?;
+        self.ser.write_colon()
+    }
+
+    #[inline]
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + ExtendSerialize {
+        value.ex_serialize(&mut *self.ser)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<W> ser::SerializeStruct for Compound<'_, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + ser::Serialize {
+        self.field_name(key)?;
+        value.serialize(&mut *self.ser)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<W> ExtendSerializeStruct for Compound<'_, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + ExtendSerialize {
+        self.field_name(key)?;
+        value.ex_serialize(&mut *self.ser)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<W> ser::SerializeStructVariant for Compound<'_, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + ser::Serialize {
+        self.field_name(key)?;
+        value.serialize(&mut *self.ser)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+impl<W> ExtendSerializeStructVariant for Compound<'_, W>
+where
+    W: Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + ExtendSerialize {
+        self.field_name(key)?;
+        value.ex_serialize(&mut *self.ser)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.finish()
+    }
+}
+
+/// 値を TON Text にシリアライズする
+pub fn to_string<T>(value: &T) -> Result<String, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let mut ser = TextSerializer::new(Vec::new());
+    value.serialize(&mut ser)?;
+    Ok(into_string(ser.into_inner()))
+}
+
+/// 値を整形した TON Text にシリアライズする
+pub fn to_string_pretty<T>(value: &T) -> Result<String, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let mut ser = TextSerializer::pretty(Vec::new());
+    value.serialize(&mut ser)?;
+    Ok(into_string(ser.into_inner()))
+}
+
+/// Value を TON Text にシリアライズする
+///
+/// config が None の場合は改行なしで出力する
+pub fn value_to_string(value: &Value, config: Option<PrettyConfig>) -> Result<String, Error> {
+    let mut ser = match config {
+        Some(config) => TextSerializer::with_config(Vec::new(), config),
+        None => TextSerializer::new(Vec::new()),
+    };
+    value.ex_serialize(&mut ser)?;
+    Ok(into_string(ser.into_inner()))
+}
+
+#[inline]
+fn into_string(buf: Vec<u8>) -> String {
+    // TextSerializer は UTF-8 しか書かない
+    String::from_utf8(buf).expect("TextSerializer wrote invalid UTF-8")
+}
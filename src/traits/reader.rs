@@ -1,9 +1,10 @@
-use std::io::{Error, Read, Seek};
+use std::{fs::File, io::{Error, Read, Seek}, ops::Range};
 
 /// Read トレイとの実装
 /// 必要機能
 /// - seek
 /// - no copy read
+///
 /// えっとシーク位置の指定はこれでよくて
 /// RTON自体は必要な長さのデータだけをほしい
 /// バッファリングするのがクソなので
@@ -18,9 +19,7 @@ pub trait Reader: Read + Seek{
     /// seekに失敗した場合 seekの位置を戻しません
     fn next(&mut self) -> Result<Option<u8>, Error> {
         let res = self.peek()?;
-        if let Some(_) = res {
-            // 1バイト進めてみて、範囲外なら元に戻す
-            let pos = self.stream_position()?;
+        if res.is_some() {
             self.seek(std::io::SeekFrom::Current(1))?;
         }
         Ok(res)
@@ -43,6 +42,7 @@ pub trait Reader: Read + Seek{
         match self.read(&mut buf) {
             Ok(0) => Ok(None), // EOF
             Ok(_) => {
+                self.seek(std::io::SeekFrom::Current(-1))?;
                 Ok(Some(buf[0]))
             },
             Err(e) => Err(Error::new(e.kind(), format!("Read error: {}", e))),
@@ -72,6 +72,49 @@ pub trait Reader: Read + Seek{
         self.read_exact(&mut buf)?;
         Ok(u64::from_le_bytes(buf))
     }
+
+    /// シーク位置の直前にある `buf.len()` バイトを読み込み、シーク位置をその先頭へ移動します
+    /// 逆順に並んだ RTON を読むための基本操作です
+    fn read_prev(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        let len = buf.len() as i64;
+        if self.stream_position()? < len as u64 {
+            return Err(Error::new(std::io::ErrorKind::UnexpectedEof, "Not enough data to read"));
+        }
+        self.seek(std::io::SeekFrom::Current(-len))?;
+        self.read_exact(buf)?;
+        self.seek(std::io::SeekFrom::Current(-len))?;
+        Ok(())
+    }
+
+    fn prev_u16(&mut self) -> Result<u16, Error> {
+        let mut buf = [0; 2];
+        self.read_prev(&mut buf)?;
+        Ok(u16::from_le_bytes(buf))
+    }
+
+    fn prev_u32(&mut self) -> Result<u32, Error> {
+        let mut buf = [0; 4];
+        self.read_prev(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn prev_u64(&mut self) -> Result<u64, Error> {
+        let mut buf = [0; 8];
+        self.read_prev(&mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+}
+
+/// 入力をそのまま借用できるかを表す Reader
+///
+/// デシリアライザはこのトレイトで `&'de [u8]` を借用し、`&RawTonRef` のような借用型に渡す
+/// 借用できない Reader は既定の実装 (常に None) のままでよい
+pub trait BorrowReader<'de>: Reader {
+    /// `range` のバイト列を入力から借用する
+    fn borrow_range(&self, range: Range<u64>) -> Option<&'de [u8]> {
+        let _ = range;
+        None
+    }
 }
 
 pub struct SliceReader<'a> {
@@ -97,6 +140,7 @@ impl Read for SliceReader<'_> {
         }
         let bytes_to_read = buf.len().min(self.slice.len() - self.pos);
         buf[..bytes_to_read].copy_from_slice(&self.slice[self.pos..self.pos + bytes_to_read]);
+        self.pos += bytes_to_read;
         Ok(bytes_to_read)
     }
 
@@ -105,6 +149,7 @@ impl Read for SliceReader<'_> {
             return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "Not enough data to read"));
         }
         buf.copy_from_slice(&self.slice[self.pos..self.pos + buf.len()]);
+        self.pos += buf.len();
         Ok(())
     }
 }
@@ -115,14 +160,14 @@ impl Seek for SliceReader<'_> {
             std::io::SeekFrom::Start(offset) => offset as usize,
             std::io::SeekFrom::End(offset) => {
                 if offset < 0 {
-                    self.slice.len().checked_sub(-offset as usize).unwrap_or(0)
+                    self.slice.len().saturating_sub(-offset as usize)
                 } else {
                     self.slice.len().saturating_add(offset as usize)
                 }
             }
             std::io::SeekFrom::Current(offset) => {
                 if offset < 0 {
-                    self.pos.checked_sub(-offset as usize).unwrap_or(0)
+                    self.pos.saturating_sub(-offset as usize)
                 } else {
                     (self.pos + offset as usize).min(self.slice.len())
                 }
@@ -143,7 +188,7 @@ impl Seek for SliceReader<'_> {
 
     fn seek_relative(&mut self, offset: i64) -> std::io::Result<()> {
         let new_pos = if offset < 0 {
-            self.pos.checked_sub(-offset as usize).unwrap_or(0)
+            self.pos.saturating_sub(-offset as usize)
         } else {
             (self.pos + offset as usize).min(self.slice.len())
         };
@@ -176,6 +221,21 @@ impl Reader for SliceReader<'_> {
         }
         Ok(Some(self.slice[self.pos]))
     }
+
+    fn read_prev(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        if self.pos < buf.len() {
+            return Err(Error::new(std::io::ErrorKind::UnexpectedEof, "Not enough data to read"));
+        }
+        self.pos -= buf.len();
+        buf.copy_from_slice(&self.slice[self.pos..self.pos + buf.len()]);
+        Ok(())
+    }
+}
+
+impl<'a> BorrowReader<'a> for SliceReader<'a> {
+    fn borrow_range(&self, range: Range<u64>) -> Option<&'a [u8]> {
+        self.slice.get(range.start as usize..range.end as usize)
+    }
 }
 
 pub struct VecReader<'a> {
@@ -200,6 +260,7 @@ impl Read for VecReader<'_> {
         }
         let bytes_to_read = buf.len().min(self.vec.len() - self.pos);
         buf[..bytes_to_read].copy_from_slice(&self.vec[self.pos..self.pos + bytes_to_read]);
+        self.pos += bytes_to_read;
         Ok(bytes_to_read)
     }
 
@@ -208,6 +269,7 @@ impl Read for VecReader<'_> {
             return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "Not enough data to read"));
         }
         buf.copy_from_slice(&self.vec[self.pos..self.pos + buf.len()]);
+        self.pos += buf.len();
         Ok(())
     }
 }
@@ -218,14 +280,14 @@ impl Seek for VecReader<'_> {
             std::io::SeekFrom::Start(offset) => offset as usize,
             std::io::SeekFrom::End(offset) => {
                 if offset < 0 {
-                    self.vec.len().checked_sub(-offset as usize).unwrap_or(0)
+                    self.vec.len().saturating_sub(-offset as usize)
                 } else {
                     self.vec.len().saturating_add(offset as usize)
                 }
             }
             std::io::SeekFrom::Current(offset) => {
                 if offset < 0 {
-                    self.pos.checked_sub(-offset as usize).unwrap_or(0)
+                    self.pos.saturating_sub(-offset as usize)
                 } else {
                     (self.pos + offset as usize).min(self.vec.len())
                 }
@@ -246,7 +308,7 @@ impl Seek for VecReader<'_> {
 
     fn seek_relative(&mut self, offset: i64) -> std::io::Result<()> {
         let new_pos = if offset < 0 {
-            self.pos.checked_sub(-offset as usize).unwrap_or(0)
+            self.pos.saturating_sub(-offset as usize)
         } else {
             (self.pos + offset as usize).min(self.vec.len())
         };
@@ -279,6 +341,21 @@ impl Reader for VecReader<'_> {
         }
         Ok(Some(self.vec[self.pos]))
     }
+
+    fn read_prev(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        if self.pos < buf.len() {
+            return Err(Error::new(std::io::ErrorKind::UnexpectedEof, "Not enough data to read"));
+        }
+        self.pos -= buf.len();
+        buf.copy_from_slice(&self.vec[self.pos..self.pos + buf.len()]);
+        Ok(())
+    }
+}
+
+impl<'a> BorrowReader<'a> for VecReader<'a> {
+    fn borrow_range(&self, range: Range<u64>) -> Option<&'a [u8]> {
+        self.vec.get(range.start as usize..range.end as usize)
+    }
 }
 
 pub struct IOReader<R>
@@ -345,7 +422,6 @@ where R: Read + Seek,
         match self.reader.read(&mut buf) {
             Ok(0) => Ok(None), // EOF
             Ok(_) => {
-                self.reader.seek(std::io::SeekFrom::Current(1))?;
                 Ok(Some(buf[0]))
             },
             Err(e) => Err(Error::new(e.kind(), format!("Read error: {}", e))),
@@ -364,6 +440,8 @@ where R: Read + Seek,
                 Ok(None)
             },
             Ok(_) => {
+                // 読んだ分進んだので戻す
+                self.reader.seek(std::io::SeekFrom::Current(-1))?;
                 Ok(Some(buf[0]))
             },
             Err(e) => Err(Error::new(e.kind(), format!("Read error: {}", e))),
@@ -375,9 +453,463 @@ where R: Read + Seek,
         match self.reader.read(&mut buf) {
             Ok(0) => Ok(None), // EOF
             Ok(_) => {
+                self.reader.seek(std::io::SeekFrom::Current(-1))?;
                 Ok(Some(buf[0]))
             },
             Err(e) => Err(Error::new(e.kind(), format!("Read error: {}", e))),
         }
     }
-}
\ No newline at end of file
+}
+impl<R> BorrowReader<'_> for IOReader<R>
+where R: Read + Seek,
+{}
+
+/// 後ろ向きに読むためのブロック単位のバッファ付き Reader
+///
+/// `IOReader` は 1 バイト戻るごとに seek と read を呼ぶので、ファイルだと 1 バイトごとに syscall が 2 回走る
+/// こちらはシーク位置で終わるブロックをまとめて読み込み、ブロックの外に出たときだけ読み直す
+/// seek はシーク位置を書き換えるだけで I/O は発生しない
+///
+/// データ長は作成時に一度だけ取得するので、読んでいる間にデータが伸びても末尾は変わらない
+pub struct ReverseBufReader<R>
+where R: Read + Seek,
+{
+    reader: R,
+    buf: Box<[u8]>,
+    /// buf[0] の位置
+    buf_start: u64,
+    /// buf の有効なバイト数
+    buf_len: usize,
+    /// シーク位置
+    pos: u64,
+    /// データ長
+    len: u64,
+}
+
+impl<R> ReverseBufReader<R>
+where R: Read + Seek,
+{
+    /// 既定のブロックサイズ
+    pub const DEFAULT_CAPACITY: usize = 64 * 1024;
+
+    pub fn new(reader: R) -> Result<Self, Error> {
+        Self::with_capacity(Self::DEFAULT_CAPACITY, reader)
+    }
+
+    /// ブロックサイズを指定して作る
+    ///
+    /// シーク位置は reader の現在位置を引き継ぐ
+    pub fn with_capacity(capacity: usize, mut reader: R) -> Result<Self, Error> {
+        let pos = reader.stream_position()?;
+        let len = reader.seek(std::io::SeekFrom::End(0))?;
+        Ok(Self {
+            reader,
+            buf: vec![0; capacity.max(16)].into_boxed_slice(),
+            buf_start: 0,
+            buf_len: 0,
+            pos,
+            len,
+        })
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// バッファを捨てて reader を返す
+    ///
+    /// reader のシーク位置は不定
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// バッファに読み込まれている範囲の終端
+    #[inline]
+    fn buf_end(&self) -> u64 {
+        self.buf_start + self.buf_len as u64
+    }
+
+    /// `start..end` をバッファに読み込む
+    fn fill(&mut self, start: u64, end: u64) -> Result<(), Error> {
+        let len = (end - start) as usize;
+        self.reader.seek(std::io::SeekFrom::Start(start))?;
+        self.reader.read_exact(&mut self.buf[..len])?;
+        self.buf_start = start;
+        self.buf_len = len;
+        Ok(())
+    }
+
+    /// `pos` で終わるブロックを読み込む
+    fn fill_back(&mut self, pos: u64) -> Result<(), Error> {
+        let start = pos.saturating_sub(self.buf.len() as u64);
+        self.fill(start, pos)
+    }
+
+    /// `pos` から始まるブロックを読み込む
+    fn fill_forward(&mut self, pos: u64) -> Result<(), Error> {
+        let end = pos.saturating_add(self.buf.len() as u64).min(self.len);
+        self.fill(pos, end)
+    }
+}
+
+impl<R> Read for ReverseBufReader<R>
+where R: Read + Seek,
+{
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, std::io::Error> {
+        if self.pos >= self.len || buf.is_empty() {
+            return Ok(0); // EOF
+        }
+        if self.pos < self.buf_start || self.pos >= self.buf_end() {
+            if buf.len() >= self.buf.len() {
+                // バッファより大きい読み込みは直接読む
+                let len = buf.len().min((self.len - self.pos) as usize);
+                self.reader.seek(std::io::SeekFrom::Start(self.pos))?;
+                self.reader.read_exact(&mut buf[..len])?;
+                self.pos += len as u64;
+                return Ok(len);
+            }
+            self.fill_forward(self.pos)?;
+        }
+        let offset = (self.pos - self.buf_start) as usize;
+        let len = buf.len().min(self.buf_len - offset);
+        buf[..len].copy_from_slice(&self.buf[offset..offset + len]);
+        self.pos += len as u64;
+        Ok(len)
+    }
+}
+
+impl<R> Seek for ReverseBufReader<R>
+where R: Read + Seek,
+{
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        let new_pos = match pos {
+            std::io::SeekFrom::Start(offset) => Some(offset),
+            std::io::SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            std::io::SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        match new_pos {
+            Some(new_pos) => {
+                self.pos = new_pos;
+                Ok(self.pos)
+            }
+            None => Err(Error::new(std::io::ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position")),
+        }
+    }
+
+    #[inline]
+    fn stream_position(&mut self) -> std::io::Result<u64> {
+        Ok(self.pos)
+    }
+}
+
+impl<R> Reader for ReverseBufReader<R>
+where R: Read + Seek,
+{
+    fn next(&mut self) -> Result<Option<u8>, Error> {
+        let res = self.peek()?;
+        if res.is_some() {
+            self.pos += 1;
+        }
+        Ok(res)
+    }
+
+    fn prev(&mut self) -> Result<Option<u8>, Error> {
+        if self.pos == 0 {
+            return Ok(None); // すでに先頭なので戻れない
+        }
+        if self.pos > self.len {
+            return Ok(None);
+        }
+        let pos = self.pos - 1;
+        if pos < self.buf_start || pos >= self.buf_end() {
+            self.fill_back(self.pos)?;
+        }
+        self.pos = pos;
+        Ok(Some(self.buf[(pos - self.buf_start) as usize]))
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>, Error> {
+        if self.pos >= self.len {
+            return Ok(None); // EOF
+        }
+        if self.pos < self.buf_start || self.pos >= self.buf_end() {
+            self.fill_forward(self.pos)?;
+        }
+        Ok(Some(self.buf[(self.pos - self.buf_start) as usize]))
+    }
+
+    fn read_prev(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        let len = buf.len() as u64;
+        if self.pos < len || self.pos > self.len {
+            return Err(Error::new(std::io::ErrorKind::UnexpectedEof, "Not enough data to read"));
+        }
+        let start = self.pos - len;
+        if buf.len() > self.buf.len() {
+            // バッファより大きい読み込みは直接読む
+            self.reader.seek(std::io::SeekFrom::Start(start))?;
+            self.reader.read_exact(buf)?;
+        } else {
+            if start < self.buf_start || self.pos > self.buf_end() {
+                self.fill_back(self.pos)?;
+            }
+            let offset = (start - self.buf_start) as usize;
+            buf.copy_from_slice(&self.buf[offset..offset + buf.len()]);
+        }
+        self.pos = start;
+        Ok(())
+    }
+}
+
+impl<R> BorrowReader<'_> for ReverseBufReader<R>
+where R: Read + Seek,
+{}
+
+/// `&File` から位置指定で読む Reader
+///
+/// `FileExt::read_at` (pread) を使い、シーク位置は自前で持つのでファイルのシーク位置を動かさない
+/// 1 つのファイルを複数のスレッドで共有して、それぞれ別の文書を同時に読める
+///
+/// Windows では `FileExt::seek_read` を使う
+/// 読む位置は毎回指定するので同時に読んでも結果は正しいが、ファイルのシーク位置は読んだ範囲の終端に動く
+/// 同じ `File` をシーク位置を使って読み書きする処理と共有する場合は、その処理の前に必ずシークし直す
+///
+/// 後ろ向きに読むとバイトごとに pread するので、`ReverseBufReader` で包んで使うとよい
+///
+/// `with_range` で範囲を指定すると、その範囲を 1 つのデータとして扱う (シーク位置は範囲の先頭からの相対位置)
+pub struct PositionalReader<'a> {
+    file: &'a File,
+    /// ファイル上の範囲の先頭
+    offset: u64,
+    /// 範囲の長さ
+    len: u64,
+    /// シーク位置 (範囲の先頭から)
+    pos: u64,
+}
+
+impl<'a> PositionalReader<'a> {
+    /// ファイル全体を読む Reader を作る
+    pub fn new(file: &'a File) -> Result<Self, Error> {
+        let len = file.metadata()?.len();
+        Ok(Self::with_range(file, 0..len))
+    }
+
+    /// ファイルの `range` だけを読む Reader を作る
+    pub fn with_range(file: &'a File, range: Range<u64>) -> Self {
+        Self {
+            file,
+            offset: range.start,
+            len: range.end.saturating_sub(range.start),
+            pos: 0,
+        }
+    }
+
+    pub fn get_ref(&self) -> &'a File {
+        self.file
+    }
+
+    #[cfg(unix)]
+    fn read_at(&self, buf: &mut [u8], pos: u64) -> Result<usize, Error> {
+        std::os::unix::fs::FileExt::read_at(self.file, buf, pos)
+    }
+
+    /// ファイルのシーク位置も動く (`PositionalReader` を参照)
+    #[cfg(windows)]
+    fn read_at(&self, buf: &mut [u8], pos: u64) -> Result<usize, Error> {
+        std::os::windows::fs::FileExt::seek_read(self.file, buf, pos)
+    }
+
+    /// 範囲内の `pos` から `buf.len()` バイトを読む
+    fn read_exact_at(&self, mut buf: &mut [u8], mut pos: u64) -> Result<(), Error> {
+        if pos + buf.len() as u64 > self.len {
+            return Err(Error::new(std::io::ErrorKind::UnexpectedEof, "Not enough data to read"));
+        }
+        while !buf.is_empty() {
+            match self.read_at(buf, self.offset + pos) {
+                Ok(0) => return Err(Error::new(std::io::ErrorKind::UnexpectedEof, "Not enough data to read")),
+                Ok(n) => {
+                    buf = &mut buf[n..];
+                    pos += n as u64;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Read for PositionalReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, std::io::Error> {
+        if self.pos >= self.len {
+            return Ok(0); // EOF
+        }
+        let len = buf.len().min((self.len - self.pos) as usize);
+        let n = self.read_at(&mut buf[..len], self.offset + self.pos)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> std::io::Result<()> {
+        self.read_exact_at(buf, self.pos)?;
+        self.pos += buf.len() as u64;
+        Ok(())
+    }
+}
+
+impl Seek for PositionalReader<'_> {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        let new_pos = match pos {
+            std::io::SeekFrom::Start(offset) => Some(offset),
+            std::io::SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            std::io::SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        match new_pos {
+            Some(new_pos) => {
+                self.pos = new_pos;
+                Ok(self.pos)
+            }
+            None => Err(Error::new(std::io::ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position")),
+        }
+    }
+
+    #[inline]
+    fn stream_position(&mut self) -> std::io::Result<u64> {
+        Ok(self.pos)
+    }
+}
+
+impl Reader for PositionalReader<'_> {
+    fn prev(&mut self) -> Result<Option<u8>, Error> {
+        if self.pos == 0 || self.pos > self.len {
+            return Ok(None); // すでに先頭なので戻れない
+        }
+        let mut buf = [0; 1];
+        self.read_exact_at(&mut buf, self.pos - 1)?;
+        self.pos -= 1;
+        Ok(Some(buf[0]))
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>, Error> {
+        if self.pos >= self.len {
+            return Ok(None); // EOF
+        }
+        let mut buf = [0; 1];
+        self.read_exact_at(&mut buf, self.pos)?;
+        Ok(Some(buf[0]))
+    }
+
+    fn read_prev(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        let len = buf.len() as u64;
+        if self.pos < len {
+            return Err(Error::new(std::io::ErrorKind::UnexpectedEof, "Not enough data to read"));
+        }
+        self.read_exact_at(buf, self.pos - len)?;
+        self.pos -= len;
+        Ok(())
+    }
+}
+
+impl BorrowReader<'_> for PositionalReader<'_> {}
+
+/// `&mut R` もそのまま Reader として使えるようにする
+///
+/// デシリアライザに reader を渡した後も手元で使い続けられる
+impl<R> Reader for &mut R
+where R: Reader + ?Sized,
+{
+    #[inline]
+    fn next(&mut self) -> Result<Option<u8>, Error> {
+        (**self).next()
+    }
+
+    #[inline]
+    fn prev(&mut self) -> Result<Option<u8>, Error> {
+        (**self).prev()
+    }
+
+    #[inline]
+    fn peek(&mut self) -> Result<Option<u8>, Error> {
+        (**self).peek()
+    }
+
+    #[inline]
+    fn read_prev(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        (**self).read_prev(buf)
+    }
+
+    #[inline]
+    fn prev_u16(&mut self) -> Result<u16, Error> {
+        (**self).prev_u16()
+    }
+
+    #[inline]
+    fn prev_u32(&mut self) -> Result<u32, Error> {
+        (**self).prev_u32()
+    }
+
+    #[inline]
+    fn prev_u64(&mut self) -> Result<u64, Error> {
+        (**self).prev_u64()
+    }
+}
+
+/// `Box<dyn Reader>` で reader の型を消して使えるようにする
+impl<R> Reader for Box<R>
+where R: Reader + ?Sized,
+{
+    #[inline]
+    fn next(&mut self) -> Result<Option<u8>, Error> {
+        (**self).next()
+    }
+
+    #[inline]
+    fn prev(&mut self) -> Result<Option<u8>, Error> {
+        (**self).prev()
+    }
+
+    #[inline]
+    fn peek(&mut self) -> Result<Option<u8>, Error> {
+        (**self).peek()
+    }
+
+    #[inline]
+    fn read_prev(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        (**self).read_prev(buf)
+    }
+
+    #[inline]
+    fn prev_u16(&mut self) -> Result<u16, Error> {
+        (**self).prev_u16()
+    }
+
+    #[inline]
+    fn prev_u32(&mut self) -> Result<u32, Error> {
+        (**self).prev_u32()
+    }
+
+    #[inline]
+    fn prev_u64(&mut self) -> Result<u64, Error> {
+        (**self).prev_u64()
+    }
+}
+
+impl<'de, R> BorrowReader<'de> for &mut R
+where R: BorrowReader<'de> + ?Sized,
+{
+    #[inline]
+    fn borrow_range(&self, range: Range<u64>) -> Option<&'de [u8]> {
+        (**self).borrow_range(range)
+    }
+}
+
+impl<'de, R> BorrowReader<'de> for Box<R>
+where R: BorrowReader<'de> + ?Sized,
+{
+    #[inline]
+    fn borrow_range(&self, range: Range<u64>) -> Option<&'de [u8]> {
+        (**self).borrow_range(range)
+    }
+}
+
+/// 型を消した reader は借用できない
+impl BorrowReader<'_> for dyn Reader + '_ {}
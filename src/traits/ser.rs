@@ -13,15 +13,16 @@ pub trait ExtendedSerializer: Sized + Serializer {
     fn serialize_uuid(self, v: &Uuid) -> Result<Self::Ok, Self::Error>;
     fn serialize_datetime<Tz>(self, v: &DateTime<Tz>) -> Result<Self::Ok, Self::Error>
     where
-        Tz: chrono::TimeZone + ?Sized;
+        Tz: chrono::TimeZone;
     fn serialize_timestamp(self, v: i64) -> Result<Self::Ok, Self::Error>;
     fn serialize_duration(self, v: &Duration) -> Result<Self::Ok, Self::Error>;
     fn serialize_wrapped_json(
         self,
         v: &serde_json::Value,
     ) -> Result<Self::Ok, Self::Error>;
-    fn serialize_meta(self, v: &Box<Value>) -> Result<Self::Ok, Self::Error>;
+    fn serialize_meta(self, v: &Value) -> Result<Self::Ok, Self::Error>;
     fn serialize_padding(self, v: usize) -> Result<Self::Ok, Self::Error>;
+    fn serialize_undefined(self) -> Result<Self::Ok, Self::Error>;
 
     fn ex_serialize_seq(self, _len: Option<usize>) -> Result<Self::ExtendSerializeSeq, Self::Error>;
     fn ex_serialize_map(self, _len: Option<usize>) -> Result<Self::ExtendSerializeMap, Self::Error>;
@@ -0,0 +1,365 @@
+//! RTON の構造検査
+//!
+//! 値を組み立てずに後ろから走査して、壊れたデータを保存前に弾くためのもの
+
+use std::collections::HashSet;
+
+use chrono::DateTime;
+use serde::de::IgnoredAny;
+
+use crate::checksum::{crc32c, find_document_checksum, mismatch, verify_container, DOCUMENT_LEN};
+use crate::compress::Compressed;
+use crate::de::{read_head, type_name, Head};
+use crate::error::{Error, ErrorCode};
+use crate::intern::{reference_index, DocumentKeys};
+use crate::packed::{decode as decode_array, TypedArray};
+use crate::raw::{parse_index, TonRef};
+use crate::value::prefix::{compression, prefix, self_describe, size_prefix};
+
+/// 走査するネストの上限
+const MAX_DEPTH: usize = 1024;
+
+/// 検査結果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// 検査したバイト数 (self-describe trailer を含む)
+    pub size: usize,
+    /// 値の数 (Padding を除く)
+    pub values: usize,
+    /// Array, Object, 型付き配列, 表, Meta の数
+    pub containers: usize,
+    /// 最も深いネスト (ルートが 0)
+    pub max_depth: usize,
+    /// Padding のバイト数
+    pub padding_bytes: usize,
+    /// 末尾に self-describe trailer があるか
+    pub self_describe: bool,
+    /// 確かめたチェックサムの数 (文書とコンテナ)
+    pub checksums: usize,
+}
+
+/// RTON のバイト列が正しい構造か検査する
+///
+/// 以下を確かめる
+/// - 全ての head が既知の型と size prefix の組み合わせであること
+/// - 子の値が親の body に収まり、子のサイズの合計が親の body と一致すること
+/// - Object が key と value の組になっていて、key に使える型であること
+/// - 表の列が行数と同じ数の要素を持つ Array か型付き配列であること
+/// - KEYREF が Object の key にだけ使われ、文書の辞書の範囲を指していること
+/// - REF が自分より前にある値の head を指していること
+/// - オフセット索引がある場合は、子の位置と一致すること
+/// - String は UTF-8, DateTime は RFC 3339, WrappedJSON は JSON として正しいこと
+/// - Compressed が展開できて、展開した body が元の型として正しいこと (組み込み以外の codec は展開しない)
+/// - ルートの値の前に余分なバイトがないこと
+/// - チェックサムがある場合は、文書とコンテナのバイト列と一致すること
+///   (コンテナが合わない場合は、合わない最も内側のコンテナの範囲をエラーにする)
+///
+/// 値は組み立てないのでメモリをほとんど使わない
+pub fn validate(buf: &[u8]) -> Result<ValidationReport, Error> {
+    let mut report = ValidationReport {
+        size: buf.len(),
+        ..ValidationReport::default()
+    };
+    let mut end = buf.len();
+    if buf.ends_with(&self_describe::TON_V1_REV_TAG) {
+        report.self_describe = true;
+        end -= self_describe::TON_V1_REV_TAG.len();
+    }
+    let checksum = find_document_checksum(buf, end);
+    if let Some((start, _)) = checksum {
+        report.padding_bytes += end - start;
+        end = start;
+    }
+
+    let keys = DocumentKeys::find(buf)?;
+    if let Some(keys) = &keys {
+        for i in keys.base()..keys.limit() {
+            let key = keys.get(i).unwrap_or_default();
+            std::str::from_utf8(key).map_err(|_| error("key dictionary has a key that is not valid UTF-8", end - 1))?;
+        }
+    }
+    let mut validator = Validator { buf, report, keys, heads: HashSet::new(), references: Vec::new(), verified: HashSet::new() };
+    validator.single(0, end, 0, "root value")?;
+    for &(target, pos) in &validator.references {
+        if !validator.heads.contains(&target) {
+            return Err(error(format!("reference to {} does not point to a value", target), pos));
+        }
+    }
+    // 壊れたコンテナはコンテナのチェックサムで先に見つける
+    if let Some((start, crc)) = checksum {
+        if crc32c(&buf[..start]) != crc {
+            return Err(mismatch(0..start, start + DOCUMENT_LEN - 1));
+        }
+        validator.report.checksums += 1;
+    }
+    Ok(validator.report)
+}
+
+struct Validator<'a> {
+    buf: &'a [u8],
+    report: ValidationReport,
+    /// ルートの後ろにある key の辞書
+    keys: Option<DocumentKeys<'a>>,
+    /// 検査した値の head の位置 (REF の参照先になれるもの)
+    heads: HashSet<usize>,
+    /// REF の (参照先, REF の head の位置)
+    references: Vec<(usize, usize)>,
+    /// 外側のコンテナと一緒に確かめたチェックサムの head の位置
+    verified: HashSet<usize>,
+}
+
+impl Validator<'_> {
+    /// Padding (コンテナのチェックサムを含む) か
+    fn skipped(&self, head: &Head) -> bool {
+        head.prefix() == prefix::PADDING
+    }
+
+    /// `start..end` に Padding を除いてちょうど 1 つの値があるか確かめる
+    fn single(&mut self, start: usize, end: usize, depth: usize, what: &str) -> Result<(), Error> {
+        let mut cursor = end;
+        let mut count = 0;
+        while cursor > start {
+            let child = self.child(start, cursor, depth)?;
+            not_keyref(&child)?;
+            if !self.skipped(&child) {
+                count += 1;
+                if count > 1 {
+                    return Err(error(format!("unexpected data before the {}", what), child.end - 1));
+                }
+            }
+            cursor = child.start;
+        }
+        if count == 0 {
+            return Err(error(format!("missing {}", what), end.saturating_sub(1)));
+        }
+        Ok(())
+    }
+
+    /// `parent_start..end` の末尾にある値を検査する
+    fn child(&mut self, parent_start: usize, end: usize, depth: usize) -> Result<Head, Error> {
+        let head = self.value(end, depth)?;
+        if head.start < parent_start {
+            return Err(error(
+                format!("{} overflows its parent by {} bytes", type_name(head.head), parent_start - head.start),
+                head.end - 1,
+            ));
+        }
+        Ok(head)
+    }
+
+    /// end の直前にある値を検査する
+    fn value(&mut self, end: usize, depth: usize) -> Result<Head, Error> {
+        if depth > MAX_DEPTH {
+            return Err(error(format!("nesting is deeper than {}", MAX_DEPTH), end.saturating_sub(1)));
+        }
+        let head = read_head(self.buf, end)?;
+        let body = &self.buf[head.start..head.head_start];
+        let pos = head.end - 1;
+
+        if head.prefix() == prefix::PADDING {
+            self.report.padding_bytes += head.end - head.start;
+            return Ok(head);
+        }
+        if self.skipped(&head) {
+            return Ok(head);
+        }
+        self.report.values += 1;
+        self.report.max_depth = self.report.max_depth.max(depth);
+        if head.prefix() != prefix::KEYREF {
+            self.heads.insert(pos);
+        }
+
+        match head.prefix() {
+            prefix::STRING | prefix::WRAPPED_JSON => check_text(head.prefix(), body, pos)?,
+            prefix::COMPRESSED => {
+                let compressed = Compressed::parse(body, pos)?;
+                if compressed.codec == compression::LZ {
+                    check_text(compressed.head, &compressed.decompress(&[], pos)?, pos)?;
+                }
+            }
+            prefix::DATETIME => {
+                let s = std::str::from_utf8(body).map_err(|_| error("datetime is not valid UTF-8", pos))?;
+                DateTime::parse_from_rfc3339(s).map_err(|e| error(format!("datetime is not RFC 3339: {}", e), pos))?;
+            }
+            prefix::ARRAY => {
+                self.report.containers += 1;
+                self.checksum(pos)?;
+                let mut ends = Vec::new();
+                let mut cursor = head.head_start;
+                while cursor > head.start {
+                    let child = self.child(head.start, cursor, depth + 1)?;
+                    not_keyref(&child)?;
+                    if !self.skipped(&child) {
+                        ends.push(child.end);
+                    }
+                    cursor = child.start;
+                }
+                self.index(&head, &ends)?;
+            }
+            prefix::OBJECT => {
+                self.report.containers += 1;
+                self.checksum(pos)?;
+                self.object(&head, depth)?;
+            }
+            prefix::TYPED_ARRAY => {
+                self.report.containers += 1;
+                TypedArray::parse(body, pos)?;
+            }
+            prefix::ENCODED_ARRAY => {
+                self.report.containers += 1;
+                decode_array(body, pos)?;
+            }
+            prefix::TABLE => {
+                self.report.containers += 1;
+                self.table(&head, depth)?;
+            }
+            prefix::META => {
+                self.report.containers += 1;
+                self.single(head.start, head.head_start, depth + 1, "meta value")?;
+            }
+            prefix::REF => {
+                let target = reference_index(body);
+                if target >= head.start as u64 {
+                    return Err(error(format!("reference to {} does not point before itself", target), pos));
+                }
+                self.references.push((target as usize, pos));
+            }
+            prefix::KEYREF => {
+                let index = reference_index(body);
+                match &self.keys {
+                    Some(keys) if index < keys.limit() => {}
+                    Some(_) => return Err(error(format!("key reference {} is out of the key dictionary", index), pos)),
+                    None => return Err(error("key reference without key dictionary", pos)),
+                }
+            }
+            _ => {}
+        }
+        Ok(head)
+    }
+
+    /// Object の body は value -> key の組が並ぶので、後ろから見ると key が先に来る
+    fn object(&mut self, head: &Head, depth: usize) -> Result<(), Error> {
+        let mut cursor = head.head_start;
+        let mut count = 0usize;
+        let mut key_ends = Vec::new();
+        while cursor > head.start {
+            let child = self.child(head.start, cursor, depth + 1)?;
+            cursor = child.start;
+            if self.skipped(&child) {
+                continue;
+            }
+            if count.is_multiple_of(2) && !is_key(child.head) {
+                return Err(error(
+                    format!("{} cannot be used as an object key", type_name(child.head)),
+                    child.end - 1,
+                ));
+            }
+            if count.is_multiple_of(2) {
+                key_ends.push(child.end);
+            } else {
+                not_keyref(&child)?;
+            }
+            count += 1;
+        }
+        if !count.is_multiple_of(2) {
+            return Err(error("object has a key without a value", head.end - 1));
+        }
+        self.index(head, &key_ends)
+    }
+
+    /// 表の body は column -> key の組の後ろに行数が来る
+    fn table(&mut self, head: &Head, depth: usize) -> Result<(), Error> {
+        let mut children = Vec::new();
+        let mut cursor = head.head_start;
+        while cursor > head.start {
+            let child = self.child(head.start, cursor, depth + 1)?;
+            cursor = child.start;
+            if !self.skipped(&child) {
+                children.push(child);
+            }
+        }
+        let Some((rows, pairs)) = children.split_first() else {
+            return Err(error("table has no row count", head.end - 1));
+        };
+        if rows.prefix() != prefix::UINT {
+            return Err(error(format!("table row count must be {}, found {}", type_name(prefix::UINT), type_name(rows.head)), rows.end - 1));
+        }
+        let rows = TonRef::at(self.buf, rows.end - 1)?.as_u64().unwrap_or_default();
+        if !pairs.len().is_multiple_of(2) {
+            return Err(error("table has a column without name", head.end - 1));
+        }
+        for pair in pairs.chunks_exact(2) {
+            let (key, column) = (&pair[0], &pair[1]);
+            if !is_key(key.head) {
+                return Err(error(format!("{} cannot be used as a column name", type_name(key.head)), key.end - 1));
+            }
+            if !matches!(column.prefix(), prefix::ARRAY | prefix::TYPED_ARRAY | prefix::ENCODED_ARRAY) {
+                return Err(error(format!("{} cannot be used as a table column", type_name(column.head)), column.end - 1));
+            }
+            let count = TonRef::at(self.buf, column.end - 1)?.count()?;
+            if count as u64 != rows {
+                return Err(error(format!("table column has {} values for {} rows", count, rows), column.end - 1));
+            }
+        }
+        Ok(())
+    }
+
+    /// `pos` の Array, Object にチェックサムがあれば確かめる
+    ///
+    /// 外側のコンテナと一緒に確かめたものは数え直さない
+    fn checksum(&mut self, pos: usize) -> Result<(), Error> {
+        if self.verified.remove(&pos) || verify_container(self.buf, TonRef::at(self.buf, pos)?, &mut self.verified)? {
+            self.report.checksums += 1;
+        }
+        Ok(())
+    }
+
+    /// オフセット索引が子の終端 (後ろから順) と一致するか確かめる
+    fn index(&self, head: &Head, ends_rev: &[usize]) -> Result<(), Error> {
+        let Some(index) = parse_index(self.buf, head.start, head.head_start) else {
+            return Ok(());
+        };
+        let matches = index.count == ends_rev.len()
+            && ends_rev.iter().rev().enumerate().all(|(i, &end)| index.end(head.start, i) == end);
+        if !matches {
+            return Err(error("container index does not match its children", head.end - 1));
+        }
+        Ok(())
+    }
+}
+
+/// Object の key に使える型か
+fn is_key(head: u8) -> bool {
+    !matches!(
+        head & !size_prefix::MASK,
+        prefix::ARRAY | prefix::OBJECT | prefix::WRAPPED_JSON | prefix::TYPED_ARRAY | prefix::ENCODED_ARRAY
+        | prefix::TABLE | prefix::REF | prefix::COMPRESSED | prefix::META | prefix::PADDING
+    )
+}
+
+/// String は UTF-8, WrappedJSON は JSON として正しいか (Bytes は何でもよい)
+fn check_text(head: u8, body: &[u8], pos: usize) -> Result<(), Error> {
+    match head {
+        prefix::STRING => {
+            std::str::from_utf8(body).map_err(|_| error("string is not valid UTF-8", pos))?;
+        }
+        prefix::WRAPPED_JSON => {
+            serde_json::from_slice::<IgnoredAny>(body).map_err(|e| error(format!("wrapped JSON is invalid: {}", e), pos))?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// KEYREF は Object の key (と表の列の名前) にしか使えない
+fn not_keyref(head: &Head) -> Result<(), Error> {
+    if head.prefix() == prefix::KEYREF {
+        return Err(error(format!("{} can only be used as an object key", type_name(head.head)), head.end - 1));
+    }
+    Ok(())
+}
+
+#[cold]
+fn error(msg: impl Into<String>, pos: usize) -> Error {
+    Error::syntax(ErrorCode::Other(msg.into()), pos)
+}
@@ -0,0 +1,290 @@
+use std::fmt;
+
+use chrono::{DateTime, Duration};
+use half::f16;
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::map::Map;
+use super::num::{Float, Int, UInt};
+use super::prefix::prefix_str;
+use super::value::{KeyValue, Value};
+
+/// Value をデシリアライズするときに使う newtype 名
+///
+/// TON 系のデシリアライザはこの名前を受け取ると、次の値の拡張型 (UUID, DateTime など) を
+/// `prefix_str` をキーにした 1 要素の map で渡してくれる
+/// それ以外のフォーマットでは普通の newtype として扱われる
+pub(crate) const VALUE_TOKEN: &str = "$serde_ton::private::Value";
+
+/// 拡張型を 1 要素の map として visitor に渡すための MapAccess
+///
+/// key: `prefix_str` の型名
+/// value: 型の中身を表すデシリアライザ
+pub(crate) struct ExtendedAccess<D> {
+    key: Option<&'static str>,
+    value: Option<D>,
+}
+
+impl<D> ExtendedAccess<D> {
+    pub(crate) fn new(key: &'static str, value: D) -> Self {
+        Self {
+            key: Some(key),
+            value: Some(value),
+        }
+    }
+}
+
+impl<'de, D> MapAccess<'de> for ExtendedAccess<D>
+where
+    D: Deserializer<'de>,
+{
+    type Error = D::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.key.take() {
+            Some(key) => seed
+                .deserialize(de::value::StrDeserializer::<D::Error>::new(key))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        match self.value.take() {
+            Some(value) => seed.deserialize(value),
+            None => Err(de::Error::custom("value is missing")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.key.is_some() as usize)
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(VALUE_TOKEN, ValueVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        KeyValue::try_from(value).map_err(|v| {
+            de::Error::custom(format!("{} cannot be used as an object key", v.type_name()))
+        })
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any TON value")
+    }
+
+    #[inline]
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    #[inline]
+    fn visit_i8<E>(self, v: i8) -> Result<Value, E> {
+        Ok(Value::Int(Int::I8(v)))
+    }
+
+    #[inline]
+    fn visit_i16<E>(self, v: i16) -> Result<Value, E> {
+        Ok(Value::Int(Int::I16(v)))
+    }
+
+    #[inline]
+    fn visit_i32<E>(self, v: i32) -> Result<Value, E> {
+        Ok(Value::Int(Int::I32(v)))
+    }
+
+    #[inline]
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Int(Int::I64(v)))
+    }
+
+    #[inline]
+    fn visit_u8<E>(self, v: u8) -> Result<Value, E> {
+        Ok(Value::UInt(UInt::U8(v)))
+    }
+
+    #[inline]
+    fn visit_u16<E>(self, v: u16) -> Result<Value, E> {
+        Ok(Value::UInt(UInt::U16(v)))
+    }
+
+    #[inline]
+    fn visit_u32<E>(self, v: u32) -> Result<Value, E> {
+        Ok(Value::UInt(UInt::U32(v)))
+    }
+
+    #[inline]
+    fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::UInt(UInt::U64(v)))
+    }
+
+    #[inline]
+    fn visit_f32<E>(self, v: f32) -> Result<Value, E> {
+        Ok(Value::Float(Float::F32(v)))
+    }
+
+    #[inline]
+    fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Float(Float::F64(v)))
+    }
+
+    #[inline]
+    fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    #[inline]
+    fn visit_string<E>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    #[inline]
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Value, E> {
+        Ok(Value::Bytes(v.to_vec()))
+    }
+
+    #[inline]
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Value, E> {
+        Ok(Value::Bytes(v))
+    }
+
+    #[inline]
+    fn visit_none<E>(self) -> Result<Value, E> {
+        Ok(Value::None)
+    }
+
+    #[inline]
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::None)
+    }
+
+    #[inline]
+    fn visit_some<D>(self, deserializer: D) -> Result<Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Value::deserialize(deserializer)
+    }
+
+    #[inline]
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut vec = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(elem) = seq.next_element()? {
+            vec.push(elem);
+        }
+        Ok(Value::Array(vec))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut values = Map::new();
+        let (key, value) = match map.next_key::<KeyValue>()? {
+            Some(key) => {
+                let value: Value = map.next_value()?;
+                (key, value)
+            }
+            None => return Ok(Value::Object(values)),
+        };
+
+        // 1 要素だけで key が拡張型の名前なら拡張型として復元する
+        let value = match &key {
+            KeyValue::String(name) if name.starts_with('$') => {
+                match map.next_key::<KeyValue>()? {
+                    None => match extended_value(name, value) {
+                        Ok(extended) => return Ok(extended),
+                        Err(value) => {
+                            values.insert(key, value);
+                            return Ok(Value::Object(values));
+                        }
+                    },
+                    Some(next_key) => {
+                        let next_value: Value = map.next_value()?;
+                        values.insert(next_key, next_value);
+                        value
+                    }
+                }
+            }
+            _ => value,
+        };
+        values.insert(key, value);
+
+        while let Some((key, value)) = map.next_entry()? {
+            values.insert(key, value);
+        }
+        Ok(Value::Object(values))
+    }
+}
+
+/// `prefix_str` の型名と中身から拡張型を組み立てる
+///
+/// 組み立てられない場合は中身をそのまま返す
+fn extended_value(name: &str, value: Value) -> Result<Value, Value> {
+    match (name, value) {
+        (prefix_str::UNDEFINED, Value::None) => Ok(Value::Undefined),
+        (prefix_str::FLOAT, Value::Float(Float::F32(v))) => Ok(Value::Float(Float::F16(f16::from_f32(v)))),
+        (prefix_str::UUID, Value::Bytes(bytes)) => match Uuid::from_slice(&bytes) {
+            Ok(uuid) => Ok(Value::UUID(uuid)),
+            Err(_) => Err(Value::Bytes(bytes)),
+        },
+        (prefix_str::UUID, Value::String(s)) => match Uuid::parse_str(&s) {
+            Ok(uuid) => Ok(Value::UUID(uuid)),
+            Err(_) => Err(Value::String(s)),
+        },
+        (prefix_str::DATETIME, Value::String(s)) => match DateTime::parse_from_rfc3339(&s) {
+            Ok(datetime) => Ok(Value::DateTime(datetime)),
+            Err(_) => Err(Value::String(s)),
+        },
+        (prefix_str::TIMESTAMP, value) => match value.as_i64() {
+            Some(v) => Ok(Value::Timestamp(v)),
+            None => Err(value),
+        },
+        (prefix_str::DURATION, value) => match value.as_i64() {
+            Some(v) => Ok(Value::Duration(Duration::nanoseconds(v))),
+            None => Err(value),
+        },
+        (prefix_str::WRAPPED_JSON, Value::String(s)) => match serde_json::from_str(&s) {
+            Ok(json) => Ok(Value::WrappedJSON(json)),
+            Err(_) => Err(Value::String(s)),
+        },
+        (prefix_str::META, value) => Ok(Value::Meta(Box::new(value))),
+        (_, value) => Err(value),
+    }
+}
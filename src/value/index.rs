@@ -0,0 +1,165 @@
+use std::ops;
+
+use super::value::{KeyValue, Value};
+
+/// `Value::get` に渡せる型
+///
+/// usize は Array の要素、文字列は Object の String キーを表す
+/// KeyValue を渡すと任意のキーで Object を引ける
+pub trait Index: private::Sealed {
+    #[doc(hidden)]
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value>;
+
+    #[doc(hidden)]
+    fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value>;
+}
+
+impl Index for usize {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        match v {
+            Value::Array(vec) => vec.get(*self),
+            Value::Meta(inner) => self.index_into(inner),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value> {
+        match v {
+            Value::Array(vec) => vec.get_mut(*self),
+            Value::Meta(inner) => self.index_into_mut(inner),
+            _ => None,
+        }
+    }
+}
+
+impl Index for KeyValue {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        match v {
+            Value::Object(map) => map.get(self),
+            Value::Meta(inner) => self.index_into(inner),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value> {
+        match v {
+            Value::Object(map) => map.get_mut(self),
+            Value::Meta(inner) => self.index_into_mut(inner),
+            _ => None,
+        }
+    }
+}
+
+impl Index for str {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        KeyValue::String(self.to_string()).index_into(v)
+    }
+
+    fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value> {
+        KeyValue::String(self.to_string()).index_into_mut(v)
+    }
+}
+
+impl Index for String {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        self[..].index_into(v)
+    }
+
+    fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value> {
+        self[..].index_into_mut(v)
+    }
+}
+
+impl<T> Index for &T
+where
+    T: ?Sized + Index,
+{
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        (**self).index_into(v)
+    }
+
+    fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value> {
+        (**self).index_into_mut(v)
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for usize {}
+    impl Sealed for str {}
+    impl Sealed for String {}
+    impl Sealed for super::KeyValue {}
+    impl<T> Sealed for &T where T: ?Sized + Sealed {}
+}
+
+impl Value {
+    /// Array の要素か Object の値を取得する
+    ///
+    /// Meta は中身を見る
+    /// 型が合わない場合や存在しない場合は None
+    ///
+    /// index: usize, &str, KeyValue など
+    ///
+    /// return: Option<&Value>
+    pub fn get<I: Index>(&self, index: I) -> Option<&Value> {
+        index.index_into(self)
+    }
+
+    /// `get` の可変参照版
+    ///
+    /// return: Option<&mut Value>
+    pub fn get_mut<I: Index>(&mut self, index: I) -> Option<&mut Value> {
+        index.index_into_mut(self)
+    }
+
+    /// `/` 区切りのパスで値を取得する
+    ///
+    /// JSON Pointer (RFC 6901) と同じ書式で、Array には数字を使う
+    /// `~0` と `~1` はそれぞれ `~` と `/` を表す
+    ///
+    /// pointer: &str e.g. "/users/0/name"
+    ///
+    /// return: Option<&Value>
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        if pointer.is_empty() {
+            return Some(self);
+        }
+        let rest = pointer.strip_prefix('/')?;
+        rest.split('/')
+            .map(|token| token.replace("~1", "/").replace("~0", "~"))
+            .try_fold(self, |mut target, token| {
+                while let Value::Meta(inner) = target {
+                    target = inner;
+                }
+                match target {
+                    Value::Array(vec) => parse_array_index(&token).and_then(|i| vec.get(i)),
+                    _ => target.get(&token),
+                }
+            })
+    }
+}
+
+/// JSON Pointer (RFC 6901) の Array の添字
+///
+/// `0` か、先頭に 0 が付かない 10 進数の数字だけを受け付ける (`+1` や `01` は添字ではない)
+pub(crate) fn parse_array_index(token: &str) -> Option<usize> {
+    if token.is_empty() || !token.bytes().all(|b| b.is_ascii_digit()) || (token.len() > 1 && token.starts_with('0')) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+// 見つからない場合に返す値
+static NONE: Value = Value::None;
+
+/// 存在しない場合は `Value::None` を返す
+impl<I> ops::Index<I> for Value
+where
+    I: Index,
+{
+    type Output = Value;
+
+    fn index(&self, index: I) -> &Value {
+        index.index_into(self).unwrap_or(&NONE)
+    }
+}
@@ -12,6 +12,13 @@ pub struct Map<K, V> {
     map: MapImpl<K, V>,
 }
 
+impl Default for Map<KeyValue, Value> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Map<KeyValue, Value> {
     /// 空のMapを新しく作る
     /// 
@@ -59,6 +66,20 @@ impl Map<KeyValue, Value> {
         self.map.get(key)
     }
 
+    /// Key に対応する値の可変参照を取得する
+    /// 
+    /// key: &KeyValue
+    /// 
+    /// return: Option<&mut Value>
+    #[inline]
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Value>
+    where
+        KeyValue: Borrow<Q>,
+        Q: ?Sized + Ord + Eq + Hash,
+    {
+        self.map.get_mut(key)
+    }
+
     /// Key が存在するか確認する
     /// 
     /// key: &KeyValue
@@ -141,7 +162,7 @@ impl Map<KeyValue, Value> {
     /// key: &Q
     /// 
     /// 
-    pub fn entry<S>(&mut self, key: S) -> Entry
+    pub fn entry<S>(&mut self, key: S) -> Entry<'_>
     where
         S: Into<KeyValue>,
     {
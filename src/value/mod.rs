@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod value;
 pub mod num;
 pub mod prefix;
@@ -21,6 +21,34 @@ pub enum UInt {
     U64(u64),
 }
 
+impl ExtendSerialize for Int {
+    fn ex_serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ExtendedSerializer,
+    {
+        match self {
+            Int::I8(v) => serializer.serialize_i8(*v),
+            Int::I16(v) => serializer.serialize_i16(*v),
+            Int::I32(v) => serializer.serialize_i32(*v),
+            Int::I64(v) => serializer.serialize_i64(*v),
+        }
+    }
+}
+
+impl ExtendSerialize for UInt {
+    fn ex_serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ExtendedSerializer,
+    {
+        match self {
+            UInt::U8(v) => serializer.serialize_u8(*v),
+            UInt::U16(v) => serializer.serialize_u16(*v),
+            UInt::U32(v) => serializer.serialize_u32(*v),
+            UInt::U64(v) => serializer.serialize_u64(*v),
+        }
+    }
+}
+
 #[derive(Deserialize, Debug, Clone, PartialOrd)]
 pub enum Float {
     F16(f16),
@@ -44,6 +72,7 @@ impl ExtendSerialize for Float {
 
 impl Eq for Float {}
 
+#[allow(clippy::derive_ord_xor_partial_ord)]
 impl Ord for Float {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         match (self, other) {
@@ -5,6 +5,8 @@ pub mod self_describe {
     pub const MODE_REV: u8 = 0x01;
 }
 
+// prefix は 6bit + 2bit のビット区切りで書いている
+#[allow(clippy::module_inception, clippy::unusual_byte_groupings)]
 pub mod prefix {
     pub const UNDEFINED:        u8 = 0b111111_00; // 0xFC
     pub const NONE:             u8 = 0b000000_00; // 0x00
@@ -21,6 +23,12 @@ pub mod prefix {
     pub const ARRAY:            u8 = 0b001011_00; // 0x2C ~ 0x2F
     pub const OBJECT:           u8 = 0b001100_00; // 0x30 ~ 0x33
     pub const WRAPPED_JSON:     u8 = 0b001101_00; // 0x34 ~ 0x37
+    pub const TYPED_ARRAY:      u8 = 0b010000_00; // 0x40 ~ 0x43
+    pub const ENCODED_ARRAY:    u8 = 0b010001_00; // 0x44 ~ 0x47
+    pub const TABLE:            u8 = 0b010010_00; // 0x48 ~ 0x4B
+    pub const KEYREF:           u8 = 0b010011_00; // 0x4C ~ 0x4F
+    pub const REF:              u8 = 0b010100_00; // 0x50 ~ 0x53
+    pub const COMPRESSED:       u8 = 0b010101_00; // 0x54 ~ 0x57
 
     pub const META:             u8 = 0b001110_00; // 0x38 ~ 0x3B
     pub const PADDING:          u8 = 0b001111_00; // 0x3C ~ 0x3F
@@ -42,15 +50,110 @@ pub mod prefix_str {
     pub const ARRAY:            &str = "$array";     // Array
     pub const OBJECT:           &str = "$object";    // Object
     pub const WRAPPED_JSON:     &str = "$wrapped_json"; // Wrapped JSON
+    pub const TYPED_ARRAY:      &str = "$typed_array"; // Typed Array
+    pub const ENCODED_ARRAY:    &str = "$encoded_array"; // Encoded Array
+    pub const TABLE:            &str = "$table";     // Table
+    pub const KEYREF:           &str = "$keyref";    // Key Reference
+    pub const REF:              &str = "$ref";       // Back Reference
+    pub const COMPRESSED:       &str = "$compressed"; // Compressed body
 
     pub const META:             &str = "$meta";      // Meta
     pub const PADDING:          &str = "$padding";   // Padding
 }
 
+#[allow(clippy::unusual_byte_groupings)]
 pub mod size_prefix {
     pub const SIZE_PREFIX_1BYTE: u8 = 0b000000_00;
     pub const SIZE_PREFIX_2BYTE: u8 = 0b000000_01;
     pub const SIZE_PREFIX_4BYTE: u8 = 0b000000_10;
     pub const SIZE_PREFIX_8BYTE: u8 = 0b000000_11;
     pub const MASK: u8 = 0b000000_11; // サイズプレフィックスのマスク
-}
\ No newline at end of file
+}
+
+/// コンテナのオフセット索引
+///
+/// コンテナの body の最後に置く PADDING で、body はこの形になる
+/// `[offset; count] count:u64 width:u8 flags:u8 MAGIC`
+/// offset は body の先頭から見た各要素の終端 (Object は key の終端) で、前から順に並ぶ
+/// PADDING なので索引を知らない reader は読み飛ばす
+pub mod container_index {
+    pub const MAGIC: [u8; 4] = *b"TIX1";
+    /// count, width, flags, MAGIC の合計
+    pub const TRAILER_LEN: usize = 8 + 1 + 1 + MAGIC.len();
+    /// Object の key が全て String で、バイト列として昇順に並んでいる
+    pub const FLAG_SORTED_KEYS: u8 = 0b0000_0001;
+}
+
+/// 文書のキー辞書
+///
+/// ルートの値の後ろに置く PADDING で、body はこの形になる
+/// `[key] [end:u32; count] base:u64 count:u64 fingerprint:u64 MAGIC`
+/// key は UTF-8 のバイト列を前から並べ、end は body の先頭から見た各 key の終端
+/// KEYREF の番号が base 未満なら外部の辞書、base 以上なら base を引いた番号の key を指す
+/// fingerprint は外部の辞書の指紋で、外部の辞書を使わない場合は 0
+pub mod key_dictionary {
+    pub const MAGIC: [u8; 4] = *b"TKD1";
+    /// base, count, fingerprint, MAGIC の合計
+    pub const TRAILER_LEN: usize = 8 + 8 + 8 + MAGIC.len();
+}
+
+/// 要素を符号化した型付き配列 (Encoded Array) の並べ方
+///
+/// body は `[payload] encoding:u8 element_head:u8` で、element_head は型付き配列と同じ
+/// 整数は 64 bit に (Int は符号) 拡張してから差分を取り、差分は zigzag して LEB128 の varint で書く
+pub mod array_encoding {
+    /// 前の要素との差分
+    pub const DELTA: u8 = 0x01;
+    /// 前の差分との差分 (一定間隔の Timestamp はほぼ 0 が並ぶ)
+    pub const DELTA_OF_DELTA: u8 = 0x02;
+    /// `[value run:varint]` の並び
+    pub const RUN_LENGTH: u8 = 0x03;
+}
+
+/// ストリームの 1 レコードの枠
+///
+/// `HEAD len:u64 [document] len:u64 TAIL` の形で、前からも後ろからも辿れる
+/// 枠が壊れていたら magic を探して次のレコードから読み直す
+pub mod stream_frame {
+    pub const HEAD: [u8; 4] = [super::self_describe::MAGIC[0], super::self_describe::MAGIC[1], b'S', b'['];
+    pub const TAIL: [u8; 4] = [super::self_describe::MAGIC[0], super::self_describe::MAGIC[1], b'S', b']'];
+    /// magic と len の合計 (先頭と末尾で同じ)
+    pub const EDGE_LEN: usize = 4 + 8;
+}
+
+/// `TonFile` の形式
+///
+/// `HEADER [record]* [footer]` の形で、record は `stream_frame` の枠で囲む
+/// footer は `[offset:u64; count] count:u64 FOOTER` で、offset は各 record の枠の先頭
+/// footer がないか壊れている場合は record の枠を前から辿り直す
+pub mod record_file {
+    pub const HEADER: [u8; 4] = [super::self_describe::MAGIC[0], super::self_describe::MAGIC[1], b'F', 0x01];
+    pub const FOOTER: [u8; 4] = [super::self_describe::MAGIC[0], super::self_describe::MAGIC[1], b'F', b'$'];
+    /// count と FOOTER の合計
+    pub const FOOTER_TRAILER_LEN: usize = 8 + 4;
+}
+
+/// 圧縮した body (Compressed)
+///
+/// body は `[圧縮したデータ] len:u64 codec:u8 head:u8` の形
+/// len は展開したデータのバイト数、head は展開したデータを body に持つ型 (String, Bytes, WrappedJSON)
+pub mod compression {
+    /// 組み込みの LZ 系の codec
+    pub const LZ: u8 = 0x01;
+    /// len, codec, head の合計
+    pub const TRAILER_LEN: usize = 8 + 1 + 1;
+}
+
+/// CRC32C のチェックサム
+///
+/// 文書のチェックサムはルートの値 (と key の辞書) の後ろに置く PADDING で、body は `crc:u32 DOCUMENT`
+/// crc はこの PADDING より前の全てのバイトの CRC32C
+/// コンテナのチェックサムは Array, Object の body の最後 (索引の前) に置く PADDING で、body は `crc:u32 CONTAINER`
+/// crc はコンテナの body のうちこの PADDING より前のバイトの CRC32C
+/// PADDING なので、チェックサムを知らない reader でもそのまま読める
+pub mod checksum {
+    pub const DOCUMENT: [u8; 4] = *b"TCD1";
+    pub const CONTAINER: [u8; 4] = *b"TCC1";
+    /// crc と MAGIC の合計
+    pub const BODY_LEN: usize = 4 + 4;
+}
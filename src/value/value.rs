@@ -1,15 +1,17 @@
+use std::fmt;
 use std::hash::Hash;
 
 use chrono::{DateTime, Duration, FixedOffset};
 use serde::Serialize;
 use uuid::Uuid;
 
+use crate::text::ser::{value_to_string, PrettyConfig};
 use crate::traits::ser::{ExtendSerialize, ExtendSerializeMap, ExtendSerializeSeq, ExtendedSerializer};
 
-use super::{map::Map, num::{Float, Int, UInt}};
+use super::{map::Map, num::{Float, Int, UInt}, prefix::prefix_str};
 
 
-#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
 pub enum Value {
     /// Undefined 型
     /// 値が未定義であることを示す型
@@ -24,6 +26,7 @@ pub enum Value {
     /// type size: 0 byte
     /// 
     /// 値が存在しないことを示すために使用されます。
+    #[default]
     None,
 
     /// Boolean 型
@@ -117,9 +120,84 @@ pub enum Value {
     Meta(Box<Value>),
 }
 
-impl Default for Value {
-    fn default() -> Self {
-        Value::None
+
+/// TON Text で表示する
+/// 
+/// `{:#}` の場合は整形して表示する
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let config = if f.alternate() { Some(PrettyConfig::default()) } else { None };
+        let text = value_to_string(self, config).map_err(|_| fmt::Error)?;
+        f.write_str(&text)
+    }
+}
+
+impl Value {
+    /// 型名を取得する
+    /// 
+    /// `prefix_str` と同じ表記です
+    /// 
+    /// return: &'static str
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Undefined => prefix_str::UNDEFINED,
+            Value::None => prefix_str::NONE,
+            Value::Bool(_) => prefix_str::BOOL,
+            Value::Int(_) => prefix_str::INT,
+            Value::UInt(_) => prefix_str::UINT,
+            Value::Float(_) => prefix_str::FLOAT,
+            Value::String(_) => prefix_str::STRING,
+            Value::Bytes(_) => prefix_str::BYTES,
+            Value::UUID(_) => prefix_str::UUID,
+            Value::DateTime(_) => prefix_str::DATETIME,
+            Value::Timestamp(_) => prefix_str::TIMESTAMP,
+            Value::Duration(_) => prefix_str::DURATION,
+            Value::Array(_) => prefix_str::ARRAY,
+            Value::Object(_) => prefix_str::OBJECT,
+            Value::WrappedJSON(_) => prefix_str::WRAPPED_JSON,
+            Value::Meta(_) => prefix_str::META,
+        }
+    }
+
+    /// 整数として i64 に収まるなら取得する
+    /// 
+    /// return: Option<i64>
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Int(Int::I8(v)) => Some(*v as i64),
+            Value::Int(Int::I16(v)) => Some(*v as i64),
+            Value::Int(Int::I32(v)) => Some(*v as i64),
+            Value::Int(Int::I64(v)) => Some(*v),
+            Value::UInt(UInt::U8(v)) => Some(*v as i64),
+            Value::UInt(UInt::U16(v)) => Some(*v as i64),
+            Value::UInt(UInt::U32(v)) => Some(*v as i64),
+            Value::UInt(UInt::U64(v)) => i64::try_from(*v).ok(),
+            _ => None,
+        }
+    }
+
+    /// 整数として u64 に収まるなら取得する
+    /// 
+    /// return: Option<u64>
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Value::UInt(UInt::U8(v)) => Some(*v as u64),
+            Value::UInt(UInt::U16(v)) => Some(*v as u64),
+            Value::UInt(UInt::U32(v)) => Some(*v as u64),
+            Value::UInt(UInt::U64(v)) => Some(*v),
+            Value::Int(_) => self.as_i64().and_then(|v| u64::try_from(v).ok()),
+            _ => None,
+        }
+    }
+
+    /// 文字列なら参照を取得する
+    /// 
+    /// return: Option<&str>
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
     }
 }
 
@@ -143,20 +221,74 @@ pub enum KeyValue {
     Duration(Duration),
 }
 
+impl From<KeyValue> for Value {
+    fn from(key: KeyValue) -> Self {
+        match key {
+            KeyValue::Undefined => Value::Undefined,
+            KeyValue::None => Value::None,
+            KeyValue::Bool(v) => Value::Bool(v),
+            KeyValue::Int(v) => Value::Int(v),
+            KeyValue::UInt(v) => Value::UInt(v),
+            KeyValue::Float(v) => Value::Float(v),
+            KeyValue::String(v) => Value::String(v),
+            KeyValue::Bytes(v) => Value::Bytes(v),
+            KeyValue::UUID(v) => Value::UUID(v),
+            KeyValue::DateTime(v) => Value::DateTime(v),
+            KeyValue::Timestamp(v) => Value::Timestamp(v),
+            KeyValue::Duration(v) => Value::Duration(v),
+        }
+    }
+}
+
+/// Key にできない型 (Array, Object, WrappedJSON, Meta) の場合は元の値を返す
+impl TryFrom<Value> for KeyValue {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Undefined => Ok(KeyValue::Undefined),
+            Value::None => Ok(KeyValue::None),
+            Value::Bool(v) => Ok(KeyValue::Bool(v)),
+            Value::Int(v) => Ok(KeyValue::Int(v)),
+            Value::UInt(v) => Ok(KeyValue::UInt(v)),
+            Value::Float(v) => Ok(KeyValue::Float(v)),
+            Value::String(v) => Ok(KeyValue::String(v)),
+            Value::Bytes(v) => Ok(KeyValue::Bytes(v)),
+            Value::UUID(v) => Ok(KeyValue::UUID(v)),
+            Value::DateTime(v) => Ok(KeyValue::DateTime(v)),
+            Value::Timestamp(v) => Ok(KeyValue::Timestamp(v)),
+            Value::Duration(v) => Ok(KeyValue::Duration(v)),
+            other => Err(other),
+        }
+    }
+}
+
+impl From<&str> for KeyValue {
+    fn from(s: &str) -> Self {
+        KeyValue::String(s.to_string())
+    }
+}
+
+impl From<String> for KeyValue {
+    fn from(s: String) -> Self {
+        KeyValue::String(s)
+    }
+}
+
 impl ExtendSerialize for KeyValue {
     fn ex_serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: ExtendedSerializer,
     {
         match self {
-            Self::Undefined => self.ex_serialize(serializer),
-            Self::None => self.ex_serialize(serializer),
+            Self::Undefined => serializer.serialize_undefined(),
+            Self::None => serializer.serialize_none(),
             Self::Bool(v) => v.serialize(serializer),
-            Self::Int(v) => v.serialize(serializer),
-            Self::UInt(v) => v.serialize(serializer),
+            Self::Int(v) => v.ex_serialize(serializer),
+            Self::UInt(v) => v.ex_serialize(serializer),
             Self::Float(v) => v.ex_serialize(serializer),
             Self::String(v) => v.serialize(serializer),
-            Self::Bytes(v) => v.serialize(serializer),
+            Self::Bytes(v) => serializer.serialize_bytes(v),
             Self::UUID(v) => serializer.serialize_uuid(v),
             Self::DateTime(v) => serializer.serialize_datetime(v),
             Self::Timestamp(v) => serializer.serialize_timestamp(*v),
@@ -171,14 +303,14 @@ impl ExtendSerialize for Value {
         S: ExtendedSerializer,
     {
         match self {
-            Self::Undefined => serializer.serialize_padding(0),
-            Self::None => serializer.serialize_padding(0),
+            Self::Undefined => serializer.serialize_undefined(),
+            Self::None => serializer.serialize_none(),
             Self::Bool(v) => v.serialize(serializer),
-            Self::Int(v) => v.serialize(serializer),
-            Self::UInt(v) => v.serialize(serializer),
+            Self::Int(v) => v.ex_serialize(serializer),
+            Self::UInt(v) => v.ex_serialize(serializer),
             Self::Float(v) => v.ex_serialize(serializer),
             Self::String(v) => v.serialize(serializer),
-            Self::Bytes(v) => v.serialize(serializer),
+            Self::Bytes(v) => serializer.serialize_bytes(v),
             Self::UUID(v) => serializer.serialize_uuid(v),
             Self::DateTime(v) => serializer.serialize_datetime(v),
             Self::Timestamp(v) => serializer.serialize_timestamp(*v),
@@ -191,10 +323,10 @@ impl ExtendSerialize for Value {
                 seq.end()
             },
             Self::Object(v) => {
-                let mut map = serializer.ex_serialize_map(None)?;
+                let mut map = serializer.ex_serialize_map(Some(v.len()))?;
                 for (key, value) in v.iter() {
-                    map.serialize_key(key)?;
-                    map.serialize_value(value)?;
+                    // RTON は value -> key の順で書く必要があるので entry 単位で渡す
+                    map.serialize_entry(key, value)?;
                 }
                 map.end()
             },
@@ -0,0 +1,296 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_ton::de::{from_slice, ReverseDeserializer};
+use serde_ton::ser::{to_vec, to_vec_with, value_to_vec, ReverseSerializer, SerializeOptions};
+use serde_ton::traits::ser::ExtendSerialize;
+use serde_ton::value::num::UInt;
+use serde_ton::value::value::Value;
+use serde_ton::{append_in_place, crc32c, edit_in_place, validate, Compression, RawEditor, Slack, TonRef};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Library {
+    name: String,
+    books: Vec<Book>,
+    tags: BTreeMap<String, u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Book {
+    title: String,
+    pages: u32,
+}
+
+fn library() -> Library {
+    Library {
+        name: "city library".to_string(),
+        books: (0..5).map(|i| Book { title: format!("volume {}", i), pages: 100 + i }).collect(),
+        tags: [("fiction".to_string(), 3), ("history".to_string(), 2)].into_iter().collect(),
+    }
+}
+
+#[test]
+fn test_crc32c() {
+    assert_eq!(crc32c(b""), 0);
+    assert_eq!(crc32c(b"123456789"), 0xe306_9283);
+    assert_eq!(crc32c(&[0u8; 32]), 0x8a91_36aa);
+}
+
+#[test]
+fn test_checksum_round_trip() {
+    let lib = library();
+    let plain = to_vec(&lib).unwrap();
+
+    let bytes = to_vec_with(&lib, &SerializeOptions::new().checksum()).unwrap();
+    assert_eq!(bytes.len(), plain.len() + 10);
+    assert_eq!(bytes[..plain.len()], plain[..]);
+    assert_eq!(validate(&bytes).unwrap().checksums, 1);
+    assert_eq!(from_slice::<Library>(&bytes).unwrap(), lib);
+
+    // 全てのコンテナにチェックサムを付ける
+    let bytes = to_vec_with(&lib, &SerializeOptions::new().container_checksums(0)).unwrap();
+    let report = validate(&bytes).unwrap();
+    assert_eq!(report.checksums, 1 + 1 + 1 + 5 + 1);
+    assert_eq!(report.values, validate(&plain).unwrap().values);
+    assert_eq!(from_slice::<Library>(&bytes).unwrap(), lib);
+    // Value でもチェックサムは子にならない
+    assert_eq!(from_slice::<Value>(&bytes).unwrap(), from_slice::<Value>(&plain).unwrap());
+
+    let root = TonRef::new(&bytes).unwrap();
+    let books = root.get("books").unwrap().unwrap();
+    assert_eq!(books.count().unwrap(), 5);
+    assert_eq!(books.children().unwrap().len(), 5);
+    assert_eq!(books.index(4).unwrap().unwrap().get("pages").unwrap().unwrap().as_u64(), Some(104));
+
+    // 閾値より小さいコンテナには付けない
+    let bytes = to_vec_with(&lib, &SerializeOptions::new().container_checksums(64)).unwrap();
+    assert_eq!(validate(&bytes).unwrap().checksums, 1 + 1 + 1);
+
+    // 後ろから 1 要素ずつ読む場合も確かめる
+    let bytes = to_vec_with(&vec![1u32, 2, 3], &SerializeOptions::new().container_checksums(0)).unwrap();
+    let mut de = ReverseDeserializer::from_slice(&bytes).unwrap();
+    let items = de.iter_array::<u32>().unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(items, vec![3, 2, 1]);
+
+    // 自分で書く場合は self-describe tag の前に書く
+    let mut ser = ReverseSerializer::with_options(Vec::new(), &SerializeOptions::new().container_checksums(0));
+    lib.serialize(&mut ser).unwrap();
+    ser.write_checksum().unwrap();
+    ser.write_self_describe().unwrap();
+    let bytes = ser.into_inner();
+    assert_eq!(validate(&bytes).unwrap().checksums, 9);
+    assert_eq!(from_slice::<Library>(&bytes).unwrap(), lib);
+}
+
+#[test]
+fn test_checksum_mismatch() {
+    let bytes = to_vec_with(&library(), &SerializeOptions::new().container_checksums(0)).unwrap();
+    let root = TonRef::new(&bytes).unwrap();
+    let book = root.get("books").unwrap().unwrap().index(2).unwrap().unwrap();
+    let title = book.get("title").unwrap().unwrap();
+    let at = title.range().start + 2;
+
+    // 文字を 1 つ変えても構造は正しいままなので、チェックサムでしか分からない
+    let mut broken = bytes.clone();
+    broken[at] ^= 0x01;
+    let err = validate(&broken).unwrap_err();
+    assert!(err.is_checksum_mismatch());
+    assert_eq!(err.checksum_range(), Some(book.range()));
+    assert!(err.to_string().contains(&format!("checksum mismatch in bytes {}..{}", book.range().start, book.range().end)));
+
+    // デシリアライザも一番内側のコンテナの範囲を返す
+    let err = from_slice::<Library>(&broken).unwrap_err();
+    assert!(err.is_checksum_mismatch());
+    assert_eq!(err.checksum_range(), Some(book.range()));
+    assert_eq!(from_slice::<Value>(&broken).unwrap_err().checksum_range(), Some(book.range()));
+    let mut de = ReverseDeserializer::from_slice(&broken).unwrap();
+    assert_eq!(Library::deserialize(&mut de).unwrap_err().checksum_range(), Some(book.range()));
+
+    // コンテナの外は文書のチェックサムで見つける
+    let bytes = to_vec_with(&library(), &SerializeOptions::new().checksum()).unwrap();
+    let root = TonRef::new(&bytes).unwrap();
+    let at = root.get("name").unwrap().unwrap().range().start;
+    let mut broken = bytes.clone();
+    broken[at] ^= 0x01;
+    let err = validate(&broken).unwrap_err();
+    assert_eq!(err.checksum_range(), Some(0..bytes.len() - 10));
+    let err = from_slice::<Library>(&broken).unwrap_err();
+    assert_eq!(err.checksum_range(), Some(0..bytes.len() - 10));
+
+    // 文書のチェックサムは `verify_checksum` を呼んだときにすぐ確かめる
+    let mut de = ReverseDeserializer::from_slice(&broken).unwrap();
+    assert_eq!(de.verify_checksum().unwrap_err().checksum_range(), Some(0..bytes.len() - 10));
+    let mut de = ReverseDeserializer::from_slice(&broken).unwrap();
+    assert!(Library::deserialize(&mut de).is_ok());
+    de.end().unwrap();
+    let mut de = ReverseDeserializer::from_slice(&bytes).unwrap();
+    assert!(de.verify_checksum().unwrap());
+    assert_eq!(Library::deserialize(&mut de).unwrap(), library());
+    assert!(!ReverseDeserializer::from_slice(&to_vec(&library()).unwrap()).unwrap().verify_checksum().unwrap());
+
+    // チェックサム自体が壊れた場合
+    let mut broken = bytes.clone();
+    let len = broken.len();
+    broken[len - 10] ^= 0x80;
+    assert!(validate(&broken).unwrap_err().is_checksum_mismatch());
+    assert!(from_slice::<Library>(&broken).unwrap_err().is_checksum_mismatch());
+}
+
+#[test]
+fn test_nested_checksums() {
+    // 深くネストしたコンテナのチェックサムも、中の body を数え直さずに確かめる
+    let mut value = Value::Bytes(vec![0x5a; 256 << 10]);
+    for depth in 0..300 {
+        value = Value::Array(vec![Value::UInt(UInt::U32(depth)), value]);
+        if depth % 100 == 50 {
+            value = Value::Meta(Box::new(value));
+        }
+    }
+    let mut ser = ReverseSerializer::with_options(Vec::new(), &SerializeOptions::new().container_checksums(0));
+    value.ex_serialize(&mut ser).unwrap();
+    let bytes = ser.into_inner();
+    assert_eq!(validate(&bytes).unwrap().checksums, 300);
+
+    // 一番内側の Array の範囲を返す
+    let innermost = TonRef::new(&bytes).unwrap().pointer(&"/1".repeat(299)).unwrap().unwrap();
+    let mut broken = bytes.clone();
+    broken[innermost.range().start + 100] ^= 0x01;
+    assert_eq!(validate(&broken).unwrap_err().checksum_range(), Some(innermost.range()));
+
+    // 表の列の中の Object (tags) も外側の Array と一緒に確かめる
+    let rows = (vec![library(); 3], 1u8);
+    let bytes = to_vec_with(&rows, &SerializeOptions::new().columnar().container_checksums(0)).unwrap();
+    assert_eq!(TonRef::new(&bytes).unwrap().index(0).unwrap().unwrap().type_name(), "$table");
+    assert_eq!(validate(&bytes).unwrap().checksums, 1 + 1 + 3);
+    assert_eq!(from_slice::<(Vec<Library>, u8)>(&bytes).unwrap(), rows);
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Counter {
+    name: Slack<String, 32>,
+    history: Slack<Vec<u32>, 64>,
+    hits: u64,
+}
+
+#[test]
+fn test_edit_keeps_checksums() {
+    let counter = Counter { name: Slack::new("page".to_string()), history: Slack::new(vec![1, 2]), hits: 3 };
+    let mut bytes = to_vec_with(&counter, &SerializeOptions::new().container_checksums(0)).unwrap();
+    let checksums = validate(&bytes).unwrap().checksums;
+
+    edit_in_place(&mut bytes, "/hits", &4u64).unwrap();
+    edit_in_place(&mut bytes, "/name", "a longer page name").unwrap();
+    append_in_place(&mut bytes, "/history", &3u32).unwrap();
+    assert_eq!(validate(&bytes).unwrap().checksums, checksums);
+    let edited: Counter = from_slice(&bytes).unwrap();
+    assert_eq!(*edited.name, "a longer page name");
+    assert_eq!(*edited.history, vec![1, 2, 3]);
+    assert_eq!(edited.hits, 4);
+
+    let lib = library();
+    let bytes = to_vec_with(&lib, &SerializeOptions::new().container_checksums(0)).unwrap();
+    let checksums = validate(&bytes).unwrap().checksums;
+    let edited = RawEditor::new(&bytes)
+        .set("/books/1/pages", &999u32)
+        .unwrap()
+        .delete("/books/4")
+        .unwrap()
+        .insert("/tags/poetry", &1u32)
+        .unwrap()
+        .apply()
+        .unwrap();
+    assert_eq!(validate(&edited).unwrap().checksums, checksums - 1);
+    let edited: Library = from_slice(&edited).unwrap();
+    assert_eq!(edited.books[1].pages, 999);
+    assert_eq!(edited.books.len(), 4);
+    assert_eq!(edited.tags["poetry"], 1);
+}
+
+#[test]
+fn test_combined_options() {
+    let mut lib = library();
+    let summary = "a long summary that repeats itself. ".repeat(20);
+    lib.books.iter_mut().for_each(|book| book.title = summary.clone());
+    let options = SerializeOptions::new()
+        .container_checksums(0)
+        .compression(Compression::new(64))
+        .dedup()
+        .interned_keys()
+        .index(2);
+    let bytes = to_vec_with(&lib, &options).unwrap();
+    let report = validate(&bytes).unwrap();
+    assert!(report.checksums > 1);
+    assert_eq!(from_slice::<Library>(&bytes).unwrap(), lib);
+    assert!(bytes.len() < to_vec(&lib).unwrap().len() / 4);
+
+    // 1 つ目の title だけを圧縮し、残りは参照にする
+    let books = TonRef::new(&bytes).unwrap().get("books").unwrap().unwrap();
+    let titles = books.children().unwrap().iter().map(|book| book.entries().unwrap()[0].1.type_name()).collect::<Vec<_>>();
+    assert_eq!(titles, vec!["$compressed", "$ref", "$ref", "$ref", "$ref"]);
+
+    // 圧縮した body が壊れてもチェックサムで分かる
+    let first = books.index(0).unwrap().unwrap();
+    let mut broken = bytes.clone();
+    broken[first.range().start + 4] ^= 0x01;
+    assert!(validate(&broken).unwrap_err().is_checksum_mismatch());
+}
+
+#[test]
+fn test_meta_value_is_not_a_checksum() {
+    // 以前のコンテナのチェックサムと同じバイト列になる Meta
+    let value = Value::Array(vec![
+        Value::UInt(UInt::U8(1)),
+        Value::Meta(Box::new(Value::Bytes(b"\0\0\0\0TCC1".to_vec()))),
+    ]);
+    let bytes = value_to_vec(&value).unwrap();
+    assert_eq!(validate(&bytes).unwrap().checksums, 0);
+    assert_eq!(from_slice::<Value>(&bytes).unwrap(), value);
+    assert_eq!(TonRef::new(&bytes).unwrap().count().unwrap(), 2);
+
+    let mut ser = ReverseSerializer::with_options(Vec::new(), &SerializeOptions::new().container_checksums(0));
+    value.ex_serialize(&mut ser).unwrap();
+    let bytes = ser.into_inner();
+    assert_eq!(validate(&bytes).unwrap().checksums, 1);
+    assert_eq!(from_slice::<Value>(&bytes).unwrap(), value);
+}
+
+/// Bytes として書く
+struct Blob(Vec<u8>);
+
+impl Serialize for Blob {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Field {
+    Blob(Blob),
+    Note(&'static str),
+}
+
+#[test]
+fn test_columnar_fallback_keeps_options() {
+    // 2 行目までは表に溜め、3 行目で key が変わって通常の Array に戻る
+    let row = |key: &'static str| -> BTreeMap<&'static str, Field> {
+        [(key, Field::Blob(Blob(vec![7; 20]))), ("note", Field::Note("the same note in every row"))].into_iter().collect()
+    };
+    let rows = vec![row("a"), row("a"), row("b")];
+
+    let options = SerializeOptions::new().container_checksums(1);
+    let bytes = to_vec_with(&rows, &options.clone().columnar()).unwrap();
+    assert_eq!(validate(&bytes).unwrap().checksums, 1 + 4);
+    assert_eq!(bytes, to_vec_with(&rows, &options).unwrap());
+
+    // 溜めていた行も境界の揃えと参照を使って書く
+    let options = SerializeOptions::new().container_checksums(1).alignment(8).dedup();
+    let bytes = to_vec_with(&rows, &options.clone().columnar()).unwrap();
+    assert_eq!(bytes, to_vec_with(&rows, &options).unwrap());
+    assert_eq!(validate(&bytes).unwrap().checksums, 1 + 4);
+    assert_eq!(from_slice::<Value>(&bytes).unwrap(), from_slice::<Value>(&to_vec(&rows).unwrap()).unwrap());
+    let root = TonRef::new(&bytes).unwrap();
+    let notes = root.children().unwrap().iter().map(|row| row.entries().unwrap()[1].1.type_name()).collect::<Vec<_>>();
+    assert_eq!(notes, vec!["$string", "$ref", "$ref"]);
+    assert_eq!(root.index(0).unwrap().unwrap().get("a").unwrap().unwrap().as_slice::<u8>().unwrap(), &[7; 20]);
+}
@@ -0,0 +1,113 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+use serde_ton::de::from_slice;
+use serde_ton::ser::to_vec;
+use serde_ton::value::value::Value;
+
+fn ton(args: &[&str], input: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ton"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+const JSON: &str = r#"{"users":[{"name":"alice","address":{"zip":"100-0001"}},{"name":"bob"}],"count":2}"#;
+
+#[test]
+fn test_json_round_trip() {
+    let out = ton(&["from-json"], JSON.as_bytes());
+    assert!(out.status.success());
+    let value: Value = from_slice(&out.stdout).unwrap();
+    assert_eq!(value.pointer("/users/1/name").and_then(Value::as_str), Some("bob"));
+
+    let out = ton(&["to-json"], &out.stdout);
+    assert!(out.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(json, serde_json::from_str::<serde_json::Value>(JSON).unwrap());
+}
+
+#[test]
+fn test_get() {
+    let ton_bytes = ton(&["from-json"], JSON.as_bytes()).stdout;
+
+    let out = ton(&["get", ".users[0].address.zip", "-"], &ton_bytes);
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8(out.stdout).unwrap(), "\"100-0001\"\n");
+
+    let out = ton(&["get", ".users[2]"], &ton_bytes);
+    assert!(!out.status.success());
+    assert!(String::from_utf8(out.stderr).unwrap().contains(".users[2] not found"));
+
+    // 表は Object の Array として引ける
+    let rows: Vec<_> = (0..3).map(|i| std::collections::BTreeMap::from([("name", format!("row{}", i))])).collect();
+    let table = serde_ton::ser::to_vec_with(&rows, &serde_ton::ser::SerializeOptions::new().columnar()).unwrap();
+    let out = ton(&["get", ".[2].name", "-"], &table);
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8(out.stdout).unwrap(), "\"row2\"\n");
+}
+
+#[test]
+fn test_text_and_cbor_round_trip() {
+    let bytes = to_vec(&(1u8, "two", vec![3.5f64])).unwrap();
+    let original: Value = from_slice(&bytes).unwrap();
+
+    let text = ton(&["to-text"], &bytes).stdout;
+    let back = ton(&["from-text"], &text).stdout;
+    assert_eq!(back, bytes);
+
+    let cbor = ton(&["to-cbor"], &bytes).stdout;
+    let back = ton(&["from-cbor"], &cbor).stdout;
+    let decoded: Value = from_slice(&back).unwrap();
+    // CBOR は数値の幅を保持しないので値だけ比べる
+    assert_eq!(decoded[0].as_u64(), original[0].as_u64());
+    assert_eq!(decoded[1], original[1]);
+    assert!(decoded[2].get(0).is_some());
+}
+
+#[test]
+fn test_validate_dump_stats() {
+    let bytes = to_vec(&vec!["a", "b"]).unwrap();
+
+    let out = ton(&["validate"], &bytes);
+    assert!(out.status.success());
+
+    let out = ton(&["validate"], &[0xff, 0xff]);
+    assert!(!out.status.success());
+
+    let out = ton(&["dump"], &bytes);
+    let dump = String::from_utf8(out.stdout).unwrap();
+    assert!(dump.contains("$array 2 items"));
+    assert!(dump.contains("\"b\""));
+
+    let out = ton(&["stats"], &bytes);
+    let stats = String::from_utf8(out.stdout).unwrap();
+    assert!(stats.contains("values:      3"));
+}
+
+#[test]
+fn test_recover() {
+    let mut bytes = to_vec(&vec!["first", "second"]).unwrap();
+    let keep = bytes.len();
+    bytes.extend(to_vec(&"tail").unwrap());
+    // 2 つ目の値の head を潰す
+    let last = bytes.len() - 1;
+    bytes[last] = 0xff;
+
+    let path = std::env::temp_dir().join(format!("serde_ton_cli_recover_{}.ton", std::process::id()));
+    let out = ton(&["recover", "-o", path.to_str().unwrap()], &bytes);
+    assert!(out.status.success());
+    let report = String::from_utf8(out.stdout).unwrap();
+    assert!(report.contains("damaged"));
+    assert!(report.contains("value") && report.contains("$array"));
+
+    let recovered: Value = from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let first: Value = from_slice(&bytes[..keep]).unwrap();
+    assert_eq!(recovered.get(0), Some(&first));
+}
@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use serde_ton::de::{from_slice, from_slice_columns};
+use serde_ton::ser::{to_vec, to_vec_with, value_to_vec, ReverseSerializer, SerializeOptions};
+use serde_ton::traits::ser::ExtendSerialize;
+use serde_ton::value::prefix::prefix;
+use serde_ton::value::value::Value;
+use serde_ton::{validate, Columnar, TonRef};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Record {
+    id: u64,
+    name: String,
+    score: f64,
+    active: bool,
+    tags: Vec<String>,
+}
+
+fn records(n: u64) -> Vec<Record> {
+    (0..n)
+        .map(|i| Record {
+            id: 1000 + i,
+            name: format!("user{}", i),
+            score: i as f64 * 0.25,
+            active: i % 3 == 0,
+            tags: (0..i % 3).map(|t| format!("t{}", t)).collect(),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Export {
+    source: String,
+    rows: Columnar<Vec<Record>>,
+}
+
+#[test]
+fn test_columnar_round_trip() {
+    let rows = records(100);
+    let bytes = to_vec_with(&rows, &SerializeOptions::new().columnar()).unwrap();
+    validate(&bytes).unwrap();
+    assert_eq!(*bytes.last().unwrap() & !0b11, prefix::TABLE);
+    assert!(bytes.len() * 2 < to_vec(&rows).unwrap().len());
+    assert_eq!(from_slice::<Vec<Record>>(&bytes).unwrap(), rows);
+
+    // Value では Object の Array と同じ
+    let value: Value = from_slice(&bytes).unwrap();
+    assert_eq!(value, from_slice::<Value>(&to_vec(&rows).unwrap()).unwrap());
+
+    // 列ごとに読める
+    let table = TonRef::new(&bytes).unwrap();
+    assert_eq!(table.type_name(), "$table");
+    assert_eq!(table.count().unwrap(), 100);
+    assert_eq!(table.column_names().unwrap(), vec!["id", "name", "score", "active", "tags"]);
+    let ids = table.column("id").unwrap().unwrap();
+    assert_eq!(ids.type_name(), "$typed_array");
+    assert_eq!(ids.to_slice::<u64>().unwrap().as_ref(), rows.iter().map(|r| r.id).collect::<Vec<_>>().as_slice());
+    assert_eq!(table.column("name").unwrap().unwrap().type_name(), "$array");
+    assert_eq!(table.column("active").unwrap().unwrap().count().unwrap(), 100);
+    assert!(table.column("missing").unwrap().is_none());
+}
+
+#[test]
+fn test_columnar_wrapper() {
+    let export = Export { source: "db".to_string(), rows: Columnar(records(10)) };
+    let bytes = to_vec(&export).unwrap();
+    validate(&bytes).unwrap();
+    let rows = TonRef::new(&bytes).unwrap().get("rows").unwrap().unwrap();
+    assert!(rows.is_table());
+    assert_eq!(from_slice::<Export>(&bytes).unwrap(), export);
+
+    // 内側のシーケンス (tags) は表にしない
+    let nested = TonRef::new(&bytes).unwrap().pointer("/rows").unwrap().unwrap().column("tags").unwrap().unwrap();
+    assert_eq!(nested.type_name(), "$array");
+
+    // 他のフォーマットでは中身と同じ
+    let json = serde_json::to_value(&export).unwrap();
+    assert_eq!(json["rows"][1]["name"], "user1");
+    assert_eq!(serde_json::from_value::<Export>(json).unwrap(), export);
+}
+
+#[test]
+fn test_table_navigation() {
+    let export = Export { source: "db".to_string(), rows: Columnar(records(10)) };
+    let mut bytes = to_vec(&export).unwrap();
+    let root = TonRef::new(&bytes).unwrap();
+
+    // 行の番号と列の名前で列の要素を指す
+    assert_eq!(root.pointer("/rows/2/name").unwrap().unwrap().as_str(), Some("user2"));
+    assert_eq!(root.get("rows").unwrap().unwrap().cell(3, "name").unwrap().unwrap().as_str(), Some("user3"));
+    assert!(root.pointer("/rows/10/name").unwrap().is_none());
+    assert!(root.pointer("/rows/2/missing").unwrap().is_none());
+
+    // 行と型付き配列の列の要素は値として書かれていない
+    let err = root.pointer("/rows/2").unwrap_err();
+    assert!(err.to_string().contains("table row is not an encoded value"));
+    assert!(root.get("rows").unwrap().unwrap().index(2).is_err());
+    let err = root.pointer("/rows/2/id").unwrap_err();
+    assert!(err.to_string().contains("$typed_array column \"id\" is not an encoded value"));
+
+    // RAW EDIT も同じ規則で辿る
+    serde_ton::edit_in_place(&mut bytes, "/rows/2/name", "USER2").unwrap();
+    validate(&bytes).unwrap();
+    assert_eq!(from_slice::<Export>(&bytes).unwrap().rows[2].name, "USER2");
+    assert!(serde_ton::edit_in_place(&mut bytes, "/rows/2/id", &7u64).unwrap_err().to_string().contains("cannot be navigated"));
+    let err = serde_ton::RawEditor::new(&bytes).set("/rows/2/name", "x").unwrap().apply().unwrap_err();
+    assert!(err.to_string().contains("table row is not an encoded value"));
+}
+
+#[test]
+fn test_columnar_fallback() {
+    // 表にできないシーケンスは通常の Array
+    assert_eq!(to_vec_with(&vec![1u8, 2], &SerializeOptions::new().columnar()).unwrap(), to_vec(&vec![1u8, 2]).unwrap());
+    assert_eq!(to_vec_with(&Vec::<Record>::new(), &SerializeOptions::new().columnar()).unwrap(), to_vec(&Vec::<Record>::new()).unwrap());
+    assert_eq!(to_vec(&Columnar(vec![vec![1u8]])).unwrap(), to_vec(&vec![vec![1u8]]).unwrap());
+
+    // 途中で key が変わる場合は、それまでの行を Object として書く
+    let text = r#"[{"a": 1u8, "b": "x"}, {"a": 2u8, "b": "y"}, {"a": 3u8, "c": "z"}]"#;
+    let mixed: Value = serde_ton::text::from_str(text).unwrap();
+    let mut ser = ReverseSerializer::with_options(Vec::new(), &SerializeOptions::new().columnar());
+    mixed.ex_serialize(&mut ser).unwrap();
+    let bytes = ser.into_inner();
+    assert_eq!(TonRef::new(&bytes).unwrap().type_name(), "$array");
+    assert_eq!(bytes, value_to_vec(&mixed).unwrap());
+    assert_eq!(from_slice::<Value>(&bytes).unwrap(), mixed);
+
+    // 型付き配列にできるシーケンスは型付き配列のまま
+    let packed = SerializeOptions::new().packed_arrays();
+    assert_eq!(to_vec_with(&Columnar(vec![1u32, 2]), &packed).unwrap(), to_vec_with(&vec![1u32, 2], &packed).unwrap());
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Projection {
+    id: u64,
+    score: f64,
+}
+
+#[test]
+fn test_column_projection() {
+    let rows = records(20);
+    let mut bytes = to_vec_with(&rows, &SerializeOptions::new().columnar()).unwrap();
+    let expected: Vec<Projection> = rows.iter().map(|r| Projection { id: r.id, score: r.score }).collect();
+    assert_eq!(from_slice_columns::<Vec<Projection>>(&bytes, &["id", "score"]).unwrap(), expected);
+    assert_eq!(from_slice::<Vec<Projection>>(&bytes).unwrap(), expected);
+
+    // 選ばなかった列は中身を読まない
+    let names = TonRef::new(&bytes).unwrap().column("name").unwrap().unwrap();
+    let first = names.index(0).unwrap().unwrap().range().start;
+    bytes[first] = 0xff;
+    assert!(from_slice::<Vec<Record>>(&bytes).is_err());
+    assert!(validate(&bytes).is_err());
+    assert_eq!(from_slice_columns::<Vec<Projection>>(&bytes, &["id", "score"]).unwrap(), expected);
+
+    // 選ばなかったフィールドは missing field
+    assert!(from_slice_columns::<Vec<Projection>>(&bytes, &["id"]).is_err());
+}
+
+#[derive(Debug, Deserialize)]
+struct Narrow {
+    #[allow(dead_code)]
+    id: u8,
+}
+
+#[test]
+fn test_columnar_errors() {
+    // 値のエラーには行と列が付く
+    let rows = vec![serde_json::json!({"id": 1}), serde_json::json!({"id": 300})];
+    let bytes = to_vec_with(&rows, &SerializeOptions::new().columnar()).unwrap();
+    let err = from_slice::<Vec<Narrow>>(&bytes).unwrap_err();
+    assert_eq!(err.path().as_deref(), Some("[1].id"));
+
+    // 行数と列の長さが合わない
+    let mut bytes = to_vec_with(&records(4), &SerializeOptions::new().columnar()).unwrap();
+    let rows = TonRef::new(&bytes).unwrap().children_rev().next().unwrap().unwrap().range();
+    bytes[rows.start] = 5;
+    assert!(validate(&bytes).unwrap_err().to_string().contains("values for 5 rows"));
+    assert!(from_slice::<Vec<Record>>(&bytes).unwrap_err().to_string().contains("values for 5 rows"));
+}
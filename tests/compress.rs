@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+use serde_ton::de::{from_slice, ReverseDeserializer};
+use serde_ton::error::Error;
+use serde_ton::ser::{to_vec, to_vec_with, ReverseSerializer, SerializeOptions};
+use serde_ton::traits::ser::ExtendSerialize;
+use serde_ton::value::value::Value;
+use serde_ton::{validate, Codec, Compression, Lz, TonRef};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Document {
+    id: u64,
+    title: String,
+    body: String,
+    attachment: Blob,
+}
+
+/// Bytes として書く
+#[derive(Debug, Clone, PartialEq)]
+struct Blob(Vec<u8>);
+
+impl Serialize for Blob {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Blob {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BlobVisitor;
+        impl serde::de::Visitor<'_> for BlobVisitor {
+            type Value = Blob;
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("bytes")
+            }
+            fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Blob, E> {
+                Ok(Blob(v))
+            }
+        }
+        deserializer.deserialize_byte_buf(BlobVisitor)
+    }
+}
+
+fn document() -> Document {
+    let body = (0..200).map(|i| format!("line {}: the quick brown fox jumps over the lazy dog\n", i % 7)).collect();
+    Document { id: 7, title: "report".to_string(), body, attachment: Blob((0..4096u32).map(|i| (i % 16) as u8).collect()) }
+}
+
+/// 決まった種から作る圧縮の効かないバイト列
+fn noise(len: usize) -> Vec<u8> {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+#[test]
+fn test_lz_round_trip() {
+    let mut long_distance = noise(100_000);
+    long_distance.extend_from_within(..1000);
+    let inputs = [
+        Vec::new(),
+        b"abc".to_vec(),
+        vec![b'a'; 10_000],
+        b"abcabcabcabcabcabcabcabcabcabc".repeat(50),
+        noise(5000),
+        long_distance,
+    ];
+    for input in inputs {
+        let compressed = Lz.compress(&input);
+        assert_eq!(Lz.decompress(&compressed, input.len()).unwrap(), input);
+        // 長さが違えばエラー
+        assert!(Lz.decompress(&compressed, input.len() + 1).is_err());
+    }
+    assert!(Lz.compress(&vec![0u8; 10_000]).len() < 100);
+}
+
+#[test]
+fn test_compressed_round_trip() {
+    let doc = document();
+    let bytes = to_vec_with(&doc, &SerializeOptions::new().compression(Compression::new(256))).unwrap();
+    validate(&bytes).unwrap();
+    assert!(bytes.len() * 4 < to_vec(&doc).unwrap().len());
+    assert_eq!(from_slice::<Document>(&bytes).unwrap(), doc);
+
+    // 閾値より小さい body と key は圧縮しない
+    let root = TonRef::new(&bytes).unwrap();
+    assert_eq!(root.get("title").unwrap().unwrap().as_str(), Some("report"));
+    let body = root.get("body").unwrap().unwrap();
+    assert!(body.is_compressed());
+    assert_eq!(body.as_str(), None);
+    assert_eq!(body.to_str().unwrap(), doc.body);
+    assert_eq!(root.get("attachment").unwrap().unwrap().to_bytes().unwrap(), doc.attachment.0);
+    assert_eq!(root.get("attachment").unwrap().unwrap().to_slice::<u8>().unwrap(), doc.attachment.0);
+    assert!(body.to_bytes().unwrap_err().to_string().contains("expected $bytes"));
+
+    // 圧縮が効かない body と閾値より小さい文書はそのまま
+    let random = Blob(noise(2000));
+    assert_eq!(to_vec_with(&random, &SerializeOptions::new().compression(Compression::default())).unwrap(), to_vec(&random).unwrap());
+    assert_eq!(to_vec_with(&doc, &SerializeOptions::new().compression(Compression::new(1 << 20))).unwrap(), to_vec(&doc).unwrap());
+
+    // Value の String, Bytes, WrappedJSON も展開した型に戻る
+    let json = serde_json::json!({"rows": vec!["repeated json content"; 100]});
+    let value = Value::Array(vec![
+        Value::String(doc.body.clone()),
+        Value::Bytes(doc.attachment.0.clone()),
+        Value::WrappedJSON(json.clone()),
+    ]);
+    let mut ser = ReverseSerializer::with_options(Vec::new(), &SerializeOptions::new().compression(Compression::new(64)));
+    value.ex_serialize(&mut ser).unwrap();
+    let bytes = ser.into_inner();
+    validate(&bytes).unwrap();
+    let root = TonRef::new(&bytes).unwrap();
+    assert!(root.children().unwrap().iter().all(|child| child.is_compressed()));
+    assert_eq!(from_slice::<Value>(&bytes).unwrap(), value);
+    assert_eq!(from_slice::<(String, Blob, serde_json::Value)>(&bytes).unwrap().2, json);
+}
+
+/// 同じバイトの連続を (長さ, バイト) で書く codec
+struct RunLength;
+
+impl Codec for RunLength {
+    fn id(&self) -> u8 {
+        200
+    }
+
+    fn compress(&self, input: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for chunk in input.chunk_by(|a, b| a == b) {
+            for part in chunk.chunks(255) {
+                out.extend_from_slice(&[part.len() as u8, part[0]]);
+            }
+        }
+        out
+    }
+
+    fn decompress(&self, input: &[u8], len: usize) -> Result<Vec<u8>, Error> {
+        if !input.len().is_multiple_of(2) {
+            return Err(serde::de::Error::custom("odd run-length data"));
+        }
+        let out: Vec<u8> = input.chunks(2).flat_map(|pair| std::iter::repeat_n(pair[1], pair[0] as usize)).collect();
+        if out.len() > len {
+            return Err(serde::de::Error::custom("run-length data is too long"));
+        }
+        Ok(out)
+    }
+}
+
+#[test]
+fn test_custom_codec() {
+    let text = "a".repeat(1000) + &"b".repeat(1000);
+    let bytes = to_vec_with(&text, &SerializeOptions::new().compression(Compression::with_codec(RunLength, 16))).unwrap();
+    assert!(bytes.len() < 40);
+    // 組み込み以外の codec は中身を検査しない
+    validate(&bytes).unwrap();
+
+    let err = from_slice::<String>(&bytes).unwrap_err();
+    assert!(err.to_string().contains("unknown codec 200"));
+    let mut de = ReverseDeserializer::from_slice(&bytes).unwrap();
+    de.add_codec(RunLength);
+    assert_eq!(String::deserialize(&mut de).unwrap(), text);
+}
+
+#[test]
+fn test_compressed_errors() {
+    let text = "compressible text ".repeat(100);
+    let bytes = to_vec_with(&text, &SerializeOptions::new().compression(Compression::default())).unwrap();
+    let root = TonRef::new(&bytes).unwrap();
+    let body = root.range().start..root.range().start + root.body_len();
+    let len_at = body.end - 10;
+
+    // 展開したバイト数が合わない
+    let mut broken = bytes.clone();
+    broken[len_at] += 1;
+    assert!(from_slice::<String>(&broken).unwrap_err().to_string().contains("instead of"));
+    assert!(validate(&broken).is_err());
+    assert!(TonRef::new(&broken).unwrap().to_str().is_err());
+
+    // 上限を超える展開したバイト数
+    let mut broken = bytes.clone();
+    broken[len_at..len_at + 8].copy_from_slice(&(1u64 << 40).to_le_bytes());
+    assert!(from_slice::<String>(&broken).unwrap_err().to_string().contains("expands beyond"));
+    assert!(validate(&broken).unwrap_err().to_string().contains("expands beyond"));
+
+    // 上限ちょうどの長さでも、データが足りなければエラーになる
+    let mut broken = bytes.clone();
+    broken[len_at..len_at + 8].copy_from_slice(&(256u64 << 20).to_le_bytes());
+    assert!(from_slice::<String>(&broken).unwrap_err().to_string().contains("instead of"));
+
+    // 知らない codec と展開した body に使えない型
+    let mut broken = bytes.clone();
+    broken[body.end - 2] = 7;
+    assert!(from_slice::<String>(&broken).unwrap_err().to_string().contains("unknown codec 7"));
+    let mut broken = bytes.clone();
+    broken[body.end - 1] = 0x2c;
+    assert!(validate(&broken).unwrap_err().to_string().contains("cannot hold head 0x2c"));
+
+    // データのどのバイトを壊してもパニックしない
+    for i in body.start..len_at {
+        let mut broken = bytes.clone();
+        broken[i] ^= 0x5a;
+        let _ = from_slice::<Value>(&broken);
+        let _ = validate(&broken);
+    }
+}
@@ -0,0 +1,403 @@
+use serde::{Deserialize, Serialize};
+use serde_ton::de::{from_slice, from_slice_partial, ArrayOrder, ReverseDeserializer};
+use serde_ton::ser::{to_vec, ReverseSerializer};
+use serde_ton::traits::ser::ExtendSerialize;
+use serde_ton::value::map::Map;
+use serde_ton::value::num::{Float, Int, UInt};
+use serde_ton::value::prefix::self_describe::TON_V1_REV_TAG;
+use serde_ton::value::prefix::{prefix, size_prefix};
+use serde_ton::value::value::{KeyValue, Value};
+use serde_ton::validate;
+
+use std::collections::HashMap;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct User {
+    id: u64,
+    name: String,
+    tags: Vec<String>,
+    score: Option<f64>,
+    kind: Kind,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Kind {
+    Guest,
+    Member(u32),
+    Pair(i8, String),
+    Admin { level: u8 },
+}
+
+fn round_trip<T>(value: &T) -> T
+where
+    T: Serialize + for<'de> Deserialize<'de>,
+{
+    let bytes = to_vec(value).unwrap();
+    from_slice(&bytes).unwrap()
+}
+
+fn value_to_vec(value: &Value) -> Vec<u8> {
+    let mut serializer = ReverseSerializer::new(Vec::new());
+    value.ex_serialize(&mut serializer).unwrap();
+    serializer.into_inner()
+}
+
+#[test]
+fn test_round_trip_primitives() {
+    assert!(round_trip(&true));
+    assert_eq!(round_trip(&-42i8), -42);
+    assert_eq!(round_trip(&-4242i16), -4242);
+    assert_eq!(round_trip(&i32::MIN), i32::MIN);
+    assert_eq!(round_trip(&i64::MAX), i64::MAX);
+    assert_eq!(round_trip(&u8::MAX), u8::MAX);
+    assert_eq!(round_trip(&u64::MAX), u64::MAX);
+    assert_eq!(round_trip(&1.5f32), 1.5);
+    assert_eq!(round_trip(&-0.25f64), -0.25);
+    assert_eq!(round_trip(&'R'), 'R');
+    assert_eq!(round_trip(&"hello".to_string()), "hello");
+    assert_eq!(round_trip(&()), ());
+    assert_eq!(round_trip(&Option::<u8>::None), None);
+    assert_eq!(round_trip(&Some(7u8)), Some(7));
+}
+
+#[test]
+fn test_round_trip_struct() {
+    let user = User {
+        id: 3,
+        name: "alice".to_string(),
+        tags: vec!["a".to_string(), "b".to_string()],
+        score: Some(0.5),
+        kind: Kind::Admin { level: 9 },
+    };
+    assert_eq!(round_trip(&user), user);
+}
+
+#[test]
+fn test_round_trip_enum() {
+    for kind in [
+        Kind::Guest,
+        Kind::Member(10),
+        Kind::Pair(-1, "x".to_string()),
+        Kind::Admin { level: 1 },
+    ] {
+        assert_eq!(round_trip(&kind), kind);
+    }
+}
+
+#[test]
+fn test_round_trip_collections() {
+    let vec = vec![vec![1u16, 2], vec![], vec![3]];
+    assert_eq!(round_trip(&vec), vec);
+
+    let mut map = HashMap::new();
+    map.insert("one".to_string(), 1i32);
+    map.insert("two".to_string(), 2i32);
+    assert_eq!(round_trip(&map), map);
+
+    let tuple = (1u8, "two".to_string(), 3.0f64);
+    assert_eq!(round_trip(&tuple), tuple);
+}
+
+#[test]
+fn test_self_describe_trailer_is_skipped() {
+    let mut bytes = to_vec(&12u32).unwrap();
+    bytes.extend_from_slice(&TON_V1_REV_TAG);
+    let value: u32 = from_slice(&bytes).unwrap();
+    assert_eq!(value, 12);
+}
+
+#[test]
+fn test_value_round_trip() {
+    let mut object = Map::new();
+    object.insert(KeyValue::String("name".to_string()), Value::String("ton".to_string()));
+    object.insert(KeyValue::UInt(UInt::U8(1)), Value::Bool(false));
+
+    let value = Value::Array(vec![
+        Value::Undefined,
+        Value::None,
+        Value::Int(Int::I16(-300)),
+        Value::UInt(UInt::U32(70000)),
+        Value::Float(Float::F16(half::f16::from_f32(1.5))),
+        Value::Float(Float::F32(2.5)),
+        Value::Float(Float::F64(-3.25)),
+        Value::Bytes(vec![0, 1, 2, 255]),
+        Value::UUID(uuid::Uuid::from_u128(0x1234_5678_9abc_def0_1234_5678_9abc_def0)),
+        Value::DateTime(chrono::DateTime::parse_from_rfc3339("2024-01-02T03:04:05+09:00").unwrap()),
+        Value::Timestamp(1_700_000_000),
+        Value::Duration(chrono::Duration::nanoseconds(1_500)),
+        Value::WrappedJSON(serde_json::json!({"a": [1, 2, null]})),
+        Value::Meta(Box::new(Value::String("meta".to_string()))),
+        Value::Object(object),
+    ]);
+
+    let bytes = value_to_vec(&value);
+    let decoded: Value = from_slice(&bytes).unwrap();
+    assert_eq!(decoded, value);
+
+    let mut deserializer = ReverseDeserializer::from_slice(&bytes).unwrap();
+    let decoded = Value::deserialize(&mut deserializer).unwrap();
+    assert_eq!(decoded, value);
+}
+
+#[test]
+fn test_meta_is_transparent_for_typed_values() {
+    let value = Value::Meta(Box::new(Value::UInt(UInt::U8(5))));
+    let bytes = value_to_vec(&value);
+    let decoded: u8 = from_slice(&bytes).unwrap();
+    assert_eq!(decoded, 5);
+}
+
+#[test]
+fn test_nesting_limit() {
+    // 深すぎるネストはスタックを使い切る前にエラーにする
+    fn nested(depth: usize, head: u8) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for _ in 0..depth {
+            let len = bytes.len() as u32;
+            bytes.extend_from_slice(&len.to_le_bytes());
+            bytes.push(head | size_prefix::SIZE_PREFIX_4BYTE);
+        }
+        bytes
+    }
+
+    // debug ビルドは 1 段のフレームが大きいので、上限まで潜れるスタックで読む
+    let deep = std::thread::Builder::new().stack_size(64 << 20);
+    deep.spawn(|| {
+        let err = from_slice::<Value>(&nested(100_000, prefix::ARRAY)).unwrap_err();
+        assert!(err.to_string().contains("nesting is deeper than 1024"));
+        assert!(from_slice::<Value>(&nested(100_000, prefix::META)).is_err());
+
+        // validate と同じ深さまで読める
+        let bytes = nested(1025, prefix::ARRAY);
+        assert!(validate(&bytes).is_ok());
+        assert!(from_slice::<Value>(&bytes).is_ok());
+        let bytes = nested(1026, prefix::ARRAY);
+        assert!(validate(&bytes).is_err());
+        assert!(from_slice::<Value>(&bytes).is_err());
+    })
+    .unwrap()
+    .join()
+    .unwrap();
+}
+
+#[test]
+fn test_truncated_input_is_error() {
+    let bytes = to_vec(&"hello".to_string()).unwrap();
+    assert!(from_slice::<String>(&bytes[1..]).is_err());
+    assert!(from_slice::<String>(&[]).is_err());
+}
+
+#[test]
+fn test_error_context() {
+    #[derive(Serialize)]
+    #[serde(untagged)]
+    enum RawZip {
+        Number(u32),
+        Text(&'static str),
+    }
+    #[derive(Serialize)]
+    struct RawAddress {
+        zip: RawZip,
+    }
+    #[derive(Serialize)]
+    struct RawUser {
+        name: String,
+        address: RawAddress,
+    }
+    #[derive(Serialize)]
+    struct RawDoc {
+        users: Vec<RawUser>,
+    }
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Address {
+        zip: u32,
+    }
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Doc {
+        users: Vec<UserAddress>,
+    }
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct UserAddress {
+        name: String,
+        address: Address,
+    }
+
+    let user = |zip| RawUser { name: "n".to_string(), address: RawAddress { zip } };
+    let users = vec![user(RawZip::Number(1)), user(RawZip::Number(2)), user(RawZip::Number(3)), user(RawZip::Text("x"))];
+    let bytes = to_vec(&RawDoc { users }).unwrap();
+    let err = from_slice::<Doc>(&bytes).unwrap_err();
+    assert!(err.is_type());
+    assert_eq!(err.path().as_deref(), Some(".users[3].address.zip"));
+    assert_eq!(err.expected(), Some("u32"));
+    assert_eq!(err.found(), Some("$string"));
+    // offset は値の head を指す
+    let offset = err.offset().unwrap();
+    assert_eq!(bytes[offset], 0x14);
+    assert!(offset < bytes.len() - 1);
+    assert!(err.to_string().contains("at .users[3].address.zip"));
+
+    // String 以外の key と識別子にならない key
+    let mut map = Map::new();
+    map.insert(KeyValue::String("a b".to_string()), Value::Bool(true));
+    let bytes = value_to_vec(&Value::Object(map));
+    let err = from_slice::<HashMap<String, u8>>(&bytes).unwrap_err();
+    assert_eq!(err.path().as_deref(), Some("[\"a b\"]"));
+
+    let mut map = Map::new();
+    map.insert(KeyValue::UInt(UInt::U8(7)), Value::Bool(true));
+    let bytes = value_to_vec(&Value::Object(map));
+    let err = from_slice::<HashMap<u8, u8>>(&bytes).unwrap_err();
+    assert_eq!(err.path().as_deref(), Some("[7u8]"));
+
+    // ルートの値はパスを持たない
+    let err = from_slice::<u8>(&to_vec("x").unwrap()).unwrap_err();
+    assert_eq!(err.path(), None);
+    assert_eq!(err.offset(), Some(2));
+}
+
+#[test]
+fn test_error_categories() {
+    use std::error::Error as _;
+
+    let bytes = to_vec(&"hello".to_string()).unwrap();
+    let err = from_slice::<String>(&bytes[1..]).unwrap_err();
+    assert!(err.is_eof(), "{}", err);
+    assert!(from_slice::<String>(&[]).unwrap_err().is_eof());
+
+    let err = from_slice::<Value>(&[0xff]).unwrap_err();
+    assert!(err.is_unknown_format());
+    assert_eq!(err.offset(), Some(0));
+
+    let err = from_slice::<Kind>(&to_vec(&1u8).unwrap()).unwrap_err();
+    assert_eq!((err.expected(), err.found()), (Some("enum"), Some("$uint")));
+
+    let io = std::io::Error::other("disk");
+    let err = serde_ton::error::Error::from(io);
+    assert!(err.is_io());
+    assert_eq!(err.source().unwrap().to_string(), "disk");
+    assert!(from_slice::<String>(&bytes[1..]).unwrap_err().source().is_none());
+}
+
+#[test]
+fn test_trailing_data() {
+    let mut bytes = vec![0x00, 0x01];
+    bytes.extend(to_vec(&"root").unwrap());
+    let err = from_slice::<String>(&bytes).unwrap_err();
+    assert!(err.is_trailing_data());
+    assert_eq!(err.offset(), Some(1));
+
+    // 使い終えた後に確かめる
+    let mut de = ReverseDeserializer::from_slice(&bytes).unwrap();
+    assert_eq!(String::deserialize(&mut de).unwrap(), "root");
+    assert!(de.end().unwrap_err().is_trailing_data());
+
+    // ルートの前の Padding は余分なデータとみなさない
+    let mut bytes = vec![0xaa, 0x01, 0x3c];
+    bytes.extend(to_vec(&1u8).unwrap());
+    bytes.extend_from_slice(&TON_V1_REV_TAG);
+    let mut de = ReverseDeserializer::from_slice(&bytes).unwrap();
+    assert_eq!(u8::deserialize(&mut de).unwrap(), 1);
+    assert!(de.finish().is_ok());
+}
+
+#[test]
+fn test_from_slice_partial() {
+    let mut bytes = to_vec(&1u8).unwrap();
+    bytes.extend(to_vec(&"two").unwrap());
+    bytes.extend(to_vec(&vec![3u16]).unwrap());
+
+    let (third, rest) = from_slice_partial::<Vec<u16>>(&bytes).unwrap();
+    assert_eq!(third, vec![3]);
+    let (second, rest) = from_slice_partial::<String>(&bytes[rest]).unwrap();
+    assert_eq!(second, "two");
+    let (first, rest) = from_slice_partial::<u8>(&bytes[rest]).unwrap();
+    assert_eq!(first, 1);
+    assert_eq!(rest, 0..0);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Row {
+    id: u32,
+    name: String,
+}
+
+fn rows(n: u32) -> Vec<Row> {
+    (0..n).map(|id| Row { id, name: format!("row-{}", id) }).collect()
+}
+
+#[test]
+fn test_iter_array() {
+    let values = rows(300);
+    let bytes = to_vec(&values).unwrap();
+
+    // 格納順は最後の要素から
+    let mut de = ReverseDeserializer::from_slice(&bytes).unwrap();
+    let ids: Vec<u32> = de.iter_array::<Row>().unwrap().map(|r| r.unwrap().id).collect();
+    assert_eq!(ids, (0..300).rev().collect::<Vec<_>>());
+    de.end().unwrap();
+
+    // 論理順は索引から要素の位置を読む
+    let indexed = serde_ton::ser::to_vec_with(&values, &serde_ton::ser::SerializeOptions::new().index(16)).unwrap();
+    let mut de = ReverseDeserializer::from_slice(&indexed).unwrap();
+    let mut iter = de.iter_array_ordered::<Row>(ArrayOrder::Forward).unwrap();
+    assert_eq!(iter.size_hint(), (300, Some(300)));
+    assert_eq!(iter.next().unwrap().unwrap(), values[0]);
+    // 読み飛ばした要素はデシリアライズしない
+    assert_eq!(iter.nth(98).unwrap().unwrap(), values[99]);
+    assert_eq!(iter.skip(150).map(|r| r.unwrap().id).collect::<Vec<_>>(), (250..300).collect::<Vec<_>>());
+    de.end().unwrap();
+
+    // 索引がなければ要素の位置を集めずにエラーにする
+    let mut de = ReverseDeserializer::from_slice(&bytes).unwrap();
+    let err = de.iter_array_ordered::<Row>(ArrayOrder::Forward).err().unwrap();
+    assert!(err.to_string().contains("container index"));
+
+    let mut de = ReverseDeserializer::from_slice(&bytes).unwrap();
+    assert_eq!(de.iter_array::<Row>().unwrap().nth(299).unwrap().unwrap(), values[0]);
+    let mut de = ReverseDeserializer::from_slice(&bytes).unwrap();
+    assert!(de.iter_array::<Row>().unwrap().nth(300).is_none());
+}
+
+#[test]
+fn test_iter_array_from_file() {
+    let values = rows(5000);
+    let path = std::env::temp_dir().join(format!("serde_ton_iter_array_{}.ton", std::process::id()));
+    std::fs::write(&path, to_vec(&values).unwrap()).unwrap();
+
+    let file = std::fs::File::open(&path).unwrap();
+    let mut de = ReverseDeserializer::from_file(file).unwrap();
+    let mut count = 0;
+    for (row, expected) in de.iter_array::<Row>().unwrap().zip(values.iter().rev()) {
+        assert_eq!(&row.unwrap(), expected);
+        count += 1;
+    }
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(count, 5000);
+}
+
+#[test]
+fn test_iter_array_errors() {
+    // 型が合わない要素があっても続きを読める
+    #[derive(Serialize)]
+    #[serde(untagged)]
+    enum Mixed {
+        Row(Row),
+        Text(&'static str),
+    }
+    let mixed = vec![Mixed::Row(Row { id: 0, name: "a".to_string() }), Mixed::Text("bad"), Mixed::Row(Row { id: 2, name: "c".to_string() })];
+    let bytes = serde_ton::ser::to_vec_with(&mixed, &serde_ton::ser::SerializeOptions::new().index(0)).unwrap();
+    let mut de = ReverseDeserializer::from_slice(&bytes).unwrap();
+    let results: Vec<_> = de.iter_array_ordered::<Row>(ArrayOrder::Forward).unwrap().collect();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[1].as_ref().unwrap_err().path().as_deref(), Some("[1]"));
+    assert_eq!(results[2].as_ref().unwrap().id, 2);
+
+    let bytes = to_vec(&"not an array").unwrap();
+    let mut de = ReverseDeserializer::from_slice(&bytes).unwrap();
+    let err = de.iter_array::<Row>().err().unwrap();
+    assert!(err.is_type());
+    assert_eq!(err.found(), Some("$string"));
+}
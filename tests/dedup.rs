@@ -0,0 +1,261 @@
+use serde::{Deserialize, Serialize};
+use serde_ton::de::{from_slice, ReverseDeserializer};
+use serde_ton::ser::{generate_header, to_vec, to_vec_with, ReverseSerializer, SerializeOptions};
+use serde_ton::traits::reader::IOReader;
+use serde_ton::traits::ser::ExtendSerialize;
+use serde_ton::value::prefix::{prefix, size_prefix};
+use serde_ton::value::value::Value;
+use serde_ton::{edit_in_place, validate, Columnar, Lazy, Packed, RawEditor, RawTon, RawTonRef, TonRef};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum Status {
+    Active,
+    Suspended,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Owner {
+    name: String,
+    email: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Event {
+    id: u64,
+    tenant: String,
+    status: Status,
+    tags: Vec<String>,
+    owner: Owner,
+}
+
+fn events(n: u64) -> Vec<Event> {
+    let tenants = ["tenant-alpha", "tenant-beta", "tenant-gamma"];
+    (0..n)
+        .map(|i| Event {
+            id: i,
+            tenant: tenants[i as usize % 3].to_string(),
+            status: if i % 4 == 0 { Status::Suspended } else { Status::Active },
+            tags: vec!["billing".to_string(), "audit".to_string()],
+            owner: Owner { name: format!("owner{}", i % 2), email: format!("owner{}@example.com", i % 2) },
+        })
+        .collect()
+}
+
+#[test]
+fn test_dedup_round_trip() {
+    let rows = events(100);
+    let bytes = to_vec_with(&rows, &SerializeOptions::new().dedup()).unwrap();
+    validate(&bytes).unwrap();
+    assert!(bytes.len() * 2 < to_vec(&rows).unwrap().len());
+    assert_eq!(from_slice::<Vec<Event>>(&bytes).unwrap(), rows);
+    let value: Value = from_slice(&bytes).unwrap();
+    assert_eq!(value, from_slice::<Value>(&to_vec(&rows).unwrap()).unwrap());
+
+    // 同じ部分木は参照になる
+    let last = TonRef::new(&bytes).unwrap().children_rev().next().unwrap().unwrap();
+    assert_eq!(last.type_name(), "$object");
+    let refs = last.children().unwrap().iter().filter(|child| child.type_name() == "$ref").count();
+    assert_eq!(refs, 4);
+
+    // Value も同じように書ける
+    let mut ser = ReverseSerializer::with_options(Vec::new(), &SerializeOptions::new().dedup());
+    value.ex_serialize(&mut ser).unwrap();
+    let bytes = ser.into_inner();
+    validate(&bytes).unwrap();
+    assert_eq!(from_slice::<Value>(&bytes).unwrap(), value);
+
+    // 値を組み立てずに参照先を辿れる
+    let root = TonRef::new(&bytes).unwrap();
+    assert_eq!(root.pointer("/40/tenant").unwrap().unwrap().as_str(), Some("tenant-beta"));
+    assert_eq!(root.pointer("/99/owner/email").unwrap().unwrap().as_str(), Some("owner1@example.com"));
+    assert_eq!(root.pointer("/98/tags/1").unwrap().unwrap().as_str(), Some("audit"));
+    assert_eq!(root.index(57).unwrap().unwrap().decode::<Event>().unwrap(), rows[57]);
+    assert_eq!(root.pointer("/57/owner").unwrap().unwrap().decode::<Owner>().unwrap(), rows[57].owner);
+}
+
+#[test]
+fn test_dedup_fallback() {
+    // 重複がなければ通常と同じ
+    let unique: Vec<String> = (0..50).map(|i| format!("value-{}", i)).collect();
+    assert_eq!(to_vec_with(&unique, &SerializeOptions::new().dedup()).unwrap(), to_vec(&unique).unwrap());
+    // 参照より短い値はそのまま
+    let small = vec![1u8; 20];
+    assert_eq!(to_vec_with(&small, &SerializeOptions::new().dedup()).unwrap(), to_vec(&small).unwrap());
+
+    // 型付き配列と表の中は参照にしない
+    let vectors = vec![Packed(vec![1.5f32; 8]); 3];
+    let bytes = to_vec_with(&vectors, &SerializeOptions::new().dedup()).unwrap();
+    assert_eq!(TonRef::new(&bytes).unwrap().index(0).unwrap().unwrap().type_name(), "$typed_array");
+    assert_eq!(TonRef::new(&bytes).unwrap().index(2).unwrap().unwrap().type_name(), "$typed_array");
+    assert_eq!(from_slice::<Vec<Packed<Vec<f32>>>>(&bytes).unwrap(), vectors);
+    let tables = vec![Columnar(events(5)), Columnar(events(5))];
+    let bytes = to_vec_with(&tables, &SerializeOptions::new().dedup()).unwrap();
+    validate(&bytes).unwrap();
+    assert_eq!(from_slice::<Vec<Columnar<Vec<Event>>>>(&bytes).unwrap(), tables);
+}
+
+/// REF を 1 つ書く (参照先は 2 バイトの位置)
+fn push_ref(doc: &mut Vec<u8>, target: usize) {
+    doc.extend_from_slice(&(target as u16).to_le_bytes());
+    doc.push(prefix::REF | size_prefix::SIZE_PREFIX_2BYTE);
+}
+
+/// 同じ値への参照を 2 つ持つ Array を書き、その head の位置を返す
+fn push_pair(doc: &mut Vec<u8>, target: usize) -> usize {
+    let start = doc.len();
+    push_ref(doc, target);
+    push_ref(doc, target);
+    let (header, header_size) = generate_header(prefix::ARRAY, (doc.len() - start) as u64);
+    doc.extend_from_slice(&header[..header_size]);
+    doc.len() - 1
+}
+
+/// 並べた値をルートの Array にする
+fn wrap(mut doc: Vec<u8>) -> Vec<u8> {
+    let (header, header_size) = generate_header(prefix::ARRAY, doc.len() as u64);
+    doc.extend_from_slice(&header[..header_size]);
+    doc
+}
+
+#[test]
+fn test_reference_limits() {
+    // 参照を重ねて 2^40 個の String に展開される文書
+    let mut doc = to_vec("payload").unwrap();
+    let mut target = doc.len() - 1;
+    for _ in 0..40 {
+        target = push_pair(&mut doc, target);
+    }
+    let doc = wrap(doc);
+    assert!(doc.len() < 400);
+    validate(&doc).unwrap();
+    let mut de = ReverseDeserializer::from_slice(&doc).unwrap();
+    de.set_reference_limit(1 << 16);
+    let err = Value::deserialize(&mut de).unwrap_err();
+    assert!(err.to_string().contains("references expand beyond 65536 bytes"));
+
+    // 上限の中なら展開する
+    let mut doc = to_vec("payload").unwrap();
+    let target = doc.len() - 1;
+    push_pair(&mut doc, target);
+    let doc = wrap(doc);
+    let value: (String, Vec<String>) = from_slice(&doc).unwrap();
+    assert_eq!(value.1, vec!["payload", "payload"]);
+    assert_eq!(TonRef::new(&doc).unwrap().pointer("/1/0").unwrap().unwrap().as_str(), Some("payload"));
+
+    // 後ろや値の途中を指す参照
+    let mut doc = Vec::new();
+    push_ref(&mut doc, 10);
+    assert!(validate(&doc).unwrap_err().to_string().contains("does not point before itself"));
+    assert!(from_slice::<Value>(&doc).unwrap_err().to_string().contains("does not point before itself"));
+    let mut doc = to_vec("payload").unwrap();
+    let inner = doc.len() - 4;
+    push_pair(&mut doc, inner);
+    assert!(validate(&wrap(doc)).unwrap_err().to_string().contains("does not point to a value"));
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Pair {
+    a: String,
+    b: String,
+    hits: u64,
+}
+
+#[test]
+fn test_dedup_edit() {
+    let pair = Pair { a: "shared value".to_string(), b: "shared value".to_string(), hits: 1 };
+    let mut bytes = to_vec_with(&pair, &SerializeOptions::new().dedup()).unwrap();
+    let root = TonRef::new(&bytes).unwrap();
+    assert_eq!(root.children().unwrap()[2].type_name(), "$ref");
+
+    // 共有している値はその場では書き換えない
+    let before = bytes.clone();
+    let err = edit_in_place(&mut bytes, "/b", "shared VALUE").unwrap_err();
+    assert!(err.to_string().contains("value is a back-reference"));
+    let err = edit_in_place(&mut bytes, "/a", "shared VALUE").unwrap_err();
+    assert!(err.to_string().contains("shared with the back-reference"));
+    assert_eq!(bytes, before);
+    edit_in_place(&mut bytes, "/hits", &2u64).unwrap();
+    let edited: Pair = from_slice(&bytes).unwrap();
+    assert_eq!(edited, Pair { hits: 2, ..pair.clone() });
+
+    // RawEditor は REF を展開するので、片方だけが変わる
+    let edited = RawEditor::new(&bytes).set("/a", "x").unwrap().apply().unwrap();
+    validate(&edited).unwrap();
+    assert_eq!(from_slice::<Pair>(&edited).unwrap(), Pair { a: "x".to_string(), hits: 2, ..pair.clone() });
+    let edited = RawEditor::new(&bytes).set("/b", "y").unwrap().apply().unwrap();
+    validate(&edited).unwrap();
+    assert_eq!(from_slice::<Pair>(&edited).unwrap(), Pair { b: "y".to_string(), hits: 2, ..pair.clone() });
+
+    // REF を通るパスの編集は展開した値だけを変える
+    let mut rows = events(20);
+    let bytes = to_vec_with(&rows, &SerializeOptions::new().dedup()).unwrap();
+    let entries = TonRef::new(&bytes).unwrap().index(3).unwrap().unwrap().entries().unwrap();
+    assert_eq!(entries[1].0.as_str(), Some("tenant"));
+    assert_eq!(entries[1].1.type_name(), "$ref");
+    let edited = RawEditor::new(&bytes)
+        .set("/3/tenant", "renamed")
+        .unwrap()
+        .set("/5/owner/name", "owner9")
+        .unwrap()
+        .delete("/0")
+        .unwrap()
+        .apply()
+        .unwrap();
+    validate(&edited).unwrap();
+    rows[3].tenant = "renamed".to_string();
+    rows[5].owner.name = "owner9".to_string();
+    rows.remove(0);
+    assert_eq!(from_slice::<Vec<Event>>(&edited).unwrap(), rows);
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CapturedEvent {
+    id: u64,
+    tenant: RawTon,
+    status: Status,
+    tags: Lazy<Vec<String>>,
+    owner: RawTon,
+}
+
+#[derive(Debug, Deserialize)]
+struct BorrowedEvent<'a> {
+    #[serde(borrow)]
+    owner: &'a RawTonRef,
+}
+
+#[test]
+fn test_dedup_raw_capture() {
+    let rows = events(10);
+    let bytes = to_vec_with(&rows, &SerializeOptions::new().dedup()).unwrap();
+
+    // 取り出した値の中の REF は参照先に展開する
+    let captured: Vec<CapturedEvent> = from_slice(&bytes).unwrap();
+    for (captured, row) in captured.iter().zip(&rows) {
+        assert_eq!(captured.tenant.decode::<String>().unwrap(), row.tenant);
+        assert_eq!(captured.owner.decode::<Owner>().unwrap(), row.owner);
+        assert_eq!(captured.tags.get().unwrap(), &row.tags);
+        assert!(!TonRef::new(captured.owner.as_bytes()).unwrap().children().unwrap().iter().any(|child| child.type_name() == "$ref"));
+    }
+    let whole: Vec<RawTon> = from_slice(&bytes).unwrap();
+    assert!(TonRef::new(&bytes).unwrap().index(9).unwrap().unwrap().children().unwrap().iter().any(|child| child.type_name() == "$ref"));
+    assert_eq!(whole[9].decode::<Event>().unwrap(), rows[9]);
+    // 別の文書に埋め込んでも読める
+    let spliced = to_vec(&captured).unwrap();
+    validate(&spliced).unwrap();
+    assert_eq!(from_slice::<Vec<Event>>(&spliced).unwrap(), rows);
+
+    // 借用できない reader からも同じ
+    let mut reader = std::io::Cursor::new(bytes.clone());
+    let mut de = ReverseDeserializer::new(IOReader::new(&mut reader)).unwrap();
+    let captured = Vec::<CapturedEvent>::deserialize(&mut de).unwrap();
+    assert_eq!(captured[7].owner.decode::<Owner>().unwrap(), rows[7].owner);
+
+    // REF を含む値は借用できない
+    let err = from_slice::<Vec<&RawTonRef>>(&bytes).unwrap_err();
+    assert!(err.to_string().contains("use RawTon"));
+    let err = from_slice::<Vec<BorrowedEvent>>(&bytes).unwrap_err();
+    assert!(err.to_string().contains("use RawTon"));
+    let bytes = to_vec_with(&rows[..1], &SerializeOptions::new().dedup()).unwrap();
+    let first = from_slice::<Vec<BorrowedEvent>>(&bytes).unwrap();
+    assert_eq!(first[0].owner.decode::<Owner>().unwrap(), rows[0].owner);
+}
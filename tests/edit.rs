@@ -0,0 +1,231 @@
+use serde::{Deserialize, Serialize};
+use serde_ton::de::from_slice;
+use serde_ton::ser::{to_vec, value_to_vec};
+use serde_ton::value::prefix::self_describe::TON_V1_REV_TAG;
+use serde_ton::value::prefix::{prefix, size_prefix};
+use serde_ton::value::value::Value;
+use serde_ton::{edit_file_in_place, edit_in_place, edit_value_in_place, validate, RawEditor};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Stats {
+    views: u64,
+    score: f32,
+    active: bool,
+    delta: i16,
+    name: String,
+    history: Vec<u32>,
+}
+
+fn stats() -> Stats {
+    Stats {
+        views: 10,
+        score: 0.5,
+        active: false,
+        delta: -3,
+        name: "page".to_string(),
+        history: vec![1, 2, 3],
+    }
+}
+
+#[test]
+fn test_edit_scalars() {
+    let mut bytes = to_vec(&stats()).unwrap();
+    let len = bytes.len();
+
+    let range = edit_in_place(&mut bytes, "/views", &11u64).unwrap();
+    assert_eq!(range.len(), 9);
+    edit_in_place(&mut bytes, "/score", &1.25f32).unwrap();
+    edit_in_place(&mut bytes, "/active", &true).unwrap();
+    edit_in_place(&mut bytes, "/delta", &7i16).unwrap();
+    edit_in_place(&mut bytes, "/history/1", &20u32).unwrap();
+
+    assert_eq!(bytes.len(), len);
+    validate(&bytes).unwrap();
+    let edited: Stats = from_slice(&bytes).unwrap();
+    assert_eq!(
+        edited,
+        Stats { views: 11, score: 1.25, active: true, delta: 7, history: vec![1, 20, 3], ..stats() }
+    );
+}
+
+#[test]
+fn test_edit_extended_scalars() {
+    let text = r#"{id: uuid("01234567-89ab-cdef-0123-456789abcdef"), at: timestamp(100), ttl: duration(5), tag: meta(1u8)}"#;
+    let value: Value = serde_ton::text::from_str(text).unwrap();
+    let mut bytes = value_to_vec(&value).unwrap();
+    bytes.extend_from_slice(&TON_V1_REV_TAG);
+
+    let new: Value = serde_ton::text::from_str(r#"[uuid("ffffffff-89ab-cdef-0123-456789abcdef"), timestamp(200), duration(6)]"#).unwrap();
+    edit_value_in_place(&mut bytes, "/id", &new[0]).unwrap();
+    edit_value_in_place(&mut bytes, "/at", &new[1]).unwrap();
+    edit_value_in_place(&mut bytes, "/ttl", &new[2]).unwrap();
+    // Meta の中の値を書き換える
+    edit_in_place(&mut bytes, "/tag", &2u8).unwrap();
+
+    let edited: Value = from_slice(&bytes).unwrap();
+    assert_eq!(edited["id"], new[0]);
+    assert_eq!(edited["at"], new[1]);
+    assert_eq!(edited["ttl"], new[2]);
+    assert!(edited.to_string().contains("meta(2u8)"));
+}
+
+#[test]
+fn test_edit_errors() {
+    let original = to_vec(&stats()).unwrap();
+    let mut bytes = original.clone();
+
+    // 幅が違う
+    let err = edit_in_place(&mut bytes, "/views", &11u32).unwrap_err();
+    assert!(err.is_size_mismatch());
+    assert!(err.to_string().contains("5 bytes"));
+    // 型が違う
+    let err = edit_in_place(&mut bytes, "/views", &11i64).unwrap_err();
+    assert_eq!(err.expected(), Some("$uint"));
+    assert_eq!(err.found(), Some("$int"));
+    // 空きのない可変長の値
+    let err = edit_in_place(&mut bytes, "/name", &"books").unwrap_err();
+    assert!(err.is_size_mismatch());
+    // 場所がない
+    let err = edit_in_place(&mut bytes, "/missing", &1u8).unwrap_err();
+    assert!(err.is_not_found());
+    assert!(err.to_string().contains("/missing"));
+
+    assert_eq!(bytes, original);
+}
+
+#[test]
+fn test_edit_file() {
+    let path = std::env::temp_dir().join(format!("serde_ton_edit_{}.ton", std::process::id()));
+    std::fs::write(&path, to_vec(&stats()).unwrap()).unwrap();
+
+    let mut file = std::fs::OpenOptions::new().read(true).write(true).open(&path).unwrap();
+    let range = edit_file_in_place(&mut file, "/views", &99u64).unwrap();
+    assert_eq!(range.end - range.start, 9);
+    assert!(edit_file_in_place(&mut file, "/views", &99u8).unwrap_err().is_size_mismatch());
+    drop(file);
+
+    let edited: Stats = from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(edited.views, 99);
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Page {
+    title: String,
+    body: String,
+    tags: Vec<String>,
+    stats: Stats,
+}
+
+fn page() -> Page {
+    Page {
+        title: "hello".to_string(),
+        body: "x".repeat(100_000),
+        tags: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+        stats: stats(),
+    }
+}
+
+#[test]
+fn test_raw_editor() {
+    let original = page();
+    let bytes = to_vec(&original).unwrap();
+
+    let mut editor = RawEditor::new(&bytes);
+    editor
+        .set("/title", "a much longer title")
+        .unwrap()
+        .set("/stats/name", "")
+        .unwrap()
+        .delete("/tags/0")
+        .unwrap()
+        .insert("/tags/2", "before c")
+        .unwrap()
+        .insert("/tags/-", "end")
+        .unwrap()
+        .set("/stats/history/1", &vec![9u8])
+        .unwrap()
+        .set("/stats/extra", &true)
+        .unwrap();
+    let edited = editor.apply().unwrap();
+    validate(&edited).unwrap();
+
+    let value: Value = from_slice(&edited).unwrap();
+    assert_eq!(value["title"].as_str(), Some("a much longer title"));
+    assert_eq!(value["body"].as_str().map(str::len), Some(100_000));
+    let tags: Vec<&str> = (0..4).map(|i| value["tags"][i].as_str().unwrap()).collect();
+    assert_eq!(tags, vec!["b", "before c", "c", "end"]);
+    assert_eq!(value["stats"]["name"].as_str(), Some(""));
+    assert_eq!(value["stats"]["history"][1][0].as_u64(), Some(9));
+    assert_eq!(value["stats"]["extra"], Value::Bool(true));
+    assert_eq!(value["stats"]["views"].as_u64(), Some(10));
+
+    // 編集していない部分木はバイト列がそのまま残る
+    let body = serde_ton::TonRef::new(&bytes).unwrap().get("body").unwrap().unwrap();
+    let new_body = serde_ton::TonRef::new(&edited).unwrap().get("body").unwrap().unwrap();
+    assert_eq!(body.raw(), new_body.raw());
+
+    // 編集がなければ元と同じ
+    assert_eq!(RawEditor::new(&bytes).apply().unwrap(), bytes);
+}
+
+#[test]
+fn test_raw_editor_keeps_meta_and_tag() {
+    let value: Value = serde_ton::text::from_str(r#"{a: meta({b: 1u8}), c: [1u8]}"#).unwrap();
+    let mut bytes = value_to_vec(&value).unwrap();
+    bytes.extend_from_slice(&TON_V1_REV_TAG);
+
+    let mut editor = RawEditor::new(&bytes);
+    editor.set("/a/b", &2u16).unwrap();
+    assert!(editor.set("", &0u8).is_err());
+    let edited = editor.apply().unwrap();
+    assert!(edited.ends_with(&TON_V1_REV_TAG));
+    let edited: Value = from_slice(&edited).unwrap();
+    let expected: Value = serde_ton::text::from_str(r#"{a: meta({b: 2u16}), c: [1u8]}"#).unwrap();
+    assert_eq!(edited, expected);
+
+    // ルートを丸ごと置き換える
+    let mut editor = RawEditor::new(&bytes);
+    editor.set("", "root").unwrap();
+    assert_eq!(from_slice::<String>(&editor.apply().unwrap()).unwrap(), "root");
+}
+
+#[test]
+fn test_raw_editor_errors() {
+    let bytes = to_vec(&page()).unwrap();
+
+    let mut editor = RawEditor::new(&bytes);
+    editor.set("/stats", &1u8).unwrap();
+    // 置き換える値の中は編集できない
+    assert!(editor.set("/stats/views", &1u8).is_err());
+    assert!(editor.delete("/stats").is_err());
+
+    let mut editor = RawEditor::new(&bytes);
+    editor.delete("/missing").unwrap();
+    assert!(editor.apply().unwrap_err().is_not_found());
+
+    let mut editor = RawEditor::new(&bytes);
+    editor.set("/tags/7", "x").unwrap();
+    assert!(editor.apply().unwrap_err().to_string().contains("/tags/7"));
+
+    let mut editor = RawEditor::new(&bytes);
+    editor.insert("/title", "again").unwrap();
+    assert!(editor.apply().unwrap_err().to_string().contains("already exists"));
+
+    let mut editor = RawEditor::new(&bytes);
+    editor.set("/title/deeper", &1u8).unwrap();
+    assert!(editor.apply().unwrap_err().is_not_found());
+}
+
+#[test]
+fn test_raw_editor_deep_nesting() {
+    // 100000 段の Array
+    let mut bytes = Vec::new();
+    for _ in 0..100_000 {
+        let len = bytes.len() as u64;
+        bytes.extend_from_slice(&len.to_le_bytes());
+        bytes.push(prefix::ARRAY | size_prefix::SIZE_PREFIX_8BYTE);
+    }
+    let edited = RawEditor::new(&bytes).set("/0", &1u8).unwrap().apply().unwrap();
+    assert_eq!(from_slice::<Vec<u8>>(&edited).unwrap(), vec![1]);
+}
@@ -0,0 +1,155 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_ton::{RecordId, TonFile};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Event {
+    seq: u64,
+    kind: String,
+    payload: Vec<u8>,
+}
+
+fn event(seq: u64) -> Event {
+    Event {
+        seq,
+        kind: format!("kind-{}", seq % 4),
+        payload: vec![seq as u8; (seq % 7) as usize],
+    }
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("serde_ton_file_{}_{}.ton", name, std::process::id()))
+}
+
+#[test]
+fn test_append_get_iter() {
+    let path = temp_path("basic");
+    let mut file = TonFile::create(&path).unwrap();
+    assert!(file.is_empty());
+    for seq in 0..100 {
+        assert_eq!(file.append(&event(seq)).unwrap(), RecordId(seq));
+    }
+    assert_eq!(file.len(), 100);
+    assert_eq!(file.get::<Event>(RecordId(42)).unwrap(), Some(event(42)));
+    assert_eq!(file.get::<Event>(RecordId(100)).unwrap(), None);
+
+    let forward: Vec<u64> = file.iter::<Event>().map(|e| e.unwrap().seq).collect();
+    assert_eq!(forward, (0..100).collect::<Vec<_>>());
+    let backward: Vec<u64> = file.iter_rev::<Event>().map(|e| e.unwrap().seq).collect();
+    assert_eq!(backward, (0..100).rev().collect::<Vec<_>>());
+    assert_eq!(file.iter::<Event>().nth(98).unwrap().unwrap(), event(98));
+
+    // 型が合わないレコードもエラーとして返して続きを読む
+    file.append(&"not an event").unwrap();
+    file.append(&event(101)).unwrap();
+    let results: Vec<_> = file.iter_rev::<Event>().take(3).collect();
+    assert_eq!(results[0].as_ref().unwrap().seq, 101);
+    assert!(results[1].as_ref().unwrap_err().is_type());
+    assert_eq!(results[2].as_ref().unwrap().seq, 99);
+    drop(file);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_reopen_with_footer() {
+    let path = temp_path("footer");
+    let mut file = TonFile::create(&path).unwrap();
+    for seq in 0..10 {
+        file.append(&event(seq)).unwrap();
+    }
+    file.sync().unwrap();
+    drop(file);
+
+    let mut file = TonFile::open(&path).unwrap();
+    assert_eq!(file.len(), 10);
+    assert_eq!(file.get::<Event>(RecordId(9)).unwrap(), Some(event(9)));
+
+    // footer の後ろに追記しても footer は上書きされる
+    file.append(&event(10)).unwrap();
+    file.sync().unwrap();
+    file.sync().unwrap();
+    drop(file);
+    let file = TonFile::open(&path).unwrap();
+    let seqs: Vec<u64> = file.iter::<Event>().map(|e| e.unwrap().seq).collect();
+    assert_eq!(seqs, (0..11).collect::<Vec<_>>());
+    drop(file);
+
+    // 空のファイルも開き直せる
+    let mut file = TonFile::create(&path).unwrap();
+    file.sync().unwrap();
+    drop(file);
+    assert!(TonFile::open(&path).unwrap().is_empty());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_recover_after_crash() {
+    let path = temp_path("crash");
+    let mut file = TonFile::create(&path).unwrap();
+    for seq in 0..20 {
+        file.append(&event(seq)).unwrap();
+    }
+    file.sync().unwrap();
+    for seq in 20..25 {
+        file.append(&event(seq)).unwrap();
+    }
+    drop(file);
+
+    // sync する前に落ちた: footer がないので trailer を辿って全て見つける
+    let file = TonFile::open(&path).unwrap();
+    assert_eq!(file.len(), 25);
+    drop(file);
+
+    // 最後のレコードを書いている途中で落ちた
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &bytes[..bytes.len() - 5]).unwrap();
+    let mut file = TonFile::open(&path).unwrap();
+    assert_eq!(file.len(), 24);
+    assert_eq!(file.get::<Event>(RecordId(23)).unwrap(), Some(event(23)));
+
+    // 切れたレコードは次の追記で上書きされる
+    assert_eq!(file.append(&event(99)).unwrap(), RecordId(24));
+    file.sync().unwrap();
+    drop(file);
+    let file = TonFile::open(&path).unwrap();
+    assert_eq!(file.len(), 25);
+    assert_eq!(file.iter_rev::<Event>().next().unwrap().unwrap(), event(99));
+    drop(file);
+
+    // footer を書いている途中で落ちた
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &bytes[..bytes.len() - 3]).unwrap();
+    assert_eq!(TonFile::open(&path).unwrap().len(), 25);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_broken_footer_offsets() {
+    let path = temp_path("offsets");
+    let mut file = TonFile::create(&path).unwrap();
+    for seq in 0..3 {
+        file.append(&event(seq)).unwrap();
+    }
+    file.sync().unwrap();
+    drop(file);
+
+    // footer の索引の位置が大きすぎる場合は、footer を使わずにレコードを辿り直す
+    let mut bytes = std::fs::read(&path).unwrap();
+    let second = bytes.len() - 12 - 16;
+    bytes[second..second + 8].copy_from_slice(&(u64::MAX - 5).to_le_bytes());
+    std::fs::write(&path, &bytes).unwrap();
+    let file = TonFile::open(&path).unwrap();
+    assert_eq!(file.len(), 3);
+    assert_eq!(file.get::<Event>(RecordId(2)).unwrap(), Some(event(2)));
+    drop(file);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_not_a_record_file() {
+    let path = temp_path("foreign");
+    std::fs::write(&path, serde_ton::ser::to_vec(&event(0)).unwrap()).unwrap();
+    assert!(TonFile::open(&path).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
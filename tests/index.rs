@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_ton::de::from_slice;
+use serde_ton::ser::{to_vec, to_vec_with, SerializeOptions};
+use serde_ton::value::value::Value;
+use serde_ton::{validate, TonRef};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Item {
+    id: u32,
+    name: String,
+    tags: Vec<String>,
+}
+
+fn items(n: u32) -> Vec<Item> {
+    (0..n)
+        .map(|id| Item {
+            id,
+            name: format!("item-{}", id),
+            tags: vec!["a".to_string(); (id % 3) as usize],
+        })
+        .collect()
+}
+
+#[test]
+fn test_indexed_array() {
+    let value = items(1000);
+    let plain = to_vec(&value).unwrap();
+    let indexed = to_vec_with(&value, &SerializeOptions::new().index(16)).unwrap();
+    assert!(indexed.len() > plain.len());
+
+    // 索引を知らない reader も同じ値を読める
+    assert_eq!(from_slice::<Vec<Item>>(&indexed).unwrap(), value);
+    assert_eq!(from_slice::<Value>(&indexed).unwrap(), from_slice::<Value>(&plain).unwrap());
+    assert!(validate(&indexed).unwrap().padding_bytes > 0);
+
+    let root = TonRef::new(&indexed).unwrap();
+    assert!(root.has_index());
+    assert!(!TonRef::new(&plain).unwrap().has_index());
+    assert_eq!(root.count().unwrap(), 1000);
+    for i in [0, 1, 499, 999] {
+        let item = root.index(i).unwrap().unwrap();
+        assert_eq!(item.decode::<Item>().unwrap(), value[i]);
+        assert_eq!(item.raw(), TonRef::new(&plain).unwrap().index(i).unwrap().unwrap().raw());
+    }
+    assert!(root.index(1000).unwrap().is_none());
+    // 子のうち小さいものは閾値に届かないので索引を持たない
+    assert!(!root.index(5).unwrap().unwrap().has_index());
+}
+
+#[test]
+fn test_indexed_object() {
+    let map: BTreeMap<String, u64> = (0..500u64).map(|i| (format!("key-{:04}", i), i * 10)).collect();
+    let bytes = to_vec_with(&map, &SerializeOptions::new().index(8)).unwrap();
+    assert_eq!(from_slice::<BTreeMap<String, u64>>(&bytes).unwrap(), map);
+    validate(&bytes).unwrap();
+
+    let root = TonRef::new(&bytes).unwrap();
+    assert!(root.has_index());
+    assert_eq!(root.count().unwrap(), 500);
+    assert_eq!(root.get("key-0000").unwrap().unwrap().as_u64().unwrap(), 0);
+    assert_eq!(root.get("key-0321").unwrap().unwrap().as_u64().unwrap(), 3210);
+    assert_eq!(root.get("key-0499").unwrap().unwrap().as_u64().unwrap(), 4990);
+    assert!(root.get("key-0500").unwrap().is_none());
+    assert!(root.get("").unwrap().is_none());
+    assert_eq!(root.keys().unwrap().len(), 500);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Wide {
+    zeta: u8,
+    alpha: u8,
+    mid: u8,
+    beta: String,
+}
+
+#[test]
+fn test_unsorted_struct_keys() {
+    // struct のフィールドは宣言順なので整列していないが、線形探索で引ける
+    let value = Wide { zeta: 1, alpha: 2, mid: 3, beta: "b".to_string() };
+    let bytes = to_vec_with(&value, &SerializeOptions::new().index(2)).unwrap();
+    assert_eq!(from_slice::<Wide>(&bytes).unwrap(), value);
+    validate(&bytes).unwrap();
+
+    let root = TonRef::new(&bytes).unwrap();
+    assert!(root.has_index());
+    assert_eq!(root.count().unwrap(), 4);
+    assert_eq!(root.get("alpha").unwrap().unwrap().as_u64().unwrap(), 2);
+    assert_eq!(root.get("zeta").unwrap().unwrap().as_u64().unwrap(), 1);
+    assert_eq!(root.get("beta").unwrap().unwrap().as_str().unwrap(), "b");
+    assert!(root.get("gamma").unwrap().is_none());
+}
+
+#[test]
+fn test_index_threshold() {
+    let bytes = to_vec_with(&vec![1u8; 15], &SerializeOptions::new().index(16)).unwrap();
+    assert_eq!(bytes, to_vec(&vec![1u8; 15]).unwrap());
+    let bytes = to_vec_with(&vec![1u8; 16], &SerializeOptions::new().index(16)).unwrap();
+    assert!(TonRef::new(&bytes).unwrap().has_index());
+}
+
+#[test]
+fn test_corrupt_index_is_rejected() {
+    let mut bytes = to_vec_with(&vec![7u32; 32], &SerializeOptions::new().index(4)).unwrap();
+    // 最初の offset を壊す (索引は body の最後、head の直前にある)
+    let root = TonRef::new(&bytes).unwrap();
+    let body_end = root.range().start + root.body_len();
+    let table_start = body_end - (32 * 4 + 14) - 2;
+    assert!(validate(&bytes).is_ok());
+    bytes[table_start] ^= 0x01;
+    let err = validate(&bytes).unwrap_err();
+    assert!(err.to_string().contains("container index"));
+}
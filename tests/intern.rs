@@ -0,0 +1,237 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_ton::de::{from_slice, from_slice_columns, from_slice_with_keys};
+use serde_ton::ser::{generate_header, to_vec, to_vec_with, ReverseSerializer, SerializeOptions};
+use serde_ton::traits::ser::ExtendSerialize;
+use serde_ton::value::prefix::{prefix, size_prefix};
+use serde_ton::value::value::Value;
+use serde_ton::{validate, Columnar, KeyDictionary, Lazy, RawTon, RawTonRef, TonRef};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Device {
+    serial: String,
+    location: Location,
+    channels: BTreeMap<String, Channel>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Location {
+    site: String,
+    rack: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Channel {
+    unit: String,
+    gain: f32,
+}
+
+/// 構造体のフィールド名と Map の key ("ch0" から "ch3") を何度も繰り返す
+fn devices(n: u16) -> Vec<Device> {
+    (0..n)
+        .map(|i| Device {
+            serial: format!("SN-{:04}", i),
+            location: Location { site: ["osaka", "kyoto"][i as usize % 2].to_string(), rack: i / 4 },
+            channels: (0..1 + i % 4)
+                .map(|c| (format!("ch{}", c), Channel { unit: "mV".to_string(), gain: 1.0 + c as f32 }))
+                .collect(),
+        })
+        .collect()
+}
+
+#[test]
+fn test_interned_round_trip() {
+    let rows = devices(40);
+    let bytes = to_vec_with(&rows, &SerializeOptions::new().interned_keys()).unwrap();
+    validate(&bytes).unwrap();
+    assert!(bytes.len() * 3 < to_vec(&rows).unwrap().len() * 2);
+    assert_eq!(from_slice::<Vec<Device>>(&bytes).unwrap(), rows);
+    let value: Value = from_slice(&bytes).unwrap();
+    assert_eq!(value, from_slice::<Value>(&to_vec(&rows).unwrap()).unwrap());
+
+    // Value の Object の key も番号で書ける
+    let mut ser = ReverseSerializer::with_options(Vec::new(), &SerializeOptions::new().interned_keys());
+    value.ex_serialize(&mut ser).unwrap();
+    ser.write_key_dictionary().unwrap();
+    let bytes = ser.into_inner();
+    validate(&bytes).unwrap();
+    assert_eq!(from_slice::<Value>(&bytes).unwrap(), value);
+
+    // 入れ子の Object と Map の key も番号になり、値を組み立てずに key で引ける
+    let root = TonRef::new(&bytes).unwrap();
+    let device = root.index(7).unwrap().unwrap();
+    let channels = device.get("channels").unwrap().unwrap();
+    for object in [device, device.get("location").unwrap().unwrap(), channels] {
+        assert!(object.keys().unwrap().iter().all(|key| key.type_name() == "$keyref"));
+    }
+    let names = channels.keys().unwrap().iter().map(|key| key.as_str().unwrap()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["ch0", "ch1", "ch2", "ch3"]);
+    assert_eq!(root.pointer("/7/channels/ch3/unit").unwrap().unwrap().as_str(), Some("mV"));
+    assert_eq!(root.pointer("/5/location/site").unwrap().unwrap().as_str(), Some("kyoto"));
+    assert!(channels.get("ch4").unwrap().is_none());
+    assert_eq!(device.decode::<Device>().unwrap(), rows[7]);
+}
+
+#[test]
+fn test_interned_distinct_keys() {
+    // 全ての key が違う Map は辞書に 1 回ずつ入る (256 個を超えると番号は 2 バイト)
+    let map: BTreeMap<String, u32> = (0..300).map(|i| (format!("key{:03}", i), i)).collect();
+    let bytes = to_vec_with(&map, &SerializeOptions::new().interned_keys()).unwrap();
+    validate(&bytes).unwrap();
+    assert_eq!(from_slice::<BTreeMap<String, u32>>(&bytes).unwrap(), map);
+    let root = TonRef::new(&bytes).unwrap();
+    let keys = root.keys().unwrap();
+    assert_eq!(keys.len(), 300);
+    assert!(keys.iter().all(|key| key.type_name() == "$keyref"));
+    assert_eq!(keys[299].range().len(), 3);
+    assert_eq!(root.get("key299").unwrap().unwrap().as_u64(), Some(299));
+
+    // 違う形の Object が混ざっても key は共有する
+    let mixed = serde_json::json!([
+        {"kind": "a", "left": 1},
+        {"kind": "b", "right": {"kind": "c", "left": 2}},
+        {"left": 3, "right": 4},
+    ]);
+    let bytes = to_vec_with(&mixed, &SerializeOptions::new().interned_keys()).unwrap();
+    validate(&bytes).unwrap();
+    assert_eq!(from_slice::<serde_json::Value>(&bytes).unwrap(), mixed);
+    assert_eq!(TonRef::new(&bytes).unwrap().pointer("/1/right/kind").unwrap().unwrap().as_str(), Some("c"));
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Site {
+    name: String,
+    racks: Columnar<Vec<Location>>,
+}
+
+#[test]
+fn test_interned_table() {
+    // 表の列の名前も番号になる
+    let site = Site {
+        name: "osaka".to_string(),
+        racks: Columnar((0..6).map(|rack| Location { site: format!("hall{}", rack % 2), rack }).collect()),
+    };
+    let bytes = to_vec_with(&site, &SerializeOptions::new().interned_keys()).unwrap();
+    validate(&bytes).unwrap();
+    assert_eq!(from_slice::<Site>(&bytes).unwrap(), site);
+    let table = TonRef::new(&bytes).unwrap().get("racks").unwrap().unwrap();
+    assert!(table.is_table());
+    assert_eq!(table.column_names().unwrap(), vec!["site", "rack"]);
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Rack {
+        rack: u16,
+    }
+    let bytes = to_vec_with(&site.racks, &SerializeOptions::new().interned_keys()).unwrap();
+    let expected: Vec<Rack> = site.racks.iter().map(|location| Rack { rack: location.rack }).collect();
+    assert_eq!(from_slice_columns::<Vec<Rack>>(&bytes, &["rack"]).unwrap(), expected);
+}
+
+#[test]
+fn test_key_dictionary() {
+    let dict = KeyDictionary::new(["serial", "location", "channels", "site", "rack"]);
+    assert_eq!(dict.position("channels"), Some(2));
+    assert_eq!(dict.get(4), Some("rack"));
+    let rows = devices(20);
+    let bytes = to_vec_with(&rows, &SerializeOptions::new().key_dictionary(&dict)).unwrap();
+    validate(&bytes).unwrap();
+    assert_eq!(from_slice_with_keys::<Vec<Device>>(&bytes, &dict).unwrap(), rows);
+
+    // 辞書にない key ("ch0" から "ch3", "unit", "gain") だけを文書に書く
+    let interned = to_vec_with(&rows, &SerializeOptions::new().interned_keys()).unwrap();
+    assert_eq!(interned.len() - bytes.len(), "seriallocationchannelssiterack".len() + 5 * 4);
+    // 文書に書いた key は外部の辞書なしで引ける
+    let channels = TonRef::new(&bytes).unwrap().index(3).unwrap().unwrap().entries().unwrap()[2].1;
+    assert_eq!(channels.pointer("/ch2/gain").unwrap().unwrap().as_f64(), Some(3.0));
+
+    // 同じ辞書は別の文書でも使える
+    let sites = vec![Location { site: "nara".to_string(), rack: 9 }];
+    let other_bytes = to_vec_with(&sites, &SerializeOptions::new().key_dictionary(&dict)).unwrap();
+    assert_eq!(from_slice_with_keys::<Vec<Location>>(&other_bytes, &dict).unwrap(), sites);
+
+    // 辞書を渡さない、違う辞書を渡す
+    let err = from_slice::<Vec<Device>>(&bytes).unwrap_err();
+    assert!(err.to_string().contains("needs an external key dictionary"));
+    let other = KeyDictionary::new(["serial", "channels", "location", "site", "rack"]);
+    let err = from_slice_with_keys::<Vec<Device>>(&bytes, &other).unwrap_err();
+    assert!(err.to_string().contains("does not match"));
+
+    // 外部の辞書を使わない文書は辞書を渡しても読める
+    assert_eq!(from_slice_with_keys::<Vec<Device>>(&interned, &other).unwrap(), rows);
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CapturedDevice {
+    serial: String,
+    location: RawTon,
+    channels: Lazy<BTreeMap<String, Channel>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BorrowedDevice<'a> {
+    #[serde(borrow)]
+    location: &'a RawTonRef,
+}
+
+#[test]
+fn test_interned_raw_capture() {
+    let rows = devices(8);
+    let bytes = to_vec_with(&rows, &SerializeOptions::new().interned_keys()).unwrap();
+
+    // 取り出した値の KEYREF は key の String に置き換える
+    let captured: Vec<CapturedDevice> = from_slice(&bytes).unwrap();
+    for (captured, row) in captured.iter().zip(&rows) {
+        let keys = captured.location.to_ton_ref().unwrap().keys().unwrap();
+        assert!(keys.iter().all(|key| key.type_name() == "$string"));
+        assert_eq!(captured.location.decode::<Location>().unwrap(), row.location);
+        assert_eq!(captured.channels.get().unwrap(), &row.channels);
+    }
+    let spliced = to_vec(&captured).unwrap();
+    validate(&spliced).unwrap();
+    assert_eq!(from_slice::<Vec<Device>>(&spliced).unwrap(), rows);
+
+    // 外部の辞書の key も置き換える
+    let dict = KeyDictionary::new(["serial", "location", "channels", "site", "rack"]);
+    let bytes = to_vec_with(&rows, &SerializeOptions::new().key_dictionary(&dict)).unwrap();
+    let whole: Vec<RawTon> = from_slice_with_keys(&bytes, &dict).unwrap();
+    assert_eq!(whole[5].decode::<Device>().unwrap(), rows[5]);
+    assert_eq!(whole[5].to_ton_ref().unwrap().pointer("/location/site").unwrap().unwrap().as_str(), Some("kyoto"));
+
+    // KEYREF を含む値は借用できない
+    let err = from_slice_with_keys::<Vec<BorrowedDevice>>(&bytes, &dict).unwrap_err();
+    assert!(err.to_string().contains("use RawTon"));
+    let plain = to_vec(&rows).unwrap();
+    let borrowed = from_slice::<Vec<BorrowedDevice>>(&plain).unwrap();
+    assert_eq!(borrowed[5].location.decode::<Location>().unwrap(), rows[5].location);
+}
+
+/// key を KEYREF で書いた 1 エントリの Object
+fn keyref_object(index: u8) -> Vec<u8> {
+    let mut body = vec![1u8, prefix::UINT | size_prefix::SIZE_PREFIX_1BYTE, index, prefix::KEYREF | size_prefix::SIZE_PREFIX_1BYTE];
+    let (header, header_size) = generate_header(prefix::OBJECT, body.len() as u64);
+    body.extend_from_slice(&header[..header_size]);
+    body
+}
+
+#[test]
+fn test_keyref_errors() {
+    // 辞書のない KEYREF
+    let bytes = keyref_object(0);
+    assert!(validate(&bytes).unwrap_err().to_string().contains("without key dictionary"));
+    assert!(from_slice::<Value>(&bytes).is_err());
+
+    // 辞書の範囲外
+    let mut bytes = to_vec_with(&serde_json::json!({"a": 1}), &SerializeOptions::new().interned_keys()).unwrap();
+    let key = TonRef::new(&bytes).unwrap().entries().unwrap()[0].0.range().start;
+    bytes[key] = 5;
+    assert!(validate(&bytes).unwrap_err().to_string().contains("key reference 5 is out of"));
+    assert!(from_slice::<Value>(&bytes).unwrap_err().to_string().contains("key reference 5 is out of"));
+
+    // key 以外には使えない
+    let mut bytes = to_vec_with(&serde_json::json!({"a": 1}), &SerializeOptions::new().interned_keys()).unwrap();
+    let value = TonRef::new(&bytes).unwrap().get("a").unwrap().unwrap().offset();
+    bytes[value] = prefix::KEYREF | size_prefix::SIZE_PREFIX_1BYTE;
+    bytes[value - 1] = 0;
+    assert!(validate(&bytes).unwrap_err().to_string().contains("can only be used as an object key"));
+}
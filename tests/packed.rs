@@ -0,0 +1,231 @@
+use serde::{Deserialize, Serialize};
+use serde_ton::de::from_slice;
+use serde_ton::ser::{generate_header, to_vec, to_vec_with, value_to_vec, ReverseSerializer, SerializeOptions};
+use serde_ton::traits::ser::ExtendSerialize;
+use serde_ton::value::prefix::{array_encoding, prefix, size_prefix};
+use serde_ton::value::value::Value;
+use serde_ton::{validate, ArrayEncoding, Packed, RawTon, TonRef};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Embedding {
+    id: u64,
+    name: String,
+    vector: Packed<Vec<f32>>,
+    flags: Packed<Vec<bool>>,
+}
+
+fn embedding() -> Embedding {
+    Embedding {
+        id: 1,
+        name: "doc".to_string(),
+        vector: Packed((0..100).map(|i| i as f32 / 4.0).collect()),
+        flags: Packed((0..11).map(|i| i % 3 == 0).collect()),
+    }
+}
+
+fn packed_value(value: &Value) -> Vec<u8> {
+    let mut ser = ReverseSerializer::with_options(Vec::new(), &SerializeOptions::new().packed_arrays());
+    value.ex_serialize(&mut ser).unwrap();
+    ser.into_inner()
+}
+
+#[test]
+fn test_packed_round_trip() {
+    let floats: Vec<f64> = (0..1000).map(|i| i as f64 * 1.5).collect();
+    let bytes = to_vec_with(&floats, &SerializeOptions::new().packed_arrays()).unwrap();
+    // 要素 8 バイト + 要素の head + データ長 2 バイト + head
+    assert_eq!(bytes.len(), 8000 + 1 + 3);
+    assert_eq!(*bytes.last().unwrap() & !0b11, prefix::TYPED_ARRAY);
+    assert!(to_vec(&floats).unwrap().len() > 9000);
+    assert_eq!(from_slice::<Vec<f64>>(&bytes).unwrap(), floats);
+
+    let ints: Vec<i16> = vec![-300, 0, 7, i16::MAX];
+    assert_eq!(from_slice::<Vec<i16>>(&to_vec_with(&ints, &SerializeOptions::new().packed_arrays()).unwrap()).unwrap(), ints);
+    let raw: Vec<u8> = (0..=255).collect();
+    assert_eq!(to_vec_with(&raw, &SerializeOptions::new().packed_arrays()).unwrap().len(), 256 + 1 + 3);
+    for len in [0, 1, 8, 9, 16] {
+        let bools: Vec<bool> = (0..len).map(|i| i % 2 == 1).collect();
+        let bytes = to_vec_with(&bools, &SerializeOptions::new().packed_arrays()).unwrap();
+        validate(&bytes).unwrap();
+        assert_eq!(from_slice::<Vec<bool>>(&bytes).unwrap(), bools);
+    }
+
+    // 通常の Array として書いたものと同じ値になる
+    let bytes = to_vec(&embedding()).unwrap();
+    validate(&bytes).unwrap();
+    assert_eq!(from_slice::<Embedding>(&bytes).unwrap(), embedding());
+    let value: Value = from_slice(&bytes).unwrap();
+    let plain: Value = from_slice(&to_vec(&(1u64, "doc", embedding().vector.0, embedding().flags.0)).unwrap()).unwrap();
+    assert_eq!(value["vector"], plain[2]);
+    assert_eq!(value["flags"], plain[3]);
+
+    // 他のフォーマットでは中身と同じ
+    let json = serde_json::to_value(embedding()).unwrap();
+    assert_eq!(json["flags"][3], serde_json::Value::Bool(true));
+    assert_eq!(serde_json::from_value::<Embedding>(json).unwrap(), embedding());
+}
+
+#[test]
+fn test_packed_fallback() {
+    // 詰められない要素があれば通常の Array になる
+    let strings = vec!["a", "b"];
+    assert_eq!(to_vec_with(&strings, &SerializeOptions::new().packed_arrays()).unwrap(), to_vec(&strings).unwrap());
+    let nested = vec![vec![1u8], vec![2u8, 3]];
+    assert_eq!(to_vec(&Packed(&nested)).unwrap(), to_vec(&nested).unwrap());
+    assert_eq!(to_vec(&Packed(5u8)).unwrap(), to_vec(&5u8).unwrap());
+    assert_eq!(to_vec_with(&Vec::<u32>::new(), &SerializeOptions::new().packed_arrays()).unwrap(), to_vec(&Vec::<u32>::new()).unwrap());
+
+    // 途中で型が変わる場合も、それまでの要素をそのまま書く
+    let text = r#"[1u8, 2u8, 3u16, "x", [4u8, 5u8]]"#;
+    let mixed: Value = serde_ton::text::from_str(text).unwrap();
+    let bytes = packed_value(&mixed);
+    let inner = TonRef::new(&bytes).unwrap().index(4).unwrap().unwrap();
+    assert_eq!(inner.type_name(), "$typed_array");
+    assert_eq!(from_slice::<Value>(&bytes).unwrap(), mixed);
+    assert!(bytes.len() < value_to_vec(&mixed).unwrap().len());
+}
+
+#[test]
+fn test_packed_extended_types() {
+    let text = r#"[[uuid("01234567-89ab-cdef-0123-456789abcdef"), uuid("ffffffff-89ab-cdef-0123-456789abcdef")], [timestamp(100), timestamp(-5)], [1.5f16, 2f16], [-1i64, 2i64]]"#;
+    let value: Value = serde_ton::text::from_str(text).unwrap();
+    let bytes = packed_value(&value);
+    validate(&bytes).unwrap();
+    let root = TonRef::new(&bytes).unwrap();
+    for i in 0..4 {
+        let child = root.index(i).unwrap().unwrap();
+        assert_eq!(child.type_name(), "$typed_array");
+        assert_eq!(child.count().unwrap(), 2);
+    }
+    // 幅や拡張型もそのまま戻る
+    assert_eq!(from_slice::<Value>(&bytes).unwrap(), value);
+    assert_eq!(root.index(3).unwrap().unwrap().to_slice::<i64>().unwrap().as_ref(), &[-1, 2]);
+
+    // 要素の型のままでも受け取れる
+    let (uuids, stamps, halves, ints): (Vec<String>, Vec<i64>, Vec<f32>, Vec<Option<i64>>) = from_slice(&bytes).unwrap();
+    assert_eq!(uuids[1], "ffffffff-89ab-cdef-0123-456789abcdef");
+    assert_eq!(stamps, vec![100, -5]);
+    assert_eq!(halves, vec![1.5, 2.0]);
+    assert_eq!(ints, vec![Some(-1), Some(2)]);
+    let (_, _, _, raws): (Value, Value, Value, Vec<RawTon>) = from_slice(&bytes).unwrap();
+    assert_eq!(raws[0].decode::<i64>().unwrap(), -1);
+    assert_eq!(raws[1].as_bytes(), to_vec(&2i64).unwrap());
+}
+
+#[test]
+fn test_packed_slices_and_errors() {
+    let vector: Vec<f32> = (0..64).map(|i| i as f32).collect();
+    let bytes = to_vec_with(&(7u8, Packed(&vector)), &SerializeOptions::new().alignment(16)).unwrap();
+    let array = TonRef::new(&bytes).unwrap().index(1).unwrap().unwrap();
+    assert_eq!(array.range().start % 16, 0);
+    assert_eq!(array.to_slice::<f32>().unwrap().as_ref(), vector.as_slice());
+    if (bytes.as_ptr() as usize).is_multiple_of(16) {
+        assert_eq!(array.as_slice::<f32>().unwrap(), vector.as_slice());
+    }
+    assert!(array.to_slice::<u32>().unwrap_err().to_string().contains("cannot be read as u32"));
+
+    // 要素の型が読めない
+    let mut broken = to_vec_with(&vec![1u32, 2], &SerializeOptions::new().packed_arrays()).unwrap();
+    let element = broken.len() - 3;
+    broken[element] = 0xff;
+    assert!(validate(&broken).is_err());
+    assert!(from_slice::<Vec<u32>>(&broken).is_err());
+
+    // 要素のエラーには添字が付く
+    let bytes = to_vec_with(&vec![1u16, 300], &SerializeOptions::new().packed_arrays()).unwrap();
+    let err = from_slice::<Vec<u8>>(&bytes).unwrap_err();
+    assert_eq!(err.path().as_deref(), Some("[1]"));
+}
+
+fn root_type(bytes: &[u8]) -> &'static str {
+    TonRef::new(bytes).unwrap().type_name()
+}
+
+#[test]
+fn test_encoded_arrays() {
+    // 一定間隔の Timestamp は差分の差分がほぼ 0 になる
+    let text = format!("[{}]", (0..1000).map(|i| format!("timestamp({})", 1_700_000_000_000i64 + i * 60_000)).collect::<Vec<_>>().join(", "));
+    let timestamps: Value = serde_ton::text::from_str(&text).unwrap();
+    let mut ser = ReverseSerializer::with_options(Vec::new(), &SerializeOptions::new().array_encoding(ArrayEncoding::Smallest));
+    timestamps.ex_serialize(&mut ser).unwrap();
+    let bytes = ser.into_inner();
+    assert_eq!(root_type(&bytes), "$encoded_array");
+    assert!(bytes.len() < 1100);
+    validate(&bytes).unwrap();
+    assert_eq!(from_slice::<Value>(&bytes).unwrap(), timestamps);
+    assert_eq!(TonRef::new(&bytes).unwrap().count().unwrap(), 1000);
+
+    // 少しずつ増えるカウンタ (途中で一周する)
+    let counters: Vec<u16> = (0..500).map(|i| 65_000u16.wrapping_add(i * 3)).collect();
+    let bytes = to_vec_with(&counters, &SerializeOptions::new().array_encoding(ArrayEncoding::Delta)).unwrap();
+    assert_eq!(root_type(&bytes), "$encoded_array");
+    assert!(bytes.len() < counters.len() + 16);
+    assert_eq!(from_slice::<Vec<u16>>(&bytes).unwrap(), counters);
+    let signed: Vec<i32> = (0..300).map(|i| if i % 2 == 0 { -i } else { i * 2 }).collect();
+    let bytes = to_vec_with(&signed, &SerializeOptions::new().array_encoding(ArrayEncoding::DeltaOfDelta)).unwrap();
+    assert_eq!(from_slice::<Vec<i32>>(&bytes).unwrap(), signed);
+
+    // 同じ値が続く列
+    let levels: Vec<f64> = (0..1000).map(|i| (i / 250) as f64 * 0.5).collect();
+    let bytes = to_vec_with(&levels, &SerializeOptions::new().array_encoding(ArrayEncoding::Smallest)).unwrap();
+    assert_eq!(root_type(&bytes), "$encoded_array");
+    assert!(bytes.len() < 64);
+    assert_eq!(from_slice::<Vec<f64>>(&bytes).unwrap(), levels);
+    let array = TonRef::new(&bytes).unwrap();
+    assert_eq!(array.to_slice::<f64>().unwrap().as_ref(), levels.as_slice());
+    assert!(array.as_slice::<f64>().is_err());
+}
+
+#[test]
+fn test_encoding_policy() {
+    // 小さくならなければ符号化しない
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let noise: Vec<u64> = (0..100)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        })
+        .collect();
+    let bytes = to_vec_with(&noise, &SerializeOptions::new().array_encoding(ArrayEncoding::Smallest)).unwrap();
+    assert_eq!(bytes, to_vec_with(&noise, &SerializeOptions::new().packed_arrays()).unwrap());
+    assert_eq!(from_slice::<Vec<u64>>(&bytes).unwrap(), noise);
+    // 差分は整数だけ、Bool は符号化しない
+    let floats = vec![1.0f32; 100];
+    assert_eq!(root_type(&to_vec_with(&floats, &SerializeOptions::new().array_encoding(ArrayEncoding::Delta)).unwrap()), "$typed_array");
+    let bools = vec![true; 100];
+    let packed = SerializeOptions::new().packed_arrays();
+    assert_eq!(to_vec_with(&bools, &SerializeOptions::new().array_encoding(ArrayEncoding::Smallest)).unwrap(), to_vec_with(&bools, &packed).unwrap());
+    let same = vec![7u32; 100];
+    assert_eq!(to_vec_with(&same, &SerializeOptions::new().array_encoding(ArrayEncoding::Plain)).unwrap(), to_vec_with(&same, &packed).unwrap());
+}
+
+#[test]
+fn test_encoded_array_limits() {
+    // 1 バイトの値を巨大な回数繰り返すランレングス
+    let mut body = vec![1u8, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f];
+    body.extend_from_slice(&[array_encoding::RUN_LENGTH, prefix::UINT | size_prefix::SIZE_PREFIX_1BYTE]);
+    let (header, header_size) = generate_header(prefix::ENCODED_ARRAY, body.len() as u64);
+    body.extend_from_slice(&header[..header_size]);
+    assert!(validate(&body).unwrap_err().to_string().contains("expands beyond"));
+    assert!(from_slice::<Vec<u8>>(&body).is_err());
+
+    // 1 回だけの run の後に 2^61 - 1 回の run が続くと長さの合計が溢れる
+    let mut body = 1u64.to_le_bytes().to_vec();
+    body.push(1);
+    body.extend_from_slice(&2u64.to_le_bytes());
+    body.extend_from_slice(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x1f]);
+    body.extend_from_slice(&[array_encoding::RUN_LENGTH, prefix::UINT | size_prefix::SIZE_PREFIX_8BYTE]);
+    let (header, header_size) = generate_header(prefix::ENCODED_ARRAY, body.len() as u64);
+    body.extend_from_slice(&header[..header_size]);
+    assert_eq!(body.len(), 30);
+    assert!(validate(&body).unwrap_err().to_string().contains("expands beyond"));
+    assert!(from_slice::<Vec<u64>>(&body).unwrap_err().to_string().contains("expands beyond"));
+
+    // 途中で切れた varint
+    let mut body = vec![0x80u8, array_encoding::DELTA, prefix::INT | size_prefix::SIZE_PREFIX_4BYTE];
+    let (header, header_size) = generate_header(prefix::ENCODED_ARRAY, body.len() as u64);
+    body.extend_from_slice(&header[..header_size]);
+    assert!(validate(&body).unwrap_err().to_string().contains("broken varint"));
+}
@@ -0,0 +1,288 @@
+use serde::{Deserialize, Serialize};
+use serde_ton::de::from_slice;
+use serde_ton::ser::{generate_header, to_vec, value_to_vec};
+use serde_ton::value::prefix::{prefix, prefix_str};
+use serde_ton::value::value::Value;
+use serde_ton::{edit_in_place, to_raw_ton, Lazy, RawTon, RawTonRef, TonRef};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Address {
+    zip: String,
+    city: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct User {
+    id: u64,
+    name: String,
+    tags: Vec<String>,
+    address: Address,
+    score: f32,
+    active: bool,
+    delta: i16,
+}
+
+fn sample() -> Vec<u8> {
+    let user = |id: u64| User {
+        id,
+        name: format!("user-{}", id),
+        tags: vec!["a".to_string(), format!("t{}", id)],
+        address: Address { zip: format!("{:03}", id), city: "tokyo".to_string() },
+        score: 0.5,
+        active: id.is_multiple_of(2),
+        delta: -(id as i16),
+    };
+    let mut doc = std::collections::BTreeMap::new();
+    doc.insert("users", (0..5).map(user).collect::<Vec<_>>());
+    to_vec(&doc).unwrap()
+}
+
+#[test]
+fn test_navigate_without_decoding() {
+    let bytes = sample();
+    let root = TonRef::new(&bytes).unwrap();
+    assert!(root.is_object());
+    assert_eq!(root.type_name(), prefix_str::OBJECT);
+    assert_eq!(root.range(), 0..bytes.len());
+    assert_eq!(root.count().unwrap(), 1);
+
+    let users = root.get("users").unwrap().unwrap();
+    assert_eq!(users.count().unwrap(), 5);
+    let user = users.index(3).unwrap().unwrap();
+    let keys: Vec<_> = user.keys().unwrap().iter().map(|k| k.as_str().unwrap()).collect();
+    assert_eq!(keys, ["id", "name", "tags", "address", "score", "active", "delta"]);
+
+    assert_eq!(user.get("id").unwrap().unwrap().as_u64(), Some(3));
+    assert_eq!(user.get("name").unwrap().unwrap().as_str(), Some("user-3"));
+    assert_eq!(user.get("score").unwrap().unwrap().as_f64(), Some(0.5));
+    assert_eq!(user.get("active").unwrap().unwrap().as_bool(), Some(false));
+    assert_eq!(user.get("delta").unwrap().unwrap().as_i64(), Some(-3));
+    assert!(user.get("missing").unwrap().is_none());
+    assert!(users.index(5).unwrap().is_none());
+    assert!(user.index(0).unwrap().is_none());
+
+    let tag = root.pointer("/users/4/tags/1").unwrap().unwrap();
+    assert_eq!(tag.as_str(), Some("t4"));
+    assert_eq!(tag.prefix(), prefix::STRING);
+    assert!(root.pointer("/users/x").unwrap().is_none());
+    assert_eq!(root.pointer("").unwrap(), Some(root));
+
+    // 葉だけをデシリアライズする
+    let address: Address = root.pointer("/users/2/address").unwrap().unwrap().decode().unwrap();
+    assert_eq!(address, Address { zip: "002".to_string(), city: "tokyo".to_string() });
+    let user: User = users.index(1).unwrap().unwrap().decode().unwrap();
+    assert_eq!(user.id, 1);
+
+    // head の位置から辿り直せる
+    let again = TonRef::at(&bytes, tag.offset()).unwrap();
+    assert_eq!(again, tag);
+    assert_eq!(again.raw(), &to_vec("t4").unwrap()[..]);
+}
+
+#[test]
+fn test_meta_and_errors() {
+    let value: Value = serde_ton::text::from_str(r#"meta({a: meta([1u8, "x"])})"#).unwrap();
+    let bytes = value_to_vec(&value).unwrap();
+    let root = TonRef::new(&bytes).unwrap();
+    assert_eq!(root.prefix(), prefix::META);
+    assert_eq!(root.pointer("/a/1").unwrap().unwrap().as_str(), Some("x"));
+    assert_eq!(root.get("a").unwrap().unwrap().index(0).unwrap().unwrap().as_u64(), Some(1));
+    assert_eq!(root.to_value().unwrap(), value);
+
+    // 子が親の body からはみ出している
+    let sized = |prefix: u8, body: &[u8]| {
+        let (header, size) = generate_header(prefix, body.len() as u64);
+        let mut out = body.to_vec();
+        out.extend_from_slice(&header[..size]);
+        out
+    };
+    let string = sized(prefix::STRING, b"abc");
+    let mut body = to_vec("xy").unwrap();
+    body.extend(sized(prefix::ARRAY, &string[2..]));
+    let broken = sized(prefix::ARRAY, &body);
+    let inner = TonRef::new(&broken).unwrap().index(1).unwrap().unwrap();
+    assert!(inner.count().unwrap_err().to_string().contains("overflows"));
+
+    assert!(TonRef::new(&[]).unwrap_err().is_eof());
+    assert!(TonRef::new(&[0xff]).unwrap_err().is_unknown_format());
+
+    // decode のエラー位置は元のバッファの位置
+    let bytes = sample();
+    let root = TonRef::new(&bytes).unwrap();
+    let name = root.pointer("/users/0/name").unwrap().unwrap();
+    let err = name.decode::<u32>().unwrap_err();
+    assert_eq!(err.offset(), Some(name.offset()));
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope<'a> {
+    id: u32,
+    #[serde(borrow)]
+    body: &'a RawTonRef,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OwnedEnvelope {
+    id: u32,
+    body: RawTon,
+    tail: String,
+}
+
+#[test]
+fn test_pointer_array_index() {
+    // Array の添字は RFC 6901 と同じく 0 か先頭に 0 の付かない数字だけ
+    let value = vec![vec![10u8, 11], vec![20, 21]];
+    let bytes = to_vec(&value).unwrap();
+    let mut edited = bytes.clone();
+    let decoded = from_slice::<Value>(&bytes).unwrap();
+    let root = TonRef::new(&bytes).unwrap();
+    assert_eq!(root.pointer("/1/0").unwrap().unwrap().as_u64(), Some(20));
+    assert_eq!(decoded.pointer("/1/0").and_then(Value::as_u64), Some(20));
+    for token in ["01", "+1", "-1", " 1", "1 ", "1.0", ""] {
+        let pointer = format!("/{}/0", token);
+        assert!(root.pointer(&pointer).unwrap().is_none(), "{:?}", token);
+        assert!(decoded.pointer(&pointer).is_none(), "{:?}", token);
+        assert!(edit_in_place(&mut edited, &pointer, &30u8).unwrap_err().is_not_found(), "{:?}", token);
+    }
+    edit_in_place(&mut edited, "/0/0", &30u8).unwrap();
+    assert_eq!(from_slice::<Vec<Vec<u8>>>(&edited).unwrap(), vec![vec![30, 11], vec![20, 21]]);
+}
+
+#[test]
+fn test_raw_ton_capture_and_splice() {
+    let address = Address { zip: "100".to_string(), city: "tokyo".to_string() };
+    let raw = to_raw_ton(&address).unwrap();
+    assert_eq!(raw.as_bytes(), &to_vec(&address).unwrap()[..]);
+
+    // 書き込むとそのまま埋め込まれ、親のサイズにも数えられる
+    let doc = OwnedEnvelope { id: 7, body: raw.clone(), tail: "end".to_string() };
+    let bytes = to_vec(&doc).unwrap();
+    assert!(serde_ton::validate(&bytes).is_ok());
+    #[derive(Deserialize)]
+    struct Decoded {
+        id: u32,
+        body: Address,
+        tail: String,
+    }
+    let decoded: Decoded = serde_ton::de::from_slice(&bytes).unwrap();
+    assert_eq!((decoded.id, &decoded.body, decoded.tail.as_str()), (7, &address, "end"));
+
+    // 読むとデコードせずにバイト列を受け取る
+    let envelope: Envelope = serde_ton::de::from_slice(&bytes).unwrap();
+    assert_eq!(envelope.id, 7);
+    assert_eq!(envelope.body.as_bytes(), raw.as_bytes());
+    assert_eq!(envelope.body.decode::<Address>().unwrap(), address);
+    let reencoded = to_vec(&envelope).unwrap();
+    assert_eq!(serde_ton::de::from_slice::<Envelope>(&reencoded).unwrap().body, &*raw);
+
+    // ファイルなど借用できない reader からは所有版で受け取る
+    let mut reader = std::io::Cursor::new(bytes.clone());
+    let mut de = serde_ton::de::ReverseDeserializer::new(serde_ton::traits::reader::IOReader::new(&mut reader)).unwrap();
+    let owned = OwnedEnvelope::deserialize(&mut de).unwrap();
+    assert_eq!(owned.body, raw);
+    let mut de = serde_ton::de::ReverseDeserializer::new(serde_ton::traits::reader::IOReader::new(&mut reader)).unwrap();
+    assert!(Envelope::deserialize(&mut de).is_err());
+
+    // TON Text では Value を経由する (key は Map の順になる)
+    let text = serde_ton::text::to_string(&doc).unwrap();
+    assert_eq!(text, r#"{"id":7u32,"body":{"city":"tokyo","zip":"100"},"tail":"end"}"#);
+    let parsed: OwnedEnvelope = serde_ton::text::from_str(&text).unwrap();
+    assert_eq!(parsed.body.decode::<Address>().unwrap(), address);
+
+    // TonRef から借用する
+    let root = TonRef::new(&bytes).unwrap();
+    assert_eq!(root.get("body").unwrap().unwrap().as_raw_ton(), &*raw);
+
+    // 受け取るバイト列も from_slice と同じく検査する
+    let city = root.get("body").unwrap().unwrap().get("city").unwrap().unwrap().range().start;
+    let mut broken = bytes.clone();
+    broken[city] = 0xff;
+    let err = serde_ton::de::from_slice::<Envelope>(&broken).unwrap_err();
+    assert!(err.to_string().contains("UTF-8"));
+    assert!(serde_ton::de::from_slice::<OwnedEnvelope>(&broken).is_err());
+
+    assert!(RawTonRef::from_slice(&[0xff]).is_err());
+    let mut tagged = raw.as_bytes().to_vec();
+    tagged.extend_from_slice(&serde_ton::value::prefix::self_describe::TON_V1_REV_TAG);
+    assert!(RawTon::from_vec(tagged).is_err());
+}
+
+#[test]
+fn test_lazy() {
+    #[derive(Serialize, Deserialize)]
+    struct Doc {
+        id: u32,
+        user: Lazy<User>,
+    }
+    let user = User {
+        id: 1,
+        name: "lazy".to_string(),
+        tags: vec![],
+        address: Address { zip: "1".to_string(), city: "c".to_string() },
+        score: 1.0,
+        active: true,
+        delta: 0,
+    };
+    let bytes = to_vec(&Doc { id: 1, user: Lazy::new(user).unwrap() }).unwrap();
+    let doc: Doc = serde_ton::de::from_slice(&bytes).unwrap();
+    assert!(!doc.user.is_decoded());
+    assert_eq!(doc.user.get().unwrap().name, "lazy");
+    assert!(doc.user.is_decoded());
+    // デコードしても書き込むバイト列は変わらない
+    assert_eq!(to_vec(&doc).unwrap(), bytes);
+    assert_eq!(doc.user.into_inner().unwrap().id, 1);
+
+    let broken: Lazy<u8> = Lazy::from_raw(to_raw_ton("x").unwrap());
+    assert!(broken.get().is_err());
+}
+
+/// 数値を詰めた Bytes として書く
+struct Packed(Vec<u8>);
+
+impl Serialize for Packed {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+#[test]
+fn test_aligned_slices() {
+    let floats: Vec<f32> = (0..64).map(|i| i as f32 * 0.5).collect();
+    let words: Vec<u64> = (0..9).map(|i| i * 1000).collect();
+    let doc = (
+        "embedding",
+        Packed(floats.iter().flat_map(|v| v.to_le_bytes()).collect()),
+        7u8,
+        Packed(words.iter().flat_map(|v| v.to_le_bytes()).collect()),
+    );
+    let bytes = serde_ton::ser::to_vec_with(&doc, &serde_ton::ser::SerializeOptions::new().alignment(16)).unwrap();
+    serde_ton::validate(&bytes).unwrap();
+    // Padding を読み飛ばすので揃えていない文書と同じ値になる
+    let plain: Value = serde_ton::de::from_slice(&to_vec(&doc).unwrap()).unwrap();
+    assert_eq!(serde_ton::de::from_slice::<Value>(&bytes).unwrap(), plain);
+
+    let root = TonRef::new(&bytes).unwrap();
+    let vector = root.index(1).unwrap().unwrap();
+    let table = root.index(3).unwrap().unwrap();
+    // 文書の先頭から 16 バイトの境界で始まる
+    assert_eq!(vector.range().start % 16, 0);
+    assert_eq!(table.range().start % 16, 0);
+    assert_eq!(vector.to_slice::<f32>().unwrap().as_ref(), floats.as_slice());
+    assert_eq!(table.to_slice::<u64>().unwrap().as_ref(), words.as_slice());
+    if (bytes.as_ptr() as usize).is_multiple_of(16) {
+        assert_eq!(vector.as_slice::<f32>().unwrap(), floats.as_slice());
+        assert_eq!(table.as_slice::<u64>().unwrap(), words.as_slice());
+
+        // ずれたバッファでは借用できないのでコピーする
+        let mut shifted = vec![0u8];
+        shifted.extend_from_slice(&bytes);
+        let vector = TonRef::new(&shifted[1..]).unwrap().index(1).unwrap().unwrap();
+        assert!(vector.as_slice::<f32>().unwrap_err().to_string().contains("not aligned"));
+        assert!(matches!(vector.to_slice::<f32>().unwrap(), std::borrow::Cow::Owned(_)));
+        assert_eq!(vector.to_slice::<f32>().unwrap().as_ref(), floats.as_slice());
+    }
+
+    // 型や長さが合わない
+    assert_eq!(root.index(0).unwrap().unwrap().as_slice::<u8>().unwrap_err().expected(), Some("$bytes"));
+    assert!(table.to_slice::<u128>().unwrap_err().to_string().contains("not a multiple"));
+}
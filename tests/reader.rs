@@ -0,0 +1,109 @@
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+use serde::Deserialize;
+use serde_ton::de::ReverseDeserializer;
+use serde_ton::ser::to_vec;
+use serde_ton::traits::reader::{PositionalReader, Reader, ReverseBufReader, SliceReader};
+
+fn sample() -> Vec<u8> {
+    (0..200u32).map(|i| (i * 7 % 251) as u8).collect()
+}
+
+#[test]
+fn test_reverse_buf_reader_matches_slice_reader() {
+    let data = sample();
+    let mut expected = SliceReader::new(&data);
+    let mut reader = ReverseBufReader::with_capacity(16, Cursor::new(data.clone())).unwrap();
+    assert_eq!(reader.seek(SeekFrom::End(0)).unwrap(), expected.seek(SeekFrom::End(0)).unwrap());
+
+    // 後ろから 1 バイトずつ
+    for _ in 0..data.len() {
+        assert_eq!(reader.prev().unwrap(), expected.prev().unwrap());
+    }
+    assert_eq!(reader.prev().unwrap(), None);
+
+    // ブロック境界をまたぐ読み込み
+    for end in [200u64, 150, 33, 17, 16, 8] {
+        reader.seek(SeekFrom::Start(end)).unwrap();
+        expected.seek(SeekFrom::Start(end)).unwrap();
+        assert_eq!(reader.prev_u64().unwrap(), expected.prev_u64().unwrap());
+        assert_eq!(reader.stream_position().unwrap(), end - 8);
+        assert_eq!(reader.peek().unwrap(), expected.peek().unwrap());
+        assert_eq!(reader.read_u32().unwrap(), expected.read_u32().unwrap());
+    }
+
+    // バッファより大きい読み込み
+    let mut large = [0u8; 40];
+    reader.seek(SeekFrom::Start(100)).unwrap();
+    reader.read_prev(&mut large).unwrap();
+    assert_eq!(&large[..], &data[60..100]);
+    reader.read_exact(&mut large).unwrap();
+    assert_eq!(&large[..], &data[60..100]);
+
+    reader.seek(SeekFrom::Start(4)).unwrap();
+    assert!(reader.read_prev(&mut [0u8; 8]).is_err());
+    assert!(reader.seek(SeekFrom::Current(-5)).is_err());
+    reader.seek(SeekFrom::End(0)).unwrap();
+    assert_eq!(reader.read(&mut large).unwrap(), 0);
+}
+
+#[test]
+fn test_from_file_is_buffered() {
+    let values: Vec<String> = (0..2000).map(|i| format!("value-{}", i)).collect();
+    let bytes = to_vec(&values).unwrap();
+    let path = std::env::temp_dir().join(format!("serde_ton_reader_{}.ton", std::process::id()));
+    std::fs::write(&path, &bytes).unwrap();
+
+    let file = std::fs::File::open(&path).unwrap();
+    let mut de = ReverseDeserializer::from_file(file).unwrap();
+    let decoded = Vec::<String>::deserialize(&mut de).unwrap();
+    de.end().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(decoded, values);
+}
+
+#[test]
+fn test_positional_reader_shared_between_threads() {
+    // 1 つのファイルに文書を 3 つ並べる
+    let docs: Vec<Vec<String>> = (0..3).map(|d| (0..50).map(|i| format!("{}-{}", d, i)).collect()).collect();
+    let mut bytes = Vec::new();
+    let mut ranges = Vec::new();
+    for doc in &docs {
+        let start = bytes.len() as u64;
+        bytes.extend(to_vec(doc).unwrap());
+        ranges.push(start..bytes.len() as u64);
+    }
+    let path = std::env::temp_dir().join(format!("serde_ton_pread_{}.ton", std::process::id()));
+    std::fs::write(&path, &bytes).unwrap();
+    let file = std::fs::File::open(&path).unwrap();
+
+    std::thread::scope(|scope| {
+        for (doc, range) in docs.iter().zip(&ranges) {
+            let file = &file;
+            scope.spawn(move || {
+                for _ in 0..10 {
+                    let mut de = ReverseDeserializer::from_shared_file(file, range.clone()).unwrap();
+                    assert_eq!(&Vec::<String>::deserialize(&mut de).unwrap(), doc);
+                    de.end().unwrap();
+                }
+            });
+        }
+    });
+
+    // バッファなしでも読める
+    let mut reader = PositionalReader::with_range(&file, ranges[1].clone());
+    let mut de = ReverseDeserializer::new(&mut reader).unwrap();
+    assert_eq!(Vec::<String>::deserialize(&mut de).unwrap(), docs[1]);
+    assert_eq!(reader.stream_position().unwrap(), 0);
+    // ファイル自体のシーク位置は動かない
+    assert_eq!((&file).stream_position().unwrap(), 0);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_boxed_reader() {
+    let bytes = to_vec(&(1u8, "two".to_string())).unwrap();
+    let reader: Box<dyn Reader + '_> = Box::new(SliceReader::new(&bytes));
+    let mut de = ReverseDeserializer::new(reader).unwrap();
+    assert_eq!(<(u8, String)>::deserialize(&mut de).unwrap(), (1, "two".to_string()));
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use serde_ton::recover;
+use serde_ton::ser::to_vec;
+use serde_ton::value::prefix::self_describe::TON_V1_REV_TAG;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Doc {
+    id: u32,
+    title: String,
+    lines: Vec<String>,
+}
+
+fn doc(id: u32) -> Doc {
+    Doc {
+        id,
+        title: format!("document {}", id),
+        lines: (0..5).map(|i| format!("line {} of {}", i, id)).collect(),
+    }
+}
+
+fn tagged(id: u32) -> Vec<u8> {
+    let mut bytes = to_vec(&doc(id)).unwrap();
+    bytes.extend_from_slice(&TON_V1_REV_TAG);
+    bytes
+}
+
+#[test]
+fn test_clean_input() {
+    let bytes = to_vec(&doc(1)).unwrap();
+    let report = recover(&bytes);
+    assert!(report.is_clean());
+    assert_eq!(report.values.len(), 1);
+    assert_eq!(report.values[0].range, 0..bytes.len());
+    assert!(!report.values[0].self_describe);
+    assert_eq!(report.values[0].value.decode::<Doc>().unwrap(), doc(1));
+
+    let bytes = tagged(1);
+    let report = recover(&bytes);
+    assert_eq!(report.values.len(), 1);
+    assert_eq!(report.values[0].range, 0..bytes.len());
+    assert!(report.values[0].self_describe);
+
+    assert!(recover(&[]).values.is_empty());
+}
+
+#[test]
+fn test_overwritten_document() {
+    let mut bytes = Vec::new();
+    for id in 0..3 {
+        bytes.extend(tagged(id));
+    }
+    let second = tagged(1).len();
+    let first = tagged(0).len();
+    // 2 つ目の文書の末尾 (ルートの head と tag) を潰す
+    let broken_end = first + second;
+    bytes[broken_end - 8..broken_end].fill(0xff);
+
+    let report = recover(&bytes);
+    assert!(!report.is_clean());
+    // 潰した範囲の直前にあったルートのサイズなども読めなくなる
+    assert_eq!(report.damaged.len(), 1);
+    assert_eq!(report.damaged[0].end, broken_end);
+    assert!(report.damaged[0].start <= broken_end - 8 && report.damaged[0].start >= broken_end - 16);
+    let first_value = report.values.first().unwrap();
+    assert!(first_value.self_describe);
+    assert_eq!(first_value.value.decode::<Doc>().unwrap(), doc(0));
+    let last_value = report.values.last().unwrap();
+    assert!(last_value.self_describe);
+    assert_eq!(last_value.range, first + second..bytes.len());
+    assert_eq!(last_value.value.decode::<Doc>().unwrap(), doc(2));
+
+    // ルートを失った 2 つ目の文書は子の値が回収される
+    let strings: Vec<&str> = report.values.iter().filter_map(|v| v.value.as_str()).collect();
+    assert!(strings.contains(&"document 1"));
+    let lines = report.values.iter().find(|v| v.value.is_array()).unwrap();
+    assert_eq!(lines.value.count().unwrap(), 5);
+}
+
+#[test]
+fn test_truncated_document() {
+    let bytes = to_vec(&doc(7)).unwrap();
+    // 先頭が欠けると、ルートの長さが合わなくなるので子を回収する
+    let report = recover(&bytes[10..]);
+    assert!(!report.is_clean());
+    assert_eq!(report.damaged.last().unwrap().end, bytes.len() - 10);
+    let lines = report.values.iter().find(|v| v.value.is_array()).unwrap();
+    assert_eq!(lines.value.decode::<Vec<String>>().unwrap(), doc(7).lines);
+    // 回収した範囲と壊れた範囲は重ならずに全体を覆う
+    let mut covered: Vec<_> = report.values.iter().map(|v| v.range.clone()).chain(report.damaged.clone()).collect();
+    covered.sort_by_key(|r| r.start);
+    assert!(covered.windows(2).all(|w| w[0].end <= w[1].start));
+}
@@ -9,6 +9,11 @@ use serde::{Serialize, Serializer};
 use serde_ton::value::prefix::prefix;
 use serde_ton::value::prefix::size_prefix::{SIZE_PREFIX_1BYTE, SIZE_PREFIX_2BYTE, SIZE_PREFIX_4BYTE, SIZE_PREFIX_8BYTE};
 
+use serde_ton::de::from_slice;
+use serde_ton::value::map::Map;
+use serde_ton::value::num::{Int, UInt};
+use serde_ton::value::value::Value;
+
 use std::collections::HashMap;
 
 // Test ReverseSerializer using an in-memory Vec<u8>
@@ -532,6 +537,79 @@ fn test_serialize_padding() {
     assert_eq!(out, expected);
 }
 
+#[test]
+fn test_serialize_value_none_and_undefined() {
+    let mut out = Vec::new();
+    {
+        let mut serializer = ReverseSerializer::new(&mut out);
+        Value::None.ex_serialize(&mut serializer).unwrap();
+        Value::Undefined.ex_serialize(&mut serializer).unwrap();
+    }
+    // None と Undefined はそれぞれ 1 byte 書く
+    let expected = vec![prefix::NONE, prefix::UNDEFINED];
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn test_serialize_value_int() {
+    let mut out = Vec::new();
+    {
+        let mut serializer = ReverseSerializer::new(&mut out);
+        Value::UInt(UInt::U16(42)).ex_serialize(&mut serializer).unwrap();
+        Value::Int(Int::I8(-1)).ex_serialize(&mut serializer).unwrap();
+    }
+    // enum の variant ではなく数値そのものとして書く
+    let expected = vec![42, 0, prefix::UINT | SIZE_PREFIX_2BYTE, 0xff, prefix::INT | SIZE_PREFIX_1BYTE];
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn test_serialize_value_bytes() {
+    let mut out = Vec::new();
+    {
+        let mut serializer = ReverseSerializer::new(&mut out);
+        Value::Bytes(vec![1, 2, 3]).ex_serialize(&mut serializer).unwrap();
+    }
+    // u8 の配列ではなく BYTES として書く
+    let expected = vec![1, 2, 3, 3, prefix::BYTES | SIZE_PREFIX_1BYTE];
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn test_serialize_value_object() {
+    let mut out = Vec::new();
+    {
+        let mut map = Map::new();
+        map.insert("key".into(), Value::UInt(UInt::U8(42)));
+        let mut serializer = ReverseSerializer::new(&mut out);
+        Value::Object(map).ex_serialize(&mut serializer).unwrap();
+    }
+    // value -> key の順で書く
+    let expected = vec![
+        42, prefix::UINT | SIZE_PREFIX_1BYTE,
+        b'k', b'e', b'y', 3, prefix::STRING | SIZE_PREFIX_1BYTE,
+        7, prefix::OBJECT | SIZE_PREFIX_1BYTE,
+    ];
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn test_deserialize_value_timestamp_and_duration() {
+    let mut out = Vec::new();
+    {
+        let mut serializer = ReverseSerializer::new(&mut out);
+        serializer.serialize_timestamp(1_700_000_000).unwrap();
+    }
+    assert_eq!(from_slice::<Value>(&out).unwrap(), Value::Timestamp(1_700_000_000));
+
+    let mut out = Vec::new();
+    {
+        let mut serializer = ReverseSerializer::new(&mut out);
+        serializer.serialize_duration(&chrono::Duration::seconds(42)).unwrap();
+    }
+    assert_eq!(from_slice::<Value>(&out).unwrap(), Value::Duration(chrono::Duration::seconds(42)));
+}
+
 
 
 
@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use serde_ton::de::from_slice;
+use serde_ton::ser::to_vec;
+use serde_ton::value::prefix::{prefix, size_prefix};
+use serde_ton::value::value::Value;
+use serde_ton::{append_in_place, edit_file_in_place, edit_in_place, validate, Slack};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Counter {
+    name: Slack<String, 32>,
+    history: Slack<Vec<u32>, 64>,
+    hits: u64,
+}
+
+fn counter() -> Counter {
+    Counter { name: Slack::new("page".to_string()), history: Slack::new(vec![1, 2]), hits: 3 }
+}
+
+#[test]
+fn test_slack_round_trip() {
+    let bytes = to_vec(&counter()).unwrap();
+    let plain = to_vec(&("page", vec![1u32, 2], 3u64)).unwrap();
+    // Padding の分だけ大きくなる
+    assert!(bytes.len() > plain.len() + 96);
+    validate(&bytes).unwrap();
+    assert_eq!(from_slice::<Counter>(&bytes).unwrap(), counter());
+
+    // Padding は読み飛ばすので、Slack のない型でも読める
+    let value: Value = from_slice(&bytes).unwrap();
+    assert_eq!(value["name"].as_str(), Some("page"));
+    assert_eq!(value["history"][1].as_u64(), Some(2));
+
+    // 他のフォーマットでは中身と同じ
+    let json = serde_json::to_string(&counter()).unwrap();
+    assert_eq!(json, r#"{"name":"page","history":[1,2],"hits":3}"#);
+    assert_eq!(serde_json::from_str::<Counter>(&json).unwrap(), counter());
+    let text = serde_ton::text::to_string(&counter()).unwrap();
+    assert_eq!(serde_ton::text::from_str::<Counter>(&text).unwrap(), counter());
+
+    let root = to_vec(&Slack::<_, 10>::new(7u8)).unwrap();
+    assert_eq!(from_slice::<u8>(&root).unwrap(), 7);
+
+    // 空きのサイズは型ごとに決まる
+    let plain = to_vec(&7u8).unwrap().len();
+    assert_eq!(to_vec(&Slack::<_, 0>::new(7u8)).unwrap().len(), plain);
+    assert_eq!(to_vec(&Slack::<_, 9>::new(7u8)).unwrap().len(), plain + 9 + 2);
+    assert_eq!(to_vec(&Slack::<_, 300>::new(7u8)).unwrap().len(), plain + 300 + 3);
+    assert_eq!(to_vec(&Slack::<_, 70000>::new(7u8)).unwrap().len(), plain + 70000 + 5);
+}
+
+#[test]
+fn test_grow_into_slack() {
+    let mut bytes = to_vec(&counter()).unwrap();
+    let len = bytes.len();
+
+    edit_in_place(&mut bytes, "/name", "a longer page name").unwrap();
+    append_in_place(&mut bytes, "/history", &3u32).unwrap();
+    append_in_place(&mut bytes, "/history", &4u32).unwrap();
+    assert_eq!(bytes.len(), len);
+    validate(&bytes).unwrap();
+    let edited: Counter = from_slice(&bytes).unwrap();
+    assert_eq!(*edited.name, "a longer page name");
+    assert_eq!(*edited.history, vec![1, 2, 3, 4]);
+    assert_eq!(edited.hits, 3);
+
+    // 小さくした分は Padding に戻り、また使える
+    edit_in_place(&mut bytes, "/name", "").unwrap();
+    edit_in_place(&mut bytes, "/name", &"x".repeat(30)).unwrap();
+    validate(&bytes).unwrap();
+    assert_eq!(from_slice::<Counter>(&bytes).unwrap().name.len(), 30);
+}
+
+#[test]
+fn test_slack_exhausted() {
+    let original = to_vec(&counter()).unwrap();
+    let mut bytes = original.clone();
+
+    let err = edit_in_place(&mut bytes, "/name", &"x".repeat(100)).unwrap_err();
+    assert!(err.is_size_mismatch());
+    // 隣のフィールドの空きは使わない
+    let err = edit_in_place(&mut bytes, "/hits", &"x".repeat(10)).unwrap_err();
+    assert_eq!(err.expected(), Some("$uint"));
+    let err = append_in_place(&mut bytes, "/hits", &1u32).unwrap_err();
+    assert_eq!(err.expected(), Some("$array"));
+    for _ in 0..12 {
+        append_in_place(&mut bytes, "/history", &0u32).unwrap();
+    }
+    assert!(append_in_place(&mut bytes, "/history", &0u32).unwrap_err().is_size_mismatch());
+    validate(&bytes).unwrap();
+
+    // Slack のない値は同じ長さでしか書けない
+    let mut plain = to_vec(&("page", vec![1u32])).unwrap();
+    edit_in_place(&mut plain, "/0", "book").unwrap();
+    assert!(edit_in_place(&mut plain, "/0", "books").unwrap_err().is_size_mismatch());
+    assert!(append_in_place(&mut plain, "/1", &2u32).unwrap_err().is_size_mismatch());
+    assert_eq!(from_slice::<(String, Vec<u32>)>(&plain).unwrap(), ("book".to_string(), vec![1]));
+}
+
+#[test]
+fn test_long_padding_run() {
+    // 空の Padding がいくつ続いても 1 段で読み飛ばす
+    let mut bytes = to_vec(&5u8).unwrap();
+    for _ in 0..200_000 {
+        bytes.extend_from_slice(&[0x00, prefix::PADDING | size_prefix::SIZE_PREFIX_1BYTE]);
+    }
+    validate(&bytes).unwrap();
+    assert_eq!(from_slice::<u8>(&bytes).unwrap(), 5);
+    assert_eq!(from_slice::<Value>(&bytes).unwrap(), from_slice::<Value>(&to_vec(&5u8).unwrap()).unwrap());
+}
+
+#[test]
+fn test_grow_root_and_file() {
+    let mut bytes = to_vec(&Slack::<_, 300>::new("root".to_string())).unwrap();
+    let range = edit_in_place(&mut bytes, "", &"r".repeat(290)).unwrap();
+    assert_eq!(range, 0..bytes.len());
+    assert_eq!(from_slice::<String>(&bytes).unwrap(), "r".repeat(290));
+    validate(&bytes).unwrap();
+
+    let path = std::env::temp_dir().join(format!("serde_ton_slack_{}.ton", std::process::id()));
+    std::fs::write(&path, to_vec(&counter()).unwrap()).unwrap();
+    let mut file = std::fs::OpenOptions::new().read(true).write(true).open(&path).unwrap();
+    edit_file_in_place(&mut file, "/name", "renamed page").unwrap();
+    drop(file);
+    let edited: Counter = from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(*edited.name, "renamed page");
+}
@@ -0,0 +1,111 @@
+use std::io::Cursor;
+
+use serde::{Deserialize, Serialize};
+use serde_ton::stream::{StreamDeserializer, StreamSerializer};
+use serde_ton::value::prefix::stream_frame::EDGE_LEN;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Log {
+    seq: u32,
+    level: String,
+    message: String,
+}
+
+fn log(seq: u32) -> Log {
+    Log {
+        seq,
+        level: if seq.is_multiple_of(2) { "info" } else { "warn" }.to_string(),
+        message: format!("message {}", seq),
+    }
+}
+
+fn stream(n: u32) -> Vec<u8> {
+    let mut ser = StreamSerializer::new(Vec::new());
+    for seq in 0..n {
+        ser.write(&log(seq)).unwrap();
+    }
+    assert_eq!(ser.count(), n as u64);
+    ser.into_inner()
+}
+
+fn read(bytes: &[u8]) -> StreamDeserializer<Cursor<&[u8]>, Log> {
+    StreamDeserializer::new(Cursor::new(bytes)).unwrap()
+}
+
+#[test]
+fn test_forward_and_backward() {
+    let bytes = stream(50);
+    let forward: Vec<Log> = read(&bytes).map(Result::unwrap).collect();
+    assert_eq!(forward, (0..50).map(log).collect::<Vec<_>>());
+
+    // 後ろからは新しい順
+    let backward: Vec<Log> = read(&bytes).rev().map(Result::unwrap).collect();
+    assert_eq!(backward, (0..50).rev().map(log).collect::<Vec<_>>());
+
+    // 両端から読んでも同じレコードを 2 回返さない
+    let mut de = read(&bytes);
+    assert_eq!(de.next().unwrap().unwrap().seq, 0);
+    assert_eq!(de.next_back().unwrap().unwrap().seq, 49);
+    assert_eq!(de.count(), 48);
+
+    assert_eq!(read(&[]).count(), 0);
+}
+
+#[test]
+fn test_appended_streams() {
+    // 別々に書いたストリームを繋げても 1 つのストリームとして読める
+    let mut bytes = stream(3);
+    let mut ser = StreamSerializer::new(&mut bytes);
+    ser.write(&log(3)).unwrap();
+    ser.write_raw(&serde_ton::ser::to_vec(&log(4)).unwrap()).unwrap();
+    let seqs: Vec<u32> = read(&bytes).map(|r| r.unwrap().seq).collect();
+    assert_eq!(seqs, vec![0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn test_corrupt_record_does_not_stop_iteration() {
+    let mut ser = StreamSerializer::new(Vec::new());
+    ser.write(&log(0)).unwrap();
+    ser.write(&"not a log").unwrap();
+    ser.write(&log(2)).unwrap();
+    let bytes = ser.into_inner();
+
+    let results: Vec<_> = read(&bytes).collect();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].as_ref().unwrap().seq, 0);
+    let err = results[1].as_ref().unwrap_err();
+    assert!(err.is_type());
+    // エラーの位置はストリーム全体での位置
+    assert!(err.offset().unwrap() > EDGE_LEN * 3);
+    assert_eq!(results[2].as_ref().unwrap().seq, 2);
+
+    let results: Vec<_> = read(&bytes).rev().collect();
+    assert_eq!(results.len(), 3);
+    assert!(results[1].is_err());
+    assert_eq!(results[0].as_ref().unwrap().seq, 2);
+}
+
+#[test]
+fn test_broken_frame_is_skipped() {
+    let bytes = stream(5);
+    let frame_len = bytes.len() / 5;
+
+    // 2 番目のレコードの枠を壊す
+    let mut broken = bytes.clone();
+    broken[frame_len + 1] ^= 0xff;
+    broken[frame_len * 2 - 1] ^= 0xff;
+    let forward: Vec<_> = read(&broken).collect();
+    assert_eq!(forward.iter().filter(|r| r.is_err()).count(), 1);
+    assert_eq!(forward.iter().filter_map(|r| r.as_ref().ok()).map(|l| l.seq).collect::<Vec<_>>(), vec![0, 2, 3, 4]);
+    let backward: Vec<_> = read(&broken).rev().collect();
+    assert_eq!(backward.iter().filter_map(|r| r.as_ref().ok()).map(|l| l.seq).collect::<Vec<_>>(), vec![4, 3, 2, 0]);
+
+    // 書き込み中に切れた末尾は読み飛ばす
+    let truncated = &bytes[..bytes.len() - 7];
+    let backward: Vec<_> = read(truncated).rev().collect();
+    assert!(backward[0].as_ref().unwrap_err().is_syntax());
+    assert_eq!(backward[1..].iter().map(|r| r.as_ref().unwrap().seq).collect::<Vec<_>>(), vec![3, 2, 1, 0]);
+    let forward: Vec<_> = read(truncated).collect();
+    assert_eq!(forward.len(), 5);
+    assert!(forward[4].is_err());
+}
@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use serde_ton::de::from_slice;
+use serde_ton::ser::ReverseSerializer;
+use serde_ton::text::{from_str, to_string, to_string_pretty, value_to_string, PrettyConfig};
+use serde_ton::traits::ser::ExtendSerialize;
+use serde_ton::value::map::Map;
+use serde_ton::value::num::{Float, Int, UInt};
+use serde_ton::value::value::{KeyValue, Value};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Config {
+    name: String,
+    port: u16,
+    ratio: f32,
+    hosts: Vec<String>,
+    mode: Mode,
+    limit: Option<i64>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Mode {
+    Off,
+    Fixed(u8),
+    Range { min: i32, max: i32 },
+}
+
+fn sample_value() -> Value {
+    let mut inner = Map::new();
+    inner.insert(KeyValue::String("zip".to_string()), Value::String("100-0001".to_string()));
+    inner.insert(KeyValue::Int(Int::I8(-1)), Value::Bytes(vec![0xde, 0xad]));
+
+    let mut object = Map::new();
+    object.insert(KeyValue::String("undefined".to_string()), Value::Undefined);
+    object.insert(KeyValue::String("none".to_string()), Value::None);
+    object.insert(KeyValue::String("bool".to_string()), Value::Bool(true));
+    object.insert(KeyValue::String("i8".to_string()), Value::Int(Int::I8(-8)));
+    object.insert(KeyValue::String("i64".to_string()), Value::Int(Int::I64(-64)));
+    object.insert(KeyValue::String("u16".to_string()), Value::UInt(UInt::U16(16)));
+    object.insert(KeyValue::String("u64".to_string()), Value::UInt(UInt::U64(u64::MAX)));
+    object.insert(KeyValue::String("f16".to_string()), Value::Float(Float::F16(half::f16::from_f32(0.5))));
+    object.insert(KeyValue::String("f32".to_string()), Value::Float(Float::F32(1.25)));
+    object.insert(KeyValue::String("f64".to_string()), Value::Float(Float::F64(1.0)));
+    object.insert(KeyValue::String("string".to_string()), Value::String("line\n\"quoted\" ✓".to_string()));
+    object.insert(
+        KeyValue::String("uuid".to_string()),
+        Value::UUID(uuid::Uuid::from_u128(0x0123_4567_89ab_cdef_0123_4567_89ab_cdef)),
+    );
+    object.insert(
+        KeyValue::String("datetime".to_string()),
+        Value::DateTime(chrono::DateTime::parse_from_rfc3339("2025-06-30T12:00:00+00:00").unwrap()),
+    );
+    object.insert(KeyValue::String("timestamp".to_string()), Value::Timestamp(-5));
+    object.insert(KeyValue::String("duration".to_string()), Value::Duration(chrono::Duration::nanoseconds(42)));
+    object.insert(KeyValue::String("json".to_string()), Value::WrappedJSON(serde_json::json!({"k": [true, 1.5]})));
+    object.insert(KeyValue::String("meta".to_string()), Value::Meta(Box::new(Value::UInt(UInt::U8(1)))));
+    object.insert(KeyValue::String("array".to_string()), Value::Array(vec![Value::Array(vec![]), Value::Object(inner)]));
+    Value::Object(object)
+}
+
+fn value_to_vec(value: &Value) -> Vec<u8> {
+    let mut serializer = ReverseSerializer::new(Vec::new());
+    value.ex_serialize(&mut serializer).unwrap();
+    serializer.into_inner()
+}
+
+#[test]
+fn test_struct_round_trip() {
+    let config = Config {
+        name: "server".to_string(),
+        port: 8080,
+        ratio: 0.75,
+        hosts: vec!["a.example".to_string(), "b.example".to_string()],
+        mode: Mode::Range { min: -1, max: 10 },
+        limit: None,
+    };
+    let text = to_string(&config).unwrap();
+    assert_eq!(from_str::<Config>(&text).unwrap(), config);
+
+    let text = to_string_pretty(&config).unwrap();
+    assert_eq!(from_str::<Config>(&text).unwrap(), config);
+
+    for mode in [Mode::Off, Mode::Fixed(3)] {
+        let text = to_string(&mode).unwrap();
+        assert_eq!(from_str::<Mode>(&text).unwrap(), mode);
+    }
+}
+
+#[test]
+fn test_compact_output() {
+    let text = to_string(&(1u8, -2i32, 3.0f64, "x", vec![true])).unwrap();
+    assert_eq!(text, "[1u8,-2i32,3.0,\"x\",[true]]");
+}
+
+#[test]
+fn test_pretty_output() {
+    let value = Value::Array(vec![Value::Int(Int::I64(1)), Value::Array(vec![])]);
+    assert_eq!(format!("{:#}", value), "[\n  1,\n  []\n]");
+
+    let config = PrettyConfig {
+        indent: "\t".to_string(),
+        trailing_comma: true,
+        ..PrettyConfig::default()
+    };
+    let text = value_to_string(&value, Some(config)).unwrap();
+    assert_eq!(text, "[\n\t1,\n\t[],\n]");
+}
+
+#[test]
+fn test_value_text_round_trip() {
+    let value = sample_value();
+
+    let compact = value.to_string();
+    assert_eq!(from_str::<Value>(&compact).unwrap(), value);
+
+    let pretty = format!("{:#}", value);
+    assert_eq!(from_str::<Value>(&pretty).unwrap(), value);
+}
+
+#[test]
+fn test_text_binary_round_trip() {
+    let value = sample_value();
+
+    // text -> Value -> binary -> Value -> text
+    let text = format!("{:#}", value);
+    let parsed: Value = from_str(&text).unwrap();
+    let bytes = value_to_vec(&parsed);
+    let decoded: Value = from_slice(&bytes).unwrap();
+    assert_eq!(decoded, value);
+    assert_eq!(format!("{:#}", decoded), text);
+}
+
+#[test]
+fn test_relaxed_syntax() {
+    let text = r#"
+        // comment
+        {
+            name: "n", /* block */
+            port: 1u16,
+            ratio: 2f32,
+            hosts: ["h",],
+            mode: "Off",
+            limit: null,
+        }
+    "#;
+    let config: Config = from_str(text).unwrap();
+    assert_eq!(config.port, 1);
+    assert_eq!(config.hosts, vec!["h".to_string()]);
+    assert_eq!(config.mode, Mode::Off);
+}
+
+#[test]
+fn test_parse_error_position() {
+    let err = from_str::<Value>("{\n  \"a\": [1, 2,\n  \"b\" }").unwrap_err();
+    assert_eq!(err.line(), 3);
+    assert!(err.column() > 0);
+    assert!(err.to_string().contains("line 3"));
+
+    let err = from_str::<Value>("[1] 2").unwrap_err();
+    assert_eq!((err.line(), err.column()), (1, 5));
+
+    assert!(from_str::<Value>("[1,").unwrap_err().is_eof());
+    let err = from_str::<Config>("{name: 1}").unwrap_err();
+    assert_eq!((err.line(), err.column()), (1, 8));
+    assert_eq!(err.expected(), Some("a string"));
+}
+
+#[test]
+fn test_nesting_limit() {
+    // 深すぎるネストはスタックを使い切る前にエラーにする
+    let err = from_str::<Value>(&"[".repeat(100_000)).unwrap_err();
+    assert!(err.to_string().contains("nesting is deeper than 128"));
+    assert_eq!((err.line(), err.column()), (1, 129));
+    assert!(from_str::<Value>(&"{a: ".repeat(1000)).is_err());
+
+    let nested = "[".repeat(128) + &"]".repeat(128);
+    assert!(from_str::<Value>(&nested).is_ok());
+    assert!(from_str::<Value>(&format!("[{}]", nested)).is_err());
+}
+
+#[test]
+fn test_value_index() {
+    let value: Value = from_str(r#"{users: [{name: "alice"}, {name: "bob"}], "a/b": meta([1])}"#).unwrap();
+    assert_eq!(value["users"][1]["name"].as_str(), Some("bob"));
+    assert_eq!(value["users"][5], Value::None);
+    assert_eq!(value.get("users").and_then(|v| v.get(0)).and_then(|v| v.get("name")), Some(&Value::String("alice".to_string())));
+    assert_eq!(value.pointer("/users/0/name").and_then(Value::as_str), Some("alice"));
+    assert_eq!(value.pointer("/a~1b/0").and_then(Value::as_i64), Some(1));
+    assert_eq!(value.pointer(""), Some(&value));
+    assert!(value.pointer("users").is_none());
+}
@@ -0,0 +1,123 @@
+use serde::Serialize;
+use serde_ton::ser::{generate_header, to_vec, value_to_vec};
+use serde_ton::validate;
+use serde_ton::value::prefix::prefix;
+use serde_ton::value::prefix::self_describe::TON_V1_REV_TAG;
+use serde_ton::value::value::Value;
+
+#[derive(Serialize)]
+struct Record {
+    id: u32,
+    name: String,
+    tags: Vec<String>,
+    parent: Option<Box<Record>>,
+}
+
+fn sample() -> Vec<u8> {
+    to_vec(&Record {
+        id: 1,
+        name: "root".to_string(),
+        tags: vec!["a".to_string()],
+        parent: Some(Box::new(Record {
+            id: 0,
+            name: "parent".to_string(),
+            tags: vec![],
+            parent: None,
+        })),
+    })
+    .unwrap()
+}
+
+/// body と head から 1 つの値を組み立てる
+fn sized(prefix: u8, body: &[u8]) -> Vec<u8> {
+    let (header, size) = generate_header(prefix, body.len() as u64);
+    let mut out = body.to_vec();
+    out.extend_from_slice(&header[..size]);
+    out
+}
+
+#[test]
+fn test_valid_document() {
+    let bytes = sample();
+    let report = validate(&bytes).unwrap();
+    assert_eq!(report.size, bytes.len());
+    assert_eq!(report.containers, 4);
+    assert_eq!(report.max_depth, 2);
+    assert!(!report.self_describe);
+
+    let mut bytes = bytes;
+    bytes.extend_from_slice(&TON_V1_REV_TAG);
+    assert!(validate(&bytes).unwrap().self_describe);
+}
+
+#[test]
+fn test_valid_extended_types() {
+    let text = r#"[uuid("01234567-89ab-cdef-0123-456789abcdef"), datetime("2024-01-01T00:00:00+00:00"), json({"a": 1}), meta(1u8), undefined, 0.5f16]"#;
+    let value: Value = serde_ton::text::from_str(text).unwrap();
+    let report = validate(&value_to_vec(&value).unwrap()).unwrap();
+    assert_eq!(report.values, 8);
+}
+
+#[test]
+fn test_truncated_head_is_rejected() {
+    // 後ろから読むので先頭を削ると必ずルートの長さが合わなくなる
+    // 末尾を削った場合は最初の子が単独で正しい値になることがある
+    let bytes = sample();
+    assert!(validate(&[]).is_err());
+    for start in 1..bytes.len() {
+        assert!(validate(&bytes[start..]).is_err(), "suffix from {} was accepted", start);
+    }
+}
+
+#[test]
+fn test_unknown_head() {
+    let err = validate(&[0xff]).unwrap_err();
+    assert!(err.to_string().contains("unknown head"));
+    // FLOAT に 1 バイトの size class はない
+    assert!(validate(&[0, prefix::FLOAT]).is_err());
+}
+
+#[test]
+fn test_invalid_bodies() {
+    let err = validate(&sized(prefix::STRING, &[0xff, 0xfe])).unwrap_err();
+    assert!(err.to_string().contains("UTF-8"));
+
+    let err = validate(&sized(prefix::DATETIME, b"yesterday")).unwrap_err();
+    assert!(err.to_string().contains("RFC 3339"));
+
+    let err = validate(&sized(prefix::WRAPPED_JSON, b"{\"a\":")).unwrap_err();
+    assert!(err.to_string().contains("JSON"));
+}
+
+#[test]
+fn test_invalid_containers() {
+    // 子が親の body からはみ出している
+    let string = sized(prefix::STRING, b"abc");
+    let mut body = to_vec("xy").unwrap();
+    body.extend(sized(prefix::ARRAY, &string[2..]));
+    let err = validate(&sized(prefix::ARRAY, &body)).unwrap_err();
+    assert!(err.to_string().contains("overflows"));
+
+    // key に Array は使えない
+    let mut entry = to_vec(&1u8).unwrap();
+    entry.extend(sized(prefix::ARRAY, &[]));
+    let err = validate(&sized(prefix::OBJECT, &entry)).unwrap_err();
+    assert!(err.to_string().contains("object key"));
+
+    // value がない
+    let err = validate(&sized(prefix::OBJECT, &to_vec("key").unwrap())).unwrap_err();
+    assert!(err.to_string().contains("without a value"));
+
+    // Meta は値を 1 つだけ持つ
+    let mut two = to_vec(&1u8).unwrap();
+    two.extend(to_vec(&2u8).unwrap());
+    assert!(validate(&sized(prefix::META, &two)).is_err());
+}
+
+#[test]
+fn test_leftover_bytes() {
+    let mut bytes = vec![0x00];
+    bytes.extend(sample());
+    let err = validate(&bytes).unwrap_err();
+    assert!(err.to_string().contains("unexpected data before the root value"));
+}